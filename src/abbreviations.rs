@@ -0,0 +1,108 @@
+use std::{borrow::Cow, collections::BTreeMap, path::Path};
+
+use crate::{errors::SsgError, utils, vfs::FileSystem};
+
+/// Abbreviations for every page, at the root of the target directory.
+pub const ABBREVIATIONS_FILE: &str = "_abbreviations.toml";
+
+/// Abbreviations and their expansions, e.g. `HTML` and `HyperText Markup Language`.
+pub type Abbreviations = BTreeMap<String, String>;
+
+/// Reads the site's `_abbreviations.toml`, a table of abbreviations and their expansions, if it
+/// has one.
+pub fn read_abbreviations(
+    fs: &dyn FileSystem,
+    target_path: &Path,
+) -> anyhow::Result<Abbreviations> {
+    let path = target_path.join(ABBREVIATIONS_FILE);
+    if !fs.is_file(&path) {
+        return Ok(Abbreviations::new());
+    }
+    log::trace!("Reading abbreviations from {:?}", &path);
+    toml::from_str(&fs.read_to_string(&path)?).map_err(|e| SsgError::MetadataError(path, e).into())
+}
+
+/// Removes the abbreviations a page defines from its source, returning them with the rest of
+/// the source. Definitions are lines like `*[HTML]: HyperText Markup Language`, outside of code
+/// blocks.
+pub fn take_definitions(input: &str) -> (Cow<'_, str>, Abbreviations) {
+    if !input.contains("*[") {
+        return (Cow::Borrowed(input), Abbreviations::new());
+    }
+    let mut abbreviations = Abbreviations::new();
+    let mut output = String::with_capacity(input.len());
+    let mut fence: Option<&str> = None;
+    for line in input.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") {
+            fence = Some("```");
+        } else if trimmed.starts_with("~~~") {
+            fence = Some("~~~");
+        } else if let Some((abbreviation, expansion)) = line
+            .strip_prefix("*[")
+            .and_then(|definition| definition.split_once("]:"))
+        {
+            abbreviations.insert(
+                abbreviation.trim().to_string(),
+                expansion.trim().to_string(),
+            );
+            continue;
+        }
+        output.push_str(line);
+    }
+    (Cow::Owned(output), abbreviations)
+}
+
+/// Wraps each use of an abbreviation in `html` in an `<abbr>` with its expansion. Text in code
+/// and existing `<abbr>` elements is skipped.
+pub fn expand(html: &str, abbreviations: &Abbreviations) -> String {
+    if abbreviations.is_empty() {
+        return html.to_string();
+    }
+    // Longer abbreviations first, so `HTML5` isn't matched as `HTML`
+    let mut abbreviations = abbreviations
+        .iter()
+        .map(|(abbreviation, expansion)| {
+            (
+                utils::escape_text(abbreviation),
+                utils::escape_html(expansion),
+            )
+        })
+        .filter(|(abbreviation, _)| !abbreviation.is_empty())
+        .collect::<Vec<_>>();
+    abbreviations.sort_by_key(|(abbreviation, _)| std::cmp::Reverse(abbreviation.len()));
+    let is_word_char = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    utils::map_text(html, &["abbr", "code", "pre", "script", "style"], |text| {
+        let mut output = String::with_capacity(text.len());
+        let mut copied = 0;
+        let mut i = 0;
+        while i < text.len() {
+            let found = (!is_word_char(text[..i].chars().next_back()))
+                .then(|| {
+                    abbreviations.iter().find(|(abbreviation, _)| {
+                        text[i..].starts_with(abbreviation.as_str())
+                            && !is_word_char(text[i + abbreviation.len()..].chars().next())
+                    })
+                })
+                .flatten();
+            match found {
+                Some((abbreviation, expansion)) => {
+                    output.push_str(&text[copied..i]);
+                    output.push_str(&format!(
+                        "<abbr title=\"{}\">{}</abbr>",
+                        expansion, abbreviation
+                    ));
+                    i += abbreviation.len();
+                    copied = i;
+                }
+                None => i += text[i..].chars().next().map_or(1, char::len_utf8),
+            }
+        }
+        output.push_str(&text[copied..]);
+        output
+    })
+}
@@ -0,0 +1,98 @@
+use std::path::Path;
+
+use time::OffsetDateTime;
+
+use crate::{abbreviations, sandbox, templating, utils, SiteContext};
+
+/// Files that configure the site instead of being downloads, so they're never listed.
+const SITE_FILES: [&str; 5] = [
+    "template.html",
+    "_dir.toml",
+    "style.css",
+    "ssg.toml",
+    abbreviations::ABBREVIATIONS_FILE,
+];
+
+/// A file listed on an asset index page.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Asset {
+    pub name: String,
+    /// The size of the file, in bytes
+    pub size: u64,
+    /// When the file was last modified, if known
+    pub modified: Option<OffsetDateTime>,
+}
+
+/// The files directly inside `dir` that are copied to the site as they are (not pages or site
+/// files), sorted by name.
+pub fn list_assets(dir: &Path, context: &SiteContext) -> anyhow::Result<Vec<Asset>> {
+    let fs = context.fs;
+    let mut assets = Vec::new();
+    for (path, _) in fs
+        .walk(dir)
+        .into_iter()
+        .flatten()
+        .filter(|(path, depth)| *depth == 1 && fs.is_file(path))
+    {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let is_page = path
+            .extension()
+            .is_some_and(|ext| ext == "dj" || ext == "djot" || ext == "md");
+        let is_ignored = path
+            .strip_prefix(context.target_path)
+            .is_ok_and(|relative| context.config.is_ignored(relative));
+        if is_page
+            || is_ignored
+            || SITE_FILES.contains(&name.as_str())
+            || templating::is_partial(&path)
+            || (context.sandbox && !sandbox::is_inside(fs, context.target_path, &path))
+        {
+            continue;
+        }
+        assets.push(Asset {
+            name,
+            size: fs.len(&path)?,
+            modified: fs.modified(&path).ok().map(OffsetDateTime::from),
+        });
+    }
+    Ok(assets)
+}
+
+/// Renders an asset index page titled `title`: a table of `assets`, linked, with their sizes
+/// and modification dates.
+pub fn index_html(title: &str, assets: &[Asset], context: &SiteContext) -> anyhow::Result<String> {
+    let mut html = format!(
+        "<h1>{}</h1>\n<table class=\"asset-index\">\n<thead>\n<tr><th>Name</th><th>Size</th><th>Modified</th></tr>\n</thead>\n<tbody>\n",
+        utils::escape_html(title)
+    );
+    for asset in assets {
+        let modified = match asset.modified {
+            Some(modified) => utils::format_date(modified, context.config.date_format.as_deref())?,
+            None => String::new(),
+        };
+        html.push_str(&format!(
+            "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td></tr>\n",
+            utils::escape_html(&asset.name),
+            utils::escape_html(&asset.name),
+            format_size(asset.size),
+            modified
+        ));
+    }
+    html.push_str("</tbody>\n</table>\n");
+    Ok(html)
+}
+
+/// Formats a file size for people, e.g. `512 B` or `1.5 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
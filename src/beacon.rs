@@ -0,0 +1,22 @@
+use crate::config::BeaconConfig;
+
+/// Adds the page view beacon of `config` to the end of the `<body>` of `html`. The script posts
+/// the page's path and the origin of its referrer, without credentials, once per page load.
+pub fn add_beacon(html: &str, config: &BeaconConfig) -> String {
+    // A json string is a javascript string, once it can't close the script element
+    let endpoint = serde_json::to_string(&config.endpoint)
+        .unwrap_or_default()
+        .replace("</", "<\\/");
+    let do_not_track = match config.ignore_do_not_track {
+        true => "",
+        false => "if(navigator.doNotTrack===\"1\"||window.doNotTrack===\"1\")return;",
+    };
+    let script = format!(
+        "<script>(function(){{{}var r=document.referrer;try{{r=r&&new URL(r).origin}}catch(e){{r=\"\"}}fetch({},{{method:\"POST\",body:JSON.stringify({{path:location.pathname,referrer:r}}),credentials:\"omit\",keepalive:true,mode:\"no-cors\"}}).catch(function(){{}})}})();</script>\n",
+        do_not_track, endpoint
+    );
+    match html.rfind("</body>") {
+        Some(end) => format!("{}{}{}", &html[..end], script, &html[end..]),
+        None => format!("{}{}", html, script),
+    }
+}
@@ -0,0 +1,391 @@
+use std::{collections::HashMap, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    config::{BibliographyConfig, CitationStyle},
+    errors::SsgError,
+    utils,
+    vfs::FileSystem,
+    SiteContext,
+};
+
+/// A work that pages can cite with `[@key]`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Reference {
+    pub key: String,
+    /// Each author's family and given names
+    pub authors: Vec<(String, String)>,
+    pub title: Option<String>,
+    pub year: Option<String>,
+    /// The journal, book, or publisher the work appeared in
+    pub container: Option<String>,
+    pub url: Option<String>,
+}
+
+impl Reference {
+    /// The short form of the authors for an inline citation, e.g. `Knuth` or `Knuth et al.`.
+    fn short_authors(&self) -> String {
+        match &self.authors[..] {
+            [] => self.title.clone().unwrap_or_else(|| self.key.clone()),
+            [(one, _)] => one.clone(),
+            [(one, _), (two, _)] => format!("{} and {}", one, two),
+            [(first, _), ..] => format!("{} et al.", first),
+        }
+    }
+
+    /// The entry for the references section of a page.
+    fn entry_html(&self) -> String {
+        let mut html = String::new();
+        let authors = self
+            .authors
+            .iter()
+            .map(|(family, given)| match given.is_empty() {
+                true => family.clone(),
+                false => format!("{}, {}", family, given),
+            })
+            .collect::<Vec<_>>()
+            .join(" and ");
+        if !authors.is_empty() {
+            html.push_str(&utils::escape_html(&authors));
+            html.push(' ');
+        }
+        if let Some(year) = &self.year {
+            html.push_str(&format!("({}). ", utils::escape_html(year)));
+        }
+        if let Some(title) = &self.title {
+            html.push_str(&format!("<cite>{}</cite>. ", utils::escape_html(title)));
+        }
+        if let Some(container) = &self.container {
+            html.push_str(&format!("{}. ", utils::escape_html(container)));
+        }
+        if let Some(url) = &self.url {
+            let url = utils::escape_html(url);
+            html.push_str(&format!("<a href=\"{}\">{}</a>", url, url));
+        }
+        html.trim_end().to_string()
+    }
+}
+
+/// The works in a site's bibliography, by key.
+pub type Bibliography = HashMap<String, Reference>;
+
+/// Reads the bibliography configured with `bibliography.path`, a BibTeX (`.bib`) or CSL-JSON
+/// (`.json`) file relative to `target_path`.
+pub fn read_bibliography(
+    fs: &dyn FileSystem,
+    target_path: &Path,
+    config: &BibliographyConfig,
+    sandbox: bool,
+) -> anyhow::Result<Option<Bibliography>> {
+    let Some(path) = &config.path else {
+        return Ok(None);
+    };
+    let path = target_path.join(path);
+    if sandbox && !crate::sandbox::is_inside(fs, target_path, &path) {
+        return Err(SsgError::OutsideTarget(path).into());
+    }
+    log::trace!("Reading bibliography from {:?}", &path);
+    let contents = fs.read_to_string(&path)?;
+    let references = if path.extension().is_some_and(|ext| ext == "json") {
+        parse_csl_json(&contents)?
+    } else {
+        parse_bibtex(&contents)
+    };
+    Ok(Some(
+        references
+            .into_iter()
+            .map(|reference| (reference.key.clone(), reference))
+            .collect(),
+    ))
+}
+
+/// Renders the `[@key]` citations in `html` (e.g. `[@knuth1984, p. 12; @lamport1994]`) as
+/// links to a references section, which is appended. Citations of unknown keys are left as
+/// they are, with a warning.
+pub fn cite(
+    html: &str,
+    bibliography: &Bibliography,
+    style: CitationStyle,
+    path: &Path,
+    context: &SiteContext,
+) -> String {
+    let mut cited: Vec<&Reference> = Vec::new();
+    let html = utils::map_text(html, &["code", "pre", "script", "style"], |text| {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("[@") {
+            output.push_str(&rest[..start]);
+            let Some(end) = rest[start..].find(']').map(|end| start + end) else {
+                rest = &rest[start..];
+                break;
+            };
+            let citation = &rest[start..=end];
+            rest = &rest[end + 1..];
+            let Some(items) = parse_citation(&citation[1..citation.len() - 1]) else {
+                output.push_str(citation);
+                continue;
+            };
+            let Some(references) = items
+                .iter()
+                .map(|(key, locator)| Some((bibliography.get(*key)?, *locator)))
+                .collect::<Option<Vec<_>>>()
+            else {
+                context.warn(format!(
+                    "{:?} cites an unknown reference: {}",
+                    path, citation
+                ));
+                output.push_str(citation);
+                continue;
+            };
+            let parts = references
+                .into_iter()
+                .map(|(reference, locator)| {
+                    let number = match cited.iter().position(|cited| cited.key == reference.key) {
+                        Some(index) => index + 1,
+                        None => {
+                            cited.push(reference);
+                            cited.len()
+                        }
+                    };
+                    let label = match style {
+                        CitationStyle::AuthorDate => match &reference.year {
+                            Some(year) => format!("{} {}", reference.short_authors(), year),
+                            None => reference.short_authors(),
+                        },
+                        CitationStyle::Numeric => number.to_string(),
+                    };
+                    let link = format!(
+                        "<a href=\"#ref-{}\">{}</a>",
+                        utils::escape_html(&reference.key),
+                        utils::escape_html(&label)
+                    );
+                    match locator {
+                        Some(locator) => format!("{}, {}", link, locator),
+                        None => link,
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("; ");
+            let (open, close) = match style {
+                CitationStyle::AuthorDate => ("(", ")"),
+                CitationStyle::Numeric => ("[", "]"),
+            };
+            output.push_str(&format!(
+                "<span class=\"citation\">{}{}{}</span>",
+                open, parts, close
+            ));
+        }
+        output.push_str(rest);
+        output
+    });
+    if cited.is_empty() {
+        return html;
+    }
+    // Numbered references are listed in the order they're first cited
+    let list = match style {
+        CitationStyle::AuthorDate => {
+            cited.sort_by_key(|reference| (reference.short_authors(), reference.year.clone()));
+            "ul"
+        }
+        CitationStyle::Numeric => "ol",
+    };
+    let mut section = format!(
+        "<section class=\"references\">\n<h2>References</h2>\n<{}>\n",
+        list
+    );
+    for reference in cited {
+        section.push_str(&format!(
+            "<li id=\"ref-{}\">{}</li>\n",
+            utils::escape_html(&reference.key),
+            reference.entry_html()
+        ));
+    }
+    section.push_str(&format!("</{}>\n</section>\n", list));
+    html + &section
+}
+
+/// Splits the inside of a citation (e.g. `@knuth1984, p. 12; @lamport1994`) into keys and
+/// their locators, or returns `None` if it isn't a citation.
+fn parse_citation(citation: &str) -> Option<Vec<(&str, Option<&str>)>> {
+    citation
+        .split(';')
+        .map(|item| {
+            let item = item.trim().strip_prefix('@')?;
+            let (key, locator) = match item.split_once(',') {
+                Some((key, locator)) => (key.trim(), Some(locator.trim())),
+                None => (item.trim(), None),
+            };
+            let valid = !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '.' | '/'));
+            valid.then_some((key, locator.filter(|locator| !locator.is_empty())))
+        })
+        .collect()
+}
+
+/// Parses the entries of a BibTeX file, e.g. `@book{knuth1984, author = {Knuth, Donald E.},
+/// title = {The {TeX}book}, year = 1984}`. Comments and `@string` macros are skipped.
+pub fn parse_bibtex(bibtex: &str) -> Vec<Reference> {
+    let mut references = Vec::new();
+    let mut rest = bibtex;
+    while let Some(at) = rest.find('@') {
+        rest = &rest[at + 1..];
+        let Some(open) = rest.find(['{', '(']) else {
+            break;
+        };
+        let entry_type = rest[..open].trim().to_ascii_lowercase();
+        let Some(body_length) = balanced_length(&rest[open + 1..]) else {
+            break;
+        };
+        let body = &rest[open + 1..open + 1 + body_length];
+        rest = &rest[open + 1 + body_length..];
+        if matches!(entry_type.as_str(), "comment" | "string" | "preamble") {
+            continue;
+        }
+        let Some((key, fields)) = body.split_once(',') else {
+            continue;
+        };
+        let fields = parse_bibtex_fields(fields);
+        let field = |name: &str| fields.get(name).cloned();
+        references.push(Reference {
+            key: key.trim().to_string(),
+            authors: field("author")
+                .map(|authors| {
+                    authors
+                        .split(" and ")
+                        .map(|author| bibtex_name(author.trim()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            title: field("title"),
+            year: field("year"),
+            container: field("journal")
+                .or_else(|| field("booktitle"))
+                .or_else(|| field("publisher")),
+            url: field("url")
+                .or_else(|| field("doi").map(|doi| format!("https://doi.org/{}", doi))),
+        });
+    }
+    references
+}
+
+/// The length of `text` up to the brace (or parenthesis) closing one that was just opened.
+fn balanced_length(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' | '(' => depth += 1,
+            '}' | ')' if depth == 0 => return Some(i),
+            '}' | ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parses `name = {value}`, `name = "value"`, and `name = 1984` fields, lowercasing names and
+/// removing the braces that protect capitalization.
+fn parse_bibtex_fields(fields: &str) -> HashMap<String, String> {
+    let mut parsed = HashMap::new();
+    let mut rest = fields;
+    while let Some(equals) = rest.find('=') {
+        let name = rest[..equals]
+            .trim()
+            .trim_start_matches(',')
+            .trim()
+            .to_ascii_lowercase();
+        let value = rest[equals + 1..].trim_start();
+        let (raw, remaining) = if let Some(braced) = value.strip_prefix('{') {
+            let end = balanced_length(braced).unwrap_or(braced.len());
+            (&braced[..end], braced.get(end + 1..).unwrap_or(""))
+        } else if let Some(quoted) = value.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            (&quoted[..end], quoted.get(end + 1..).unwrap_or(""))
+        } else {
+            let end = value.find(',').unwrap_or(value.len());
+            (value[..end].trim(), &value[end..])
+        };
+        let value = raw
+            .replace(['{', '}'], "")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        parsed.insert(name, value);
+        rest = remaining;
+    }
+    parsed
+}
+
+/// Splits a BibTeX name (`Knuth, Donald E.` or `Donald E. Knuth`) into family and given names.
+fn bibtex_name(name: &str) -> (String, String) {
+    match name.split_once(',') {
+        Some((family, given)) => (family.trim().to_string(), given.trim().to_string()),
+        None => match name.rsplit_once(' ') {
+            Some((given, family)) => (family.to_string(), given.trim().to_string()),
+            None => (name.to_string(), String::new()),
+        },
+    }
+}
+
+#[derive(Deserialize)]
+struct CslItem {
+    id: String,
+    #[serde(default)]
+    author: Vec<CslName>,
+    title: Option<String>,
+    issued: Option<CslDate>,
+    #[serde(rename = "container-title")]
+    container_title: Option<String>,
+    publisher: Option<String>,
+    #[serde(rename = "URL")]
+    url: Option<String>,
+    #[serde(rename = "DOI")]
+    doi: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CslName {
+    family: Option<String>,
+    given: Option<String>,
+    /// Names that aren't split, such as organizations
+    literal: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CslDate {
+    #[serde(rename = "date-parts", default)]
+    date_parts: Vec<Vec<serde_json::Value>>,
+}
+
+/// Parses a CSL-JSON bibliography: an array of items such as
+/// `{"id": "knuth1984", "author": [{"family": "Knuth"}], "issued": {"date-parts": [[1984]]}}`.
+pub fn parse_csl_json(json: &str) -> anyhow::Result<Vec<Reference>> {
+    let items: Vec<CslItem> = serde_json::from_str(json)?;
+    Ok(items
+        .into_iter()
+        .map(|item| Reference {
+            key: item.id,
+            authors: item
+                .author
+                .into_iter()
+                .map(|name| {
+                    (
+                        name.family.or(name.literal).unwrap_or_default(),
+                        name.given.unwrap_or_default(),
+                    )
+                })
+                .collect(),
+            title: item.title,
+            year: item
+                .issued
+                .and_then(|issued| issued.date_parts.into_iter().next()?.into_iter().next())
+                .map(|year| year.to_string().trim_matches('"').to_string()),
+            container: item.container_title.or(item.publisher),
+            url: item
+                .url
+                .or_else(|| item.doi.map(|doi| format!("https://doi.org/{}", doi))),
+        })
+        .collect())
+}
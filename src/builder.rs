@@ -0,0 +1,151 @@
+use std::{num::NonZeroUsize, path::PathBuf};
+
+use crate::{
+    first_pass, run_program_with, site_paths, templates::BuiltInTemplate, vfs, BuildReport,
+    ConsoleArgs, FirstPassResult, SiteContext,
+};
+
+/// Generates a site, with the options of the `simple-ssg` command.
+///
+/// Options that aren't set are read from the site's `ssg.toml`, as they are by the command.
+#[derive(Debug)]
+pub struct SiteBuilder {
+    args: ConsoleArgs,
+}
+
+impl SiteBuilder {
+    /// A builder for the site in `directory`.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            args: ConsoleArgs {
+                directory: Some(directory.into()),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// A builder for a single page, generated beside it.
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        Self {
+            args: ConsoleArgs {
+                file: Some(path.into()),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Where to generate the site (defaults to `output_path` in ssg.toml, or `./output`).
+    pub fn output_path(mut self, output_path: impl Into<PathBuf>) -> Self {
+        self.args.output_path = Some(output_path.into());
+        self
+    }
+
+    /// Removes the output directory before generating the site.
+    pub fn clean(mut self, clean: bool) -> Self {
+        self.args.clean = clean;
+        self
+    }
+
+    /// The prefix of the site's urls (defaults to relative paths).
+    pub fn web_prefix(mut self, web_prefix: impl Into<String>) -> Self {
+        self.args.web_prefix = Some(web_prefix.into());
+        self
+    }
+
+    /// A built in template for every page, instead of their `template.html` files.
+    pub fn template(mut self, template: BuiltInTemplate) -> Self {
+        self.args.template = Some(template);
+        self
+    }
+
+    /// Renders `README` pages as their directory's index.
+    pub fn readme_as_index(mut self, readme_as_index: bool) -> Self {
+        self.args.readme_as_index = readme_as_index;
+        self
+    }
+
+    /// Treats each subdirectory as a version of the site, like `--versioned`.
+    pub fn versioned(mut self, versioned: bool) -> Self {
+        self.args.versioned = versioned;
+        self
+    }
+
+    /// Enables a flag, including content marked with its `only-<FLAG>` class.
+    pub fn flag(mut self, flag: impl Into<String>) -> Self {
+        self.args.flags.push(flag.into());
+        self
+    }
+
+    /// Also generates a text-only mirror of the site under `lite/`.
+    pub fn lite(mut self, lite: bool) -> Self {
+        self.args.lite = lite;
+        self
+    }
+
+    /// Writes each page to its own directory, like `--pretty-urls`.
+    pub fn pretty_urls(mut self, pretty_urls: bool) -> Self {
+        self.args.pretty_urls = pretty_urls;
+        self
+    }
+
+    /// Publishes the site as a notes vault, like `--notes`.
+    pub fn notes(mut self, notes: bool) -> Self {
+        self.args.notes = notes;
+        self
+    }
+
+    /// Writes only the rendered content of each page, without a template.
+    pub fn fragment(mut self, fragment: bool) -> Self {
+        self.args.fragment = fragment;
+        self
+    }
+
+    /// Hardens the build for untrusted content, like `--sandbox`.
+    pub fn sandbox(mut self, sandbox: bool) -> Self {
+        self.args.sandbox = sandbox;
+        self
+    }
+
+    /// Includes draft pages and files, like `--drafts`.
+    pub fn drafts(mut self, drafts: bool) -> Self {
+        self.args.drafts = drafts;
+        self
+    }
+
+    /// Skips rendering pages and writing files that haven't changed since the previous build.
+    pub fn incremental(mut self, incremental: bool) -> Self {
+        self.args.incremental = incremental;
+        self
+    }
+
+    /// Renders at most `jobs` pages at once, like `--jobs`.
+    pub fn jobs(mut self, jobs: NonZeroUsize) -> Self {
+        self.args.jobs = Some(jobs);
+        self
+    }
+
+    /// Reads, writes, and copies at most `io_jobs` files at once, like `--io-jobs`.
+    pub fn io_jobs(mut self, io_jobs: NonZeroUsize) -> Self {
+        self.args.io_jobs = Some(io_jobs);
+        self
+    }
+
+    /// Generates the site, returning what was written.
+    pub fn generate_site(self) -> anyhow::Result<BuildReport> {
+        run_program_with(self.args, &vfs::RealFs)
+    }
+
+    /// Generates the site from and into `fs` instead of the disk, e.g. a [`vfs::MemoryFs`].
+    pub fn generate_site_with(self, fs: &dyn vfs::FileSystem) -> anyhow::Result<BuildReport> {
+        run_program_with(self.args, fs)
+    }
+
+    /// Runs only the first pass of an unversioned site: renders its pages without writing them,
+    /// and copies its other files to the output directory.
+    pub fn first_pass(mut self) -> anyhow::Result<Vec<FirstPassResult>> {
+        let fs = &vfs::RealFs;
+        let (target_path, output_path) = site_paths(&mut self.args, fs)?;
+        let context = SiteContext::new(&target_path, &output_path, &self.args, fs)?;
+        first_pass(&target_path, &context)
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    sync::Arc,
+};
+
+use clap::Parser;
+use walkdir::WalkDir;
+
+/// Marks the end of an executable with a site appended to it.
+const BUNDLE_MAGIC: &[u8; 8] = b"SSGBUNDL";
+
+/// The files of a bundled site, by their path from the site root (e.g. `guides/index.html`).
+pub type BundleFiles = HashMap<String, Vec<u8>>;
+
+/// Options for running a bundled site.
+#[derive(Parser, Debug)]
+#[command(about = "Serves the site bundled into this executable")]
+struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    address: String,
+    #[arg(short, long, default_value_t = 8080)]
+    port: u16,
+}
+
+/// Writes a copy of this executable to `executable`, with every file in `site` (a generated
+/// site) appended to it. Running the copy serves the site instead of generating one.
+pub fn write_bundle(site: &Path, executable: &Path) -> anyhow::Result<()> {
+    if !site.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Site {} is not a directory. Generate the site first.",
+            site.display()
+        ));
+    }
+    let mut archive = Vec::new();
+    let mut count = 0;
+    for entry in WalkDir::new(site).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(site)?;
+        let name = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let contents = std::fs::read(entry.path())?;
+        log::trace!("Bundling {:?} ({} bytes)", &name, contents.len());
+        archive.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(&(contents.len() as u64).to_le_bytes());
+        archive.extend_from_slice(&contents);
+        count += 1;
+    }
+
+    let current = std::env::current_exe()?;
+    // Only the executable itself is copied, in case it is a bundle already
+    let stub_length = match find_bundle(&current)? {
+        Some((start, _)) => start,
+        None => std::fs::metadata(&current)?.len(),
+    };
+    let mut stub = Vec::new();
+    File::open(&current)?
+        .take(stub_length)
+        .read_to_end(&mut stub)?;
+    let mut output = File::create(executable)?;
+    output.write_all(&stub)?;
+    output.write_all(&archive)?;
+    output.write_all(&(archive.len() as u64).to_le_bytes())?;
+    output.write_all(BUNDLE_MAGIC)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        output.set_permissions(std::fs::Permissions::from_mode(0o755))?;
+    }
+    log::info!(
+        "Bundled {} files from {:?} into {:?}",
+        count,
+        site,
+        executable
+    );
+    Ok(())
+}
+
+/// Reads the site bundled into this executable, if there is one.
+pub fn read_bundle() -> anyhow::Result<Option<BundleFiles>> {
+    let current = std::env::current_exe()?;
+    let Some((start, length)) = find_bundle(&current)? else {
+        return Ok(None);
+    };
+    let mut archive = Vec::new();
+    let mut file = File::open(&current)?;
+    file.seek(SeekFrom::Start(start))?;
+    file.take(length).read_to_end(&mut archive)?;
+
+    let mut files = BundleFiles::new();
+    let mut rest = &archive[..];
+    while !rest.is_empty() {
+        let name_length = u32::from_le_bytes(take(&mut rest, 4)?.try_into()?) as usize;
+        let name = String::from_utf8(take(&mut rest, name_length)?.to_vec())?;
+        let contents_length = u64::from_le_bytes(take(&mut rest, 8)?.try_into()?) as usize;
+        files.insert(name, take(&mut rest, contents_length)?.to_vec());
+    }
+    Ok(Some(files))
+}
+
+/// Serves `files` over http, with options read from the command line.
+pub fn serve_bundle(files: BundleFiles) -> anyhow::Result<()> {
+    let args = ServeArgs::parse();
+    let listener = TcpListener::bind((args.address.as_str(), args.port))?;
+    println!("Serving on http://{}", listener.local_addr()?);
+    serve(listener, move |path| files.get(path).cloned());
+    Ok(())
+}
+
+/// Serves the files that `read` finds by their path from the site root (e.g.
+/// `guides/index.html`) to each connection to `listener`.
+pub fn serve(
+    listener: TcpListener,
+    read: impl Fn(&str) -> Option<Vec<u8>> + Send + Sync + 'static,
+) {
+    let read = Arc::new(read);
+    for stream in listener.incoming() {
+        let read = Arc::clone(&read);
+        match stream {
+            Ok(stream) => {
+                std::thread::spawn(move || {
+                    if let Err(e) = handle_request(stream, &*read) {
+                        log::warn!("Could not handle request: {}", e);
+                    }
+                });
+            }
+            Err(e) => log::warn!("Could not accept connection: {}", e),
+        }
+    }
+}
+
+/// Finds the start and length of the site appended to `executable`, if there is one.
+fn find_bundle(executable: &Path) -> anyhow::Result<Option<(u64, u64)>> {
+    let mut file = File::open(executable)?;
+    let size = file.metadata()?.len();
+    if size < 16 {
+        return Ok(None);
+    }
+    let mut trailer = [0u8; 16];
+    file.seek(SeekFrom::End(-16))?;
+    file.read_exact(&mut trailer)?;
+    if &trailer[8..] != BUNDLE_MAGIC {
+        return Ok(None);
+    }
+    let length = u64::from_le_bytes(trailer[..8].try_into()?);
+    match (size - 16).checked_sub(length) {
+        Some(start) => Ok(Some((start, length))),
+        None => Err(anyhow::anyhow!("Bundled site is corrupt")),
+    }
+}
+
+fn take<'a>(rest: &mut &'a [u8], length: usize) -> anyhow::Result<&'a [u8]> {
+    if rest.len() < length {
+        return Err(anyhow::anyhow!("Bundled site is corrupt"));
+    }
+    let (taken, remaining) = rest.split_at(length);
+    *rest = remaining;
+    Ok(taken)
+}
+
+fn handle_request(
+    mut stream: TcpStream,
+    read: &dyn Fn(&str) -> Option<Vec<u8>>,
+) -> anyhow::Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or("/"));
+    log::debug!("{} {}", method, target);
+    if method != "GET" && method != "HEAD" {
+        return respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"",
+            true,
+        );
+    }
+    let path = percent_decode(target.split(['?', '#']).next().unwrap_or("/"));
+    let path = path.trim_start_matches('/');
+    let (name, found) = if path.is_empty() || path.ends_with('/') {
+        let name = format!("{}index.html", path);
+        let found = read(&name);
+        (name, found)
+    } else {
+        match read(path) {
+            None if read(&format!("{}/index.html", path)).is_some() => {
+                // Redirect to the directory, so relative links on its index page work
+                write!(
+                    stream,
+                    "HTTP/1.1 301 Moved Permanently\r\nLocation: /{}/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    path
+                )?;
+                return Ok(());
+            }
+            found => (path.to_string(), found),
+        }
+    };
+    match found {
+        Some(contents) => respond(
+            &mut stream,
+            "200 OK",
+            content_type(&name),
+            &contents,
+            method == "GET",
+        ),
+        None => respond(
+            &mut stream,
+            "404 Not Found",
+            "text/plain",
+            b"Not found",
+            method == "GET",
+        ),
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+    with_body: bool,
+) -> anyhow::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    if with_body {
+        stream.write_all(body)?;
+    }
+    Ok(())
+}
+
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn content_type(name: &str) -> &'static str {
+    match name.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("json") => "application/json",
+        Some("xml") => "application/xml",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("woff2") => "font/woff2",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::TextDirection,
+    metadata::{Date, PageTemplate},
+    vfs::FileSystem,
+    webmention, FirstPassResult, PageLayout, SiteContext,
+};
+
+/// The cache of an incremental build, in the output directory.
+pub const CACHE_FILE: &str = ".ssg-cache.json";
+
+/// What an incremental build kept from the previous build.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheData {
+    /// The hash of everything besides its own source that a page's rendering depends on
+    site: String,
+    /// The rendered pages, by their sources (relative to the target directory)
+    pages: BTreeMap<PathBuf, CachedPage>,
+    /// The hash of each file written to the output directory
+    outputs: BTreeMap<PathBuf, String>,
+}
+
+/// A page's first pass result and the warnings rendering it gave, and the hash of the source it
+/// was rendered from.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CachedPage {
+    hash: String,
+    #[serde(default)]
+    warnings: Vec<String>,
+    depth: usize,
+    html: String,
+    relative_path: PathBuf,
+    unlisted: bool,
+    id: Option<String>,
+    lite_content: Option<String>,
+    title: Option<String>,
+    date: Option<String>,
+    source: PathBuf,
+    lang: Option<String>,
+    dir: Option<TextDirection>,
+    template: Option<PageTemplate>,
+    tags: Vec<String>,
+    event_date: Option<String>,
+    event_end: Option<String>,
+    event_location: Option<String>,
+}
+
+/// The previous build's cache, read at the start of an incremental build, and the cache of this
+/// build, written at its end.
+#[derive(Debug, Default)]
+pub struct BuildCache {
+    previous: CacheData,
+    next: Mutex<CacheData>,
+}
+
+impl BuildCache {
+    /// Reads the cache of the previous build into `output_path`, starting over if there isn't
+    /// one (or it can't be read).
+    pub fn read(fs: &dyn FileSystem, output_path: &Path) -> Self {
+        let path = output_path.join(CACHE_FILE);
+        let previous = match fs.read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents).unwrap_or_else(|e| {
+                log::warn!(
+                    "Could not read the build cache {:?}, rebuilding: {}",
+                    &path,
+                    e
+                );
+                CacheData::default()
+            }),
+            Err(_) => CacheData::default(),
+        };
+        Self {
+            previous,
+            next: Mutex::default(),
+        }
+    }
+
+    /// Sets the hash of the site's other inputs (see `site_hash`). Cached pages are only used if
+    /// it's the same as the previous build's.
+    pub fn set_site_hash(&self, site: String) {
+        self.next.lock().unwrap().site = site;
+    }
+
+    /// The cached result of the page at `source` and the warnings rendering it gave, if it was
+    /// rendered from the same source (hashed as `hash`) in a site that hasn't changed otherwise.
+    pub fn page(&self, source: &Path, hash: &str) -> Option<(FirstPassResult, Vec<String>)> {
+        let mut next = self.next.lock().unwrap();
+        if next.site != self.previous.site {
+            return None;
+        }
+        let page = self
+            .previous
+            .pages
+            .get(source)
+            .filter(|page| page.hash == hash)?;
+        next.pages.insert(source.to_path_buf(), page.clone());
+        let page = page.clone();
+        let result = FirstPassResult::HtmlOutput {
+            depth: page.depth,
+            html: page.html,
+            relative_path: page.relative_path,
+            unlisted: page.unlisted,
+            in_toc: true,
+            id: page.id,
+            lite_content: page.lite_content,
+            title: page.title,
+            date: page.date.as_deref().map(parse_date),
+            layout: Box::new(PageLayout {
+                source: page.source,
+                lang: page.lang,
+                dir: page.dir,
+                template: page.template,
+                tags: page.tags,
+                event_date: page.event_date.as_deref().map(parse_date),
+                event_end: page.event_end.as_deref().map(parse_date),
+                event_location: page.event_location,
+            }),
+        };
+        Some((result, page.warnings))
+    }
+
+    /// Caches `result`, the page rendered from `source` (hashed as `hash`), with the `warnings`
+    /// rendering it gave.
+    pub fn store_page(
+        &self,
+        source: &Path,
+        hash: String,
+        result: &FirstPassResult,
+        warnings: Vec<String>,
+    ) {
+        let FirstPassResult::HtmlOutput {
+            depth,
+            html,
+            relative_path,
+            unlisted,
+            id,
+            lite_content,
+            title,
+            date,
+            layout,
+            ..
+        } = result.clone()
+        else {
+            return;
+        };
+        let layout = *layout;
+        self.next.lock().unwrap().pages.insert(
+            source.to_path_buf(),
+            CachedPage {
+                hash,
+                warnings,
+                depth,
+                html,
+                relative_path,
+                unlisted,
+                id,
+                lite_content,
+                title,
+                date: date.as_ref().map(ToString::to_string),
+                source: layout.source,
+                lang: layout.lang,
+                dir: layout.dir,
+                template: layout.template,
+                tags: layout.tags,
+                event_date: layout.event_date.as_ref().map(ToString::to_string),
+                event_end: layout.event_end.as_ref().map(ToString::to_string),
+                event_location: layout.event_location,
+            },
+        );
+    }
+
+    /// Records the output `contents` written to `path`, returning whether the previous build
+    /// wrote the same contents there.
+    pub fn is_unchanged_output(&self, path: &Path, contents: &[u8]) -> bool {
+        let hash = hash(contents);
+        let unchanged = self.previous.outputs.get(path) == Some(&hash);
+        self.next
+            .lock()
+            .unwrap()
+            .outputs
+            .insert(path.to_path_buf(), hash);
+        unchanged
+    }
+
+    /// Writes this build's cache to `output_path`, for the next build.
+    pub fn save(&self, fs: &dyn FileSystem, output_path: &Path) -> anyhow::Result<()> {
+        let path = output_path.join(CACHE_FILE);
+        log::debug!("Writing the build cache to {:?}", &path);
+        fs.write(&path, &serde_json::to_vec(&*self.next.lock().unwrap())?)?;
+        Ok(())
+    }
+}
+
+/// The sha256 hash of `contents`, in hex.
+pub fn hash(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+/// Hashes what a page's rendering can depend on besides its own source: the site's
+/// configuration, the generator's version, and the site's other files (`paths`, outside the
+/// output directory) by their paths, along with the sizes and modification times of those that
+/// aren't pages, such as the data files of shortcodes.
+pub fn site_hash(context: &SiteContext, paths: &[(PathBuf, usize)]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION"));
+    hasher.update(format!(
+        "{:?}{:?}{}{}{}",
+        context.config, context.web_prefix, context.sandbox, context.fragment, context.drafts
+    ));
+    for (path, _) in paths {
+        let relative = path.strip_prefix(context.target_path).unwrap_or(path);
+        // Written after each build that sends webmentions
+        if path.starts_with(context.output_path) || relative == Path::new(webmention::SENT_FILE) {
+            continue;
+        }
+        hasher.update(relative.to_string_lossy().as_bytes());
+        let is_page = path
+            .extension()
+            .is_some_and(|ext| ext == "dj" || ext == "djot" || ext == "md");
+        if !is_page && context.fs.is_file(path) {
+            let modified = context.fs.modified(path).ok();
+            hasher.update(format!("{:?}{:?}", context.fs.len(path).ok(), modified));
+        }
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Reads a date written by `Date`'s `Display`.
+fn parse_date(text: &str) -> Date {
+    if let Ok(datetime) = text.parse() {
+        Date::Datetime(datetime)
+    } else if let Ok(year) = text.parse() {
+        Date::Year(year)
+    } else {
+        Date::Text(text.to_string())
+    }
+}
@@ -0,0 +1,284 @@
+use std::path::{Path, PathBuf};
+
+use time::{PrimitiveDateTime, UtcOffset};
+
+use crate::{metadata::Date, templating, utils, FirstPassResult, SiteContext};
+
+/// The generated calendar of events, at the root of the output directory.
+pub const CALENDAR_PAGE: &str = "calendar.html";
+/// The iCalendar feed of events, for calendar apps to subscribe to, at the root of the output
+/// directory.
+pub const CALENDAR_FEED: &str = "events.ics";
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// A page with an `event_date`.
+struct Event {
+    title: String,
+    /// The page, relative to the output directory
+    path: PathBuf,
+    start: EventTime,
+    /// The `event_end`, if the page has one
+    end: Option<EventTime>,
+    location: Option<String>,
+}
+
+/// When an event starts or ends.
+#[derive(Clone, Copy)]
+struct EventTime {
+    date: time::Date,
+    /// The time of day, if it has one
+    time: Option<time::Time>,
+    /// The offset of `time`, unless it's a local time
+    offset: Option<UtcOffset>,
+}
+
+impl EventTime {
+    fn from_date(date: &Date) -> Option<Self> {
+        let datetime = match date {
+            Date::Datetime(datetime) => *datetime,
+            Date::Text(text) => text.parse().ok()?,
+            Date::Year(_) => return None,
+        };
+        let time = datetime
+            .time
+            .and_then(|time| time::Time::from_hms(time.hour, time.minute, time.second).ok());
+        let offset = match datetime.offset {
+            Some(toml::value::Offset::Z) => Some(UtcOffset::UTC),
+            Some(toml::value::Offset::Custom { minutes }) => {
+                UtcOffset::from_whole_seconds(i32::from(minutes) * 60).ok()
+            }
+            None => None,
+        };
+        Some(Self {
+            date: date.to_date()?,
+            time,
+            offset,
+        })
+    }
+
+    /// Formats the time as the value of an iCalendar `DTSTART` or `DTEND` property named
+    /// `name`: in UTC if it has an offset, or else in the reader's local time, or as a date if
+    /// it has no time of day.
+    fn to_ics(self, name: &str) -> String {
+        let compact = |date: time::Date| date.to_string().replace('-', "");
+        match (self.time, self.offset) {
+            (Some(time), Some(offset)) => {
+                let utc = PrimitiveDateTime::new(self.date, time)
+                    .assume_offset(offset)
+                    .to_offset(UtcOffset::UTC);
+                format!(
+                    "{}:{}T{}Z",
+                    name,
+                    compact(utc.date()),
+                    format_time(utc.time())
+                )
+            }
+            (Some(time), None) => format!("{}:{}T{}", name, compact(self.date), format_time(time)),
+            (None, _) => format!("{};VALUE=DATE:{}", name, compact(self.date)),
+        }
+    }
+}
+
+/// Whether `date` is a date (or a date and time) that an event can start or end at.
+pub fn is_event_time(date: &Date) -> bool {
+    EventTime::from_date(date).is_some()
+}
+
+/// The listed pages with an `event_date`, earliest first.
+fn events(results: &[FirstPassResult]) -> Vec<Event> {
+    let titles = templating::site_pages(results);
+    let mut events = results
+        .iter()
+        .filter_map(|result| match result {
+            FirstPassResult::HtmlOutput {
+                relative_path,
+                unlisted: false,
+                layout,
+                ..
+            } => Some((relative_path, layout)),
+            _ => None,
+        })
+        .filter_map(|(relative_path, layout)| {
+            let start = EventTime::from_date(layout.event_date.as_ref()?)?;
+            let path = relative_path.to_string_lossy().replace('\\', "/");
+            let end = layout.event_end.as_ref().and_then(EventTime::from_date);
+            Some(Event {
+                title: titles
+                    .iter()
+                    .find(|page| page.path == path)
+                    .and_then(|page| page.title.clone())
+                    .unwrap_or(path),
+                path: relative_path.clone(),
+                start,
+                end,
+                location: layout.event_location.clone(),
+            })
+        })
+        .collect::<Vec<_>>();
+    events.sort_by_key(|event| (event.start.date, event.start.time));
+    events
+}
+
+/// Renders the calendar page: a month grid, starting on Mondays, for each month with events,
+/// with links to their pages. Returns `None` if no page has an `event_date`.
+pub fn calendar_html(results: &[FirstPassResult], context: &SiteContext) -> Option<String> {
+    let events = events(results);
+    let mut months = events
+        .iter()
+        .map(|event| (event.start.date.year(), event.start.date.month()))
+        .collect::<Vec<_>>();
+    months.dedup();
+    if months.is_empty() {
+        return None;
+    }
+    let mut html = format!(
+        "<h1>Calendar</h1>\n<p class=\"calendar-subscribe\"><a href=\"{}\">Subscribe to these events</a></p>\n",
+        utils::page_url(1, context.web_prefix, Path::new(CALENDAR_FEED))
+    );
+    for (year, month) in months {
+        html.push_str(&format!(
+            "<section class=\"calendar-month\">\n<h2>{} {}</h2>\n<table class=\"calendar\">\n<thead>\n<tr>",
+            month, year
+        ));
+        for weekday in WEEKDAYS {
+            html.push_str(&format!("<th>{}</th>", weekday));
+        }
+        html.push_str("</tr>\n</thead>\n<tbody>\n<tr>");
+        let first = time::Date::from_calendar_date(year, month, 1).unwrap();
+        let blanks = first.weekday().number_days_from_monday();
+        html.push_str(&"<td></td>".repeat(blanks.into()));
+        let mut day = Some(first);
+        while let Some(date) = day.filter(|date| date.month() == month) {
+            if date != first && date.weekday() == time::Weekday::Monday {
+                html.push_str("</tr>\n<tr>");
+            }
+            html.push_str(&format!(
+                "<td><span class=\"calendar-day\">{}</span>",
+                date.day()
+            ));
+            let day_events = events
+                .iter()
+                .filter(|event| event.start.date == date)
+                .map(|event| {
+                    format!(
+                        "<li><a href=\"{}\">{}</a></li>",
+                        utils::page_url(1, context.web_prefix, &event.path),
+                        utils::escape_html(&event.title)
+                    )
+                })
+                .collect::<String>();
+            if !day_events.is_empty() {
+                html.push_str(&format!("<ul>{}</ul>", day_events));
+            }
+            html.push_str("</td>");
+            day = date.next_day();
+        }
+        let last = day.and_then(time::Date::previous_day).unwrap_or(first);
+        let trailing = 6 - last.weekday().number_days_from_monday();
+        html.push_str(&"<td></td>".repeat(trailing.into()));
+        html.push_str("</tr>\n</tbody>\n</table>\n</section>\n");
+    }
+    Some(html)
+}
+
+/// Writes an iCalendar feed of the events to `output_path`, if any page has an `event_date`.
+/// Their urls are absolute with the sitemap's `base_url`, when it's set.
+pub fn generate_ics(
+    context: &SiteContext,
+    results: &[FirstPassResult],
+    output_path: &Path,
+) -> anyhow::Result<()> {
+    let events = events(results);
+    if events.is_empty() {
+        return Ok(());
+    }
+    let base_url = context
+        .config
+        .sitemap
+        .base_url
+        .as_deref()
+        .map(|base_url| base_url.trim_end_matches('/'));
+    let mut ics = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//simple-ssg//Calendar//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+    ];
+    if let Some(title) = &context.config.title {
+        ics.push(format!("X-WR-CALNAME:{}", escape_text(title)));
+    }
+    for event in &events {
+        let path = event.path.to_string_lossy().replace('\\', "/");
+        ics.push("BEGIN:VEVENT".to_string());
+        let url = base_url.map(|base_url| format!("{}/{}", base_url, path));
+        ics.push(format!(
+            "UID:{}",
+            escape_text(url.as_deref().unwrap_or(&path))
+        ));
+        // Stamped with the event's own date, so the feed only changes when the events do
+        ics.push(format!(
+            "DTSTAMP:{}T000000Z",
+            event.start.date.to_string().replace('-', "")
+        ));
+        ics.push(event.start.to_ics("DTSTART"));
+        if let Some(end) = event.end {
+            // The end of an all day event is the day after its last day
+            let end = match end.time {
+                None => EventTime {
+                    date: end.date.next_day().unwrap_or(end.date),
+                    ..end
+                },
+                Some(_) => end,
+            };
+            ics.push(end.to_ics("DTEND"));
+        }
+        ics.push(format!("SUMMARY:{}", escape_text(&event.title)));
+        if let Some(location) = &event.location {
+            ics.push(format!("LOCATION:{}", escape_text(location)));
+        }
+        if let Some(url) = url {
+            ics.push(format!("URL:{}", url));
+        }
+        ics.push("END:VEVENT".to_string());
+    }
+    ics.push("END:VCALENDAR".to_string());
+    let ics = ics
+        .iter()
+        .map(|line| fold_line(line))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+        + "\r\n";
+    log::debug!("Writing calendar feed of {} event(s)", events.len());
+    context.write_output(&output_path.join(CALENDAR_FEED), ics.as_bytes())?;
+    Ok(())
+}
+
+/// Formats `time` as iCalendar does, e.g. `190000`.
+fn format_time(time: time::Time) -> String {
+    format!("{:02}{:02}{:02}", time.hour(), time.minute(), time.second())
+}
+
+/// Escapes the characters iCalendar reads as separators in a text value.
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Folds a content line longer than 75 bytes onto continuation lines, which start with a space.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
@@ -0,0 +1,210 @@
+use std::path::Path;
+
+use crate::{errors::SsgError, markdown_to_html, slug, utils, SiteContext};
+
+/// A single release, read from a changelog section or a git tag.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Release {
+    pub version: String,
+    /// The release date as `YYYY-MM-DD`, if known
+    pub date: Option<String>,
+    /// Release notes, as markdown
+    pub notes: String,
+}
+
+impl Release {
+    /// The anchor for this release on the releases page.
+    pub fn id(&self) -> String {
+        self.version
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                    c.to_ascii_lowercase()
+                } else {
+                    '-'
+                }
+            })
+            .collect()
+    }
+}
+
+/// Renders the releases from `source` (a changelog path relative to `page`, or `git` for the
+/// repository's tags) to html, and writes an rss feed of them next to `page_html`.
+pub fn generate_releases(
+    source: &str,
+    page: &Path,
+    page_html: &Path,
+    context: &SiteContext,
+) -> anyhow::Result<String> {
+    let page_dir = page.parent().unwrap();
+    let (releases, notes_dir) = if source == "git" {
+        (get_git_tag_releases(page_dir)?, page_dir.to_path_buf())
+    } else {
+        let changelog_path = page_dir.join(source);
+        if context.sandbox
+            && !crate::sandbox::is_inside(context.fs, context.target_path, &changelog_path)
+        {
+            context.warn(SsgError::OutsideTarget(changelog_path));
+            return Ok(String::new());
+        } else if !utils::is_within(context.target_path, &changelog_path) {
+            context.warn(format!(
+                "Changelog {:?} of {:?} is outside the target directory",
+                source, page
+            ));
+        }
+        log::trace!("Reading changelog from {:?}", &changelog_path);
+        let changelog = context.fs.read_to_string(&changelog_path)?;
+        (
+            parse_changelog(&changelog),
+            changelog_path.parent().unwrap().to_path_buf(),
+        )
+    };
+
+    let page_url = format!(
+        "{}{}",
+        context.web_prefix.unwrap_or(""),
+        page_html.to_string_lossy()
+    );
+    let mut html = String::new();
+    let mut feed = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\">\n<channel>\n<title>Releases</title>\n<link>{}</link>\n<description>Releases</description>\n",
+        utils::escape_html(&page_url)
+    );
+    for release in &releases {
+        let id = match &context.config.slugs {
+            Some(slugs) => slug::slugify(&release.version, slugs),
+            None => release.id(),
+        };
+        let notes = markdown_to_html(&release.notes, &notes_dir, context)?;
+        let version = utils::escape_html(&release.version);
+        html.push_str(&format!(
+            "<section class=\"release\" id=\"{}\">\n<h2><a href=\"#{}\">{}</a></h2>\n",
+            id, id, version
+        ));
+        if let Some(date) = &release.date {
+            html.push_str(&format!(
+                "<p class=\"release-date\"><time datetime=\"{}\">{}</time></p>\n",
+                date, date
+            ));
+        }
+        html.push_str(&notes);
+        html.push_str("</section>\n");
+
+        let link = utils::escape_html(&format!("{}#{}", page_url, id));
+        feed.push_str(&format!(
+            "<item>\n<title>{}</title>\n<link>{}</link>\n<guid>{}</guid>\n",
+            version, link, link
+        ));
+        if let Some(pub_date) = release.date.as_deref().and_then(rfc822_date) {
+            feed.push_str(&format!("<pubDate>{}</pubDate>\n", pub_date));
+        }
+        feed.push_str(&format!(
+            "<description>{}</description>\n</item>\n",
+            utils::escape_html(&notes)
+        ));
+    }
+    feed.push_str("</channel>\n</rss>\n");
+
+    let feed_path = context.output_path.join(page_html).with_extension("xml");
+    log::debug!("Writing releases feed to {:?}", &feed_path);
+    context.write_output(&feed_path, feed.as_bytes())?;
+    Ok(html)
+}
+
+/// Splits a changelog into releases at each level 2 heading, e.g. `## [1.0.0] - 2024-01-31`.
+///
+/// Anything before the first release (such as the changelog's title) is skipped.
+pub fn parse_changelog(changelog: &str) -> Vec<Release> {
+    let mut releases: Vec<Release> = Vec::new();
+    let mut fence: Option<&str> = None;
+    for line in changelog.lines() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") {
+            fence = Some("```");
+        } else if trimmed.starts_with("~~~") {
+            fence = Some("~~~");
+        } else if let Some(heading) = line.strip_prefix("## ") {
+            let (version, date) = match heading.split_once(" - ") {
+                Some((version, date)) => (version, Some(date.trim().to_string())),
+                None => (heading, None),
+            };
+            // Keep a Changelog style versions link to their diff, e.g. `[1.0.0]`
+            let version = version.trim();
+            let version = version
+                .strip_prefix('[')
+                .and_then(|version| version.split_once(']'))
+                .map_or(version, |(version, _)| version);
+            releases.push(Release {
+                version: version.to_string(),
+                date,
+                notes: String::new(),
+            });
+            continue;
+        }
+        if let Some(release) = releases.last_mut() {
+            release.notes.push_str(line);
+            release.notes.push('\n');
+        }
+    }
+    releases
+}
+
+/// Reads the tags of the git repository containing `dir`, newest first. Each tag's message
+/// becomes its release notes.
+pub fn get_git_tag_releases(dir: &Path) -> anyhow::Result<Vec<Release>> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "for-each-ref",
+            "--sort=-creatordate",
+            "--format=%(refname:short)%00%(creatordate:short)%00%(contents)%00",
+            "refs/tags",
+        ])
+        .output()
+        .map_err(|e| SsgError::GitTagsError(dir.to_path_buf(), e.to_string()))?;
+    if !output.status.success() {
+        return Err(SsgError::GitTagsError(
+            dir.to_path_buf(),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )
+        .into());
+    }
+    let output = String::from_utf8_lossy(&output.stdout);
+    Ok(output
+        .split("\0\n")
+        .filter_map(|tag| {
+            let mut fields = tag.splitn(3, '\0');
+            let version = fields.next()?.trim();
+            if version.is_empty() {
+                return None;
+            }
+            Some(Release {
+                version: version.to_string(),
+                date: fields.next().map(str::to_string),
+                notes: fields.next().unwrap_or("").to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Converts a `YYYY-MM-DD` date to the RFC 822 format used by rss.
+fn rfc822_date(date: &str) -> Option<String> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let mut parts = date.splitn(3, '-');
+    let year: u32 = parts.next()?.parse().ok()?;
+    let month: usize = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.get(..2)?.parse().ok()?;
+    Some(format!(
+        "{:02} {} {} 00:00:00 +0000",
+        day,
+        MONTHS.get(month.checked_sub(1)?)?,
+        year
+    ))
+}
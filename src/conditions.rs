@@ -0,0 +1,52 @@
+use jotdown::{Container, Event};
+
+/// Strips djot elements with an `only-<flag>` class (e.g. `{.only-internal}` or
+/// `::: only-internal`) unless one of their flags is enabled.
+pub struct ConditionalFilter<'a> {
+    flags: &'a [String],
+    /// Depth inside the element being stripped, or 0 when not stripping
+    skip_depth: usize,
+}
+
+impl<'a> ConditionalFilter<'a> {
+    pub fn new(flags: &'a [String]) -> Self {
+        Self {
+            flags,
+            skip_depth: 0,
+        }
+    }
+
+    /// Checks if `event` should be kept in the output.
+    pub fn keep(&mut self, event: &Event) -> bool {
+        if self.skip_depth > 0 {
+            match event {
+                Event::Start(..) => self.skip_depth += 1,
+                Event::End(_) => self.skip_depth -= 1,
+                _ => {}
+            }
+            return false;
+        }
+        if let Event::Start(container, attributes) = event {
+            let div_class = match container {
+                Container::Div { class } => *class,
+                _ => "",
+            };
+            let classes = attributes
+                .get_value("class")
+                .map(|value| value.to_string())
+                .unwrap_or_default();
+            let mut required_flags = div_class
+                .split_whitespace()
+                .chain(classes.split_whitespace())
+                .filter_map(|class| class.strip_prefix("only-"))
+                .peekable();
+            if required_flags.peek().is_some()
+                && !required_flags.any(|flag| self.flags.iter().any(|f| f == flag))
+            {
+                self.skip_depth = 1;
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,595 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::SsgError, filters, templates::BuiltInTemplate, vfs::FileSystem};
+
+/// Site-wide configuration, read from an optional `ssg.toml` in the target directory.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SiteConfig {
+    /// The site's title, for `<!-- {SITE_TITLE} -->` and pages without a title of their own
+    pub title: Option<String>,
+    /// The website prefix, unless `--web-prefix` is given
+    pub web_prefix: Option<String>,
+    /// The output directory, relative to the target directory, unless `-o` is given
+    pub output_path: Option<PathBuf>,
+    /// The built in template to use, unless `--template` is given
+    pub template: Option<BuiltInTemplate>,
+    /// Glob patterns (such as `drafts/**` or `*.bak`) of the files and directories to leave out
+    /// of the site, relative to the target directory
+    pub ignore: Vec<String>,
+    /// Additional file stems (besides `index`) that are rendered as a directory's `index.html`
+    pub index_names: Vec<String>,
+    /// Link prefixes (e.g. `@api`) mapped to the base URLs of other sites
+    pub link_prefixes: HashMap<String, String>,
+    pub toc: TocConfig,
+    /// The most characters of a page's title in the table of contents, breadcrumbs, prev/next
+    /// links, and feeds. Longer titles are cut between words and end with `…`
+    pub max_title_length: Option<usize>,
+    /// Format for dates such as `<!-- {LAST_UPDATED} -->`, as a `time` format description
+    /// (defaults to `[year]-[month]-[day]`)
+    pub date_format: Option<String>,
+    pub contributors: ContributorsConfig,
+    /// Values substituted for `{{name}}` in page content
+    pub variables: HashMap<String, String>,
+    /// Enabled flags, which include djot content marked with their `only-<flag>` class
+    pub flags: Vec<String>,
+    pub lint: LintConfig,
+    /// Write redirects from the old urls of pages that were moved or renamed in the git history
+    pub redirect_moved_pages: bool,
+    /// Generate a page for each tag in the pages' front matter under `tags/`, listing its pages,
+    /// and an index of every tag at `tags/index.html`
+    pub tag_pages: bool,
+    /// The most entries on a generated listing page (such as a tag page), with the rest on
+    /// `page/2.html`, `page/3.html`, ... Without it, each listing is a single page
+    pub listing_page_size: Option<usize>,
+    pub sitemap: SitemapConfig,
+    /// Writes rss and atom feeds (`feed.xml` and `atom.xml`) of the most recent pages. Without
+    /// it, no feeds are written
+    pub feed: Option<FeedConfig>,
+    /// Lists of the figures and tables with captions, on each page and for the whole site.
+    /// Without it, they aren't listed
+    pub figures: Option<FiguresConfig>,
+    /// IndieWeb metadata: webmention and IndieAuth links, microformats in the built-in
+    /// templates, and a webfinger stub. Without it, none of them are added
+    pub indieweb: Option<IndieWebConfig>,
+    /// A self-hosted page view beacon, sent by a small script on every page. Without it, pages
+    /// have no script for counting views
+    pub beacon: Option<BeaconConfig>,
+    /// A Content-Security-Policy computed from the scripts, styles, and media that pages use.
+    /// Without it, pages have no Content-Security-Policy
+    pub security: Option<SecurityConfig>,
+    /// Also generate a text-only mirror of the site under `lite/`
+    pub lite: bool,
+    /// Write each page to a directory of its own (`about.dj` to `about/index.html`), and link
+    /// to it as `about/`
+    pub pretty_urls: bool,
+    /// Publish the site as a notes vault: wikilinks, backlinks, and an index of every note
+    pub notes: bool,
+    /// Options for the built-in templates
+    pub template_options: TemplateOptions,
+    /// The language of the site's pages, as a tag such as `en` or `ar`
+    pub lang: Option<String>,
+    /// The text direction of the site's pages (defaults to the direction of `lang`)
+    pub dir: Option<TextDirection>,
+    pub limits: LimitsConfig,
+    /// How heading anchors, page file names, and other generated urls are slugged. Without it,
+    /// page file names are kept, djot generates heading ids, and markdown heading ids are
+    /// slugged with the defaults
+    pub slugs: Option<SlugConfig>,
+    /// Append a `¶` link to its anchor to each heading, for linking to a part of a page
+    pub heading_anchors: bool,
+    pub glossary: GlossaryConfig,
+    pub bibliography: BibliographyConfig,
+    /// Classes (and icons) added to links by kind. Without it, links are left as written
+    pub links: Option<LinksConfig>,
+    /// Highlights the code blocks with a language, with the theme's colors written to
+    /// `highlight.css`. Without it, code blocks are left unhighlighted
+    pub highlight: Option<HighlightConfig>,
+    /// Prebuilt html trees (such as the output of `cargo doc`) copied into the site as they are
+    pub mounts: Vec<MountConfig>,
+    /// Find-and-replace filters applied, in order, to the html of every generated page
+    pub filters: Vec<FilterConfig>,
+}
+
+/// A prebuilt html tree placed in the site without being processed, and linked from the table
+/// of contents.
+#[derive(Clone, Debug, Deserialize)]
+pub struct MountConfig {
+    /// Where the tree is placed in the site, e.g. `api`
+    pub path: PathBuf,
+    /// The tree, relative to the target directory, e.g. `../target/doc`
+    pub source: PathBuf,
+    /// The page the table of contents links to, relative to `path` (defaults to `index.html`)
+    pub index: Option<PathBuf>,
+    /// The label of the link in the table of contents (defaults to the last part of `path`)
+    pub title: Option<String>,
+}
+
+/// A find-and-replace filter for the html of generated pages, such as rewriting an old domain.
+#[derive(Clone, Debug, Deserialize)]
+pub struct FilterConfig {
+    /// The text to find, or a regular expression with `regex`
+    pub find: String,
+    /// The text to replace it with. With `regex`, `$1` or `${name}` is replaced with a group
+    pub replace: String,
+    #[serde(default)]
+    pub regex: bool,
+    /// Glob patterns (such as `blog/**`) of the pages to apply the filter to, relative to the
+    /// output directory. Without them, it applies to every page
+    #[serde(default)]
+    pub paths: Vec<String>,
+}
+
+/// Markup used by the table of contents generator.
+///
+/// Each template may use `{NAME}`, and item templates may also use `{URL}`. The active item
+/// template (the current page) and the open folder template (folders containing the current
+/// page) may use `{CLASS}`, which is replaced with `active_class` or `open_class` respectively.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct TocConfig {
+    pub item_template: String,
+    pub active_item_template: String,
+    pub folder_template: String,
+    pub open_folder_template: String,
+    pub active_class: String,
+    pub open_class: String,
+    /// Number each folder and page (`1`, `1.1`, `1.1.1`, ...)
+    pub numbered: bool,
+    /// Number the headings of each page, continuing from the page's number if `numbered`
+    pub number_headings: bool,
+}
+
+impl Default for TocConfig {
+    fn default() -> Self {
+        Self {
+            item_template: "<li><a href=\"{URL}\">{NAME}</a></li>".to_string(),
+            active_item_template: "<li class=\"{CLASS}\"><b>{NAME}</b></li>".to_string(),
+            folder_template: "<li><b><u>{NAME}</u></b></li>".to_string(),
+            open_folder_template: "<li class=\"{CLASS}\"><b><u>{NAME}</u></b></li>".to_string(),
+            active_class: "active".to_string(),
+            open_class: "open".to_string(),
+            numbered: false,
+            number_headings: false,
+        }
+    }
+}
+
+/// Markup used for the `<!-- {CONTRIBUTORS} -->` list.
+///
+/// The item template may use `{NAME}`, `{EMAIL}`, and `{AVATAR}`, which is `avatar_url` with its
+/// own `{NAME}` and `{EMAIL}` replaced.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ContributorsConfig {
+    pub item_template: String,
+    pub separator: String,
+    pub avatar_url: String,
+}
+
+impl Default for ContributorsConfig {
+    fn default() -> Self {
+        Self {
+            item_template: "{NAME}".to_string(),
+            separator: ", ".to_string(),
+            avatar_url: String::new(),
+        }
+    }
+}
+
+/// Options for the glossary generated from a site's `_glossary.dj`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct GlossaryConfig {
+    /// Link the first use of each term on a page to its glossary entry
+    pub link_terms: bool,
+}
+
+/// A bibliography that pages can cite with `[@key]`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct BibliographyConfig {
+    /// A BibTeX (`.bib`) or CSL-JSON (`.json`) file, relative to the target directory
+    pub path: Option<PathBuf>,
+    pub style: CitationStyle,
+}
+
+/// How citations are rendered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CitationStyle {
+    /// `(Knuth 1984)`, with references sorted by author
+    #[default]
+    AuthorDate,
+    /// `[1]`, with references numbered in the order they're cited
+    Numeric,
+}
+
+/// The syntect themes of highlighted code, such as `InspiredGitHub` or `base16-ocean.dark`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct HighlightConfig {
+    pub theme: String,
+    /// Used instead of `theme` for readers who prefer a dark color scheme
+    pub dark_theme: Option<String>,
+}
+
+impl Default for HighlightConfig {
+    fn default() -> Self {
+        Self {
+            theme: "InspiredGitHub".to_string(),
+            dark_theme: None,
+        }
+    }
+}
+
+/// Classes and icons added to the links in page content: internal links, external links (with
+/// a scheme, or protocol-relative), and downloads (by extension). Empty classes and icons are
+/// left out.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct LinksConfig {
+    pub internal_class: String,
+    pub external_class: String,
+    pub download_class: String,
+    /// Extensions (without the `.`) of files linked as downloads
+    pub download_extensions: Vec<String>,
+    /// Html placed at the end of each external link's content, e.g. `<span>↗</span>`
+    pub external_icon: String,
+    /// Html placed at the end of each download link's content
+    pub download_icon: String,
+}
+
+impl Default for LinksConfig {
+    fn default() -> Self {
+        Self {
+            internal_class: "internal".to_string(),
+            external_class: "external".to_string(),
+            download_class: "download".to_string(),
+            download_extensions: [
+                "pdf", "zip", "gz", "tgz", "xz", "bz2", "7z", "tar", "dmg", "exe", "msi", "deb",
+                "rpm", "apk", "epub",
+            ]
+            .iter()
+            .map(|ext| ext.to_string())
+            .collect(),
+            external_icon: String::new(),
+            download_icon: String::new(),
+        }
+    }
+}
+
+/// Content lint rules, reported as warnings while generating the site.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LintConfig {
+    /// Every page must have exactly one level 1 heading
+    pub single_h1: bool,
+    /// The deepest heading level allowed
+    pub max_heading_depth: Option<u16>,
+    /// Links must describe where they go: not "click here" or a bare url, and not the same text
+    /// as a link to somewhere else on the page
+    pub link_text: bool,
+    pub required_sections: Vec<RequiredSections>,
+}
+
+/// Headings that every page under `path` (relative to the target directory) must have.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RequiredSections {
+    pub path: PathBuf,
+    pub headings: Vec<String>,
+}
+
+/// Sitemap generation, enabled by setting `base_url`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SitemapConfig {
+    /// The absolute url of the site, e.g. `https://example.com/docs/`
+    pub base_url: Option<String>,
+    /// Pages per sitemap file before it is split (at most 50,000)
+    pub max_urls: usize,
+}
+
+impl Default for SitemapConfig {
+    fn default() -> Self {
+        Self {
+            base_url: None,
+            max_urls: crate::sitemap::MAX_SITEMAP_URLS,
+        }
+    }
+}
+
+/// Options for the lists of figures and tables.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct FiguresConfig {
+    /// Generate `figures.html` and `tables.html`, listing the figures and tables of every
+    /// listed page
+    pub list_pages: bool,
+}
+
+impl Default for FiguresConfig {
+    fn default() -> Self {
+        Self { list_pages: true }
+    }
+}
+
+/// Options for the site's rss and atom feeds.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct FeedConfig {
+    /// The feeds' title (defaults to the site's `title`)
+    pub title: Option<String>,
+    /// The absolute url of the site, which the feeds link to (defaults to `sitemap.base_url`)
+    pub base_url: Option<String>,
+    /// The author of the pages, named in the atom feed
+    pub author: Option<String>,
+    /// Only pages under this directory (such as `posts`) are in the feeds (defaults to every
+    /// page)
+    pub path: Option<PathBuf>,
+    /// The most pages in the feeds, newest first
+    pub max_items: usize,
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        Self {
+            title: None,
+            base_url: None,
+            author: None,
+            path: None,
+            max_items: 20,
+        }
+    }
+}
+
+/// Options for taking part in the IndieWeb. Urls are absolute with `sitemap.base_url`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct IndieWebConfig {
+    /// The webmention endpoint, linked from every page so other sites can notify it of links
+    pub webmention: Option<String>,
+    /// The IndieAuth authorization endpoint, for signing in with the site's url
+    pub authorization_endpoint: Option<String>,
+    /// The IndieAuth token endpoint
+    pub token_endpoint: Option<String>,
+    /// Profiles elsewhere (such as a code forge account) that belong to the site's author,
+    /// linked from every page with `rel="me"`
+    pub me: Vec<String>,
+    /// The name of the site's author, for the h-card and the author of each h-entry
+    pub author: Option<String>,
+    /// A photo of the author for the h-card, as a url or a path from the site root
+    pub author_photo: Option<String>,
+    /// A short bio of the author for the h-card
+    pub author_note: Option<String>,
+    /// A fediverse account (`@user@example.social`), linked with `rel="me"` and found from
+    /// the site's domain through the `.well-known/webfinger` stub
+    pub fediverse: Option<String>,
+}
+
+/// Options for the page view beacon. It sends only the page's path and the site that linked
+/// to it, and uses no cookies or storage.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BeaconConfig {
+    /// The url each page view is posted to, as a json body of `path` and `referrer`
+    pub endpoint: String,
+    /// Also count views from browsers that ask not to be tracked
+    #[serde(default)]
+    pub ignore_do_not_track: bool,
+}
+
+/// Options for the generated Content-Security-Policy.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SecurityConfig {
+    pub csp: CspOutput,
+    /// Extra sources for each directive (e.g. `font-src = ["https://fonts.gstatic.com"]`),
+    /// for what pages load that isn't in their html
+    pub sources: BTreeMap<String, Vec<String>>,
+}
+
+/// Where the Content-Security-Policy is written.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CspOutput {
+    /// A `<meta http-equiv>` tag in each page, with the policy of that page
+    #[default]
+    Meta,
+    /// A `_headers` file (as read by Netlify and Cloudflare Pages) with the policy of the whole
+    /// site, and other recommended security headers
+    Headers,
+}
+
+/// Limits on rendering a single page. Pages that exceed them are skipped with a warning.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// The largest page source, in bytes
+    pub max_source_size: u64,
+    /// The largest generated html of a page, in bytes, checked as it grows between rendering steps
+    pub max_page_size: usize,
+    /// Seconds a page may take to render, checked between rendering steps (such as expanding
+    /// its shortcodes, and rendering its markup)
+    pub render_timeout: u64,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_source_size: 16 * 1024 * 1024,
+            max_page_size: 64 * 1024 * 1024,
+            render_timeout: 60,
+        }
+    }
+}
+
+impl LimitsConfig {
+    /// Describes the limit exceeded by a page with `html` that has been rendering for `elapsed`.
+    pub fn exceeded(&self, html: &str, elapsed: Duration) -> Option<String> {
+        if html.len() > self.max_page_size {
+            Some(format!(
+                "page size limit ({} bytes > {})",
+                html.len(),
+                self.max_page_size
+            ))
+        } else if elapsed > Duration::from_secs(self.render_timeout) {
+            Some(format!(
+                "render timeout ({:.1}s > {}s)",
+                elapsed.as_secs_f64(),
+                self.render_timeout
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// The rules for turning text (such as a heading or file name) into a slug.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SlugConfig {
+    /// Replace latin letters with diacritics with their ascii spelling (e.g. `é` with `e`),
+    /// dropping other non-ascii letters. Otherwise non-ascii letters are kept
+    pub transliterate: bool,
+    pub lowercase: bool,
+    /// Placed between the words of a slug
+    pub separator: String,
+    /// The most characters in a slug
+    pub max_length: Option<usize>,
+    /// Slug the file names of pages and the names of directories, and the links to them (off by
+    /// default, since it renames pages). Otherwise only anchors are slugged
+    pub file_names: bool,
+}
+
+impl Default for SlugConfig {
+    fn default() -> Self {
+        Self {
+            transliterate: true,
+            lowercase: true,
+            separator: "-".to_string(),
+            max_length: None,
+            file_names: false,
+        }
+    }
+}
+
+/// Options for the built-in templates.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TemplateOptions {
+    pub color_scheme: ColorScheme,
+    /// Show a button that switches between the light and dark color schemes, remembering the
+    /// choice in the browser
+    pub theme_toggle: bool,
+    /// A css color for links and highlights, replacing the color scheme's accent color
+    pub accent_color: Option<String>,
+    /// A css `font-family` for the page text
+    pub font_family: Option<String>,
+    /// An image shown at the top of each page, as a url or a path from the site root
+    pub logo: Option<String>,
+    /// Html shown at the bottom of each page
+    pub footer: Option<String>,
+    /// Show a button that prints the page
+    pub print_button: bool,
+}
+
+/// The default color scheme of a built-in template.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorScheme {
+    /// Follow the browser's `prefers-color-scheme`
+    #[default]
+    Auto,
+    Light,
+    Dark,
+}
+
+impl ColorScheme {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Light => "light",
+            Self::Dark => "dark",
+        }
+    }
+}
+
+pub fn get_site_config(fs: &dyn FileSystem, target_dir: &Path) -> anyhow::Result<SiteConfig> {
+    let config_file = target_dir.join("ssg.toml");
+    if !fs.is_file(&config_file) {
+        return Ok(SiteConfig::default());
+    }
+    log::trace!("Reading site config from {:?}", &config_file);
+    let contents = fs.read_to_string(&config_file)?;
+    let config: SiteConfig =
+        toml::from_str(&contents).map_err(|e| SsgError::MetadataError(config_file, e))?;
+    for pattern in &config.ignore {
+        glob::Pattern::new(pattern)
+            .map_err(|e| anyhow::anyhow!("Invalid ignore pattern {:?}: {}", pattern, e))?;
+    }
+    filters::compile(&config.filters)?;
+    Ok(config)
+}
+
+impl SiteConfig {
+    /// Checks if `relative` (a path relative to the target directory) or a directory containing
+    /// it matches one of the `ignore` patterns.
+    pub fn is_ignored(&self, relative: &Path) -> bool {
+        let patterns = self
+            .ignore
+            .iter()
+            .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+            .collect::<Vec<_>>();
+        relative
+            .ancestors()
+            .filter(|path| !path.as_os_str().is_empty())
+            .any(|path| patterns.iter().any(|pattern| pattern.matches_path(path)))
+    }
+
+    /// The rules for slugging the file names of pages, if they're slugged.
+    pub fn file_name_slugs(&self) -> Option<&SlugConfig> {
+        self.slugs.as_ref().filter(|slugs| slugs.file_names)
+    }
+}
+
+/// The direction of a page's text, set as the `dir` attribute of its `<html>` element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextDirection {
+    Ltr,
+    Rtl,
+    /// Let the browser decide from the page's text
+    Auto,
+}
+
+impl TextDirection {
+    /// The direction that `lang` is written in, from its primary language subtag.
+    pub fn of_lang(lang: &str) -> Self {
+        const RTL_LANGUAGES: [&str; 14] = [
+            "ar", "arc", "ckb", "dv", "fa", "he", "iw", "nqo", "ps", "sd", "syr", "ug", "ur", "yi",
+        ];
+        let primary = lang.split(['-', '_']).next().unwrap_or("");
+        if RTL_LANGUAGES
+            .iter()
+            .any(|rtl| rtl.eq_ignore_ascii_case(primary))
+        {
+            Self::Rtl
+        } else {
+            Self::Ltr
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ltr => "ltr",
+            Self::Rtl => "rtl",
+            Self::Auto => "auto",
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::path::{Path, PathBuf};
+
+use crate::{html_import, metadata, vfs::FileSystem, InputFormat};
+
+/// Converts the djot or markdown page at `path` to the other language, writing it to
+/// `output_path` (or next to the page, with the other extension), and returns where it was
+/// written. The page is rendered to html and converted back, so its front matter is kept as
+/// it's written, but markup with no equivalent in the other language is kept as raw html.
+pub fn convert_page(
+    fs: &dyn FileSystem,
+    path: &Path,
+    output_path: Option<&Path>,
+) -> anyhow::Result<PathBuf> {
+    let from = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("dj" | "djot") => InputFormat::Djot,
+        Some("md") => InputFormat::Markdown,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "{:?} isn't a djot (.dj, .djot) or markdown (.md) page",
+                path
+            ))
+        }
+    };
+    let (to, extension) = match from {
+        InputFormat::Djot => (InputFormat::Markdown, "md"),
+        InputFormat::Markdown => (InputFormat::Djot, "dj"),
+    };
+    let output_path = match output_path {
+        Some(output_path) => output_path.to_path_buf(),
+        None => path.with_extension(extension),
+    };
+    if fs.exists(&output_path) {
+        return Err(anyhow::anyhow!("{:?} already exists", output_path));
+    }
+    let input = fs.read_to_string(path)?;
+    let (front_matter, body) = match metadata::split_front_matter(&input) {
+        Some((_, Some((_, body)))) => (&input[..input.len() - body.len()], body),
+        _ => ("", input.as_str()),
+    };
+    let html = match from {
+        InputFormat::Djot => jotdown::html::render_to_string(jotdown::Parser::new(body)),
+        InputFormat::Markdown => {
+            use pulldown_cmark::Options;
+            let options =
+                Options::ENABLE_GFM | Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+            let mut html = String::new();
+            pulldown_cmark::html::push_html(
+                &mut html,
+                pulldown_cmark::Parser::new_ext(body, options),
+            );
+            html
+        }
+    };
+    let converted = html_import::html_to_markup(&html, to, str::to_string)?;
+    if converted.raw_elements > 0 {
+        log::warn!(
+            "{:?} has {} element(s) with no equivalent in {:?}, which were kept as raw html",
+            path,
+            converted.raw_elements,
+            to
+        );
+    }
+    fs.write(
+        &output_path,
+        format!("{}{}", front_matter, converted.markup).as_bytes(),
+    )?;
+    Ok(output_path)
+}
@@ -0,0 +1,59 @@
+use serde::Serialize;
+
+use crate::report::BuildReport;
+
+/// The json summary printed at the end of a `--ci` run.
+#[derive(Serialize, Debug)]
+pub struct Summary {
+    pub success: bool,
+    pub error: Option<String>,
+    /// The number of pages rendered
+    pub pages: usize,
+    /// The number of files copied as they are
+    pub assets: usize,
+    pub warnings: Vec<String>,
+}
+
+/// Sets up logging, filtered by `RUST_LOG` (warnings by default). `plain` disables colors.
+pub fn init_logger(plain: bool) {
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn"));
+    if plain {
+        builder.write_style(env_logger::WriteStyle::Never);
+    }
+    builder.init();
+}
+
+/// Summarizes a run that ended with `result`.
+pub fn summarize(result: &anyhow::Result<BuildReport>) -> Summary {
+    match result {
+        Ok(report) => Summary {
+            success: report.warnings.is_empty(),
+            error: None,
+            pages: report.pages.len(),
+            assets: report.assets.len(),
+            warnings: report.warnings.clone(),
+        },
+        Err(e) => Summary {
+            success: false,
+            error: Some(e.to_string()),
+            pages: 0,
+            assets: 0,
+            warnings: Vec::new(),
+        },
+    }
+}
+
+/// Prints the json summary of a `--ci` run to stdout, failing if there were any warnings.
+pub fn report(result: anyhow::Result<BuildReport>) -> anyhow::Result<()> {
+    let summary = summarize(&result);
+    println!("{}", serde_json::to_string_pretty(&summary)?);
+    result?;
+    if !summary.success {
+        return Err(anyhow::anyhow!(
+            "{} warning(s) reported",
+            summary.warnings.len()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+
+use crate::{links, render_source, utils, vfs::FileSystem, ConsoleArgs, RenderedPage, SiteContext};
+
+/// Elements removed from emails along with their contents, since email clients don't run or
+/// render them.
+const REMOVED_ELEMENTS: [&str; 9] = [
+    "script", "style", "noscript", "iframe", "form", "object", "embed", "video", "audio",
+];
+
+/// The inline styles of each element, since many email clients ignore stylesheets. Styles the
+/// page already sets on an element take precedence.
+const STYLES: [(&str, &str); 17] = [
+    ("h1", "font-size:28px;line-height:1.25;margin:0 0 16px;"),
+    ("h2", "font-size:22px;line-height:1.3;margin:24px 0 12px;"),
+    ("h3", "font-size:18px;line-height:1.3;margin:20px 0 8px;"),
+    ("p", "margin:0 0 16px;"),
+    ("a", "color:#0969da;text-decoration:underline;"),
+    ("img", "max-width:100%;height:auto;border:0;"),
+    (
+        "blockquote",
+        "margin:0 0 16px;padding:0 16px;border-left:4px solid #d0d7de;color:#57606a;",
+    ),
+    (
+        "pre",
+        "margin:0 0 16px;padding:12px;background:#f6f8fa;white-space:pre-wrap;font-family:Menlo,Consolas,monospace;font-size:13px;",
+    ),
+    ("code", "font-family:Menlo,Consolas,monospace;font-size:13px;"),
+    ("ul", "margin:0 0 16px;padding-left:24px;"),
+    ("ol", "margin:0 0 16px;padding-left:24px;"),
+    ("li", "margin:0 0 4px;"),
+    ("table", "border-collapse:collapse;margin:0 0 16px;"),
+    (
+        "th",
+        "border:1px solid #d0d7de;padding:6px 12px;text-align:left;",
+    ),
+    ("td", "border:1px solid #d0d7de;padding:6px 12px;"),
+    ("hr", "border:0;border-top:1px solid #d0d7de;margin:24px 0;"),
+    ("figcaption", "font-size:14px;color:#57606a;"),
+];
+
+/// Renders the page at `page` as a standalone email: its content (without the site's template)
+/// in a single centered column, with inline styles, absolute urls (with `base_url`, or else the
+/// sitemap's `base_url`), and without scripts, forms, or embedded media.
+pub fn export_email(
+    fs: &dyn FileSystem,
+    page: &Path,
+    base_url: Option<&str>,
+) -> anyhow::Result<String> {
+    let page = fs.canonicalize(page)?;
+    // The page is rendered as part of its site, the closest directory with an ssg.toml
+    let root = page
+        .ancestors()
+        .skip(1)
+        .find(|dir| fs.is_file(&dir.join("ssg.toml")))
+        .or(page.parent())
+        .ok_or_else(|| anyhow!("{} has no parent directory", page.display()))?
+        .to_path_buf();
+    let args = ConsoleArgs {
+        directory: Some(root.clone()),
+        fragment: true,
+        drafts: true,
+        ..Default::default()
+    };
+    let context = SiteContext::new(&root, &root, &args, fs)?;
+    // Emails aren't written to a directory of their own, even with pretty urls
+    let relative = page.strip_prefix(&root)?;
+    let Some(RenderedPage {
+        front_matter, html, ..
+    }) = render_source(
+        &fs.read_to_string(&page)?,
+        &page,
+        &relative.with_extension("html"),
+        relative.components().count(),
+        &context,
+    )?
+    else {
+        return Err(anyhow!("{} is a draft", page.display()));
+    };
+    if front_matter.password.is_some() {
+        return Err(anyhow!(
+            "{} is encrypted, so it can't be exported",
+            page.display()
+        ));
+    }
+    let base_url = base_url.or(context.config.sitemap.base_url.as_deref());
+    if base_url.is_none() {
+        context.warn("The email has no base_url, so its links are left relative");
+    }
+    let title = front_matter.title.or_else(|| utils::first_heading(&html));
+    let page_dir = page.parent().and_then(|dir| dir.strip_prefix(&root).ok());
+    let content = email_markup(&html, page_dir, base_url);
+    let lang = front_matter
+        .lang
+        .or(context.config.lang.clone())
+        .map(|lang| format!(" lang=\"{}\"", utils::escape_html(&lang)))
+        .unwrap_or_default();
+    Ok(format!(
+        "<!DOCTYPE html>\n<html{}>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n</head>\n<body style=\"margin:0;padding:0;background:#ffffff;\">\n<table role=\"presentation\" width=\"100%\" cellpadding=\"0\" cellspacing=\"0\" border=\"0\">\n<tr>\n<td align=\"center\" style=\"padding:24px 12px;\">\n<table role=\"presentation\" width=\"600\" cellpadding=\"0\" cellspacing=\"0\" border=\"0\" style=\"max-width:600px;width:100%;\">\n<tr>\n<td style=\"font-family:-apple-system,'Segoe UI',Helvetica,Arial,sans-serif;font-size:16px;line-height:1.6;color:#24292f;text-align:left;\">\n{}</td>\n</tr>\n</table>\n</td>\n</tr>\n</table>\n</body>\n</html>\n",
+        lang,
+        utils::escape_html(title.as_deref().unwrap_or_default()),
+        content
+    ))
+}
+
+/// Removes the elements email clients don't support from `html`, inlines its styles, and makes
+/// its relative urls (from a page in `page_dir`) absolute with `base_url`.
+fn email_markup(html: &str, page_dir: Option<&Path>, base_url: Option<&str>) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = links::tag_end(rest) else {
+            break;
+        };
+        let mut tag = rest[..end].to_string();
+        let name = tag[1..]
+            .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        if REMOVED_ELEMENTS.contains(&name.as_str()) {
+            let closing_tag = format!("</{}>", name);
+            rest = match rest.to_ascii_lowercase().find(&closing_tag) {
+                Some(close) => &rest[close + closing_tag.len()..],
+                None => &rest[end..],
+            };
+            continue;
+        }
+        rest = &rest[end..];
+        if let Some(base_url) = base_url {
+            for attribute in ["href", "src"] {
+                if let Some(range) = links::attribute_range(&tag, attribute) {
+                    let url = absolute_url(&tag[range.clone()], page_dir, base_url);
+                    tag.replace_range(range, &url);
+                }
+            }
+        }
+        if let Some((_, style)) = STYLES.iter().find(|(element, _)| *element == name) {
+            match links::attribute_range(&tag, "style") {
+                Some(range) => tag.insert_str(range.start, style),
+                None => tag.insert_str(1 + name.len(), &format!(" style=\"{}\"", style)),
+            }
+        }
+        output.push_str(&tag);
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Makes `url`, relative to a page in `page_dir`, absolute with `base_url`. External urls,
+/// fragments, and `mailto:` links are kept as they are.
+fn absolute_url(url: &str, page_dir: Option<&Path>, base_url: &str) -> String {
+    if url.is_empty() || url.starts_with('#') || links::is_external(url) {
+        return url.to_string();
+    }
+    let base_url = base_url.trim_end_matches('/');
+    if let Some(path) = url.strip_prefix('/') {
+        return format!("{}/{}", base_url, path);
+    }
+    let path = page_dir.unwrap_or(Path::new("")).join(url);
+    let path = utils::normalize_path(&path);
+    let path = path
+        .components()
+        .skip_while(|component| matches!(component, std::path::Component::ParentDir))
+        .collect::<PathBuf>();
+    format!("{}/{}", base_url, path.to_string_lossy().replace('\\', "/"))
+}
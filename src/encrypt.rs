@@ -0,0 +1,53 @@
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// PBKDF2 iterations used to derive the key from a page's password.
+pub const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// Derives the AES-256 key for `password` and `salt`, the same way the decryptor script does.
+pub fn derive_key(password: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(password.as_bytes(), salt, PBKDF2_ITERATIONS, &mut key);
+    key
+}
+
+/// Encrypts the `html` content of a page with `password`, returning a passphrase form and a
+/// script that decrypts the content in the browser.
+pub fn encrypt_html(html: &str, password: &str) -> anyhow::Result<String> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(password, &salt);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, html.as_bytes())
+        .map_err(|e| anyhow::anyhow!("Could not encrypt page: {}", e))?;
+    Ok(format!(
+        "<div class=\"encrypted-page\" data-salt=\"{}\" data-iv=\"{}\" data-ciphertext=\"{}\">\n<form>\n<input type=\"password\" placeholder=\"Passphrase\" aria-label=\"Passphrase\" autocomplete=\"current-password\">\n<button type=\"submit\">Unlock</button>\n<p class=\"encrypted-page-error\" hidden>Incorrect passphrase</p>\n</form>\n</div>\n<script>\n{}\n</script>\n",
+        STANDARD.encode(salt),
+        STANDARD.encode(nonce),
+        STANDARD.encode(ciphertext),
+        DECRYPTOR_SCRIPT.replace("{ITERATIONS}", &PBKDF2_ITERATIONS.to_string())
+    ))
+}
+
+const DECRYPTOR_SCRIPT: &str = r#"document.querySelectorAll(".encrypted-page").forEach((page) => {
+  const bytes = (name) => Uint8Array.from(atob(page.dataset[name]), (c) => c.charCodeAt(0));
+  page.querySelector("form").addEventListener("submit", async (event) => {
+    event.preventDefault();
+    const password = new TextEncoder().encode(page.querySelector("input").value);
+    try {
+      const material = await crypto.subtle.importKey("raw", password, "PBKDF2", false, ["deriveKey"]);
+      const key = await crypto.subtle.deriveKey(
+        { name: "PBKDF2", salt: bytes("salt"), iterations: {ITERATIONS}, hash: "SHA-256" },
+        material, { name: "AES-GCM", length: 256 }, false, ["decrypt"]);
+      const html = await crypto.subtle.decrypt({ name: "AES-GCM", iv: bytes("iv") }, key, bytes("ciphertext"));
+      page.outerHTML = new TextDecoder().decode(html);
+    } catch (e) {
+      page.querySelector(".encrypted-page-error").hidden = false;
+    }
+  });
+});"#;
@@ -9,7 +9,27 @@ pub enum SsgError {
     #[error("Path {0} is not relative to target directory")]
     PathNotRelative(PathBuf),
     #[error("An entry returned error {0}")]
-    DirEntryError(walkdir::Error),
+    DirEntryError(std::io::Error),
     #[error("Referenced file path {0} does not exist!")]
     LinkError(PathBuf),
+    #[error("Could not parse metadata file {0}: {1}")]
+    MetadataError(PathBuf, toml::de::Error),
+    #[error("Could not parse the front matter of {0}: {1}")]
+    FrontMatterError(PathBuf, serde_yaml::Error),
+    #[error("Could not read git tags in {0}: {1}")]
+    GitTagsError(PathBuf, String),
+    #[error("Invalid date format {0:?}: {1}")]
+    DateFormatError(String, time::error::InvalidFormatDescription),
+    #[error("Could not render the template for {0}: {1}")]
+    TemplateError(String, String),
+    #[error("Lint: {0}: {1}")]
+    LintError(PathBuf, String),
+    #[error("Skipped {0}: it exceeds the {1}")]
+    PageLimitError(PathBuf, String),
+    #[error("Skipped {0}: it is outside the target directory")]
+    OutsideTarget(PathBuf),
+    #[error("Link {0:?} resolves outside the target directory, leaving it as is")]
+    LinkOutsideTarget(String),
+    #[error("Removed the url {0:?}: it could run script in sandboxed content")]
+    UnsafeUrl(String),
 }
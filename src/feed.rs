@@ -0,0 +1,153 @@
+use std::path::Path;
+
+use time::{
+    format_description::well_known::{Rfc2822, Rfc3339},
+    OffsetDateTime,
+};
+
+use crate::{config::FeedConfig, metadata::Date, templating, utils, FirstPassResult, SiteContext};
+
+/// The rss feed, written to the root of the site.
+pub const RSS_FEED: &str = "feed.xml";
+/// The atom feed, written to the root of the site.
+pub const ATOM_FEED: &str = "atom.xml";
+
+/// A page in the feeds.
+struct Entry {
+    title: String,
+    url: String,
+    date: OffsetDateTime,
+    html: String,
+}
+
+/// Writes rss and atom feeds of the site's most recent pages to `output_path`, dated by their
+/// front matter `date` (or else when their source was last modified).
+pub fn generate_feeds(
+    context: &SiteContext,
+    results: &[FirstPassResult],
+    output_path: &Path,
+    config: &FeedConfig,
+) -> anyhow::Result<()> {
+    let base_url = match config
+        .base_url
+        .as_deref()
+        .or(context.config.sitemap.base_url.as_deref())
+    {
+        Some(base_url) => base_url.trim_end_matches('/'),
+        None => {
+            context.warn("The feeds have no base_url, so their links are relative");
+            ""
+        }
+    };
+    let titles = templating::site_pages(results);
+    let mut entries = results
+        .iter()
+        .filter_map(|result| match result {
+            FirstPassResult::HtmlOutput {
+                relative_path,
+                unlisted: false,
+                html,
+                date,
+                layout,
+                ..
+            } => Some((relative_path, html, date, layout)),
+            _ => None,
+        })
+        .filter(|(relative_path, ..)| {
+            config
+                .path
+                .as_ref()
+                .is_none_or(|path| relative_path.starts_with(path))
+        })
+        .filter_map(|(relative_path, html, date, layout)| {
+            let path = relative_path.to_string_lossy().replace('\\', "/");
+            let date = date
+                .as_ref()
+                .and_then(Date::to_date)
+                .map(|date| date.midnight().assume_utc())
+                .or_else(|| {
+                    let modified = context.fs.modified(&layout.source).ok()?;
+                    Some(OffsetDateTime::from(modified))
+                })?;
+            let title = titles
+                .iter()
+                .find(|page| page.path == path)
+                .and_then(|page| page.title.as_deref())
+                .unwrap_or(&path);
+            let title = utils::shorten_title(title, context.config.max_title_length);
+            Some(Entry {
+                title,
+                url: format!("{}/{}", base_url, path),
+                date,
+                html: html.clone(),
+            })
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.date));
+    entries.truncate(config.max_items);
+
+    let title = utils::escape_html(
+        config
+            .title
+            .as_deref()
+            .or(context.config.title.as_deref())
+            .unwrap_or("Feed"),
+    );
+    let site_url = utils::escape_html(&format!("{}/", base_url));
+    let updated = entries
+        .first()
+        .map_or(OffsetDateTime::UNIX_EPOCH, |entry| entry.date);
+
+    let mut rss = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\">\n<channel>\n<title>{}</title>\n<link>{}</link>\n<description>{}</description>\n",
+        title, site_url, title
+    );
+    if let Some(lang) = &context.config.lang {
+        rss.push_str(&format!(
+            "<language>{}</language>\n",
+            utils::escape_html(lang)
+        ));
+    }
+    let mut atom = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n<title>{}</title>\n<link href=\"{}\"/>\n<link rel=\"self\" href=\"{}\"/>\n<id>{}</id>\n<updated>{}</updated>\n",
+        title,
+        site_url,
+        utils::escape_html(&format!("{}/{}", base_url, ATOM_FEED)),
+        site_url,
+        updated.format(&Rfc3339)?
+    );
+    if let Some(author) = &config.author {
+        atom.push_str(&format!(
+            "<author><name>{}</name></author>\n",
+            utils::escape_html(author)
+        ));
+    }
+    for entry in &entries {
+        let entry_title = utils::escape_html(&entry.title);
+        let url = utils::escape_html(&entry.url);
+        let html = utils::escape_html(&entry.html);
+        rss.push_str(&format!(
+            "<item>\n<title>{}</title>\n<link>{}</link>\n<guid>{}</guid>\n<pubDate>{}</pubDate>\n<description>{}</description>\n</item>\n",
+            entry_title,
+            url,
+            url,
+            entry.date.format(&Rfc2822)?,
+            html
+        ));
+        atom.push_str(&format!(
+            "<entry>\n<title>{}</title>\n<link href=\"{}\"/>\n<id>{}</id>\n<updated>{}</updated>\n<content type=\"html\">{}</content>\n</entry>\n",
+            entry_title,
+            url,
+            url,
+            entry.date.format(&Rfc3339)?,
+            html
+        ));
+    }
+    rss.push_str("</channel>\n</rss>\n");
+    atom.push_str("</feed>\n");
+
+    log::debug!("Writing feeds of {} page(s)", entries.len());
+    context.write_output(&output_path.join(RSS_FEED), rss.as_bytes())?;
+    context.write_output(&output_path.join(ATOM_FEED), atom.as_bytes())?;
+    Ok(())
+}
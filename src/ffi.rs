@@ -0,0 +1,93 @@
+//! A C ABI for embedding the generator in other tools, built with `--features ffi`.
+//!
+//! Strings are nul-terminated utf-8. Strings returned by these functions are owned by the
+//! caller, and must be freed with `ssg_free_string`. Errors are logged, and reported by
+//! returning null (or a nonzero status).
+
+use std::{
+    ffi::{c_char, c_int, CStr, CString},
+    path::PathBuf,
+    ptr,
+};
+
+use crate::{render_page, run_program, ConsoleArgs};
+
+/// Renders a markdown page to html, or returns null if it couldn't be rendered.
+///
+/// # Safety
+///
+/// `input` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ssg_render_markdown(input: *const c_char) -> *mut c_char {
+    render(input, true)
+}
+
+/// Renders a djot page to html, or returns null if it couldn't be rendered.
+///
+/// # Safety
+///
+/// `input` must be a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ssg_render_djot(input: *const c_char) -> *mut c_char {
+    render(input, false)
+}
+
+/// Generates the site in `directory` into `output_path` (or `./output` if null), returning 0
+/// on success.
+///
+/// # Safety
+///
+/// `directory` and `output_path` (if not null) must be valid nul-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn ssg_generate_site(
+    directory: *const c_char,
+    output_path: *const c_char,
+) -> c_int {
+    let Some(directory) = to_str(directory) else {
+        return 1;
+    };
+    let args = ConsoleArgs {
+        directory: Some(PathBuf::from(directory)),
+        output_path: to_str(output_path).map(PathBuf::from),
+        ..Default::default()
+    };
+    match run_program(args) {
+        Ok(_) => 0,
+        Err(e) => {
+            log::error!("{}", e);
+            1
+        }
+    }
+}
+
+/// Frees a string returned by one of the functions above.
+///
+/// # Safety
+///
+/// `string` must be null, or a string returned by this library that hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn ssg_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+unsafe fn render(input: *const c_char, is_markdown: bool) -> *mut c_char {
+    let Some(input) = to_str(input) else {
+        return ptr::null_mut();
+    };
+    match render_page(input, Default::default(), is_markdown) {
+        Ok(html) => CString::new(html).map_or(ptr::null_mut(), CString::into_raw),
+        Err(e) => {
+            log::error!("{}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+unsafe fn to_str<'a>(string: *const c_char) -> Option<&'a str> {
+    if string.is_null() {
+        return None;
+    }
+    CStr::from_ptr(string).to_str().ok()
+}
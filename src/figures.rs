@@ -0,0 +1,219 @@
+use crate::{links, templating, utils, FirstPassResult, SiteContext};
+
+/// What's listed: figures (`<figure>` with a `<figcaption>`) or tables (`<table>` with a
+/// `<caption>`).
+#[derive(Clone, Copy)]
+pub enum Kind {
+    Figure,
+    Table,
+}
+
+impl Kind {
+    fn element(self) -> &'static str {
+        match self {
+            Self::Figure => "figure",
+            Self::Table => "table",
+        }
+    }
+
+    fn caption(self) -> &'static str {
+        match self {
+            Self::Figure => "figcaption",
+            Self::Table => "caption",
+        }
+    }
+
+    /// The title of the list, and of its page.
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Figure => "List of Figures",
+            Self::Table => "List of Tables",
+        }
+    }
+
+    /// The page listing every figure or table of the site, at the root of the output directory.
+    pub fn page(self) -> &'static str {
+        match self {
+            Self::Figure => "figures.html",
+            Self::Table => "tables.html",
+        }
+    }
+
+    /// The macro replaced with the list of a page's own figures or tables.
+    pub fn macro_name(self) -> &'static str {
+        match self {
+            Self::Figure => "<!-- {LIST_OF_FIGURES} -->",
+            Self::Table => "<!-- {LIST_OF_TABLES} -->",
+        }
+    }
+
+    fn list_class(self) -> &'static str {
+        match self {
+            Self::Figure => "list-of-figures",
+            Self::Table => "list-of-tables",
+        }
+    }
+}
+
+/// A captioned figure or table, and where it's found in a page's html.
+struct Captioned<'a> {
+    /// Where the start tag starts
+    start: usize,
+    id: Option<&'a str>,
+    /// The text of the caption, escaped
+    caption: String,
+}
+
+/// The figures or tables of `html` that have a caption, in the order they're in.
+fn captioned(html: &str, kind: Kind) -> Vec<Captioned<'_>> {
+    let (open, close) = (
+        format!("<{}", kind.element()),
+        format!("</{}>", kind.element()),
+    );
+    let mut found = Vec::new();
+    let mut searched = 0;
+    while let Some(offset) = html[searched..].find(&open) {
+        let start = searched + offset;
+        searched = start + open.len();
+        if !html[searched..].starts_with(|c: char| c == '>' || c.is_whitespace()) {
+            continue;
+        }
+        let Some(tag_end) = links::tag_end(&html[start..]) else {
+            break;
+        };
+        let tag = &html[start..start + tag_end];
+        let content = &html[start + tag_end..];
+        let content = &content[..content.find(&close).unwrap_or(content.len())];
+        if let Some(caption) = caption_text(content, kind.caption()) {
+            found.push(Captioned {
+                start,
+                id: links::attribute(tag, "id").filter(|id| !id.is_empty()),
+                caption,
+            });
+        }
+    }
+    found
+}
+
+/// The text of the `<caption>` element (or `<figcaption>`, as `element`) in `content`, without
+/// any tags in it, escaped.
+fn caption_text(content: &str, element: &str) -> Option<String> {
+    let open = format!("<{}", element);
+    let start = content.match_indices(&open).find_map(|(start, _)| {
+        content[start + open.len()..]
+            .starts_with(|c: char| c == '>' || c.is_whitespace())
+            .then_some(start)
+    })?;
+    let caption = &content[start..];
+    let caption = &caption[links::tag_end(caption)?..];
+    let caption = &caption[..caption.find(&format!("</{}>", element))?];
+    let mut text = String::with_capacity(caption.len());
+    let mut in_tag = false;
+    for c in caption.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    // Raw html captions may have unescaped characters
+    (!text.is_empty()).then(|| utils::escape_html(&utils::unescape_html(&text)))
+}
+
+/// Gives each figure and table in `html` with a caption, but without an id, one to link to
+/// (`figure-1`, `table-2`, ... numbered in the order they're in).
+pub fn add_ids(html: &str) -> String {
+    let mut html = html.to_string();
+    for kind in [Kind::Figure, Kind::Table] {
+        let mut inserts = Vec::new();
+        for (i, captioned) in captioned(&html, kind).iter().enumerate() {
+            if captioned.id.is_none() {
+                let at = captioned.start + 1 + kind.element().len();
+                inserts.push((at, format!(" id=\"{}-{}\"", kind.element(), i + 1)));
+            }
+        }
+        for (at, id) in inserts.into_iter().rev() {
+            html.insert_str(at, &id);
+        }
+    }
+    html
+}
+
+/// The list of the captioned figures or tables of `html` that have ids, each linked to at
+/// `url` (the page's url, or empty for the page itself), or nothing if it has none.
+fn list_html(html: &str, kind: Kind, url: &str) -> String {
+    let items = captioned(html, kind)
+        .into_iter()
+        .filter_map(|captioned| {
+            captioned.id.map(|id| {
+                format!(
+                    "<li><a href=\"{}#{}\">{}</a></li>",
+                    url, id, captioned.caption
+                )
+            })
+        })
+        .collect::<String>();
+    match items.is_empty() {
+        true => String::new(),
+        false => format!("<ol class=\"{}\">{}</ol>", kind.list_class(), items),
+    }
+}
+
+/// Replaces the `<!-- {LIST_OF_FIGURES} -->` and `<!-- {LIST_OF_TABLES} -->` of `text` with
+/// lists of the figures and tables of the page's content, `html`.
+pub fn fill_lists(text: &str, html: &str) -> String {
+    let mut text = text.to_string();
+    for kind in [Kind::Figure, Kind::Table] {
+        if text.contains(kind.macro_name()) {
+            text = text.replace(kind.macro_name(), &list_html(html, kind, ""));
+        }
+    }
+    text
+}
+
+/// The page listing the captioned figures or tables of every listed page, under a heading
+/// linking to each page, or nothing if there are none.
+pub fn list_page_html(
+    results: &[FirstPassResult],
+    kind: Kind,
+    context: &SiteContext,
+) -> Option<String> {
+    let titles = templating::site_pages(results);
+    let mut html = String::new();
+    for result in results {
+        let FirstPassResult::HtmlOutput {
+            html: content,
+            relative_path,
+            unlisted: false,
+            ..
+        } = result
+        else {
+            continue;
+        };
+        let url = utils::page_link(
+            1,
+            context.web_prefix,
+            relative_path,
+            context.config.pretty_urls,
+        );
+        let list = list_html(content, kind, &url);
+        if list.is_empty() {
+            continue;
+        }
+        let path = relative_path.to_string_lossy().replace('\\', "/");
+        let title = titles
+            .iter()
+            .find(|page| page.path == path)
+            .and_then(|page| page.title.clone())
+            .unwrap_or(path);
+        html.push_str(&format!(
+            "<section>\n<h2><a href=\"{}\">{}</a></h2>\n{}\n</section>\n",
+            url,
+            utils::escape_html(&title),
+            list
+        ));
+    }
+    (!html.is_empty()).then(|| format!("<h1>{}</h1>\n{}", kind.title(), html))
+}
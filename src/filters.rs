@@ -0,0 +1,65 @@
+use std::{borrow::Cow, path::Path};
+
+use regex::Regex;
+
+use crate::config::FilterConfig;
+
+/// A find-and-replace filter of `ssg.toml`, ready to apply to pages.
+pub struct Filter {
+    find: Regex,
+    replace: String,
+    paths: Vec<glob::Pattern>,
+}
+
+/// Compiles the filters of `ssg.toml`, in their order.
+pub fn compile(filters: &[FilterConfig]) -> anyhow::Result<Vec<Filter>> {
+    filters
+        .iter()
+        .map(|filter| {
+            let find = match filter.regex {
+                true => Regex::new(&filter.find),
+                false => Regex::new(&regex::escape(&filter.find)),
+            }
+            .map_err(|e| anyhow::anyhow!("Invalid filter {:?}: {}", filter.find, e))?;
+            let replace = match filter.regex {
+                true => filter.replace.clone(),
+                // `$` is literal in plain text replacements
+                false => filter.replace.replace('$', "$$"),
+            };
+            let paths = filter
+                .paths
+                .iter()
+                .map(|pattern| {
+                    glob::Pattern::new(pattern).map_err(|e| {
+                        anyhow::anyhow!("Invalid filter path pattern {:?}: {}", pattern, e)
+                    })
+                })
+                .collect::<anyhow::Result<_>>()?;
+            Ok(Filter {
+                find,
+                replace,
+                paths,
+            })
+        })
+        .collect()
+}
+
+/// Applies `filters` in order to the html of the page at `relative_path` (relative to the
+/// output directory). Filters with path patterns only apply to the pages they match.
+pub fn apply<'a>(html: &'a str, relative_path: &Path, filters: &[Filter]) -> Cow<'a, str> {
+    let mut html = Cow::Borrowed(html);
+    for filter in filters {
+        if !filter.paths.is_empty()
+            && !filter
+                .paths
+                .iter()
+                .any(|pattern| pattern.matches_path(relative_path))
+        {
+            continue;
+        }
+        if let Cow::Owned(replaced) = filter.find.replace_all(&html, filter.replace.as_str()) {
+            html = Cow::Owned(replaced);
+        }
+    }
+    html
+}
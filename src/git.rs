@@ -0,0 +1,122 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use time::OffsetDateTime;
+
+use crate::vfs::FileSystem;
+
+/// Runs git in `dir`, returning its output if it succeeded.
+fn run_git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output();
+    match output {
+        Ok(output) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).to_string())
+        }
+        Ok(output) => {
+            log::trace!(
+                "git {:?} failed in {:?}: {}",
+                args,
+                dir,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            None
+        }
+        Err(e) => {
+            log::trace!("Could not run git: {}", e);
+            None
+        }
+    }
+}
+
+/// An author of commits to a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Contributor {
+    pub name: String,
+    pub email: String,
+}
+
+/// The authors of the commits that changed `path`, in order of their first commit.
+///
+/// Empty if `path` is not tracked by git.
+pub fn contributors(path: &Path) -> Vec<Contributor> {
+    let (Some(dir), Some(file_name)) = (path.parent(), path.file_name().and_then(|f| f.to_str()))
+    else {
+        return Vec::new();
+    };
+    let Some(log) = run_git(
+        dir,
+        &["log", "--reverse", "--format=%aN%x00%aE", "--", file_name],
+    ) else {
+        return Vec::new();
+    };
+    let mut contributors: Vec<Contributor> = Vec::new();
+    for line in log.lines() {
+        let Some((name, email)) = line.split_once('\0') else {
+            continue;
+        };
+        if !contributors.iter().any(|c| c.email == email) {
+            contributors.push(Contributor {
+                name: name.to_string(),
+                email: email.to_string(),
+            });
+        }
+    }
+    contributors
+}
+
+/// The time of the last commit that changed `path`, if it is tracked by git.
+pub fn last_commit_time(path: &Path) -> Option<OffsetDateTime> {
+    let file_name = path.file_name()?.to_str()?;
+    let timestamp = run_git(
+        path.parent()?,
+        &["log", "-1", "--format=%ct", "--", file_name],
+    )?;
+    OffsetDateTime::from_unix_timestamp(timestamp.trim().parse().ok()?).ok()
+}
+
+/// When `path` was last updated: its last commit, falling back to its modification time, or to
+/// now for generated pages without a source file.
+pub fn last_updated(path: &Path, fs: &dyn FileSystem) -> OffsetDateTime {
+    last_commit_time(path)
+        .or_else(|| fs.modified(path).ok().map(OffsetDateTime::from))
+        .unwrap_or_else(OffsetDateTime::now_utc)
+}
+
+/// The files renamed in the history of `dir`, oldest first, as absolute `(from, to)` paths.
+///
+/// Empty if `dir` is not in a git repository.
+pub fn renamed_files(dir: &Path) -> Vec<(PathBuf, PathBuf)> {
+    let Some(top_level) = run_git(dir, &["rev-parse", "--show-toplevel"]) else {
+        return Vec::new();
+    };
+    let top_level = PathBuf::from(top_level.trim());
+    let Some(log) = run_git(
+        dir,
+        &[
+            "log",
+            "--reverse",
+            "-M",
+            "--diff-filter=R",
+            "--name-status",
+            "--format=",
+            "--",
+            ".",
+        ],
+    ) else {
+        return Vec::new();
+    };
+    log.lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            if !fields.next()?.starts_with('R') {
+                return None;
+            }
+            Some((
+                top_level.join(fields.next()?),
+                top_level.join(fields.next()?),
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{config::SlugConfig, djot_to_html, slug, utils, vfs::FileSystem, SiteContext};
+
+/// The glossary source, at the root of the target directory.
+pub const GLOSSARY_SOURCE: &str = "_glossary.dj";
+/// The generated glossary page, at the root of the output directory.
+pub const GLOSSARY_PAGE: &str = "glossary.html";
+
+/// The terms defined in a site's `_glossary.dj`, and the pages that use them.
+#[derive(Debug, Default)]
+pub struct Glossary {
+    pub terms: Vec<Term>,
+    /// The pages using each term, by the term's id
+    uses: Mutex<BTreeMap<String, Vec<PathBuf>>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Term {
+    pub name: String,
+    /// The anchor of the term on the glossary page
+    pub id: String,
+    /// The definition, as djot
+    pub definition: String,
+}
+
+impl Glossary {
+    /// Reads the glossary of the site in `target_path`, if it has one.
+    pub fn read(
+        fs: &dyn FileSystem,
+        target_path: &Path,
+        slugs: &SlugConfig,
+    ) -> anyhow::Result<Option<Self>> {
+        let path = target_path.join(GLOSSARY_SOURCE);
+        if !fs.is_file(&path) {
+            return Ok(None);
+        }
+        log::trace!("Reading glossary from {:?}", &path);
+        Ok(Some(Self {
+            terms: parse_glossary(&fs.read_to_string(&path)?, slugs),
+            uses: Mutex::default(),
+        }))
+    }
+
+    /// Finds the first use of each term in `html` (a rendered page at `relative_path`, `depth`
+    /// deep), recording it for the glossary's backlinks, and links it to the glossary entry if
+    /// `link`. Text in links, headings, and code is skipped.
+    pub fn link_terms(
+        &self,
+        html: &str,
+        relative_path: &Path,
+        depth: usize,
+        web_prefix: Option<&str>,
+        link: bool,
+    ) -> String {
+        let glossary_url = utils::page_url(depth, web_prefix, Path::new(GLOSSARY_PAGE));
+        let mut found = vec![false; self.terms.len()];
+        let next_term = |text: &str, found: &[bool]| {
+            self.terms
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !found[*i])
+                .filter_map(|(i, term)| {
+                    Some((i, find_word(text, &utils::escape_text(&term.name))?))
+                })
+                .min_by_key(|(_, start)| *start)
+        };
+        let output = utils::map_text(html, &SKIPPED_ELEMENTS, |mut text| {
+            let mut output = String::with_capacity(text.len());
+            while let Some((i, start)) = next_term(text, &found) {
+                found[i] = true;
+                let end = start + utils::escape_text(&self.terms[i].name).len();
+                output.push_str(&text[..start]);
+                if link {
+                    output.push_str(&format!(
+                        "<a class=\"glossary-term\" href=\"{}#{}\">{}</a>",
+                        glossary_url,
+                        self.terms[i].id,
+                        &text[start..end]
+                    ));
+                } else {
+                    output.push_str(&text[start..end]);
+                }
+                text = &text[end..];
+            }
+            output.push_str(text);
+            output
+        });
+        let mut uses = self.uses.lock().unwrap();
+        for (term, _) in self.terms.iter().zip(found).filter(|(_, found)| *found) {
+            uses.entry(term.id.clone())
+                .or_default()
+                .push(relative_path.to_path_buf());
+        }
+        output
+    }
+
+    /// Renders the glossary page's content: each term with its definition, and a placeholder for
+    /// its backlinks.
+    pub fn page_html(&self, context: &SiteContext) -> anyhow::Result<String> {
+        let mut html = "<dl class=\"glossary\">\n".to_string();
+        for term in &self.terms {
+            html.push_str(&format!(
+                "<dt id=\"{}\">{}</dt>\n<dd>\n{}<!-- {{GLOSSARY_BACKLINKS:{}}} -->\n</dd>\n",
+                term.id,
+                utils::escape_html(&term.name),
+                djot_to_html(&term.definition, context.target_path, context)?,
+                term.id
+            ));
+        }
+        html.push_str("</dl>\n");
+        Ok(html)
+    }
+
+    /// Fills in the backlinks of the glossary page `html`, `depth` deep: the pages that use each
+    /// term.
+    pub fn fill_backlinks(&self, html: &str, depth: usize, web_prefix: Option<&str>) -> String {
+        let uses = self.uses.lock().unwrap();
+        let mut html = html.to_string();
+        for term in &self.terms {
+            // Pages are rendered in parallel, so they're recorded in any order
+            let pages = uses.get(&term.id).map(|pages| {
+                let mut pages = pages.clone();
+                pages.sort();
+                pages
+            });
+            let backlinks = match pages {
+                Some(pages) => format!(
+                    "<p class=\"glossary-backlinks\">Used in: {}</p>",
+                    pages
+                        .iter()
+                        .map(|page| format!(
+                            "<a href=\"{}\">{}</a>",
+                            utils::page_url(depth, web_prefix, page),
+                            utils::escape_html(&page.file_stem().unwrap().to_string_lossy())
+                        ))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                None => String::new(),
+            };
+            html = html.replace(
+                &format!("<!-- {{GLOSSARY_BACKLINKS:{}}} -->", term.id),
+                &backlinks,
+            );
+        }
+        html
+    }
+}
+
+/// Elements whose text is never linked to the glossary.
+const SKIPPED_ELEMENTS: [&str; 11] = [
+    "a", "code", "pre", "script", "style", "h1", "h2", "h3", "h4", "h5", "h6",
+];
+
+/// Splits a glossary into terms. Each term is a line starting with `: `, followed by its
+/// definition (indented, as in a djot definition list):
+///
+/// ```djot
+/// : API
+///
+///   Application programming interface.
+/// ```
+pub fn parse_glossary(glossary: &str, slugs: &SlugConfig) -> Vec<Term> {
+    let mut terms: Vec<Term> = Vec::new();
+    for line in glossary.lines() {
+        if let Some(name) = line.strip_prefix(": ") {
+            let name = name.trim().to_string();
+            let id = match slug::slugify(&name, slugs) {
+                id if id.is_empty() => format!("term-{}", terms.len() + 1),
+                id => id,
+            };
+            terms.push(Term {
+                name,
+                id,
+                definition: String::new(),
+            });
+        } else if let Some(term) = terms.last_mut() {
+            term.definition
+                .push_str(line.strip_prefix("  ").unwrap_or(line));
+            term.definition.push('\n');
+        }
+    }
+    for term in &mut terms {
+        term.definition = term.definition.trim().to_string();
+    }
+    terms
+}
+
+/// Finds `word` in `text`, where it isn't part of a longer word.
+pub fn find_word(text: &str, word: &str) -> Option<usize> {
+    if word.is_empty() {
+        return None;
+    }
+    let is_word_char = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    text.match_indices(word)
+        .map(|(start, _)| start)
+        .find(|start| {
+            !is_word_char(text[..*start].chars().next_back())
+                && !is_word_char(text[start + word.len()..].chars().next())
+        })
+}
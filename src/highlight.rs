@@ -0,0 +1,140 @@
+use std::sync::OnceLock;
+
+use syntect::{
+    highlighting::ThemeSet,
+    html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+use crate::{config::HighlightConfig, utils};
+
+/// The stylesheet with the colors of highlighted code, written to the root of the site.
+pub const STYLESHEET: &str = "highlight.css";
+
+/// The class of highlighted code blocks' `<pre>` elements, with the classes of their spans
+/// prefixed the same way, so they can't clash with a template's classes.
+pub const CODE_CLASS: &str = "hl-code";
+
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// Highlights `code` as `language` (a name or file extension, such as `rust` or `rs`), or
+/// returns `None` if the language isn't known.
+pub fn highlight_code(code: &str, language: &str) -> Option<String> {
+    let syntax_set = syntax_set();
+    let syntax = syntax_set.find_syntax_by_token(language)?;
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, syntax_set, CLASS_STYLE);
+    for line in LinesWithEndings::from(code) {
+        if let Err(e) = generator.parse_html_for_line_which_includes_newline(line) {
+            log::warn!("Could not highlight {} code: {}", language, e);
+            return None;
+        }
+    }
+    Some(format!(
+        "<pre class=\"{}\"><code class=\"language-{}\">{}</code></pre>\n",
+        CODE_CLASS,
+        utils::escape_html(language),
+        generator.finalize()
+    ))
+}
+
+/// Replaces each fenced code block with a known language in a markdown document with its
+/// highlighted html.
+pub fn highlight_markdown_code(events: &mut Vec<pulldown_cmark::Event>) {
+    use pulldown_cmark::{CodeBlockKind, Event, Tag, TagEnd};
+    let mut highlighted = Vec::with_capacity(events.len());
+    let mut block: Option<(String, String)> = None;
+    for event in events.drain(..) {
+        match (&mut block, event) {
+            (None, Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)))) => {
+                // The language is the first word of the info string
+                let language = info.split_whitespace().next().unwrap_or_default();
+                if language.is_empty() {
+                    highlighted.push(Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))));
+                } else {
+                    block = Some((language.to_string(), String::new()));
+                }
+            }
+            (Some((_, code)), Event::Text(text)) => code.push_str(&text),
+            (Some(_), Event::End(TagEnd::CodeBlock)) => {
+                let (language, code) = block.take().unwrap();
+                match highlight_code(&code, &language) {
+                    Some(html) => highlighted.push(Event::Html(html.into())),
+                    None => {
+                        highlighted.extend([
+                            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(language.into()))),
+                            Event::Text(code.into()),
+                            Event::End(TagEnd::CodeBlock),
+                        ]);
+                    }
+                }
+            }
+            (_, event) => highlighted.push(event),
+        }
+    }
+    *events = highlighted;
+}
+
+/// Replaces each code block with a known language in a djot document with its highlighted
+/// html.
+pub fn highlight_djot_code<'s>(events: &mut Vec<jotdown::Event<'s>>) {
+    use jotdown::{Container, Event};
+    let mut highlighted = Vec::with_capacity(events.len());
+    let mut block: Option<(&'s str, jotdown::Attributes<'s>, String)> = None;
+    for event in events.drain(..) {
+        match (&mut block, event) {
+            (None, Event::Start(Container::CodeBlock { language }, attributes))
+                if !language.is_empty() =>
+            {
+                block = Some((language, attributes, String::new()));
+            }
+            (Some((_, _, code)), Event::Str(text)) => code.push_str(&text),
+            (Some(_), Event::End(Container::CodeBlock { .. })) => {
+                let (language, attributes, code) = block.take().unwrap();
+                match highlight_code(&code, language) {
+                    Some(html) => highlighted.extend([
+                        Event::Start(Container::RawBlock { format: "html" }, attributes),
+                        Event::Str(html.into()),
+                        Event::End(Container::RawBlock { format: "html" }),
+                    ]),
+                    None => highlighted.extend([
+                        Event::Start(Container::CodeBlock { language }, attributes),
+                        Event::Str(code.into()),
+                        Event::End(Container::CodeBlock { language }),
+                    ]),
+                }
+            }
+            (_, event) => highlighted.push(event),
+        }
+    }
+    *events = highlighted;
+}
+
+/// Generates the stylesheet for highlighted code, with the colors of the configured theme (and
+/// of the dark theme, for readers who prefer a dark color scheme).
+pub fn stylesheet(config: &HighlightConfig) -> anyhow::Result<String> {
+    let themes = ThemeSet::load_defaults();
+    let theme_css = |name: &str| -> anyhow::Result<String> {
+        let theme = themes.themes.get(name).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown highlight theme {:?}, expected one of: {}",
+                name,
+                themes.themes.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        Ok(css_for_theme_with_class_style(theme, CLASS_STYLE)?)
+    };
+    let mut css = theme_css(&config.theme)?;
+    if let Some(dark_theme) = &config.dark_theme {
+        css.push_str(&format!(
+            "@media (prefers-color-scheme: dark) {{\n{}}}\n",
+            theme_css(dark_theme)?
+        ));
+    }
+    Ok(css)
+}
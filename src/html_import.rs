@@ -0,0 +1,651 @@
+use tl::{Node, NodeHandle, Parser};
+
+use crate::InputFormat;
+
+/// An html page converted to djot or markdown.
+#[derive(Debug)]
+pub struct ConvertedPage {
+    /// The text of the page's `<title>`
+    pub title: Option<String>,
+    /// The `lang` of the page's `<html>` element
+    pub lang: Option<String>,
+    pub markup: String,
+    /// The elements with no equivalent in the markup (such as forms and videos), kept as raw
+    /// html
+    pub raw_elements: usize,
+}
+
+/// Elements converted to blocks of their own.
+const BLOCK_ELEMENTS: [&str; 32] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "center",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hgroup",
+    "hr",
+    "html",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "ul",
+];
+
+/// Elements with no djot or markdown equivalent, kept as raw html (as a block of their own, unless they're
+/// in a paragraph).
+const RAW_ELEMENTS: [&str; 15] = [
+    "audio", "button", "canvas", "details", "embed", "form", "iframe", "input", "math", "object",
+    "picture", "select", "svg", "textarea", "video",
+];
+
+/// Elements left out, along with their contents.
+const SKIPPED_ELEMENTS: [&str; 9] = [
+    "head", "link", "meta", "nav", "noscript", "script", "style", "template", "title",
+];
+
+/// Converts an html page to djot or markdown (`format`). Its content is read from its `<main>`
+/// element, or else its `<body>` without the `<header>`, `<footer>`, and `<nav>` elements around
+/// the content, which the site's template replaces. `link` rewrites the url of each link, e.g. to point to the
+/// converted pages instead of the html pages.
+pub fn html_to_markup(
+    html: &str,
+    format: InputFormat,
+    link: impl Fn(&str) -> String,
+) -> anyhow::Result<ConvertedPage> {
+    let dom = tl::parse(html, tl::ParserOptions::default())?;
+    let parser = dom.parser();
+    let find = |selector: &str| {
+        dom.query_selector(selector)
+            .and_then(|mut found| found.next())
+            .and_then(|handle| handle.get(parser))
+            .and_then(Node::as_tag)
+    };
+    let title = find("title")
+        .map(|title| collapse_whitespace(&decode_entities(&title.inner_text(parser))))
+        .filter(|title| !title.is_empty());
+    let lang = find("html").and_then(|html| {
+        html.attributes()
+            .get("lang")
+            .flatten()
+            .map(|lang| lang.as_utf8_str().to_string())
+    });
+    let mut writer = Writer {
+        parser,
+        format,
+        link: &link,
+        skip_layout: false,
+        raw_elements: 0,
+    };
+    let blocks = match (find("main"), find("body")) {
+        (Some(main), _) => writer.blocks(main.children().top().as_slice()),
+        (None, Some(body)) => {
+            writer.skip_layout = true;
+            writer.blocks(body.children().top().as_slice())
+        }
+        (None, None) => writer.blocks(dom.children()),
+    };
+    Ok(ConvertedPage {
+        title,
+        lang,
+        markup: blocks.join("\n\n") + "\n",
+        raw_elements: writer.raw_elements,
+    })
+}
+
+fn tag_name(handle: &NodeHandle, parser: &Parser) -> Option<String> {
+    handle
+        .get(parser)?
+        .as_tag()
+        .map(|tag| tag.name().as_utf8_str().to_ascii_lowercase())
+}
+
+struct Writer<'p, 'a, F> {
+    parser: &'p Parser<'a>,
+    format: InputFormat,
+    link: &'p F,
+    /// Leaves out `<header>` and `<footer>` elements outside of articles and sections, as the
+    /// page's own
+    skip_layout: bool,
+    raw_elements: usize,
+}
+
+impl<F: Fn(&str) -> String> Writer<'_, '_, F> {
+    /// Converts `children` to blocks, with the text and inline elements between block
+    /// elements as paragraphs (so unclosed `<p>` elements still make a paragraph each).
+    fn blocks(&mut self, children: &[NodeHandle]) -> Vec<String> {
+        let mut blocks = Vec::new();
+        let mut paragraph = String::new();
+        for child in children {
+            let Some(node) = child.get(self.parser) else {
+                continue;
+            };
+            match node {
+                Node::Tag(tag) => {
+                    let name = tag.name().as_utf8_str().to_ascii_lowercase();
+                    if SKIPPED_ELEMENTS.contains(&name.as_str())
+                        || (self.skip_layout && matches!(name.as_str(), "header" | "footer"))
+                    {
+                        continue;
+                    }
+                    let is_raw_block =
+                        RAW_ELEMENTS.contains(&name.as_str()) && paragraph.trim().is_empty();
+                    if BLOCK_ELEMENTS.contains(&name.as_str()) || is_raw_block {
+                        push_paragraph(&mut blocks, &mut paragraph);
+                        blocks.extend(self.block(&name, tag));
+                    } else {
+                        self.inline(child, &mut paragraph);
+                    }
+                }
+                _ => self.inline(child, &mut paragraph),
+            }
+        }
+        push_paragraph(&mut blocks, &mut paragraph);
+        blocks
+    }
+
+    /// Converts the block element `tag` (named `name`).
+    fn block(&mut self, name: &str, tag: &tl::HTMLTag) -> Vec<String> {
+        let children = tag.children();
+        let children = children.top().as_slice();
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let text = self.inline_text(children);
+                if text.is_empty() {
+                    return Vec::new();
+                }
+                let level = name[1..].parse().unwrap_or(1);
+                vec![format!("{} {}", "#".repeat(level), text)]
+            }
+            "hr" => vec!["* * *".to_string()],
+            "pre" => vec![code_block(tag, self.parser)],
+            "blockquote" => {
+                let quoted = self.blocks(children).join("\n\n");
+                if quoted.is_empty() {
+                    return Vec::new();
+                }
+                vec![quoted
+                    .lines()
+                    .map(|line| {
+                        if line.is_empty() {
+                            ">".to_string()
+                        } else {
+                            format!("> {}", line)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")]
+            }
+            "ul" | "ol" => self.list(name, tag),
+            // Markdown has no definition lists
+            "dl" if self.format == InputFormat::Markdown => {
+                self.raw_elements += 1;
+                vec![tag.outer_html(self.parser).trim().to_string()]
+            }
+            "dl" => self.definition_list(children),
+            "table" => self.table(children),
+            _ if RAW_ELEMENTS.contains(&name) => {
+                self.raw_elements += 1;
+                let html = tag.outer_html(self.parser);
+                vec![match self.format {
+                    InputFormat::Djot => format!("```=html\n{}\n```", html.trim()),
+                    InputFormat::Markdown => html.trim().to_string(),
+                }]
+            }
+            "article" | "section" => {
+                let skip_layout = std::mem::replace(&mut self.skip_layout, false);
+                let blocks = self.blocks(children);
+                self.skip_layout = skip_layout;
+                blocks
+            }
+            _ => self.blocks(children),
+        }
+    }
+
+    fn list(&mut self, name: &str, tag: &tl::HTMLTag) -> Vec<String> {
+        let start = tag
+            .attributes()
+            .get("start")
+            .flatten()
+            .and_then(|start| start.as_utf8_str().parse().ok())
+            .unwrap_or(1usize);
+        let mut items = Vec::new();
+        for child in tag.children().top().iter() {
+            let Some(Node::Tag(item)) = child.get(self.parser) else {
+                continue;
+            };
+            if !item.name().as_utf8_str().eq_ignore_ascii_case("li") {
+                continue;
+            }
+            let marker = match name {
+                "ol" => format!("{}. ", start + items.len()),
+                _ => "- ".to_string(),
+            };
+            let blocks = self.blocks(item.children().top().as_slice());
+            items.push((marker, blocks));
+        }
+        if items.is_empty() {
+            return Vec::new();
+        }
+        let is_loose = items.iter().any(|(_, blocks)| blocks.len() > 1);
+        let items: Vec<String> = items
+            .into_iter()
+            .map(|(marker, blocks)| indent(&marker, &blocks.join("\n\n")))
+            .collect();
+        vec![items.join(if is_loose { "\n\n" } else { "\n" })]
+    }
+
+    fn definition_list(&mut self, children: &[NodeHandle]) -> Vec<String> {
+        let mut items = Vec::new();
+        for child in children {
+            let Some(Node::Tag(tag)) = child.get(self.parser) else {
+                continue;
+            };
+            let children = tag.children();
+            let children = children.top().as_slice();
+            match tag.name().as_utf8_str().to_ascii_lowercase().as_str() {
+                "dt" => items.push(format!(": {}", self.inline_text(children))),
+                "dd" => {
+                    let definition = self.blocks(children).join("\n\n");
+                    if let Some(item) = items.last_mut() {
+                        item.push_str("\n\n");
+                        item.push_str(&indent("  ", &definition));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if items.is_empty() {
+            return Vec::new();
+        }
+        vec![items.join("\n\n")]
+    }
+
+    fn table(&mut self, children: &[NodeHandle]) -> Vec<String> {
+        let mut rows = Vec::new();
+        self.table_rows(children, &mut rows);
+        if rows.is_empty() {
+            return Vec::new();
+        }
+        let mut lines = Vec::new();
+        for (i, (cells, is_header)) in rows.iter().enumerate() {
+            lines.push(format!("| {} |", cells.join(" | ")));
+            if i == 0 && *is_header {
+                lines.push(format!("|{}|", vec!["---"; cells.len()].join("|")));
+            }
+        }
+        vec![lines.join("\n")]
+    }
+
+    /// Collects the rows of a table, through its `<thead>`, `<tbody>`, and `<tfoot>`, with
+    /// whether each is a row of header cells.
+    fn table_rows(&mut self, children: &[NodeHandle], rows: &mut Vec<(Vec<String>, bool)>) {
+        for child in children {
+            let Some(Node::Tag(tag)) = child.get(self.parser) else {
+                continue;
+            };
+            let children = tag.children();
+            let children = children.top().as_slice();
+            match tag.name().as_utf8_str().to_ascii_lowercase().as_str() {
+                "thead" | "tbody" | "tfoot" => self.table_rows(children, rows),
+                "tr" => {
+                    let mut cells = Vec::new();
+                    let mut is_header = true;
+                    for cell in children {
+                        match tag_name(cell, self.parser).as_deref() {
+                            Some("th") => {}
+                            Some("td") => is_header = false,
+                            _ => continue,
+                        }
+                        let Some(Node::Tag(cell)) = cell.get(self.parser) else {
+                            continue;
+                        };
+                        let text = self.inline_text(cell.children().top().as_slice());
+                        cells.push(text.replace('|', "\\|"));
+                    }
+                    if !cells.is_empty() {
+                        rows.push((cells, is_header));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Converts `children` to inline text on a single line, as for a heading.
+    fn inline_text(&mut self, children: &[NodeHandle]) -> String {
+        let mut text = String::new();
+        for child in children {
+            self.inline(child, &mut text);
+        }
+        text.replace("\\\n", " ")
+            .replace('\n', " ")
+            .trim()
+            .to_string()
+    }
+
+    /// Appends the markup for the inline node `handle` to `output`.
+    fn inline(&mut self, handle: &NodeHandle, output: &mut String) {
+        let Some(node) = handle.get(self.parser) else {
+            return;
+        };
+        let tag = match node {
+            Node::Raw(text) => {
+                let text = escape_djot(&collapse_whitespace(&decode_entities(&text.as_utf8_str())));
+                // Whitespace between elements collapses to one space
+                match text.strip_prefix(' ') {
+                    Some(rest) if output.is_empty() || output.ends_with([' ', '\n']) => {
+                        output.push_str(rest)
+                    }
+                    _ => output.push_str(&text),
+                }
+                return;
+            }
+            Node::Comment(_) => return,
+            Node::Tag(tag) => tag,
+        };
+        let name = tag.name().as_utf8_str().to_ascii_lowercase();
+        let attribute = |name: &str| {
+            tag.attributes()
+                .get(name)
+                .flatten()
+                .map(|value| decode_entities(&value.as_utf8_str()))
+        };
+        let children = tag.children();
+        let children = children.top().as_slice();
+        match name.as_str() {
+            _ if SKIPPED_ELEMENTS.contains(&name.as_str()) => {}
+            "br" => output.push_str("\\\n"),
+            "a" => {
+                let text = self.contents(children);
+                match attribute("href") {
+                    Some(href) => {
+                        let href = (self.link)(&href);
+                        let text = match text.trim() {
+                            "" => escape_djot(&href),
+                            _ => text,
+                        };
+                        push_wrapped(output, &text, "[", &format!("]({})", href));
+                    }
+                    None => output.push_str(&text),
+                }
+            }
+            "img" => output.push_str(&format!(
+                "![{}]({})",
+                escape_djot(&attribute("alt").unwrap_or_default()),
+                attribute("src").unwrap_or_default()
+            )),
+            "em" | "i" => push_wrapped(output, &self.contents(children), "_", "_"),
+            "q" => push_wrapped(output, &self.contents(children), "\"", "\""),
+            "strong" | "b" | "del" | "s" | "strike" | "ins" | "mark" | "sup" | "sub" => {
+                let (open, close) = match (name.as_str(), self.format) {
+                    ("strong" | "b", InputFormat::Djot) => ("*", "*"),
+                    ("strong" | "b", InputFormat::Markdown) => ("**", "**"),
+                    ("del" | "s" | "strike", InputFormat::Djot) => ("{-", "-}"),
+                    ("del" | "s" | "strike", InputFormat::Markdown) => ("~~", "~~"),
+                    ("ins", InputFormat::Djot) => ("{+", "+}"),
+                    ("mark", InputFormat::Djot) => ("{=", "=}"),
+                    ("sup", InputFormat::Djot) => ("^", "^"),
+                    ("sub", InputFormat::Djot) => ("~", "~"),
+                    // Markdown has no syntax for these, so they're kept as html
+                    (name, _) => {
+                        let contents = self.contents(children);
+                        output.push_str(&format!("<{}>{}</{}>", name, contents.trim(), name));
+                        return;
+                    }
+                };
+                push_wrapped(output, &self.contents(children), open, close)
+            }
+            "code" | "kbd" | "samp" | "tt" => {
+                output.push_str(&verbatim(&decode_entities(&tag.inner_text(self.parser))))
+            }
+            _ if RAW_ELEMENTS.contains(&name.as_str()) => {
+                self.raw_elements += 1;
+                let html = tag.outer_html(self.parser);
+                match self.format {
+                    InputFormat::Djot => {
+                        let fence = "`".repeat(longest_run(&html, '`') + 1);
+                        output.push_str(&format!("{}{}{}{{=html}}", fence, html, fence));
+                    }
+                    InputFormat::Markdown => output.push_str(&html),
+                }
+            }
+            // Block elements inside inline ones (e.g. a `<div>` in a link) are run in
+            _ if BLOCK_ELEMENTS.contains(&name.as_str()) => {
+                let text = self.contents(children);
+                if !output.is_empty() && !output.ends_with([' ', '\n']) {
+                    output.push(' ');
+                }
+                output.push_str(text.trim());
+            }
+            _ => {
+                let text = self.contents(children);
+                output.push_str(&text);
+            }
+        }
+    }
+
+    /// Converts the inline `children` of an element.
+    fn contents(&mut self, children: &[NodeHandle]) -> String {
+        let mut contents = String::new();
+        for child in children {
+            self.inline(child, &mut contents);
+        }
+        contents
+    }
+}
+
+/// Adds the paragraph collected in `paragraph` to `blocks`, if it has any text.
+fn push_paragraph(blocks: &mut Vec<String>, paragraph: &mut String) {
+    let mut text = paragraph.trim();
+    // Line breaks at the ends of a paragraph do nothing
+    while let Some(rest) = text.strip_suffix('\\') {
+        text = rest.trim_end();
+    }
+    while let Some(rest) = text.strip_prefix("\\\n") {
+        text = rest.trim_start();
+    }
+    if !text.is_empty() {
+        // Text that would start another kind of block, e.g. `# not a heading` or `1. not a list`
+        let digits = text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let text = match text.chars().next() {
+            Some('#' | '>' | '-' | '+' | ':' | '|') => format!("\\{}", text),
+            _ if digits > 0 && text[digits..].starts_with(['.', ')']) => {
+                format!("{}\\{}", &text[..digits], &text[digits..])
+            }
+            _ => text.to_string(),
+        };
+        blocks.push(text);
+    }
+    paragraph.clear();
+}
+
+/// Appends `text` between `open` and `close`, with the whitespace at its ends outside them, as
+/// djot requires.
+fn push_wrapped(output: &mut String, text: &str, open: &str, close: &str) {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        output.push_str(text);
+        return;
+    }
+    if text.starts_with(' ') && !output.is_empty() && !output.ends_with([' ', '\n']) {
+        output.push(' ');
+    }
+    output.push_str(open);
+    output.push_str(trimmed);
+    output.push_str(close);
+    if text.ends_with(' ') {
+        output.push(' ');
+    }
+}
+
+/// Indents each line of `text` after the first, which starts with `marker`, to line up with
+/// the text after the marker.
+fn indent(marker: &str, text: &str) -> String {
+    let padding = " ".repeat(marker.len());
+    let mut indented = String::new();
+    for (i, line) in text.lines().enumerate() {
+        if i == 0 {
+            indented.push_str(marker);
+        } else {
+            indented.push('\n');
+            if !line.is_empty() {
+                indented.push_str(&padding);
+            }
+        }
+        indented.push_str(line);
+    }
+    indented
+}
+
+/// A fenced code block of the text in `pre`, with its language from a `language-*` class on it
+/// or on the `<code>` inside it.
+fn code_block(pre: &tl::HTMLTag, parser: &Parser) -> String {
+    let language_of = |tag: &tl::HTMLTag| {
+        tag.attributes()
+            .class_iter()?
+            .find_map(|class| class.strip_prefix("language-").map(str::to_string))
+    };
+    let code_tag = pre
+        .children()
+        .top()
+        .iter()
+        .find_map(|child| child.get(parser)?.as_tag())
+        .filter(|tag| tag.name().as_utf8_str().eq_ignore_ascii_case("code"));
+    let language = language_of(pre)
+        .or_else(|| code_tag.and_then(language_of))
+        .unwrap_or_default();
+    let code = decode_entities(&pre.inner_text(parser));
+    let code = code.strip_prefix('\n').unwrap_or(&code).trim_end();
+    let fence = "`".repeat((longest_run(code, '`') + 1).max(3));
+    format!("{}{}\n{}\n{}", fence, language, code, fence)
+}
+
+/// Inline verbatim text, delimited by more backticks than it contains in a row.
+fn verbatim(text: &str) -> String {
+    let text = collapse_whitespace(text);
+    let fence = "`".repeat(longest_run(&text, '`') + 1);
+    // Verbatim text starting or ending with a backtick is padded with a space
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{} {} {}", fence, text, fence)
+    } else {
+        format!("{}{}{}", fence, text, fence)
+    }
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|other| other != c)
+        .map(str::len)
+        .max()
+        .unwrap_or_default()
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_whitespace = false;
+    for c in text.chars() {
+        // Not a non-breaking space
+        if c.is_ascii_whitespace() {
+            if !in_whitespace {
+                collapsed.push(' ');
+            }
+            in_whitespace = true;
+        } else {
+            collapsed.push(c);
+            in_whitespace = false;
+        }
+    }
+    collapsed
+}
+
+/// Escapes the characters of `text` that djot or markdown would read as markup.
+fn escape_djot(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '*' | '_' | '[' | ']' | '`' | '{' | '}' | '^' | '~' | '<' | '$'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Decodes the character references in html text, such as `&amp;` and `&#8212;`. Unknown
+/// references are left as they are.
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(';').filter(|end| *end <= 10);
+        let c = end.and_then(|end| match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "hellip" => Some('…'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            "copy" => Some('©'),
+            "reg" => Some('®'),
+            "trade" => Some('™'),
+            "middot" => Some('·'),
+            "bull" => Some('•'),
+            "times" => Some('×'),
+            "laquo" => Some('«'),
+            "raquo" => Some('»'),
+            reference => {
+                let number = reference.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (c, end) {
+            (Some(c), Some(end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
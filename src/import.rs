@@ -0,0 +1,768 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+
+use clap::ValueEnum;
+
+use crate::{
+    html_import,
+    metadata::{self, RawFrontMatter},
+    utils,
+    vfs::FileSystem,
+    InputFormat,
+};
+
+/// A static site generator whose sites can be imported, or a folder of html pages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ImportSource {
+    Jekyll,
+    Hugo,
+    Html,
+}
+
+/// What was written by an import.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub pages: usize,
+    pub files: usize,
+    /// Redirect pages written at the old urls of pages (and their aliases)
+    pub redirects: usize,
+}
+
+/// A page of the imported site.
+struct Page {
+    source: PathBuf,
+    /// Where the page is written, relative to the target directory
+    path: PathBuf,
+    /// The date from a Jekyll post's file name
+    date: Option<String>,
+    draft: bool,
+}
+
+/// The site's options that affect its pages' urls.
+struct SiteOptions {
+    /// Jekyll's `permalink` pattern for posts
+    permalink: String,
+    /// Hugo's `uglyURLs`
+    ugly_urls: bool,
+}
+
+/// Converts the Jekyll or Hugo site in `source` into a simple-ssg site in `target`, which must
+/// be empty or not exist yet:
+///
+/// - Pages keep their place in the site, except Jekyll posts and drafts, which move to `posts/`
+///   without the date in their file names, and Hugo's `_index.md` pages, which become
+///   `index.md`. Other files (and Hugo's `static/` files) are copied as they are.
+/// - Front matter keeps its format. `categories` become `tags`, and `published: false` becomes
+///   `draft: true`. Keys only meaningful to the source's layouts are removed.
+/// - A redirect page is written at each page's old url (from its permalink, or the source's url
+///   rules) if it moved, and at each of its `aliases` or `redirect_from` urls.
+/// - Code highlighting, links to other pages, and Hugo figures are converted to markdown, and
+///   other Liquid tags and shortcodes are left as they are, with a warning.
+/// - The site's title and language are written to `ssg.toml`.
+///
+/// A folder of html pages is converted to djot pages in the same places (see
+/// `html_import::html_to_markup`), with links between them pointing to the djot pages, and its
+/// other files are copied.
+pub fn import_site(
+    fs: &dyn FileSystem,
+    from: ImportSource,
+    source: &Path,
+    target: &Path,
+) -> anyhow::Result<ImportReport> {
+    if fs.is_dir(target) && fs.walk(target).into_iter().flatten().nth(1).is_some() {
+        return Err(anyhow::anyhow!(
+            "{} isn't empty, import into a new directory",
+            target.display()
+        ));
+    }
+    let config = read_config(fs, from, source)?;
+    let options = SiteOptions {
+        permalink: config
+            .as_ref()
+            .and_then(|config| config.text("permalink"))
+            .unwrap_or_else(|| "date".to_string()),
+        ugly_urls: config
+            .as_ref()
+            .and_then(|config| config.bool("uglyURLs"))
+            .unwrap_or(false),
+    };
+    let (pages, files) = match from {
+        ImportSource::Jekyll => jekyll_files(fs, source)?,
+        ImportSource::Hugo => hugo_files(fs, source)?,
+        ImportSource::Html => html_files(fs, source)?,
+    };
+    let mut report = ImportReport::default();
+    for (file, path) in files {
+        fs.create_dir_all(target.join(&path).parent().unwrap())?;
+        fs.copy(&file, &target.join(&path))?;
+        report.files += 1;
+    }
+    let new_paths: HashMap<&Path, &Path> = pages
+        .iter()
+        .map(|page| (page.source.as_path(), page.path.as_path()))
+        .collect();
+    let mut redirects = Vec::new();
+    for page in &pages {
+        let input = fs.read_to_string(&page.source)?;
+        if from == ImportSource::Html {
+            import_html_page(fs, &input, page, source, target, &new_paths)?;
+            report.pages += 1;
+            continue;
+        }
+        let (mut front_matter, body) = match metadata::split_front_matter(&input) {
+            Some((delimiter, Some((front_matter, body)))) => (
+                RawFrontMatter::parse(front_matter, delimiter, &page.source)?,
+                body,
+            ),
+            _ => (RawFrontMatter::Yaml(serde_yaml::Mapping::new()), &input[..]),
+        };
+        let old_url = match from {
+            ImportSource::Jekyll => jekyll_url(page, &front_matter, source, &options),
+            ImportSource::Hugo => hugo_url(page, &front_matter, source, &options),
+            ImportSource::Html => None,
+        };
+        redirects.extend(
+            old_url
+                .into_iter()
+                .chain(front_matter.list("aliases"))
+                .chain(front_matter.list("redirect_from"))
+                .map(|url| (url, page.path.clone())),
+        );
+        convert_front_matter(&mut front_matter, page)?;
+        let (body, unconverted) = match from {
+            ImportSource::Jekyll => convert_liquid(body, page, source, &new_paths),
+            ImportSource::Hugo => convert_shortcodes(body, page, source, &new_paths),
+            ImportSource::Html => (body.to_string(), 0),
+        };
+        if unconverted > 0 {
+            log::warn!(
+                "{:?} has {} {} that couldn't be converted, and were left as they are",
+                &page.source,
+                unconverted,
+                match from {
+                    ImportSource::Jekyll => "Liquid tag(s)",
+                    ImportSource::Hugo | ImportSource::Html => "shortcode(s)",
+                }
+            );
+        }
+        let path = target.join(&page.path);
+        fs.create_dir_all(path.parent().unwrap())?;
+        fs.write(
+            &path,
+            format!("{}{}", front_matter.to_block()?, body).as_bytes(),
+        )?;
+        report.pages += 1;
+    }
+    for (url, page) in redirects {
+        let Some(from_path) = url_path(&url) else {
+            continue;
+        };
+        let to_path = page.with_extension("html");
+        let from_file = target.join(&from_path);
+        if from_path == to_path || fs.exists(&from_file) || is_page_path(&pages, &from_path) {
+            continue;
+        }
+        let url = utils::page_url(from_path.components().count(), None, &to_path);
+        fs.create_dir_all(from_file.parent().unwrap())?;
+        fs.write(&from_file, utils::redirect_html(&url).as_bytes())?;
+        report.redirects += 1;
+    }
+    if let Some(config) = config {
+        let mut site_config = toml::Table::new();
+        if let Some(title) = config.text("title") {
+            site_config.insert("title".to_string(), title.into());
+        }
+        if let Some(lang) = config.text("lang").or(config.text("languageCode")) {
+            site_config.insert("lang".to_string(), lang.into());
+        }
+        if !site_config.is_empty() {
+            fs.write(
+                &target.join("ssg.toml"),
+                toml::to_string(&site_config)?.as_bytes(),
+            )?;
+        }
+    }
+    Ok(report)
+}
+
+/// Reads the site's config, `_config.yml` for Jekyll and `hugo.toml` (or `config.toml`, or
+/// their yaml versions) for Hugo.
+fn read_config(
+    fs: &dyn FileSystem,
+    from: ImportSource,
+    source: &Path,
+) -> anyhow::Result<Option<RawFrontMatter>> {
+    let names: &[&str] = match from {
+        ImportSource::Jekyll => &["_config.yml", "_config.yaml"],
+        ImportSource::Hugo => &["hugo.toml", "config.toml", "hugo.yaml", "config.yaml"],
+        ImportSource::Html => &[],
+    };
+    for name in names {
+        let path = source.join(name);
+        if fs.is_file(&path) {
+            let delimiter = if name.ends_with(".toml") {
+                "+++"
+            } else {
+                "---"
+            };
+            return Ok(Some(RawFrontMatter::parse(
+                &fs.read_to_string(&path)?,
+                delimiter,
+                &path,
+            )?));
+        }
+    }
+    Ok(None)
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "md" || ext == "markdown")
+}
+
+fn is_page_path(pages: &[Page], path: &Path) -> bool {
+    pages
+        .iter()
+        .any(|page| page.path.with_extension("html") == path)
+}
+
+/// The pages and other files of a Jekyll site, with where they're written.
+#[allow(clippy::type_complexity)]
+fn jekyll_files(
+    fs: &dyn FileSystem,
+    source: &Path,
+) -> anyhow::Result<(Vec<Page>, Vec<(PathBuf, PathBuf)>)> {
+    let mut pages = Vec::new();
+    let mut files = Vec::new();
+    for entry in fs.walk(source) {
+        let (file, _) = entry?;
+        let relative = file.strip_prefix(source)?.to_path_buf();
+        let first = relative
+            .components()
+            .next()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .unwrap_or_default();
+        let is_skipped = relative.components().any(|c| {
+            let name = c.as_os_str().to_string_lossy();
+            name.starts_with('.')
+                || (name.starts_with('_') && name != "_posts" && name != "_drafts")
+        }) || ["Gemfile", "Gemfile.lock", "node_modules", "vendor"]
+            .contains(&first.as_str());
+        if is_skipped || !fs.is_file(&file) {
+            continue;
+        }
+        if first == "_posts" || first == "_drafts" {
+            if !is_markdown(&file) {
+                continue;
+            }
+            // Posts are named `YYYY-MM-DD-title.md`
+            let stem = file.file_stem().unwrap().to_string_lossy();
+            let (date, name) = match (stem.get(..10), stem.get(11..)) {
+                (Some(date), Some(name)) if date.parse::<toml::value::Datetime>().is_ok() => {
+                    (Some(date.to_string()), name.to_string())
+                }
+                _ => (None, stem.to_string()),
+            };
+            let dir = relative.parent().unwrap().strip_prefix(&first)?;
+            pages.push(Page {
+                source: file.clone(),
+                path: Path::new("posts").join(dir).join(name).with_extension("md"),
+                date,
+                draft: first == "_drafts",
+            });
+        } else if is_markdown(&file) {
+            pages.push(Page {
+                source: file.clone(),
+                path: relative.with_extension("md"),
+                date: None,
+                draft: false,
+            });
+        } else if file.extension().is_some_and(|ext| ext == "html")
+            && metadata::split_front_matter(&fs.read_to_string(&file)?).is_some()
+        {
+            log::warn!(
+                "{:?} is an html page rendered by Jekyll, and wasn't imported; write it as markdown or a template",
+                &file
+            );
+        } else {
+            files.push((file, relative));
+        }
+    }
+    Ok((pages, files))
+}
+
+/// The pages and other files of a Hugo site (its `content/` and `static/`), with where they're
+/// written.
+#[allow(clippy::type_complexity)]
+fn hugo_files(
+    fs: &dyn FileSystem,
+    source: &Path,
+) -> anyhow::Result<(Vec<Page>, Vec<(PathBuf, PathBuf)>)> {
+    let mut pages = Vec::new();
+    let mut files = Vec::new();
+    for dir in ["content", "static"] {
+        let root = source.join(dir);
+        if !fs.is_dir(&root) {
+            continue;
+        }
+        for entry in fs.walk(&root) {
+            let (file, _) = entry?;
+            let relative = file.strip_prefix(&root)?.to_path_buf();
+            if !fs.is_file(&file) {
+                continue;
+            }
+            if dir == "content" && is_markdown(&file) {
+                let path = if file.file_stem().is_some_and(|stem| stem == "_index") {
+                    relative.with_file_name("index.md")
+                } else {
+                    relative.with_extension("md")
+                };
+                pages.push(Page {
+                    source: file.clone(),
+                    path,
+                    date: None,
+                    draft: false,
+                });
+            } else {
+                files.push((file, relative));
+            }
+        }
+    }
+    Ok((pages, files))
+}
+
+/// The html pages of a folder, written as djot pages, and its other files.
+#[allow(clippy::type_complexity)]
+fn html_files(
+    fs: &dyn FileSystem,
+    source: &Path,
+) -> anyhow::Result<(Vec<Page>, Vec<(PathBuf, PathBuf)>)> {
+    let mut pages = Vec::new();
+    let mut files = Vec::new();
+    for entry in fs.walk(source) {
+        let (file, _) = entry?;
+        let relative = file.strip_prefix(source)?.to_path_buf();
+        let is_hidden = relative
+            .components()
+            .any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+        if is_hidden || !fs.is_file(&file) {
+            continue;
+        }
+        if file
+            .extension()
+            .is_some_and(|ext| ext == "html" || ext == "htm")
+        {
+            pages.push(Page {
+                source: file.clone(),
+                path: relative.with_extension("dj"),
+                date: None,
+                draft: false,
+            });
+        } else {
+            files.push((file, relative));
+        }
+    }
+    Ok((pages, files))
+}
+
+/// Converts the html page `input` to djot, with its `<title>` (if it isn't the text of its
+/// first heading) and `lang` in its front matter.
+fn import_html_page(
+    fs: &dyn FileSystem,
+    input: &str,
+    page: &Page,
+    source: &Path,
+    target: &Path,
+    new_paths: &HashMap<&Path, &Path>,
+) -> anyhow::Result<()> {
+    let converted = html_import::html_to_markup(input, InputFormat::Djot, |href| {
+        html_link(href, page, source, new_paths)
+    })?;
+    if converted.raw_elements > 0 {
+        log::warn!(
+            "{:?} has {} element(s) with no djot equivalent, which were kept as raw html",
+            &page.source,
+            converted.raw_elements
+        );
+    }
+    let mut front_matter = toml::Table::new();
+    let first_heading = converted
+        .markup
+        .lines()
+        .find_map(|line| line.strip_prefix("# "));
+    if let Some(title) = converted.title {
+        if first_heading.is_none_or(|heading| heading.replace('\\', "") != title) {
+            front_matter.insert("title".to_string(), title.into());
+        }
+    }
+    if let Some(lang) = converted.lang {
+        front_matter.insert("lang".to_string(), lang.into());
+    }
+    let front_matter = match front_matter.is_empty() {
+        true => String::new(),
+        false => RawFrontMatter::Toml(front_matter).to_block()?,
+    };
+    let path = target.join(&page.path);
+    fs.create_dir_all(path.parent().unwrap())?;
+    fs.write(
+        &path,
+        format!("{}{}", front_matter, converted.markup).as_bytes(),
+    )?;
+    Ok(())
+}
+
+/// The link from `page` to `href`, pointing to the djot page if it's one of the imported html
+/// pages.
+fn html_link(href: &str, page: &Page, source: &Path, new_paths: &HashMap<&Path, &Path>) -> String {
+    if href.contains(':') || href.starts_with('#') {
+        return href.to_string();
+    }
+    let (path, fragment) = match href.find(['?', '#']) {
+        Some(end) => href.split_at(end),
+        None => (href, ""),
+    };
+    let file = match path.strip_prefix('/') {
+        Some(path) => source.join(path),
+        None => page.source.parent().unwrap().join(path),
+    };
+    let file = if path.is_empty() || path.ends_with('/') {
+        file.join("index.html")
+    } else {
+        file
+    };
+    match new_paths.get(utils::normalize_path(&file).as_path()) {
+        Some(new_path) => format!("{}{}", page_link(page, new_path), fragment),
+        None => href.to_string(),
+    }
+}
+
+/// The url of a Jekyll page: its `permalink`, or for posts, the site's `permalink` pattern (or
+/// style, such as `pretty`) filled in.
+fn jekyll_url(
+    page: &Page,
+    front_matter: &RawFrontMatter,
+    source: &Path,
+    options: &SiteOptions,
+) -> Option<String> {
+    if let Some(permalink) = front_matter.text("permalink") {
+        return Some(permalink);
+    }
+    page.date.as_ref()?;
+    let date = page.date.as_deref().unwrap_or_default();
+    let title = page.source.file_stem()?.to_string_lossy();
+    let title = title.get(11..).unwrap_or(&title);
+    let mut categories = front_matter.list("categories");
+    categories.extend(front_matter.list("category"));
+    // Categories are also the folders the post is in, under `_posts`
+    let dir = page.source.parent()?.strip_prefix(source).ok()?;
+    let categories = dir
+        .components()
+        .skip(1)
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .chain(
+            categories
+                .iter()
+                .flat_map(|categories| categories.split_whitespace().map(str::to_string)),
+        )
+        .collect::<Vec<_>>()
+        .join("/");
+    let pattern = match options.permalink.as_str() {
+        "date" => "/:categories/:year/:month/:day/:title:output_ext",
+        "pretty" => "/:categories/:year/:month/:day/:title/",
+        "none" => "/:categories/:title:output_ext",
+        pattern => pattern,
+    };
+    let url = pattern
+        .replace(":categories", &categories)
+        .replace(":year", date.get(..4)?)
+        .replace(":month", date.get(5..7)?)
+        .replace(":day", date.get(8..10)?)
+        .replace(":title", title)
+        .replace(
+            ":slug",
+            &front_matter.text("slug").unwrap_or(title.to_string()),
+        )
+        .replace(":output_ext", ".html");
+    // Empty categories leave double slashes
+    let mut collapsed = String::with_capacity(url.len());
+    for c in url.chars() {
+        if !(c == '/' && collapsed.ends_with('/')) {
+            collapsed.push(c);
+        }
+    }
+    Some(collapsed)
+}
+
+/// The url of a Hugo page: its `url`, or its section and `slug` (or file name), as a folder
+/// unless the site has `uglyURLs`.
+fn hugo_url(
+    page: &Page,
+    front_matter: &RawFrontMatter,
+    source: &Path,
+    options: &SiteOptions,
+) -> Option<String> {
+    if let Some(url) = front_matter.text("url") {
+        return Some(url);
+    }
+    let relative = page.source.strip_prefix(source.join("content")).ok()?;
+    let dir = relative
+        .parent()?
+        .components()
+        .map(|c| format!("/{}", c.as_os_str().to_string_lossy()))
+        .collect::<String>();
+    let stem = relative.file_stem()?.to_string_lossy();
+    if stem == "_index" || stem == "index" {
+        return Some(format!("{}/", dir));
+    }
+    let name = front_matter.text("slug").unwrap_or(stem.to_string());
+    Some(if options.ugly_urls {
+        format!("{}/{}.html", dir, name)
+    } else {
+        format!("{}/{}/", dir, name)
+    })
+}
+
+/// The file a url is served from, relative to the site, e.g. `blog/post/index.html` for
+/// `/blog/post/`. External urls have none.
+fn url_path(url: &str) -> Option<PathBuf> {
+    if url.contains("://") {
+        return None;
+    }
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    let path = url.trim_start_matches('/');
+    let path = if path.is_empty() || path.ends_with('/') {
+        format!("{}index.html", path)
+    } else if Path::new(path).extension().is_none() {
+        format!("{}/index.html", path)
+    } else {
+        path.to_string()
+    };
+    let path = PathBuf::from(path);
+    // Urls can't point outside the site
+    path.components()
+        .all(|c| matches!(c, Component::Normal(_)))
+        .then_some(path)
+}
+
+/// Converts the source's front matter conventions to simple-ssg's.
+fn convert_front_matter(front_matter: &mut RawFrontMatter, page: &Page) -> anyhow::Result<()> {
+    if let (Some(date), false) = (&page.date, front_matter.contains("date")) {
+        front_matter.set("date", date.clone().into())?;
+    }
+    // Dates with a time and offset that simple-ssg can't read (e.g. Jekyll's
+    // `2024-01-31 10:00:00 +0000`) are kept to the day
+    if let Some(date) = front_matter.text("date") {
+        if date.parse::<toml::value::Datetime>().is_err() {
+            if let Some(day) = date
+                .get(..10)
+                .filter(|day| day.parse::<toml::value::Datetime>().is_ok())
+            {
+                front_matter.set("date", day.to_string().into())?;
+            }
+        }
+    }
+    let mut tags = front_matter.list("tags");
+    // Jekyll allows space separated lists
+    if tags.len() == 1 && front_matter.text("tags").is_some() {
+        tags = tags[0].split_whitespace().map(str::to_string).collect();
+    }
+    for key in ["categories", "category"] {
+        for category in front_matter.list(key) {
+            tags.extend(category.split_whitespace().map(str::to_string));
+        }
+    }
+    tags.dedup();
+    if !tags.is_empty() {
+        front_matter.set("tags", tags.into())?;
+    }
+    if page.draft || front_matter.bool("published") == Some(false) {
+        front_matter.set("draft", true.into())?;
+    }
+    for key in [
+        "layout",
+        "permalink",
+        "url",
+        "slug",
+        "aliases",
+        "redirect_from",
+        "categories",
+        "category",
+        "published",
+    ] {
+        front_matter.remove(key);
+    }
+    Ok(())
+}
+
+/// Replaces each tag between `open` and `close` in `body` with `convert` of its contents
+/// (trimmed), returning the new body and the number of tags left as they are.
+fn replace_tags(
+    body: &str,
+    open: &str,
+    close: &str,
+    mut convert: impl FnMut(&str) -> Option<String>,
+) -> (String, usize) {
+    let mut output = String::with_capacity(body.len());
+    let mut unconverted = 0;
+    let mut rest = body;
+    while let Some(start) = rest.find(open) {
+        let Some(end) = rest[start..].find(close) else {
+            break;
+        };
+        let end = start + end + close.len();
+        output.push_str(&rest[..start]);
+        let tag = &rest[start..end];
+        let contents =
+            tag[open.len()..tag.len() - close.len()].trim_matches(['-', ' ', '\t', '\n']);
+        match convert(contents) {
+            Some(replacement) => output.push_str(&replacement),
+            None => {
+                output.push_str(tag);
+                unconverted += 1;
+            }
+        }
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    (output, unconverted)
+}
+
+/// The link from `page` to the imported page at `path`.
+fn page_link(page: &Page, path: &Path) -> String {
+    utils::page_url(page.path.components().count(), None, path)
+}
+
+/// Converts Jekyll's Liquid tags for code highlighting and links to other pages.
+fn convert_liquid(
+    body: &str,
+    page: &Page,
+    source: &Path,
+    new_paths: &HashMap<&Path, &Path>,
+) -> (String, usize) {
+    let (body, unconverted) = replace_tags(body, "{%", "%}", |tag| {
+        let (name, args) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let args = args.trim();
+        match name {
+            "highlight" => Some(format!(
+                "```{}",
+                args.split_whitespace().next().unwrap_or_default()
+            )),
+            "endhighlight" => Some("```".to_string()),
+            "raw" | "endraw" => Some(String::new()),
+            // `{% post_url 2024-01-31-title %}`, from `_posts`
+            "post_url" => new_paths
+                .iter()
+                .find(|(post, _)| {
+                    post.strip_prefix(source.join("_posts"))
+                        .is_ok_and(|post| post.with_extension("") == Path::new(args))
+                })
+                .map(|(_, path)| page_link(page, path)),
+            // `{% link about.md %}`, from the site root
+            "link" => new_paths
+                .get(source.join(args).as_path())
+                .map(|path| page_link(page, path)),
+            _ => None,
+        }
+    });
+    let (body, other) = replace_tags(&body, "{{", "}}", |_| None);
+    (body, unconverted + other)
+}
+
+/// Converts Hugo's shortcodes for code highlighting, links to other pages, and figures.
+fn convert_shortcodes(
+    body: &str,
+    page: &Page,
+    source: &Path,
+    new_paths: &HashMap<&Path, &Path>,
+) -> (String, usize) {
+    let content = source.join("content");
+    let convert = |tag: &str| {
+        let (name, args) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let args = parse_arguments(args);
+        let arg = |key: &str, position: usize| {
+            args.iter()
+                .find(|(name, _)| name.as_deref() == Some(key))
+                .or(args.iter().filter(|(name, _)| name.is_none()).nth(position))
+                .map(|(_, value)| value.as_str())
+        };
+        match name {
+            "highlight" => Some(format!("```{}", arg("lang", 0).unwrap_or_default())),
+            "/highlight" => Some("```".to_string()),
+            // `{{< ref "posts/first.md#intro" >}}`, from the page, the content root, or by name
+            "ref" | "relref" => {
+                let target = arg("path", 0)?;
+                let (target, anchor) = match target.split_once('#') {
+                    Some((target, anchor)) => (target, format!("#{}", anchor)),
+                    None => (target, String::new()),
+                };
+                let from_dir = page.source.parent().unwrap();
+                let candidates = [
+                    content.join(target.trim_start_matches('/')),
+                    from_dir.join(target),
+                ];
+                let path = candidates
+                    .iter()
+                    .flat_map(|candidate| {
+                        [
+                            candidate.clone(),
+                            candidate.with_extension("md"),
+                            candidate.join("_index.md"),
+                            candidate.join("index.md"),
+                        ]
+                    })
+                    .find_map(|candidate| new_paths.get(candidate.as_path()).copied())
+                    .or_else(|| {
+                        new_paths
+                            .iter()
+                            .find(|(source, _)| source.file_name() == Path::new(target).file_name())
+                            .map(|(_, path)| *path)
+                    })?;
+                Some(format!("{}{}", page_link(page, path), anchor))
+            }
+            "figure" => {
+                let src = arg("src", 0)?;
+                let alt = arg("alt", 1).or(arg("caption", 1)).unwrap_or_default();
+                Some(match arg("title", 2) {
+                    Some(title) => format!("![{}]({} \"{}\")", alt, src, title),
+                    None => format!("![{}]({})", alt, src),
+                })
+            }
+            _ => None,
+        }
+    };
+    let (body, angle) = replace_tags(body, "{{<", ">}}", convert);
+    let (body, percent) = replace_tags(&body, "{{%", "%}}", convert);
+    (body, angle + percent)
+}
+
+/// Splits a shortcode's arguments, each `"positional"` or `name="value"` (quoted or not).
+fn parse_arguments(args: &str) -> Vec<(Option<String>, String)> {
+    let mut parsed = Vec::new();
+    let mut rest = args.trim();
+    while !rest.is_empty() {
+        let (name, value_start) = match rest.find('=') {
+            Some(equals)
+                if !rest[..equals].contains(char::is_whitespace)
+                    && !rest[..equals].contains('"') =>
+            {
+                (Some(rest[..equals].to_string()), &rest[equals + 1..])
+            }
+            _ => (None, rest),
+        };
+        let (value, after) = match value_start.strip_prefix('"') {
+            Some(quoted) => match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            },
+            None => match value_start.find(char::is_whitespace) {
+                Some(end) => (&value_start[..end], &value_start[end..]),
+                None => (value_start, ""),
+            },
+        };
+        parsed.push((name, value.to_string()));
+        rest = after.trim_start();
+    }
+    parsed
+}
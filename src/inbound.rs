@@ -0,0 +1,93 @@
+use std::path::{Path, PathBuf};
+
+use crate::SiteContext;
+
+/// Warns about each page in the sitemap of a previous deployment (a file, or an `http(s)` url)
+/// that this build didn't write, as a page or a redirect, so links to it would break.
+pub fn check_previous_sitemap(sitemap: &str, context: &SiteContext) -> anyhow::Result<()> {
+    log::info!("Checking the pages of the previous sitemap {}...", sitemap);
+    let mut missing = 0;
+    for url in sitemap_urls(sitemap, context)? {
+        let page = page_path(&url, context.config.sitemap.base_url.as_deref());
+        if !context.fs.is_file(&context.output_path.join(&page)) {
+            missing += 1;
+            context.warn(format!(
+                "{} was in the previous sitemap, but isn't in this build (and has no redirect)",
+                url
+            ));
+        }
+    }
+    log::info!("{} page(s) of the previous sitemap are missing", missing);
+    Ok(())
+}
+
+/// The page urls of `sitemap`, following a sitemap index to its sitemaps. The sitemaps of an
+/// index read from a file are read from beside it.
+fn sitemap_urls(sitemap: &str, context: &SiteContext) -> anyhow::Result<Vec<String>> {
+    let xml = if is_url(sitemap) {
+        ureq::get(sitemap).call()?.into_string()?
+    } else {
+        context.fs.read_to_string(Path::new(sitemap))?
+    };
+    if !xml.contains("<sitemapindex") {
+        return Ok(locations(&xml));
+    }
+    let mut urls = Vec::new();
+    for location in locations(&xml) {
+        let child = if is_url(sitemap) {
+            location
+        } else {
+            let name = location.rsplit('/').next().unwrap_or(&location);
+            Path::new(sitemap)
+                .with_file_name(name)
+                .to_string_lossy()
+                .to_string()
+        };
+        urls.extend(sitemap_urls(&child, context)?);
+    }
+    Ok(urls)
+}
+
+fn is_url(sitemap: &str) -> bool {
+    sitemap.starts_with("http://") || sitemap.starts_with("https://")
+}
+
+/// The text of each `<loc>` element in `xml`.
+fn locations(xml: &str) -> Vec<String> {
+    xml.split("<loc>")
+        .skip(1)
+        .filter_map(|rest| rest.split_once("</loc>"))
+        .map(|(location, _)| unescape_xml(location.trim()))
+        .collect()
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// The output path of the page at `url`: relative to `base_url` if it's under it, and
+/// otherwise the url's path. Urls of directories are their `index.html`.
+fn page_path(url: &str, base_url: Option<&str>) -> PathBuf {
+    let base_url = base_url.map(|base_url| base_url.trim_end_matches('/'));
+    let path = match base_url.and_then(|base_url| url.strip_prefix(base_url)) {
+        Some(path) => path,
+        None => {
+            let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+            without_scheme
+                .find('/')
+                .map_or("", |start| &without_scheme[start..])
+        }
+    };
+    let path = path.split(['#', '?']).next().unwrap_or("");
+    let path = path.trim_start_matches('/');
+    if path.is_empty() || path.ends_with('/') {
+        Path::new(path).join("index.html")
+    } else {
+        PathBuf::from(path)
+    }
+}
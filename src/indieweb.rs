@@ -0,0 +1,175 @@
+use std::path::Path;
+
+use crate::{config::IndieWebConfig, templating::PageVariables, utils, SiteContext};
+
+/// The webfinger stub, relative to the output directory.
+pub const WEBFINGER: &str = ".well-known/webfinger";
+
+/// The user and host of a fediverse account such as `@user@example.social`.
+fn fediverse_account(account: &str) -> Option<(&str, &str)> {
+    let (user, host) = account.trim_start_matches('@').split_once('@')?;
+    (!user.is_empty() && !host.is_empty() && !host.contains(['@', '/'])).then_some((user, host))
+}
+
+/// Adds the webmention and IndieAuth endpoints of `config`, and the author's profiles
+/// elsewhere, to the `<head>` of `html` as `<link>`s.
+pub fn add_links(html: &str, config: &IndieWebConfig) -> String {
+    let Some(end) = html.find("</head>") else {
+        return html.to_string();
+    };
+    let mut links = String::new();
+    for (rel, url) in [
+        ("webmention", &config.webmention),
+        ("authorization_endpoint", &config.authorization_endpoint),
+        ("token_endpoint", &config.token_endpoint),
+    ] {
+        if let Some(url) = url {
+            links.push_str(&format!(
+                "<link rel=\"{}\" href=\"{}\">\n",
+                rel,
+                utils::escape_html(url)
+            ));
+        }
+    }
+    let fediverse = config.fediverse.as_deref().and_then(fediverse_account);
+    let fediverse_profile = fediverse.map(|(user, host)| format!("https://{}/@{}", host, user));
+    for url in config.me.iter().chain(&fediverse_profile) {
+        links.push_str(&format!(
+            "<link rel=\"me\" href=\"{}\">\n",
+            utils::escape_html(url)
+        ));
+    }
+    if let Some((user, host)) = fediverse {
+        links.push_str(&format!(
+            "<meta name=\"fediverse:creator\" content=\"@{}@{}\">\n",
+            utils::escape_html(user),
+            utils::escape_html(host)
+        ));
+    }
+    format!("{}{}{}", &html[..end], links, &html[end..])
+}
+
+/// The `<!-- {H_CARD} -->` of a page at `depth`: a visible h-card of the site's author, or
+/// nothing if `author` isn't set.
+pub fn h_card(config: &IndieWebConfig, depth: usize, context: &SiteContext) -> String {
+    let Some(author) = &config.author else {
+        return String::new();
+    };
+    let mut card = "<div class=\"h-card site-author\">\n".to_string();
+    if let Some(photo) = &config.author_photo {
+        let url = if photo.contains("://") || photo.starts_with('/') {
+            photo.clone()
+        } else {
+            utils::page_url(depth, context.web_prefix, Path::new(photo))
+        };
+        card.push_str(&format!(
+            "<img class=\"u-photo\" src=\"{}\" alt=\"\">\n",
+            utils::escape_html(&url)
+        ));
+    }
+    match &context.config.sitemap.base_url {
+        Some(base_url) => card.push_str(&format!(
+            "<a class=\"p-name u-url\" href=\"{}\">{}</a>\n",
+            utils::escape_html(base_url),
+            utils::escape_html(author)
+        )),
+        None => card.push_str(&format!(
+            "<span class=\"p-name\">{}</span>\n",
+            utils::escape_html(author)
+        )),
+    }
+    if let Some(note) = &config.author_note {
+        card.push_str(&format!(
+            "<p class=\"p-note\">{}</p>\n",
+            utils::escape_html(note)
+        ));
+    }
+    card.push_str("</div>");
+    card
+}
+
+/// The `<!-- {H_ENTRY} -->` of `page`: its h-entry properties (name, url, date, and author),
+/// hidden, for the element with the `h-entry` class around it.
+pub fn h_entry(page: &PageVariables, config: &IndieWebConfig, context: &SiteContext) -> String {
+    let base_url = context.config.sitemap.base_url.as_deref();
+    let mut entry = "<div class=\"h-entry-properties\" hidden>\n".to_string();
+    if let Some(title) = &page.title {
+        entry.push_str(&format!(
+            "<data class=\"p-name\" value=\"{}\"></data>\n",
+            utils::escape_html(title)
+        ));
+    }
+    if let Some(base_url) = base_url {
+        entry.push_str(&format!(
+            "<a class=\"u-url\" href=\"{}/{}\"></a>\n",
+            utils::escape_html(base_url.trim_end_matches('/')),
+            utils::escape_html(&page.path)
+        ));
+    }
+    if let Some(date) = &page.date {
+        entry.push_str(&format!(
+            "<time class=\"dt-published\" datetime=\"{}\">{}</time>\n",
+            utils::escape_html(date),
+            utils::escape_html(date)
+        ));
+    }
+    if let Some(author) = &config.author {
+        let href = base_url
+            .map(|base_url| format!(" href=\"{}\"", utils::escape_html(base_url)))
+            .unwrap_or_default();
+        entry.push_str(&format!(
+            "<a class=\"p-author h-card\"{}>{}</a>\n",
+            href,
+            utils::escape_html(author)
+        ));
+    }
+    entry.push_str("</div>");
+    entry
+}
+
+/// Writes a `.well-known/webfinger` stub to `output_path`, so the site's domain finds the
+/// `fediverse` account (at its server's Mastodon style urls). A webfinger file of the site's
+/// own is left as it is.
+pub fn generate_webfinger(
+    context: &SiteContext,
+    output_path: &Path,
+    config: &IndieWebConfig,
+) -> anyhow::Result<()> {
+    let Some(account) = &config.fediverse else {
+        return Ok(());
+    };
+    let Some((user, host)) = fediverse_account(account) else {
+        context.warn(format!(
+            "The fediverse account {:?} isn't like @user@example.social, so no webfinger stub is written",
+            account
+        ));
+        return Ok(());
+    };
+    if context.fs.is_file(&context.target_path.join(WEBFINGER)) {
+        log::debug!("The site has its own webfinger file, leaving it as it is");
+        return Ok(());
+    }
+    let profile = format!("https://{}/@{}", host, user);
+    let actor = format!("https://{}/users/{}", host, user);
+    let webfinger = serde_json::json!({
+        "subject": format!("acct:{}@{}", user, host),
+        "aliases": [&profile, &actor],
+        "links": [
+            {
+                "rel": "http://webfinger.net/rel/profile-page",
+                "type": "text/html",
+                "href": &profile,
+            },
+            {
+                "rel": "self",
+                "type": "application/activity+json",
+                "href": &actor,
+            },
+        ],
+    });
+    let path = output_path.join(WEBFINGER);
+    context.fs.create_dir_all(path.parent().unwrap())?;
+    log::debug!("Writing webfinger stub for {}@{}", user, host);
+    context.write_output(&path, serde_json::to_string_pretty(&webfinger)?.as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use crate::outputs::OutputWriter;
+
+/// Renders a document to a standalone LaTeX article.
+pub struct LatexWriter {
+    tex: String,
+    in_code_block: bool,
+}
+
+impl LatexWriter {
+    pub fn new(title: &str) -> Self {
+        let mut writer = Self {
+            tex: String::new(),
+            in_code_block: false,
+        };
+        writer.line("\\documentclass{article}");
+        writer.line("\\usepackage[utf8]{inputenc}");
+        writer.line("\\usepackage[T1]{fontenc}");
+        writer.line("\\usepackage{hyperref}");
+        writer.tex.push_str("\\title{");
+        writer.push_escaped(title);
+        writer.tex.push_str("}\n");
+        writer.line("\\date{}");
+        writer.line("\\begin{document}");
+        writer
+    }
+
+    pub fn finish(mut self) -> String {
+        self.line("\\end{document}");
+        self.tex
+    }
+
+    /// Writes a command on its own line.
+    fn line(&mut self, command: &str) {
+        self.end_line();
+        self.tex.push_str(command);
+        self.tex.push('\n');
+    }
+
+    /// Ends the current line of text, if there is one.
+    fn end_line(&mut self) {
+        if !self.tex.is_empty() && !self.tex.ends_with('\n') {
+            self.tex.push('\n');
+        }
+    }
+
+    fn push_escaped(&mut self, text: &str) {
+        for c in text.chars() {
+            match c {
+                '\\' => self.tex.push_str("\\textbackslash{}"),
+                '~' => self.tex.push_str("\\textasciitilde{}"),
+                '^' => self.tex.push_str("\\textasciicircum{}"),
+                '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                    self.tex.push('\\');
+                    self.tex.push(c);
+                }
+                _ => self.tex.push(c),
+            }
+        }
+    }
+}
+
+impl OutputWriter for LatexWriter {
+    fn start_heading(&mut self, level: u16) {
+        let command = match level {
+            1 => "\\section*{",
+            2 => "\\subsection*{",
+            3 => "\\subsubsection*{",
+            _ => "\\paragraph*{",
+        };
+        self.end_line();
+        self.tex.push('\n');
+        self.tex.push_str(command);
+    }
+
+    fn end_heading(&mut self, _level: u16) {
+        self.tex.push_str("}\n");
+    }
+
+    fn start_paragraph(&mut self) {
+        self.end_line();
+        self.tex.push('\n');
+    }
+
+    fn end_paragraph(&mut self) {
+        self.end_line();
+    }
+
+    fn start_code_block(&mut self) {
+        self.in_code_block = true;
+        self.line("\\begin{verbatim}");
+    }
+
+    fn end_code_block(&mut self) {
+        self.in_code_block = false;
+        self.line("\\end{verbatim}");
+    }
+
+    fn start_block_quote(&mut self) {
+        self.line("\\begin{quote}");
+    }
+
+    fn end_block_quote(&mut self) {
+        self.line("\\end{quote}");
+    }
+
+    fn start_list(&mut self, ordered: bool) {
+        self.line(if ordered {
+            "\\begin{enumerate}"
+        } else {
+            "\\begin{itemize}"
+        });
+    }
+
+    fn end_list(&mut self, ordered: bool) {
+        self.line(if ordered {
+            "\\end{enumerate}"
+        } else {
+            "\\end{itemize}"
+        });
+    }
+
+    fn start_item(&mut self) {
+        self.end_line();
+        self.tex.push_str("\\item ");
+    }
+
+    fn end_item(&mut self) {
+        self.end_line();
+    }
+
+    fn start_emphasis(&mut self) {
+        self.tex.push_str("\\emph{");
+    }
+
+    fn end_emphasis(&mut self) {
+        self.tex.push('}');
+    }
+
+    fn start_strong(&mut self) {
+        self.tex.push_str("\\textbf{");
+    }
+
+    fn end_strong(&mut self) {
+        self.tex.push('}');
+    }
+
+    fn start_link(&mut self, url: &str) {
+        self.tex.push_str("\\href{");
+        // Only the characters that would break the argument are escaped in urls
+        for c in url.chars() {
+            if matches!(c, '\\' | '#' | '%' | '{' | '}') {
+                self.tex.push('\\');
+            }
+            self.tex.push(c);
+        }
+        self.tex.push_str("}{");
+    }
+
+    fn end_link(&mut self, _url: &str) {
+        self.tex.push('}');
+    }
+
+    fn code(&mut self, text: &str) {
+        self.tex.push_str("\\texttt{");
+        self.push_escaped(text);
+        self.tex.push('}');
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.in_code_block {
+            // verbatim environments take their contents as-is
+            self.tex.push_str(text);
+        } else {
+            self.push_escaped(text);
+        }
+    }
+
+    fn soft_break(&mut self) {
+        self.tex.push('\n');
+    }
+
+    fn hard_break(&mut self) {
+        self.tex.push_str("\\\\\n");
+    }
+
+    fn rule(&mut self) {
+        self.line("\\noindent\\rule{\\textwidth}{0.4pt}");
+    }
+}
@@ -0,0 +1,2584 @@
+//! Plain and simple static site generator for Djot and Markdown light markup languages.
+//!
+//! Generate a site with [`SiteBuilder`], as the `simple-ssg` command does:
+//!
+//! ```no_run
+//! let report = simple_ssg::SiteBuilder::new("docs")
+//!     .output_path("public")
+//!     .web_prefix("https://example.com/")
+//!     .generate_site()?;
+//! println!("Generated {} page(s)", report.pages.len());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Or render a single page to html with [`process_markdown`] or [`process_djot`].
+
+use abbreviations::Abbreviations;
+use anyhow::anyhow;
+use bibliography::Bibliography;
+use cache::BuildCache;
+use config::{CspOutput, TocConfig};
+pub use config::{SiteConfig, TextDirection};
+use errors::SsgError;
+use glossary::Glossary;
+use jotdown::{Container, Event};
+use metadata::OutputFormat;
+pub use metadata::{Date, PageTemplate};
+use notes::Notes;
+use pulldown_cmark::{CowStr, Options};
+use rayon::prelude::*;
+pub use report::BuildReport;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    env,
+    io::{Read, Write},
+    net::TcpListener,
+    num::NonZeroUsize,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+pub use templates::BuiltInTemplate;
+use utils::TemplateCache;
+use versions::Versions;
+pub use vfs::{FileSystem, LimitedFs, MemoryFs, RealFs};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+pub use builder::SiteBuilder;
+
+mod abbreviations;
+mod asset_index;
+mod beacon;
+mod bibliography;
+mod builder;
+mod bundle;
+mod cache;
+mod calendar;
+mod changelog;
+mod conditions;
+mod config;
+mod convert;
+mod diagnostics;
+mod email;
+mod encrypt;
+mod errors;
+mod feed;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod figures;
+mod filters;
+mod git;
+mod glossary;
+mod highlight;
+mod html_import;
+mod import;
+mod inbound;
+mod indieweb;
+mod latex;
+mod links;
+mod lint;
+mod lite;
+mod man;
+mod metadata;
+mod migrate;
+mod mounts;
+mod navigation;
+mod notes;
+mod numbering;
+mod openapi;
+mod output_diff;
+mod outputs;
+mod pagination;
+#[cfg(feature = "python")]
+mod python;
+mod redirects;
+mod report;
+mod sandbox;
+mod scaffold;
+mod security;
+mod shortcodes;
+mod sitemap;
+mod slug;
+mod snapshots;
+mod tags;
+mod templates;
+mod templating;
+#[cfg(test)]
+mod tests;
+mod utils;
+mod versions;
+pub mod vfs;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod watch;
+mod webmention;
+
+/// Djot static site generator
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Options for generating a site, or a single page with -f or --stdin.
+#[derive(Args, Debug, Default)]
+struct ConsoleArgs {
+    /// Path to the directory to use to generate the site (not required if -f is specified)
+    directory: Option<PathBuf>,
+    /// Process a single file instead of a directory
+    #[arg(short, conflicts_with = "clean", conflicts_with = "output_path")]
+    file: Option<PathBuf>,
+    /// Optional output path override. Defaults to `output_path` in ssg.toml, or ./output for
+    /// directories
+    #[arg(short, conflicts_with = "file")]
+    output_path: Option<PathBuf>,
+    /// Clean the output directory before generating the site. Useful for multiple runs
+    #[arg(long, conflicts_with = "file")]
+    clean: bool,
+    /// Specify the website prefix (defaults to local paths i.e. `./`)
+    #[arg(long)]
+    web_prefix: Option<String>,
+    /// Specify a built in template to use (will override a template.html
+    /// in any directory!). defaults to whatever templates are found in template.html in the
+    /// directories.
+    #[arg(short, long)]
+    template: Option<BuiltInTemplate>,
+    /// Render README.{dj|djot|md} files as their directory's index.html
+    #[arg(long)]
+    readme_as_index: bool,
+    /// Treat each subdirectory of <DIRECTORY> as a version of the site (e.g. v1.0, v2.0) and
+    /// generate each into its own output directory, plus a copy of the newest as `latest`
+    #[arg(long, conflicts_with = "file")]
+    versioned: bool,
+    /// Enable a flag, including djot content marked with its `only-<FLAG>` class (may be repeated)
+    #[arg(long = "flag", value_name = "FLAG")]
+    flags: Vec<String>,
+    /// Also generate a text-only mirror of the site, without css or javascript, under `lite/`
+    #[arg(long)]
+    lite: bool,
+    /// Write each page to its own directory (`about.dj` to `about/index.html`), linked as
+    /// `about/`
+    #[arg(long)]
+    pretty_urls: bool,
+    /// Publish the directory as a notes vault: `[[wikilinks]]` between notes, backlinks and
+    /// unlinked mentions on each note, and an index of every note in `all-notes.html`
+    #[arg(long, conflicts_with = "file")]
+    notes: bool,
+    /// Cache the rendered pages and the hashes of the written files in the output directory,
+    /// and skip rendering pages and writing files that haven't changed since the previous build
+    #[arg(long, conflicts_with_all = ["file", "stdin"])]
+    incremental: bool,
+    /// Render at most JOBS pages at once, instead of one per cpu, e.g. to bound the cpus a
+    /// build uses on a small machine
+    #[arg(short, long, value_name = "JOBS")]
+    jobs: Option<NonZeroUsize>,
+    /// Read, write, and copy at most IO_JOBS files at once, instead of as many as the pages
+    /// being rendered need, e.g. to keep a build from flooding a slow disk or network mount
+    #[arg(long, value_name = "IO_JOBS")]
+    io_jobs: Option<NonZeroUsize>,
+    /// Render a single document read from stdin (djot unless FORMAT is given), writing the page
+    /// to stdout. Only built-in templates are used, and no files are read or written
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        default_missing_value = "djot",
+        conflicts_with_all = ["directory", "file", "output_path", "clean", "versioned"]
+    )]
+    stdin: Option<InputFormat>,
+    /// Write the page generated with -f to stdout instead of beside the file
+    #[arg(long, conflicts_with_all = ["directory", "versioned"])]
+    stdout: bool,
+    /// Write only the rendered content of each page, without a template or anything else
+    /// around it, for embedding in other systems
+    #[arg(long, conflicts_with = "template")]
+    fragment: bool,
+    /// Non-interactive mode for pipelines: no colors, a json summary on stdout, and a nonzero
+    /// exit status if any warnings were reported
+    #[arg(long, conflicts_with_all = ["stdin", "stdout"])]
+    ci: bool,
+    /// Hardened mode for untrusted content: skip files (and templates) that are symlinked from
+    /// outside the directory, and strip raw html and links to scripts from pages
+    #[arg(long)]
+    sandbox: bool,
+    /// Include draft pages (with `draft = true` in their front matter) and files whose names
+    /// start with `_draft`, for previewing them
+    #[arg(long)]
+    drafts: bool,
+    /// Keep running after generating the site, and regenerate the pages affected by each
+    /// change (set by `serve`)
+    #[arg(skip)]
+    watch: bool,
+    /// Warn about each page in the sitemap of a previous deployment (a file or an http(s) url)
+    /// that this build no longer has, as a page or a redirect
+    #[arg(long, value_name = "SITEMAP", conflicts_with_all = ["file", "stdin", "versioned"])]
+    previous_sitemap: Option<String>,
+    /// After generating the site, send a webmention to each external page that a page links
+    /// to for the first time (needs `base_url` in the `[sitemap]` section of ssg.toml)
+    #[arg(long, conflicts_with_all = ["file", "stdin", "versioned"])]
+    send_webmentions: bool,
+    /// List the webmentions that --send-webmentions would send, without sending them
+    #[arg(long, requires = "send_webmentions")]
+    dry_run: bool,
+}
+
+/// The options of a site for the commands that generate it for a purpose other than
+/// publishing it.
+#[derive(Args, Debug)]
+struct SiteOptions {
+    /// Path to the directory of the site
+    #[arg(default_value = ".")]
+    directory: PathBuf,
+    /// Specify a built in template to use, instead of the template.html files
+    #[arg(short, long)]
+    template: Option<BuiltInTemplate>,
+    /// Render README.{dj|djot|md} files as their directory's index.html
+    #[arg(long)]
+    readme_as_index: bool,
+    /// Enable a flag, including djot content marked with its `only-<FLAG>` class (may be repeated)
+    #[arg(long = "flag", value_name = "FLAG")]
+    flags: Vec<String>,
+    /// Also generate the text-only mirror of the site under `lite/`
+    #[arg(long)]
+    lite: bool,
+    /// Write each page to its own directory, linked as `about/`
+    #[arg(long)]
+    pretty_urls: bool,
+    /// Publish the directory as a notes vault
+    #[arg(long)]
+    notes: bool,
+    /// Hardened mode for untrusted content
+    #[arg(long)]
+    sandbox: bool,
+    /// Include draft pages and files
+    #[arg(long)]
+    drafts: bool,
+}
+
+impl From<SiteOptions> for ConsoleArgs {
+    fn from(options: SiteOptions) -> Self {
+        ConsoleArgs {
+            directory: Some(options.directory),
+            template: options.template,
+            readme_as_index: options.readme_as_index,
+            flags: options.flags,
+            lite: options.lite,
+            pretty_urls: options.pretty_urls,
+            notes: options.notes,
+            sandbox: options.sandbox,
+            drafts: options.drafts,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generate a site, or a single page with -f or --stdin
+    Build(ConsoleArgs),
+    /// Generate a site and serve it over http, regenerating the pages affected by each change
+    Serve {
+        #[command(flatten)]
+        options: SiteOptions,
+        /// Optional output path override. Defaults to `output_path` in ssg.toml, or ./output
+        #[arg(short)]
+        output_path: Option<PathBuf>,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        address: String,
+        /// Port to listen on
+        #[arg(short, long, default_value_t = 8000)]
+        port: u16,
+    },
+    /// Remove the output directory of a site
+    Clean {
+        /// Path to the directory of the site
+        #[arg(default_value = ".")]
+        directory: PathBuf,
+        /// Optional output path override. Defaults to `output_path` in ssg.toml, or ./output
+        #[arg(short)]
+        output_path: Option<PathBuf>,
+    },
+    /// Generate a site into a temporary directory, failing if any warnings are reported
+    Check {
+        #[command(flatten)]
+        options: SiteOptions,
+        /// Also warn about each page in the sitemap of a previous deployment that the site no
+        /// longer has
+        #[arg(long, value_name = "SITEMAP")]
+        previous_sitemap: Option<String>,
+    },
+    /// Create a new site with an ssg.toml, an index page, a template.html, and an assets folder
+    Init {
+        /// Directory to create the site in, which must be empty or not exist yet
+        directory: PathBuf,
+        /// A built in template for the site's pages, instead of a template.html
+        #[arg(short, long)]
+        template: Option<BuiltInTemplate>,
+    },
+    /// Create a page with front matter: a title from its file name, today's date, and
+    /// `draft = true`
+    New {
+        /// Path of the page, in the site's directories (`.dj` is added if it has no extension)
+        path: PathBuf,
+    },
+    /// Bundle a generated site into a single executable that serves it (run it with --help for
+    /// its options)
+    Bundle {
+        /// The generated site to bundle
+        site: PathBuf,
+        /// Path of the executable to write
+        #[arg(short, default_value = "site")]
+        output_path: PathBuf,
+    },
+    /// Render each `.dj` and `.md` fixture in a directory and compare it to the `.html`
+    /// snapshot beside it
+    Snapshot {
+        /// Directory of fixtures (with an optional ssg.toml and template.html)
+        fixtures: PathBuf,
+        /// Wrap the rendered fixtures in a built in template instead of template.html
+        #[arg(short, long)]
+        template: Option<BuiltInTemplate>,
+        /// Write the snapshots instead of comparing them
+        #[arg(long)]
+        update: bool,
+    },
+    /// Compare two generated sites, listing the files added, removed, and changed, with the
+    /// changed lines of each page's html
+    DiffOutput {
+        /// The site generated before
+        old: PathBuf,
+        /// The site generated after
+        new: PathBuf,
+    },
+    /// Apply the steps of a migration script (renaming keys, adding defaults, and converting
+    /// dates) to the front matter of each page in a directory
+    Migrate {
+        /// The site to migrate
+        directory: PathBuf,
+        /// The migration script, a toml file of `[[step]]` tables
+        script: PathBuf,
+        /// List the pages that would change without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Convert a Jekyll or Hugo site into a new site, with its front matter, permalinks, and
+    /// shortcodes converted, or a folder of html pages into djot pages
+    Import {
+        /// The generator the site was written for, or `html` for a folder of html pages
+        #[arg(long)]
+        from: import::ImportSource,
+        /// The site to import
+        source: PathBuf,
+        /// Directory to write the new site to, which must be empty
+        #[arg(short, default_value = "site")]
+        output_path: PathBuf,
+    },
+    /// Convert a djot page to markdown, or a markdown page to djot
+    Convert {
+        /// The page to convert
+        file: PathBuf,
+        /// Where to write the converted page (defaults to the page's path with the other
+        /// extension), which must not exist
+        #[arg(short)]
+        output_path: Option<PathBuf>,
+    },
+    /// Render a page as a standalone email, with inline styles and absolute urls, for pasting
+    /// into newsletter services
+    ExportEmail {
+        /// The page to export
+        page: PathBuf,
+        /// Where to write the email (defaults to stdout)
+        #[arg(short)]
+        output_path: Option<PathBuf>,
+        /// The url of the site, for absolute links (defaults to `base_url` in the `[sitemap]`
+        /// section of the site's ssg.toml)
+        #[arg(long)]
+        base_url: Option<String>,
+    },
+}
+
+/// The markup language of a document read from stdin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum InputFormat {
+    #[value(alias = "dj")]
+    Djot,
+    #[value(alias = "md")]
+    Markdown,
+}
+
+/// Runs the `simple-ssg` command with the process's arguments.
+pub fn run_cli() -> anyhow::Result<()> {
+    // Executables written by `bundle` serve their site instead
+    let bundle = bundle::read_bundle();
+    if let Ok(Some(files)) = bundle {
+        diagnostics::init_logger(false);
+        return bundle::serve_bundle(files);
+    }
+    let cli = Cli::parse();
+    let ci = matches!(&cli.command, Command::Build(args) if args.ci);
+    diagnostics::init_logger(ci);
+    log::trace!("Begin simple-ssg::main()");
+    if let Err(e) = bundle {
+        log::warn!("Could not check for a bundled site: {}", e);
+    }
+    if ci {
+        diagnostics::report(run_command(cli.command, &vfs::RealFs))
+    } else {
+        run_command(cli.command, &vfs::RealFs).map(|_| ())
+    }
+}
+
+#[cfg(any(test, feature = "ffi", feature = "python"))]
+fn run_program(args: ConsoleArgs) -> anyhow::Result<BuildReport> {
+    run_program_with(args, &vfs::RealFs)
+}
+
+/// Runs a subcommand with `fs` for its file IO.
+fn run_command(command: Command, fs: &dyn FileSystem) -> anyhow::Result<BuildReport> {
+    match command {
+        Command::Build(args) => run_program_with(args, fs),
+        Command::Serve {
+            options,
+            output_path,
+            address,
+            port,
+        } => {
+            let mut args = ConsoleArgs {
+                output_path,
+                watch: true,
+                ..options.into()
+            };
+            let (_, output_path) = site_paths(&mut args, fs)?;
+            fs.create_dir_all(&output_path)?;
+            let listener = TcpListener::bind((address.as_str(), port))?;
+            println!("Serving on http://{}", listener.local_addr()?);
+            let root = fs.canonicalize(&output_path)?;
+            std::thread::spawn(move || {
+                bundle::serve(listener, move |path| {
+                    let path = Path::new(path);
+                    // Only paths inside the output directory are served
+                    match path.components().all(|c| matches!(c, Component::Normal(_))) {
+                        true => std::fs::read(root.join(path)).ok(),
+                        false => None,
+                    }
+                })
+            });
+            run_program_with(args, fs)
+        }
+        Command::Clean {
+            directory,
+            output_path,
+        } => {
+            let mut args = ConsoleArgs {
+                directory: Some(directory),
+                output_path,
+                ..Default::default()
+            };
+            let (_, output_path) = site_paths(&mut args, fs)?;
+            match fs.remove_dir_all(&output_path) {
+                Ok(()) => println!("Removed {}", output_path.display()),
+                Err(_) => println!("Nothing to clean at {}", output_path.display()),
+            }
+            Ok(BuildReport::default())
+        }
+        Command::Check {
+            options,
+            previous_sitemap,
+        } => {
+            let output_path =
+                env::temp_dir().join(format!("simple-ssg-check-{}", std::process::id()));
+            let args = ConsoleArgs {
+                output_path: Some(output_path.clone()),
+                clean: true,
+                previous_sitemap,
+                ..options.into()
+            };
+            let result = run_program_with(args, fs);
+            if let Err(e) = fs.remove_dir_all(&output_path) {
+                log::debug!("Could not remove {}: {}", output_path.display(), e);
+            }
+            let report = result?;
+            if !report.warnings.is_empty() {
+                return Err(anyhow!("{} warning(s) reported", report.warnings.len()));
+            }
+            println!(
+                "Checked {} page(s) and {} file(s), no warnings",
+                report.pages.len(),
+                report.assets.len()
+            );
+            Ok(report)
+        }
+        Command::Init {
+            directory,
+            template,
+        } => {
+            let written = scaffold::new_site(fs, &directory, template)?;
+            for path in &written {
+                println!("Created {}", path.display());
+            }
+            Ok(BuildReport {
+                outputs: written,
+                ..Default::default()
+            })
+        }
+        Command::New { path } => {
+            let today = time::OffsetDateTime::now_utc().date();
+            let written = scaffold::new_page(fs, &path, today)?;
+            println!("Created {}", written.display());
+            Ok(BuildReport {
+                outputs: vec![written],
+                ..Default::default()
+            })
+        }
+        Command::Bundle { site, output_path } => {
+            bundle::write_bundle(&site, &output_path)?;
+            Ok(BuildReport {
+                outputs: vec![output_path],
+                ..Default::default()
+            })
+        }
+        Command::Snapshot {
+            fixtures,
+            template,
+            update,
+        } => {
+            let mismatches = snapshots::check_snapshots(fs, &fixtures, template, update)?;
+            for mismatch in &mismatches {
+                log::error!("{}", mismatch);
+            }
+            if !mismatches.is_empty() {
+                return Err(anyhow!("{} snapshot(s) don't match", mismatches.len()));
+            }
+            Ok(BuildReport::default())
+        }
+        Command::DiffOutput { old, new } => {
+            let changes = output_diff::diff_outputs(fs, &old, &new)?;
+            for change in &changes {
+                println!("{}", change);
+            }
+            let count = |kind: fn(&output_diff::FileChange) -> bool| {
+                changes.iter().filter(|change| kind(change)).count()
+            };
+            println!(
+                "{} added, {} removed, {} changed",
+                count(|change| matches!(change, output_diff::FileChange::Added(_))),
+                count(|change| matches!(change, output_diff::FileChange::Removed(_))),
+                count(|change| matches!(change, output_diff::FileChange::Changed(..)))
+            );
+            Ok(BuildReport::default())
+        }
+        Command::Migrate {
+            directory,
+            script,
+            dry_run,
+        } => {
+            let migration: migrate::Migration = toml::from_str(&fs.read_to_string(&script)?)
+                .map_err(|e| SsgError::MetadataError(script.clone(), e))?;
+            let migrated = migrate::migrate_site(fs, &directory, &migration, dry_run)?;
+            for path in &migrated {
+                println!("{}", path.display());
+            }
+            println!(
+                "{} page(s) {}",
+                migrated.len(),
+                if dry_run { "would change" } else { "changed" }
+            );
+            Ok(BuildReport::default())
+        }
+        Command::Import {
+            from,
+            source,
+            output_path,
+        } => {
+            let report = import::import_site(fs, from, &source, &output_path)?;
+            println!(
+                "Imported {} page(s) and {} file(s), with {} redirect(s), into {}",
+                report.pages,
+                report.files,
+                report.redirects,
+                output_path.display()
+            );
+            Ok(BuildReport::default())
+        }
+        Command::Convert { file, output_path } => {
+            let written = convert::convert_page(fs, &file, output_path.as_deref())?;
+            println!("Converted {} to {}", file.display(), written.display());
+            Ok(BuildReport::default())
+        }
+        Command::ExportEmail {
+            page,
+            output_path,
+            base_url,
+        } => {
+            let email = email::export_email(fs, &page, base_url.as_deref())?;
+            match output_path {
+                Some(output_path) => {
+                    fs.write(&output_path, email.as_bytes())?;
+                    println!("Exported {} to {}", page.display(), output_path.display());
+                }
+                None => std::io::stdout().write_all(email.as_bytes())?,
+            }
+            Ok(BuildReport::default())
+        }
+    }
+}
+
+/// Generates the site or page given in `args` with `fs` for its file IO. Git history (for
+/// features such as `<!-- {LAST_UPDATED} -->`) is always read from the disk.
+fn run_program_with(mut args: ConsoleArgs, fs: &dyn FileSystem) -> anyhow::Result<BuildReport> {
+    if let Some(format) = args.stdin {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        let config = SiteConfig::default();
+        let template = args
+            .template
+            .map(|template| template.get_template(&config.template_options));
+        let html = render_page(&input, config, format == InputFormat::Markdown)?;
+        std::io::stdout()
+            .write_all(utils::wrap_html_content(&html, template.as_deref())?.as_bytes())?;
+        return Ok(BuildReport::default());
+    }
+    let limited_fs;
+    let fs = match args.io_jobs {
+        Some(io_jobs) => {
+            limited_fs = LimitedFs::new(fs, io_jobs);
+            &limited_fs as &dyn FileSystem
+        }
+        None => fs,
+    };
+    let (target_path, output_path) = site_paths(&mut args, fs)?;
+    // Clean the output directory if clean is specified
+    if args.clean {
+        log::debug!(
+            "Clean argument specified, cleaning output path {:?}...",
+            &output_path
+        );
+        if let Err(_) = fs.remove_dir_all(&output_path) {
+            log::trace!("Nothing to clean!");
+        } else {
+            log::trace!("Clean successful!");
+        }
+    }
+    if args.versioned {
+        let mut report = versions::generate_versioned_site(&target_path, &output_path, &args, fs)?;
+        report.sort();
+        return Ok(report);
+    }
+    if args.watch {
+        // Events are reported with absolute paths
+        fs.create_dir_all(&output_path)?;
+        let target_path = fs.canonicalize(&target_path)?;
+        let output_path = fs.canonicalize(&output_path)?;
+        return watch::watch_site(&target_path, &output_path, &args, fs);
+    }
+    let context = SiteContext::new(&target_path, &output_path, &args, fs)?;
+    let mut report = generate_site(&target_path, &context)?;
+    if let Some(sitemap) = &args.previous_sitemap {
+        inbound::check_previous_sitemap(sitemap, &context)?;
+        report.extend(std::mem::take(&mut *context.report.lock().unwrap()));
+    }
+    if args.send_webmentions {
+        webmention::send_webmentions(&report, &context, args.dry_run)?;
+        report.extend(std::mem::take(&mut *context.report.lock().unwrap()));
+    }
+    report.sort();
+    Ok(report)
+}
+
+/// Finds the target and output paths of the site or file given in `args`, applying the options
+/// of the site's ssg.toml that aren't given in `args`.
+fn site_paths(args: &mut ConsoleArgs, fs: &dyn FileSystem) -> anyhow::Result<(PathBuf, PathBuf)> {
+    // Options in the site's ssg.toml apply unless they're given on the command line
+    let site_root = match (&args.directory, &args.file) {
+        (Some(dir), _) => Some(dir.as_path()),
+        (None, Some(path)) => path.parent(),
+        (None, None) => None,
+    };
+    let site_config = match site_root {
+        Some(root) if fs.is_dir(root) => config::get_site_config(fs, root)?,
+        _ => SiteConfig::default(),
+    };
+    args.web_prefix = args.web_prefix.take().or(site_config.web_prefix);
+    if !args.fragment {
+        args.template = args.template.take().or(site_config.template);
+    }
+    if args.directory.is_some() && args.file.is_some() {
+        Err(anyhow!(
+            "Cannot specify both a directory and a path! (Specified {} and -f {})",
+            args.directory.as_ref().unwrap().display(),
+            args.file.as_ref().unwrap().display()
+        ))
+    } else if let Some(dir) = args.directory.clone() {
+        if fs.is_file(&dir) {
+            return Err(anyhow!(
+                "Path {} is a file. Specify -f <FILE> if this was intended.",
+                dir.display()
+            ));
+        }
+        let output_path = match (&args.output_path, site_config.output_path) {
+            (Some(output_path), _) => output_path.clone(),
+            (None, Some(output_path)) => dir.join(output_path),
+            (None, None) => env::current_dir()?.join("output"),
+        };
+        Ok((dir, output_path))
+    } else if let Some(path) = args.file.clone() {
+        if fs.is_dir(&path) {
+            return Err(anyhow!("Path {} is a directory. Specify <DIRECTORY> without the -f positional argument if this was intended.", path.display()));
+        }
+        Ok((path.clone(), path.parent().unwrap().to_path_buf()))
+    } else {
+        Err(anyhow!(
+            "Must specify either a directory <DIRECTORY> or a path with -f <PATH>"
+        ))
+    }
+}
+
+/// What a page's template needs to wrap its content, which happens in the second pass once the
+/// site's pages are known.
+#[derive(Clone, Debug)]
+pub struct PageLayout {
+    /// The file the page is rendered from, which finds its `template.html` and partials
+    pub source: PathBuf,
+    /// The language of the page, from its front matter or the site's config
+    pub lang: Option<String>,
+    /// The text direction of the page, from its front matter or the site's config
+    pub dir: Option<TextDirection>,
+    /// The template from the page's front matter
+    pub template: Option<PageTemplate>,
+    /// The tags from the page's front matter
+    pub tags: Vec<String>,
+    /// The event date from the page's front matter
+    pub event_date: Option<Date>,
+    /// The end of the event, from the page's front matter
+    pub event_end: Option<Date>,
+    /// The location of the event, from the page's front matter
+    pub event_location: Option<String>,
+}
+
+impl PageLayout {
+    /// The layout of a page that the site generates (such as the calendar), whose template is
+    /// found from `source`, in the site's language.
+    fn generated(source: PathBuf, context: &SiteContext) -> Self {
+        let lang = context.config.lang.clone();
+        let dir = context
+            .config
+            .dir
+            .or(lang.as_deref().map(TextDirection::of_lang));
+        Self {
+            source,
+            lang,
+            dir,
+            template: None,
+            tags: Vec::new(),
+            event_date: None,
+            event_end: None,
+            event_location: None,
+        }
+    }
+
+    /// The content `html` of the page for the site's text-only mirror, if it has one.
+    fn lite_content(&self, html: &str, context: &SiteContext) -> Option<String> {
+        context.config.lite.then(|| {
+            utils::set_language(&lite::strip_content(html), self.lang.as_deref(), self.dir)
+        })
+    }
+}
+
+/// What the first pass found at a path of the site, in the order the site was walked. Paths
+/// are relative to the output directory, and `depth` is how deep they are in the site (1 at
+/// its root).
+#[derive(Clone, Debug)]
+pub enum FirstPassResult {
+    /// A directory, listed in the table of contents
+    Dir {
+        depth: usize,
+        relative_path: PathBuf,
+        /// The title from the directory's `_dir.toml`
+        title: Option<String>,
+    },
+    /// A rendered page, written by the second pass
+    HtmlOutput {
+        depth: usize,
+        /// The page's rendered content, without its template
+        html: String,
+        relative_path: PathBuf,
+        /// Rendered, but left out of the table of contents
+        unlisted: bool,
+        /// Listed in the table of contents. Generated pages that would crowd it (such as the
+        /// page of each tag) are left out of it, but are still listed everywhere else
+        in_toc: bool,
+        /// Id that other pages can link to with `ref:<id>`
+        id: Option<String>,
+        /// The page's content for the lite mirror, when generating one
+        lite_content: Option<String>,
+        /// The title from the page's front matter
+        title: Option<String>,
+        /// The date from the page's front matter
+        date: Option<Date>,
+        /// What the second pass needs to wrap the page in its template
+        layout: Box<PageLayout>,
+    },
+    /// A table of contents entry for a page that isn't generated, such as a mounted tree's
+    Link {
+        depth: usize,
+        relative_path: PathBuf,
+        title: Option<String>,
+    },
+}
+
+fn generate_site(target_path: &Path, context: &SiteContext) -> anyhow::Result<BuildReport> {
+    let first_pass_results = first_pass(target_path, context)?;
+    second_pass(target_path, &first_pass_results, None, context)?;
+    if let Some(cache) = &context.cache {
+        cache.save(context.fs, context.output_path)?;
+    }
+
+    log::info!("3/3: Done!");
+
+    Ok(std::mem::take(&mut *context.report.lock().unwrap()))
+}
+
+/// Renders every page under `target_path` (or just `target_path`, if it's a file) and copies
+/// every other file to the output directory, then adds the pages the site generates.
+fn first_pass(target_path: &Path, context: &SiteContext) -> anyhow::Result<Vec<FirstPassResult>> {
+    let mut first_pass_results = process_site(target_path, context)?;
+    if context.fs.is_dir(target_path) {
+        generate_pages(target_path, context, &mut first_pass_results)?;
+    }
+    Ok(first_pass_results)
+}
+
+/// Renders every page under `target_path` (or just `target_path`, if it's a file) and copies
+/// every other file to the output directory, without the pages the site generates from them.
+fn process_site(target_path: &Path, context: &SiteContext) -> anyhow::Result<Vec<FirstPassResult>> {
+    let output_path = context.output_path;
+    let fs = context.fs;
+    let _ = fs.create_dir_all(output_path);
+    log::trace!(
+        "Created output directory {:?} if it didn't exist...",
+        output_path
+    );
+
+    let mut first_pass_results = Vec::new();
+
+    log::info!("1/3: Site generation and indexing...");
+    if fs.is_dir(target_path) && fs.is_dir(output_path) {
+        if !utils::check_has_index(fs, target_path, &context.config.index_names) {
+            context.warn(SsgError::IndexPageNotFound);
+        }
+        let entries = fs
+            .walk(target_path)
+            .into_iter()
+            .filter_map(|entry| match entry {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    context.warn(SsgError::DirEntryError(e));
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        if let Some(cache) = &context.cache {
+            cache.set_site_hash(cache::site_hash(context, &entries));
+        }
+        // Each path is processed on its own, so they're processed in parallel, with their
+        // results kept in the walk's order
+        let process_entries = || {
+            entries
+                .par_iter()
+                .map(|(path, depth)| {
+                    let mut results = Vec::new();
+                    process_path(path, context, *depth, &mut results)?;
+                    Ok(results)
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+        let results = match context.jobs {
+            Some(jobs) => rayon::ThreadPoolBuilder::new()
+                .num_threads(jobs.get())
+                .build()?
+                .install(process_entries)?,
+            None => process_entries()?,
+        };
+        first_pass_results.extend(results.into_iter().flatten());
+        check_output_collisions(target_path, &first_pass_results, context);
+        mounts::copy_mounts(context, &mut first_pass_results)?;
+    } else if fs.is_file(target_path) {
+        process_path(target_path, context, 1, &mut first_pass_results)?;
+    } else {
+        return Err(anyhow!(
+            "Target path {} is not a file or a directory.",
+            target_path.display()
+        ));
+    }
+    Ok(first_pass_results)
+}
+
+/// Adds the pages the site generates from the pages of `first_pass_results`, such as the
+/// calendar and the tag pages, to them.
+fn generate_pages(
+    target_path: &Path,
+    context: &SiteContext,
+    first_pass_results: &mut Vec<FirstPassResult>,
+) -> anyhow::Result<()> {
+    if let Some(notes) = &context.notes {
+        generate_notes_index(notes, target_path, context, first_pass_results)?;
+    }
+    generate_calendar(target_path, context, first_pass_results);
+    if context.config.tag_pages {
+        generate_tag_pages(target_path, context, first_pass_results)?;
+    }
+    if context
+        .config
+        .figures
+        .as_ref()
+        .is_some_and(|figures| figures.list_pages)
+    {
+        generate_figure_lists(target_path, context, first_pass_results);
+    }
+    Ok(())
+}
+
+/// Fills in the site-wide parts of the pages rendered by the first pass (such as the table of
+/// contents) and writes them, along with the sitemap, feeds and redirects. Only the pages at
+/// `pages` (relative to the output directory) are written if given, along with the sitemap,
+/// feeds and calendar, which list every page, but without the redirects.
+fn second_pass(
+    target_path: &Path,
+    first_pass_results: &[FirstPassResult],
+    pages: Option<&[PathBuf]>,
+    context: &SiteContext,
+) -> anyhow::Result<()> {
+    let output_path = context.output_path;
+    let fs = context.fs;
+    log::info!("2/3: Generating additional site content (if necessary) and saving...");
+
+    let page_ids = links::collect_page_ids(first_pass_results, context);
+    let filters = filters::compile(&context.config.filters)?;
+    let site_pages = templating::site_pages(first_pass_results);
+    let toc = &context.config.toc;
+    let section_numbers = if toc.numbered || toc.number_headings {
+        numbering::section_numbers(first_pass_results)
+    } else {
+        HashMap::new()
+    };
+    let lite_template = BuiltInTemplate::Lite.get_template(&context.config.template_options);
+    let written = first_pass_results
+        .iter()
+        .filter(|result| match (result, pages) {
+            (FirstPassResult::HtmlOutput { relative_path, .. }, Some(pages)) => {
+                pages.contains(relative_path)
+            }
+            _ => true,
+        });
+    for result in written.cloned() {
+        match result {
+            FirstPassResult::Dir { .. } | FirstPassResult::Link { .. } => continue,
+            FirstPassResult::HtmlOutput {
+                depth,
+                html,
+                relative_path,
+                lite_content,
+                title,
+                date,
+                layout,
+                ..
+            } => {
+                // Pages written to a directory of their own are a level deeper than their source
+                let depth = match context.config.pretty_urls {
+                    true => relative_path.components().count(),
+                    false => depth,
+                };
+                let page = templating::PageVariables {
+                    title,
+                    url: utils::page_url(depth, context.web_prefix, &relative_path),
+                    path: relative_path.to_string_lossy().replace('\\', "/"),
+                    date: date.as_ref().map(ToString::to_string),
+                    tags: layout.tags.clone(),
+                    lang: layout.lang.clone(),
+                };
+                let table_of_contents = generate_table_of_contents(
+                    first_pass_results,
+                    depth,
+                    &relative_path,
+                    context.web_prefix,
+                    &context.config,
+                    &section_numbers,
+                );
+                // Ends with a slash, so templates can link to `<!-- {SITE_ROOT} -->style.css`
+                let site_root = utils::page_url(depth, context.web_prefix, Path::new(""));
+                let text =
+                    format_page(&html, depth, page, &layout, &site_pages, context, |name| {
+                        Ok(Some(match name {
+                            "TABLE_OF_CONTENTS" => table_of_contents.clone(),
+                            "BREADCRUMBS" => navigation::breadcrumbs_html(
+                                first_pass_results,
+                                depth,
+                                &relative_path,
+                                context.web_prefix,
+                                &context.config,
+                            ),
+                            "PREV_NEXT" => navigation::prev_next_html(
+                                first_pass_results,
+                                depth,
+                                &relative_path,
+                                context.web_prefix,
+                                &context.config,
+                            ),
+                            "DATE" => match &date {
+                                Some(date) => utils::escape_html(
+                                    &date.format(context.config.date_format.as_deref())?,
+                                ),
+                                None => String::new(),
+                            },
+                            "TAGS" => tags::tags_html(&layout.tags, depth, context),
+                            "SITE_ROOT" if site_root.is_empty() => "./".to_string(),
+                            "SITE_ROOT" => site_root.clone(),
+                            "LOGO" => match &context.config.template_options.logo {
+                                Some(logo) => templates::logo_html(logo, depth, context.web_prefix),
+                                None => String::new(),
+                            },
+                            "PRINT_BUTTON" => templates::PRINT_BUTTON.to_string(),
+                            "VERSION_SWITCHER" => match &context.versions {
+                                Some(versions) => versions.switcher_html(depth),
+                                None => return Ok(None),
+                            },
+                            _ => return Ok(None),
+                        }))
+                    })?;
+                if let Some(limit) = context.config.limits.exceeded(&text, Duration::ZERO) {
+                    context.warn(SsgError::PageLimitError(layout.source, limit));
+                    continue;
+                }
+                let page_number = toc
+                    .numbered
+                    .then(|| section_numbers.get(&relative_path))
+                    .flatten()
+                    .map(String::as_str);
+                let text = if toc.number_headings {
+                    numbering::number_headings(&text, page_number)
+                } else {
+                    text
+                };
+                let backlinks = context
+                    .glossary
+                    .as_ref()
+                    .filter(|_| relative_path == Path::new(glossary::GLOSSARY_PAGE));
+                let text = match backlinks {
+                    Some(glossary) => glossary.fill_backlinks(&text, depth, context.web_prefix),
+                    None => text,
+                };
+                let text = match &context.notes {
+                    Some(notes) => {
+                        notes.fill_backlinks(&text, &relative_path, depth, context.web_prefix)
+                    }
+                    None => text,
+                };
+                let text = links::resolve_page_references(
+                    &text,
+                    &page_ids,
+                    depth,
+                    &relative_path,
+                    context,
+                );
+                let text = match &context.config.figures {
+                    Some(_) => figures::fill_lists(&text, &html),
+                    None => text
+                        .replace(figures::Kind::Figure.macro_name(), "")
+                        .replace(figures::Kind::Table.macro_name(), ""),
+                };
+                let text = filters::apply(&text, &relative_path, &filters).into_owned();
+                let text = match &context.config.security {
+                    Some(security) if security.csp == CspOutput::Meta && !context.fragment => {
+                        security::add_meta(&text, security, context)
+                    }
+                    _ => text,
+                };
+                let result_path = output_path.join(&relative_path);
+                log::debug!("{:?} :: {:?}", &result_path, &relative_path);
+                if context.stdout {
+                    std::io::stdout().write_all(text.as_bytes())?;
+                } else {
+                    context.write_output(&result_path, text.as_bytes())?;
+                }
+                context.report.lock().unwrap().pages.push(result_path);
+                if let Some(lite_content) = lite_content {
+                    let lite_text = utils::wrap_html_content_with(
+                        &lite_content,
+                        Some(&lite_template),
+                        |name| match name {
+                            "TABLE_OF_CONTENTS" => Ok(Some(table_of_contents.clone())),
+                            _ => Ok(None),
+                        },
+                    )?;
+                    let lite_text = if toc.number_headings {
+                        numbering::number_headings(&lite_text, page_number)
+                    } else {
+                        lite_text
+                    };
+                    let lite_text = match backlinks {
+                        Some(glossary) => {
+                            glossary.fill_backlinks(&lite_text, depth, context.web_prefix)
+                        }
+                        None => lite_text,
+                    };
+                    let lite_text = links::resolve_page_references(
+                        &lite_text,
+                        &page_ids,
+                        depth,
+                        &relative_path,
+                        context,
+                    );
+                    let lite_text = filters::apply(&lite_text, &relative_path, &filters);
+                    let lite_path = output_path.join("lite").join(&relative_path);
+                    log::debug!("Writing lite page {:?}", &lite_path);
+                    context.write_output(&lite_path, lite_text.as_bytes())?;
+                }
+            }
+        }
+        // Generate the table of contents
+    }
+
+    if let Some(base_url) = &context.config.sitemap.base_url {
+        sitemap::generate_sitemap(
+            context,
+            first_pass_results,
+            output_path,
+            &context.config.sitemap,
+            base_url,
+        )?;
+    }
+    calendar::generate_ics(context, first_pass_results, output_path)?;
+    if let Some(feed) = &context.config.feed {
+        feed::generate_feeds(context, first_pass_results, output_path, feed)?;
+    }
+    if pages.is_some() {
+        return Ok(());
+    }
+    if let Some(indieweb) = &context.config.indieweb {
+        indieweb::generate_webfinger(context, output_path, indieweb)?;
+    }
+    if context.config.redirect_moved_pages && fs.is_dir(target_path) {
+        redirects::generate_moved_page_redirects(context)?;
+    }
+    if let Some(highlight) = &context.config.highlight {
+        context.write_output(
+            &output_path.join(highlight::STYLESHEET),
+            highlight::stylesheet(highlight)?.as_bytes(),
+        )?;
+    }
+    if let Some(security) = &context.config.security {
+        if security.csp == CspOutput::Headers {
+            security::generate_headers(context, output_path, security)?;
+        }
+    }
+    Ok(())
+}
+
+thread_local! {
+    /// The warnings given on this thread since a `WarningCollector` was created
+    static COLLECTED_WARNINGS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Collects the warnings given on this thread while it's alive, e.g. to cache them with the
+/// page that gave them, and adds them to the build report when it's dropped.
+struct WarningCollector<'a> {
+    report: &'a Mutex<BuildReport>,
+    /// What an enclosing collector had collected
+    outer: Option<Vec<String>>,
+}
+
+impl<'a> WarningCollector<'a> {
+    fn new(context: &'a SiteContext) -> Self {
+        let outer = COLLECTED_WARNINGS.with(|collected| collected.replace(Some(Vec::new())));
+        Self {
+            report: &context.report,
+            outer,
+        }
+    }
+
+    /// The warnings collected so far.
+    fn warnings(&self) -> Vec<String> {
+        COLLECTED_WARNINGS.with(|collected| collected.borrow().clone().unwrap_or_default())
+    }
+}
+
+impl Drop for WarningCollector<'_> {
+    fn drop(&mut self) {
+        let warnings = COLLECTED_WARNINGS
+            .with(|collected| collected.replace(self.outer.take()))
+            .unwrap_or_default();
+        COLLECTED_WARNINGS.with(|collected| match &mut *collected.borrow_mut() {
+            Some(outer) => outer.extend(warnings),
+            None => self.report.lock().unwrap().warnings.extend(warnings),
+        });
+    }
+}
+
+/// Settings shared by every path processed during a single site generation
+pub struct SiteContext<'a> {
+    /// The directory being generated (the parent directory when processing a single file)
+    pub target_path: &'a Path,
+    pub output_path: &'a Path,
+    pub template: Option<BuiltInTemplate>,
+    pub web_prefix: Option<&'a str>,
+    pub config: SiteConfig,
+    /// Set when generating one version of a versioned site
+    pub versions: Option<Versions>,
+    pub template_cache: TemplateCache,
+    /// Write generated pages to stdout instead of the output directory
+    pub stdout: bool,
+    /// Write only the rendered content of each page, without a template
+    pub fragment: bool,
+    /// Never read files from outside `target_path`, and strip raw html from pages
+    pub sandbox: bool,
+    /// Include draft pages and files, instead of leaving them out of the site
+    pub drafts: bool,
+    /// How many pages are rendered at once, if not one per cpu
+    pub jobs: Option<NonZeroUsize>,
+    pub fs: &'a dyn FileSystem,
+    /// What has been generated so far
+    pub report: Mutex<BuildReport>,
+    /// The terms of the site's `_glossary.dj`, if it has one
+    pub glossary: Option<Glossary>,
+    /// The works pages can cite, if the site has a bibliography
+    pub bibliography: Option<Bibliography>,
+    /// The abbreviations of the site's `_abbreviations.toml`, expanded on every page
+    pub abbreviations: Abbreviations,
+    /// The notes of the site, when generating it as a notes vault
+    pub notes: Option<Notes>,
+    /// The previous build's cache, for incremental builds
+    pub cache: Option<BuildCache>,
+}
+
+impl<'a> SiteContext<'a> {
+    fn new(
+        target_path: &'a Path,
+        output_path: &'a Path,
+        args: &'a ConsoleArgs,
+        fs: &'a dyn FileSystem,
+    ) -> anyhow::Result<Self> {
+        let root_path = if fs.is_dir(target_path) {
+            target_path
+        } else {
+            target_path.parent().unwrap()
+        };
+        let config = config::get_site_config(fs, root_path)?;
+        Self::with_config(root_path, output_path, args, fs, config)
+    }
+
+    /// The context of the site in the directory `target_path`, with `config` instead of the
+    /// site's `ssg.toml`.
+    fn with_config(
+        target_path: &'a Path,
+        output_path: &'a Path,
+        args: &'a ConsoleArgs,
+        fs: &'a dyn FileSystem,
+        mut config: SiteConfig,
+    ) -> anyhow::Result<Self> {
+        let root_path = target_path;
+        if args.readme_as_index {
+            config.index_names.push("README".to_string());
+        }
+        config.flags.extend(args.flags.iter().cloned());
+        config.lite |= args.lite;
+        config.pretty_urls |= args.pretty_urls;
+        config.notes |= args.notes;
+        let glossary = Glossary::read(fs, root_path, &config.slugs.clone().unwrap_or_default())?;
+        let bibliography =
+            bibliography::read_bibliography(fs, root_path, &config.bibliography, args.sandbox)?;
+        let abbreviations = abbreviations::read_abbreviations(fs, root_path)?;
+        let notes = match config.notes && fs.is_dir(target_path) {
+            true => Some(Notes::read(fs, root_path, &config)?),
+            false => None,
+        };
+        Ok(Self {
+            target_path: root_path,
+            output_path,
+            template: args.template.clone(),
+            web_prefix: args.web_prefix.as_deref(),
+            config,
+            versions: None,
+            template_cache: TemplateCache::default(),
+            stdout: args.stdout,
+            fragment: args.fragment,
+            sandbox: args.sandbox,
+            drafts: args.drafts,
+            jobs: args.jobs,
+            fs,
+            report: Mutex::default(),
+            glossary,
+            bibliography,
+            abbreviations,
+            notes,
+            cache: args.incremental.then(|| BuildCache::read(fs, output_path)),
+        })
+    }
+
+    /// Logs a warning, and adds it to the build report.
+    pub fn warn(&self, warning: impl std::fmt::Display) {
+        log::warn!("{}", warning);
+        let warning = warning.to_string();
+        COLLECTED_WARNINGS.with(|collected| match &mut *collected.borrow_mut() {
+            Some(collected) => collected.push(warning),
+            None => self.report.lock().unwrap().warnings.push(warning),
+        });
+    }
+
+    /// Writes a generated file, creating its directory if needed, and adds it to the build
+    /// report's outputs. Incremental builds skip writing files that are unchanged since the
+    /// previous build.
+    pub fn write_output(&self, path: &Path, contents: &[u8]) -> std::io::Result<()> {
+        if !self.is_unchanged_output(path, contents) {
+            if let Some(parent) = path.parent() {
+                self.fs.create_dir_all(parent)?;
+            }
+            self.fs.write(path, contents)?;
+        }
+        self.report.lock().unwrap().outputs.push(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Copies a file to the output directory, unless an incremental build finds it unchanged.
+    /// Unlike `write_output`, this doesn't add it to the build report.
+    pub fn copy_output(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        match &self.cache {
+            Some(_) => {
+                let contents = self.fs.read(from)?;
+                if !self.is_unchanged_output(to, &contents) {
+                    self.fs.write(to, &contents)?;
+                }
+            }
+            None => self.fs.copy(from, to)?,
+        }
+        Ok(())
+    }
+
+    fn is_unchanged_output(&self, path: &Path, contents: &[u8]) -> bool {
+        self.cache
+            .as_ref()
+            .is_some_and(|cache| cache.is_unchanged_output(path, contents))
+            && self.fs.is_file(path)
+    }
+}
+
+fn process_path(
+    entity: &Path,
+    context: &SiteContext,
+    depth: usize,
+    first_pass_results: &mut Vec<FirstPassResult>,
+) -> anyhow::Result<()> {
+    let relative = match entity.strip_prefix(context.target_path) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => {
+            context.warn(SsgError::PathNotRelative(entity.to_path_buf()));
+            return Ok(());
+        }
+    };
+    log::debug!("{:?} :: {}", &relative, depth);
+    let fs = context.fs;
+    if context.sandbox && !sandbox::is_inside(fs, context.target_path, entity) {
+        context.warn(SsgError::OutsideTarget(entity.to_path_buf()));
+        return Ok(());
+    }
+    if context.config.is_ignored(&relative) || mounts::is_mount_source(entity, context) {
+        log::trace!("Path {:?} is ignored, continuing...", entity);
+        return Ok(());
+    } else if entity.starts_with(context.output_path) {
+        log::trace!(
+            "Path {:?} is in the output directory, continuing...",
+            entity
+        );
+        return Ok(());
+    }
+    if fs.is_dir(entity) {
+        log::trace!("Path {:?} is a directory, continuing...", entity);
+        check_file_name(entity, &relative, context);
+        let dir_metadata = metadata::get_dir_metadata(fs, entity)?;
+        let relative_dir = slug::dir_path(&relative, context.config.file_name_slugs());
+        first_pass_results.push(FirstPassResult::Dir {
+            depth,
+            relative_path: relative_dir.clone(),
+            title: dir_metadata.title.clone(),
+        });
+        if dir_metadata.asset_index {
+            if utils::check_has_index(fs, entity, &context.config.index_names) {
+                context.warn(format!(
+                    "{:?} has an index page, so its asset index isn't generated",
+                    entity
+                ));
+                return Ok(());
+            }
+            let title = dir_metadata
+                .title
+                .unwrap_or_else(|| match entity.file_name() {
+                    Some(name) if !relative.as_os_str().is_empty() => {
+                        name.to_string_lossy().to_string()
+                    }
+                    _ => "Files".to_string(),
+                });
+            let assets = asset_index::list_assets(entity, context)?;
+            let html = asset_index::index_html(&title, &assets, context)?;
+            // The index is generated from `_dir.toml`, which finds the directory's template
+            let layout = PageLayout::generated(entity.join("_dir.toml"), context);
+            first_pass_results.push(FirstPassResult::HtmlOutput {
+                depth: depth + 1,
+                lite_content: layout.lite_content(&html, context),
+                html,
+                relative_path: relative_dir.join("index.html"),
+                unlisted: false,
+                in_toc: true,
+                id: None,
+                title: Some(title),
+                date: None,
+                layout: Box::new(layout),
+            });
+        }
+        return Ok(());
+    } else if entity.ends_with("template.html") {
+        log::trace!("Path {:?} is a template, continuing...", entity);
+        return Ok(());
+    } else if templating::is_partial(entity) {
+        log::trace!("Path {:?} is a template partial, continuing...", entity);
+        return Ok(());
+    } else if relative == Path::new(abbreviations::ABBREVIATIONS_FILE) {
+        log::trace!(
+            "Path {:?} is the site's abbreviations, continuing...",
+            entity
+        );
+        return Ok(());
+    } else if entity.ends_with("_dir.toml") {
+        log::trace!("Path {:?} is directory metadata, continuing...", entity);
+        return Ok(());
+    } else if relative == Path::new("ssg.toml") {
+        log::trace!("Path {:?} is the site config, continuing...", entity);
+        return Ok(());
+    } else if relative == Path::new(webmention::SENT_FILE) {
+        log::trace!(
+            "Path {:?} is the record of sent webmentions, continuing...",
+            entity
+        );
+        return Ok(());
+    } else if !context.drafts
+        && entity
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with("_draft"))
+    {
+        log::debug!("Path {:?} is a draft, skipping...", entity);
+        return Ok(());
+    }
+    check_file_name(entity, &relative, context);
+    log::trace!("Path: {:?}", entity);
+    // Files are copied into their directory's slugged path, beside the pages that link to them
+    let new_path = context
+        .output_path
+        .join(slug::file_path(&relative, context.config.file_name_slugs()));
+    let _ = fs.create_dir_all(new_path.parent().unwrap());
+    match entity.extension().map(|x| x.to_str().unwrap()) {
+        Some("dj") | Some("djot") | Some("md") => {
+            let is_index_alias = utils::is_index_alias(fs, entity, &context.config.index_names);
+            if !is_index_alias
+                && entity.file_stem().is_some_and(|stem| {
+                    context
+                        .config
+                        .index_names
+                        .iter()
+                        .any(|name| stem == name.as_str())
+                })
+            {
+                context.warn(format!(
+                    "{:?} is configured as an index page, but an index page already exists beside it",
+                    entity
+                ));
+            }
+            let relative_html = page_html_path(entity, &relative, context);
+            let result_path = context.output_path.join(&relative_html);
+            log::debug!(
+                "Generating .html from {:?} and moving to {:?}",
+                entity,
+                &result_path
+            );
+            let limits = &context.config.limits;
+            let source_size = fs.len(entity)?;
+            if source_size > limits.max_source_size {
+                let limit = format!(
+                    "source size limit ({} bytes > {})",
+                    source_size, limits.max_source_size
+                );
+                context.warn(SsgError::PageLimitError(entity.to_path_buf(), limit));
+                return Ok(());
+            }
+            let started = Instant::now();
+            let input_str = fs.read_to_string(entity)?;
+            // Pages record what they link to and mention in notes and glossary sites, so
+            // they're always rendered there
+            let cache = context
+                .cache
+                .as_ref()
+                .filter(|_| context.glossary.is_none() && context.notes.is_none());
+            let source_hash = cache.map(|_| cache::hash(input_str.as_bytes()));
+            if let (Some(cache), Some(hash)) = (cache, &source_hash) {
+                if let Some((result, warnings)) = cache.page(&relative, hash) {
+                    log::debug!("{:?} is unchanged, using its cached render", entity);
+                    for warning in warnings {
+                        context.warn(warning);
+                    }
+                    first_pass_results.push(result);
+                    return Ok(());
+                }
+            }
+            // The warnings of rendering the page are cached with it, to give them again when
+            // the cached render is used
+            let warnings = cache.map(|_| WarningCollector::new(context));
+            let rendered = match render_source(&input_str, entity, &relative_html, depth, context) {
+                Err(error)
+                    if matches!(error.downcast_ref(), Some(SsgError::PageLimitError(..))) =>
+                {
+                    context.warn(error);
+                    return Ok(());
+                }
+                rendered => rendered?,
+            };
+            let Some(RenderedPage {
+                front_matter,
+                markup: input_str,
+                html,
+            }) = rendered
+            else {
+                log::debug!("Page {:?} is a draft, skipping...", entity);
+                return Ok(());
+            };
+            let input_str = &*input_str;
+            let is_markdown = entity.extension().is_some_and(|ext| ext == "md");
+            for (name, date) in [
+                ("event_date", &front_matter.event_date),
+                ("event_end", &front_matter.event_end),
+            ] {
+                if date
+                    .as_ref()
+                    .is_some_and(|date| !calendar::is_event_time(date))
+                {
+                    context.warn(format!(
+                        "{:?} has an {} that isn't a date, so it's left out of the calendar",
+                        entity, name
+                    ));
+                }
+            }
+            if let Some(limit) = limits.exceeded(&html, started.elapsed()) {
+                context.warn(SsgError::PageLimitError(entity.to_path_buf(), limit));
+                return Ok(());
+            }
+            let output_formats = if front_matter.password.is_some() {
+                if !front_matter.output_formats.is_empty() {
+                    context.warn(format!(
+                        "{:?} is encrypted, skipping its other output formats",
+                        entity
+                    ));
+                }
+                &[][..]
+            } else {
+                &front_matter.output_formats[..]
+            };
+            let file_stem = entity.file_stem().unwrap().to_string_lossy();
+            for output_format in output_formats {
+                match output_format {
+                    OutputFormat::Man => {
+                        let section = front_matter.man_section.unwrap_or(1);
+                        let mut writer = man::ManWriter::new(&file_stem, section);
+                        outputs::write_document(input_str, is_markdown, &mut writer);
+                        let man_path = new_path.with_extension(section.to_string());
+                        log::debug!("Writing man page for {:?} to {:?}", entity, &man_path);
+                        context.write_output(&man_path, writer.finish().as_bytes())?;
+                    }
+                    OutputFormat::Latex => {
+                        let mut writer = latex::LatexWriter::new(&file_stem);
+                        outputs::write_document(input_str, is_markdown, &mut writer);
+                        let tex_path = new_path.with_extension("tex");
+                        log::debug!("Writing LaTeX for {:?} to {:?}", entity, &tex_path);
+                        context.write_output(&tex_path, writer.finish().as_bytes())?;
+                    }
+                }
+            }
+            let lang = front_matter
+                .lang
+                .as_deref()
+                .or(context.config.lang.as_deref());
+            let dir = front_matter
+                .dir
+                .or(front_matter.lang.as_deref().map(TextDirection::of_lang))
+                .or(context.config.dir)
+                .or(context.config.lang.as_deref().map(TextDirection::of_lang));
+            let layout = PageLayout {
+                source: entity.to_path_buf(),
+                lang: lang.map(str::to_string),
+                dir,
+                template: front_matter.template,
+                tags: front_matter.tags,
+                event_date: front_matter.event_date,
+                event_end: front_matter.event_end,
+                event_location: front_matter.event_location,
+            };
+            // Encrypted pages can't be read without javascript, so they're left out of the mirror
+            let lite_content = match front_matter.password {
+                Some(_) => None,
+                None => layout.lite_content(&html, context),
+            };
+            let html = match &context.notes {
+                Some(_) => html + notes::BACKLINKS_PLACEHOLDER,
+                None => html,
+            };
+            let result = FirstPassResult::HtmlOutput {
+                depth,
+                html,
+                relative_path: relative_html,
+                unlisted: front_matter.unlisted,
+                in_toc: true,
+                id: front_matter.id,
+                lite_content,
+                title: front_matter.title,
+                date: front_matter.date,
+                layout: Box::new(layout),
+            };
+            // Changelogs are read from the git history, and the other output formats are only
+            // written when the page is rendered
+            if let (Some(cache), Some(hash)) = (cache, source_hash) {
+                if front_matter.changelog.is_none() && output_formats.is_empty() {
+                    let warnings = warnings.as_ref().map(WarningCollector::warnings);
+                    cache.store_page(&relative, hash, &result, warnings.unwrap_or_default());
+                }
+            }
+            first_pass_results.push(result);
+        }
+        _ => {
+            context.copy_output(entity, &new_path)?;
+            let mut report = context.report.lock().unwrap();
+            report.assets.push(new_path.clone());
+            report.outputs.push(new_path);
+        }
+    }
+    Ok(())
+}
+
+/// Adds the index of every note to the site, unless a page is already written in its place.
+fn generate_notes_index(
+    notes: &Notes,
+    target_path: &Path,
+    context: &SiteContext,
+    first_pass_results: &mut Vec<FirstPassResult>,
+) -> anyhow::Result<()> {
+    let relative_path = PathBuf::from(notes::NOTES_INDEX);
+    if notes
+        .notes
+        .iter()
+        .any(|note| note.html_path == relative_path)
+    {
+        context.warn(format!(
+            "A note is already written to {:?}, so the index of notes isn't generated",
+            notes::NOTES_INDEX
+        ));
+        return Ok(());
+    }
+    let html = notes.index_html(context)?;
+    // The index finds the root's template
+    let layout = PageLayout::generated(target_path.join(notes::NOTES_INDEX), context);
+    first_pass_results.push(FirstPassResult::HtmlOutput {
+        depth: 1,
+        lite_content: layout.lite_content(&html, context),
+        html,
+        relative_path,
+        unlisted: false,
+        in_toc: true,
+        id: None,
+        title: Some("All notes".to_string()),
+        date: None,
+        layout: Box::new(layout),
+    });
+    Ok(())
+}
+
+/// Adds the calendar of the pages with an `event_date` to the site, if there are any, unless a
+/// page is already written in its place.
+fn generate_calendar(
+    target_path: &Path,
+    context: &SiteContext,
+    first_pass_results: &mut Vec<FirstPassResult>,
+) {
+    let Some(html) = calendar::calendar_html(first_pass_results, context) else {
+        return;
+    };
+    let relative_path = PathBuf::from(calendar::CALENDAR_PAGE);
+    if first_pass_results.iter().any(|result| {
+        matches!(result, FirstPassResult::HtmlOutput { relative_path: path, .. } if *path == relative_path)
+    }) {
+        context.warn(format!(
+            "A page is already written to {:?}, so the calendar isn't generated",
+            calendar::CALENDAR_PAGE
+        ));
+        return;
+    }
+    // The calendar finds the root's template
+    let layout = PageLayout::generated(target_path.join(calendar::CALENDAR_PAGE), context);
+    first_pass_results.push(FirstPassResult::HtmlOutput {
+        depth: 1,
+        lite_content: layout.lite_content(&html, context),
+        html,
+        relative_path,
+        unlisted: false,
+        in_toc: true,
+        id: None,
+        title: Some("Calendar".to_string()),
+        date: None,
+        layout: Box::new(layout),
+    });
+}
+
+/// Adds `figures.html` and `tables.html` to the root of the site, listing the captioned figures
+/// and tables of every listed page, when it has any.
+fn generate_figure_lists(
+    target_path: &Path,
+    context: &SiteContext,
+    first_pass_results: &mut Vec<FirstPassResult>,
+) {
+    for kind in [figures::Kind::Figure, figures::Kind::Table] {
+        let Some(html) = figures::list_page_html(first_pass_results, kind, context) else {
+            continue;
+        };
+        let relative_path = PathBuf::from(kind.page());
+        if first_pass_results.iter().any(|result| {
+            matches!(result, FirstPassResult::HtmlOutput { relative_path: path, .. } if *path == relative_path)
+        }) {
+            context.warn(format!(
+                "A page is already written to {:?}, so the {} isn't generated",
+                kind.page(),
+                kind.title().to_lowercase()
+            ));
+            continue;
+        }
+        // The lists find the root's template
+        let layout = PageLayout::generated(target_path.join(&relative_path), context);
+        first_pass_results.push(FirstPassResult::HtmlOutput {
+            depth: 1,
+            lite_content: layout.lite_content(&html, context),
+            html,
+            relative_path,
+            unlisted: false,
+            in_toc: true,
+            id: None,
+            title: Some(kind.title().to_string()),
+            date: None,
+            layout: Box::new(layout),
+        });
+    }
+}
+
+/// Warns if the name of the file or directory at `entity` would make a problematic url. Pages
+/// and directories are renamed with `file_names` in `[slugs]`, so it's suggested for them.
+fn check_file_name(entity: &Path, relative: &Path, context: &SiteContext) {
+    let Some(name) = relative.file_name() else {
+        return;
+    };
+    let is_dir = context.fs.is_dir(entity);
+    let is_page = !is_dir
+        && matches!(
+            entity.extension().and_then(|ext| ext.to_str()),
+            Some("dj" | "djot" | "md")
+        );
+    // A slugged page or directory's url has its slug instead of its name
+    if (is_page || is_dir) && context.config.file_name_slugs().is_some() {
+        return;
+    }
+    let name = match is_page {
+        true => entity.file_stem().unwrap_or(name),
+        false => name,
+    };
+    let problems = slug::url_problems(&name.to_string_lossy());
+    if problems.is_empty() {
+        return;
+    }
+    let fix = match is_page || is_dir {
+        true => "rename it, or set file_names = true in the [slugs] section of ssg.toml to slug the names of pages and directories",
+        false => "rename it",
+    };
+    context.warn(format!(
+        "The url of {:?} will have {}, which some hosts and tools don't handle; {}",
+        relative,
+        problems.join(", "),
+        fix
+    ));
+}
+
+/// Warns about pages that are generated at the same path as another page, or as a file of the
+/// site, such as `My Page.dj` and `my-page.md` with slugged file names.
+fn check_output_collisions(
+    target_path: &Path,
+    first_pass_results: &[FirstPassResult],
+    context: &SiteContext,
+) {
+    let mut sources: HashMap<&Path, &Path> = HashMap::new();
+    for result in first_pass_results {
+        let FirstPassResult::HtmlOutput {
+            relative_path,
+            layout,
+            ..
+        } = result
+        else {
+            continue;
+        };
+        let source = layout
+            .source
+            .strip_prefix(target_path)
+            .unwrap_or(&layout.source);
+        match sources.insert(relative_path, source) {
+            Some(other) if other != source => context.warn(format!(
+                "{:?} and {:?} are both generated as {:?}, so one is written over the other; rename one of them",
+                other, source, relative_path
+            )),
+            _ if source != relative_path.as_path()
+                && context.fs.is_file(&target_path.join(relative_path)) =>
+            {
+                context.warn(format!(
+                    "{:?} is generated as {:?}, which is written over the site's own file; rename one of them",
+                    source, relative_path
+                ))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Adds a page for each tag of the listed pages, and an index of the tags, to the site under
+/// `tags/`, unless the site already has pages there.
+fn generate_tag_pages(
+    target_path: &Path,
+    context: &SiteContext,
+    first_pass_results: &mut Vec<FirstPassResult>,
+) -> anyhow::Result<()> {
+    let tags = tags::collect_tags(first_pass_results, context);
+    if tags.is_empty() {
+        return Ok(());
+    }
+    if first_pass_results.iter().any(|result| {
+        matches!(result, FirstPassResult::HtmlOutput { relative_path, .. } if relative_path.starts_with(tags::TAGS_DIR))
+    }) {
+        context.warn(format!(
+            "The site already has pages in {:?}, so the tag pages aren't generated",
+            tags::TAGS_DIR
+        ));
+        return Ok(());
+    }
+    let index_path = Path::new(tags::TAGS_DIR).join("index.html");
+    let mut pages = tags::index_pages(&tags, context)
+        .into_iter()
+        .map(|(page, html)| (page, "Tags".to_string(), html))
+        .collect::<Vec<_>>();
+    for tag in tags.values() {
+        if tag.path == index_path {
+            context.warn(format!(
+                "The tag {:?} would be written over the index of tags, so it has no page",
+                tag.name
+            ));
+            continue;
+        }
+        for (page, html) in tags::tag_pages(tag, context)? {
+            pages.push((page, tags::tag_title(tag), html));
+        }
+    }
+    first_pass_results.push(FirstPassResult::Dir {
+        depth: 1,
+        relative_path: PathBuf::from(tags::TAGS_DIR),
+        title: Some("Tags".to_string()),
+    });
+    for (page, title, html) in pages {
+        let title = match page.number {
+            1 => title,
+            number => format!("{} (page {})", title, number),
+        };
+        // Only the index is in the table of contents, so many tags don't crowd it
+        let in_toc = page.path == index_path;
+        // The tag pages find the root's template
+        let layout = PageLayout::generated(target_path.join(&page.path), context);
+        first_pass_results.push(FirstPassResult::HtmlOutput {
+            depth: page.depth,
+            lite_content: layout.lite_content(&html, context),
+            html,
+            relative_path: page.path,
+            unlisted: false,
+            in_toc,
+            id: None,
+            title: Some(title),
+            date: None,
+            layout: Box::new(layout),
+        });
+    }
+    Ok(())
+}
+
+/// Wraps the rendered content `html` of `page`, `depth` deep, in its template (the template in
+/// its front matter, if it has one), rendered with the page's variables and the `site_pages`,
+/// and fills in the template's per-page placeholders (such as `<!-- {FOOTER} -->`).
+fn format_page(
+    html: &str,
+    depth: usize,
+    mut page: templating::PageVariables,
+    layout: &PageLayout,
+    site_pages: &[templating::PageVariables],
+    context: &SiteContext,
+    mut macros: impl FnMut(&str) -> anyhow::Result<Option<String>>,
+) -> anyhow::Result<String> {
+    let entity = layout.source.as_path();
+    let page_template = layout.template.as_ref();
+    // Templates from files can include, import, or extend the templates found from them
+    let (html_template, template_path) = if context.fragment {
+        (None, None)
+    } else if let Some(PageTemplate::BuiltIn(template)) = page_template {
+        (
+            Some(template.get_template(&context.config.template_options)),
+            None,
+        )
+    } else if let Some(PageTemplate::File(template)) = page_template {
+        let template = entity.parent().unwrap().join(template);
+        if !utils::is_within(context.target_path, &template)
+            || (context.sandbox && !sandbox::is_inside(context.fs, context.target_path, &template))
+        {
+            return Err(SsgError::OutsideTarget(template).into());
+        }
+        (Some(context.fs.read_to_string(&template)?), Some(template))
+    } else if let Some(template) = &context.template {
+        (
+            Some(template.get_template(&context.config.template_options)),
+            None,
+        )
+    } else {
+        (
+            context
+                .template_cache
+                .read(context, entity, "template.html")?,
+            context
+                .template_cache
+                .find(context, entity, "template.html")?,
+        )
+    };
+    // The page's title is its front matter title or first level 1 heading, or else the site's
+    let site_title = context.config.title.as_deref().map(utils::escape_html);
+    let title = page
+        .title
+        .as_deref()
+        .map(utils::escape_html)
+        .or_else(|| utils::first_heading(html))
+        .or(site_title.clone());
+    page.title = title.as_deref().map(utils::unescape_html);
+    let h_entry = match &context.config.indieweb {
+        Some(indieweb) => indieweb::h_entry(&page, indieweb, context),
+        None => String::new(),
+    };
+    // The template's macros are filled in one pass, so the content isn't searched for them
+    let mut fill = |name: &str| -> anyhow::Result<Option<String>> {
+        Ok(Some(match name {
+            "CONTENT" => html.to_string(),
+            "TITLE" => title.clone().unwrap_or_default(),
+            "SITE_TITLE" => match &site_title {
+                Some(site_title) => site_title.clone(),
+                None => return Ok(None),
+            },
+            "HEAD" => context
+                .template_cache
+                .read(context, entity, "_head.html")?
+                .unwrap_or_default(),
+            "FOOTER" => {
+                match context
+                    .template_cache
+                    .read(context, entity, "_footer.html")?
+                {
+                    Some(footer) => footer,
+                    None => context
+                        .config
+                        .template_options
+                        .footer
+                        .as_ref()
+                        .map_or(String::new(), |footer| {
+                            format!("<div class=\"site-footer\">{}</div>", footer)
+                        }),
+                }
+            }
+            "LAST_UPDATED" => utils::format_date(
+                git::last_updated(entity, context.fs),
+                context.config.date_format.as_deref(),
+            )?,
+            "CONTRIBUTORS" => {
+                utils::contributors_html(&git::contributors(entity), &context.config.contributors)
+            }
+            "H_ENTRY" => match &context.config.indieweb {
+                Some(_) if !context.fragment => h_entry.clone(),
+                _ => String::new(),
+            },
+            "H_CARD" => match &context.config.indieweb {
+                Some(indieweb) if !context.fragment => indieweb::h_card(indieweb, depth, context),
+                _ => String::new(),
+            },
+            name => return macros(name),
+        }))
+    };
+    let html_formatted = match html_template {
+        Some(template) => utils::fill_macros(
+            &templating::render(
+                template,
+                template_path.as_deref().map(|path| (path, context)),
+                &entity.display().to_string(),
+                || templating::Variables::new(page, depth, site_pages, context),
+            )?,
+            &mut fill,
+        )?,
+        None => html.to_string(),
+    };
+    // Fragments are embedded in other pages, which set their own language and styles
+    let (html_formatted, stylesheet) = if context.fragment {
+        (html_formatted, None)
+    } else {
+        (
+            utils::set_language(&html_formatted, layout.lang.as_deref(), layout.dir),
+            context.template_cache.find(context, entity, "style.css")?,
+        )
+    };
+    let html_formatted = match stylesheet {
+        Some(stylesheet) => utils::link_stylesheet(
+            &html_formatted,
+            &utils::page_url(
+                depth,
+                context.web_prefix,
+                stylesheet.strip_prefix(context.target_path)?,
+            ),
+        ),
+        None => html_formatted,
+    };
+    let has_highlighted_code = context.config.highlight.is_some()
+        && html.contains(&format!("<pre class=\"{}\">", highlight::CODE_CLASS));
+    let html_formatted = if has_highlighted_code && !context.fragment {
+        utils::link_stylesheet(
+            &html_formatted,
+            &utils::page_url(depth, context.web_prefix, Path::new(highlight::STYLESHEET)),
+        )
+    } else {
+        html_formatted
+    };
+    let html_formatted = match &title {
+        Some(title) if !context.fragment => utils::set_title(&html_formatted, title),
+        _ => html_formatted,
+    };
+    let html_formatted = if layout.tags.is_empty() {
+        html_formatted
+    } else {
+        utils::add_meta(&html_formatted, "keywords", &layout.tags.join(", "))
+    };
+    let html_formatted = match &context.config.indieweb {
+        Some(indieweb) if !context.fragment => indieweb::add_links(&html_formatted, indieweb),
+        _ => html_formatted,
+    };
+    let html_formatted = match &context.config.beacon {
+        Some(beacon) if !context.fragment => beacon::add_beacon(&html_formatted, beacon),
+        _ => html_formatted,
+    };
+    Ok(html_formatted)
+}
+
+/// Renders a markdown page to html (without a template) on its own, as if it were at the root
+/// of a site with `config`, as the pages of a site are rendered.
+pub fn process_markdown(input: &str, config: SiteConfig) -> anyhow::Result<String> {
+    render_page(input, config, true)
+}
+
+/// Renders a djot page to html (without a template) on its own, as if it were at the root of a
+/// site with `config`, as the pages of a site are rendered.
+pub fn process_djot(input: &str, config: SiteConfig) -> anyhow::Result<String> {
+    render_page(input, config, false)
+}
+
+/// Renders a page on its own, as if it were at the root of a site with `config`. Pages rendered
+/// on their own can't read any other files, so they're rendered in an empty site in memory.
+fn render_page(input: &str, config: SiteConfig, is_markdown: bool) -> anyhow::Result<String> {
+    let page = Path::new(if is_markdown { "page.md" } else { "page.dj" });
+    render_site_page(input, page, config, &vfs::MemoryFs::new())
+}
+
+/// Renders `input` (without a template) as the page at `page` of a site in `fs` with `config`,
+/// whose root is the page's directory.
+fn render_site_page(
+    input: &str,
+    page: &Path,
+    config: SiteConfig,
+    fs: &dyn FileSystem,
+) -> anyhow::Result<String> {
+    let root = page.parent().unwrap_or(Path::new(""));
+    let args = ConsoleArgs {
+        fragment: true,
+        drafts: true,
+        ..Default::default()
+    };
+    let context = SiteContext::with_config(root, root, &args, fs, config)?;
+    let relative = page.strip_prefix(root)?;
+    let relative_html = page_html_path(page, relative, &context);
+    let rendered = render_source(input, page, &relative_html, 1, &context)?;
+    Ok(rendered.map(|rendered| rendered.html).unwrap_or_default())
+}
+
+/// The output path of the page at `entity` (`relative` to the target directory), relative to
+/// the output directory.
+fn page_html_path(entity: &Path, relative: &Path, context: &SiteContext) -> PathBuf {
+    if context.glossary.is_some() && relative == Path::new(glossary::GLOSSARY_SOURCE) {
+        PathBuf::from(glossary::GLOSSARY_PAGE)
+    } else if utils::is_index_alias(context.fs, entity, &context.config.index_names) {
+        relative.with_file_name("index.html")
+    } else if context.config.pretty_urls {
+        utils::pretty_path(&slug::html_path(relative, context.config.file_name_slugs()))
+    } else {
+        slug::html_path(relative, context.config.file_name_slugs())
+    }
+}
+
+/// A page's source rendered to html, before it's placed in the site.
+struct RenderedPage {
+    front_matter: metadata::FrontMatter,
+    /// The source without its front matter, with its variables, shortcodes, wikilinks, and
+    /// abbreviation definitions processed
+    markup: String,
+    html: String,
+}
+
+/// Renders `input`, the source of the page at `entity` (written to `relative_html` in the
+/// output directory, at `depth`), to html, or nothing if it's a draft that's left out. Pages
+/// of sites and pages rendered on their own are rendered the same way.
+fn render_source(
+    input: &str,
+    entity: &Path,
+    relative_html: &Path,
+    depth: usize,
+    context: &SiteContext,
+) -> anyhow::Result<Option<RenderedPage>> {
+    let relative = entity.strip_prefix(context.target_path).unwrap_or(entity);
+    let glossary = context
+        .glossary
+        .as_ref()
+        .filter(|_| relative == Path::new(glossary::GLOSSARY_SOURCE));
+    // Links in the content are relative to the source, so they're rebased when the page is
+    // written to a directory of its own
+    let moved = relative_html.components().count() > relative.components().count();
+    let parent = entity.parent().unwrap_or(context.target_path);
+    // Checked as the page grows, so a page over its limits is given up on as soon as it is
+    let started = Instant::now();
+    let within_limits = |text: &str| match context.config.limits.exceeded(text, started.elapsed()) {
+        Some(limit) => Err(SsgError::PageLimitError(entity.to_path_buf(), limit)),
+        None => Ok(()),
+    };
+    let (front_matter, input_str) = metadata::parse_front_matter(input, entity, context)?;
+    if front_matter.draft && !context.drafts {
+        return Ok(None);
+    }
+    let input_str = utils::substitute_variables(input_str, entity, context);
+    let is_markdown = entity.extension().is_some_and(|ext| ext == "md");
+    let input_str = shortcodes::expand_shortcodes(&input_str, entity, is_markdown, context);
+    let input_str = match &context.notes {
+        Some(notes) => {
+            let (linked, unresolved) =
+                notes.link_wikilinks(&input_str, relative_html, depth, context.web_prefix);
+            for name in unresolved {
+                context.warn(format!("{:?} links to a missing note {:?}", entity, name));
+            }
+            linked.into()
+        }
+        None => input_str,
+    };
+    within_limits(&input_str)?;
+    let input_str = &*input_str;
+    let (input_str, page_abbreviations) = abbreviations::take_definitions(input_str);
+    let input_str = &*input_str;
+    if glossary.is_none() {
+        for problem in lint::lint_page(input_str, is_markdown, relative, &context.config.lint) {
+            context.warn(SsgError::LintError(entity.to_path_buf(), problem));
+        }
+    }
+    let html = if let Some(glossary) = glossary {
+        glossary.page_html(context)?
+    } else if is_markdown {
+        markdown_to_html(input_str, parent, context)?
+    } else {
+        djot_to_html(input_str, parent, context)?
+    };
+    within_limits(&html)?;
+    let html = match &context.bibliography {
+        Some(bibliography) if glossary.is_none() => bibliography::cite(
+            &html,
+            bibliography,
+            context.config.bibliography.style,
+            entity,
+            context,
+        ),
+        _ => html,
+    };
+    let html = match &context.glossary {
+        Some(terms) if glossary.is_none() => terms.link_terms(
+            &html,
+            relative_html,
+            depth,
+            context.web_prefix,
+            context.config.glossary.link_terms,
+        ),
+        _ => html,
+    };
+    if let Some(notes) = &context.notes {
+        notes.find_mentions(&html, relative_html);
+    }
+    let mut all_abbreviations = context.abbreviations.clone();
+    all_abbreviations.extend(page_abbreviations);
+    let html = abbreviations::expand(&html, &all_abbreviations);
+    within_limits(&html)?;
+    // Numbers are filled in once the table of contents is known, which they can't be
+    // inside encrypted content
+    let html = if context.config.toc.number_headings && front_matter.password.is_none() {
+        numbering::mark_headings(&html)
+    } else {
+        html
+    };
+    let html = match &front_matter.changelog {
+        Some(source) => {
+            html + &changelog::generate_releases(source, entity, relative_html, context)?
+        }
+        None => html,
+    };
+    within_limits(&html)?;
+    let html = match &context.config.links {
+        Some(links) => links::classify_links(&html, links, context.web_prefix),
+        None => html,
+    };
+    let html = match &context.config.figures {
+        Some(_) => figures::add_ids(&html),
+        None => html,
+    };
+    let html = match moved {
+        true => links::rebase_relative_urls(&html, "../"),
+        false => html,
+    };
+    let html = match &front_matter.password {
+        Some(password) => encrypt::encrypt_html(&html, password)?,
+        None => html,
+    };
+    Ok(Some(RenderedPage {
+        front_matter,
+        markup: input_str.to_string(),
+        html,
+    }))
+}
+
+fn markdown_to_html(
+    markdown_input: &str,
+    file_parent_dir: &Path,
+    context: &SiteContext,
+) -> anyhow::Result<String> {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_GFM);
+    let mut events = pulldown_cmark::Parser::new_ext(markdown_input, options)
+        .filter(|event| !context.sandbox || sandbox::keep_markdown_event(event))
+        .map(|event| match context.sandbox {
+            true => sandbox::safe_markdown_event(event, context),
+            false => event,
+        })
+        .map(|event| -> anyhow::Result<pulldown_cmark::Event> {
+            match event {
+                // Inline, reference, collapsed, and shortcut links all arrive here with their
+                // destination already resolved. Autolinks and emails always have a scheme.
+                pulldown_cmark::Event::Start(pulldown_cmark::Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }) if !matches!(
+                    link_type,
+                    pulldown_cmark::LinkType::Autolink | pulldown_cmark::LinkType::Email
+                ) =>
+                {
+                    let dest_url = if let Some(url) =
+                        links::expand_link_prefix(&dest_url, &context.config)
+                    {
+                        CowStr::Boxed(url.into_boxed_str())
+                    } else {
+                        match links::rewrite_document_link(&dest_url, file_parent_dir, context) {
+                            Some(link) if link.outside_target => {
+                                context.warn(SsgError::LinkOutsideTarget(dest_url.to_string()));
+                                dest_url
+                            }
+                            Some(link) => {
+                                if !context.fs.exists(&link.referenced_path) {
+                                    context.warn(SsgError::LinkError(link.referenced_path))
+                                }
+                                CowStr::Boxed(link.url.into_boxed_str())
+                            }
+                            None => dest_url,
+                        }
+                    };
+                    Ok(pulldown_cmark::Event::Start(pulldown_cmark::Tag::Link {
+                        link_type,
+                        dest_url,
+                        title,
+                        id,
+                    }))
+                }
+                _ => Ok(event),
+            }
+        })
+        .collect::<Result<Vec<pulldown_cmark::Event>, _>>()?;
+    // Markdown has no heading ids of its own, so they're always slugged
+    slug::slug_markdown_headings(
+        &mut events,
+        &context.config.slugs.clone().unwrap_or_default(),
+    );
+    if context.config.highlight.is_some() {
+        highlight::highlight_markdown_code(&mut events);
+    }
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events.iter().cloned());
+    if context.config.heading_anchors {
+        html = slug::add_heading_anchors(&html);
+    }
+    Ok(html)
+}
+
+fn djot_to_html(
+    djot_input: &str,
+    file_parent_dir: &Path,
+    context: &SiteContext,
+) -> anyhow::Result<String> {
+    let mut conditional_filter = conditions::ConditionalFilter::new(&context.config.flags);
+    let mut raw_filter = sandbox::RawFilter::default();
+    let mut events = jotdown::Parser::new(djot_input)
+        .filter(|event| conditional_filter.keep(event))
+        .filter(|event| !context.sandbox || raw_filter.keep(event))
+        .map(|event| match context.sandbox {
+            true => sandbox::safe_event(event, context),
+            false => event,
+        })
+        .map(|event| -> anyhow::Result<Event> {
+            match event {
+                // Inline and resolved reference links both carry their destination here
+                Event::Start(
+                    Container::Link(text, link_type @ jotdown::LinkType::Span(_)),
+                    attributes,
+                ) => {
+                    if let Some(url) = links::expand_link_prefix(&text, &context.config) {
+                        return Ok(Event::Start(
+                            Container::Link(std::borrow::Cow::Owned(url), link_type),
+                            attributes,
+                        ));
+                    }
+                    match links::rewrite_document_link(&text, file_parent_dir, context) {
+                        Some(link) if link.outside_target => {
+                            context.warn(SsgError::LinkOutsideTarget(text.to_string()));
+                            Ok(Event::Start(Container::Link(text, link_type), attributes))
+                        }
+                        Some(link) if context.fs.exists(&link.referenced_path) => Ok(Event::Start(
+                            Container::Link(std::borrow::Cow::Owned(link.url), link_type),
+                            attributes,
+                        )),
+                        Some(link) => {
+                            context.warn(SsgError::LinkError(link.referenced_path));
+                            Ok(Event::Start(Container::Link(text, link_type), attributes))
+                        }
+                        None => Ok(Event::Start(Container::Link(text, link_type), attributes)),
+                    }
+                }
+                Event::End(Container::Link(text, link_type @ jotdown::LinkType::Span(_))) => {
+                    if let Some(url) = links::expand_link_prefix(&text, &context.config) {
+                        return Ok(Event::End(Container::Link(
+                            std::borrow::Cow::Owned(url),
+                            link_type,
+                        )));
+                    }
+                    match links::rewrite_document_link(&text, file_parent_dir, context) {
+                        Some(link)
+                            if !link.outside_target && context.fs.exists(&link.referenced_path) =>
+                        {
+                            Ok(Event::End(Container::Link(
+                                std::borrow::Cow::Owned(link.url),
+                                link_type,
+                            )))
+                        }
+                        _ => Ok(Event::End(Container::Link(text, link_type))),
+                    }
+                }
+                _ => Ok(event),
+            }
+        })
+        .collect::<Result<Vec<Event>, _>>()?;
+    if let Some(slugs) = &context.config.slugs {
+        slug::slug_djot_headings(&mut events, slugs);
+    }
+    if context.config.highlight.is_some() {
+        highlight::highlight_djot_code(&mut events);
+    }
+    let html = jotdown::html::render_to_string(events.iter().cloned());
+    if context.config.heading_anchors {
+        return Ok(slug::add_heading_anchors(&html));
+    }
+    Ok(html)
+}
+
+fn generate_table_of_contents(
+    results: &[FirstPassResult],
+    my_depth: usize,
+    my_result: &Path,
+    web_prefix: Option<&str>,
+    config: &SiteConfig,
+    section_numbers: &HashMap<PathBuf, String>,
+) -> String {
+    let toc_config = &config.toc;
+    let mut table_of_contents_html = "<ul>".to_string();
+    log::debug!("<ul>");
+    let mut prev_depth = 0;
+    let mut prev_file_depth = 0;
+    let mut prev_folders = Vec::new();
+    for result in results {
+        match result {
+            FirstPassResult::Dir {
+                depth,
+                relative_path,
+                title,
+            } => {
+                log::trace!("Dir: {}", &relative_path.to_string_lossy());
+                let mut depth_diff = *depth as i32 - prev_depth as i32;
+                while depth_diff < 0 {
+                    if prev_folders.pop().is_none() {
+                        let format_string = format!("</ul>");
+                        log::debug!("{} (Dir, depth_diff={})", &format_string, depth_diff);
+                        table_of_contents_html.push_str(&format_string);
+                    }
+                    depth_diff += 1;
+                }
+                prev_depth = *depth;
+                if *depth > 0 {
+                    log::trace!(
+                        "Adding {} to the folders stack (at depth {})",
+                        &relative_path.to_string_lossy(),
+                        *depth
+                    );
+                    let folder_name = match title {
+                        Some(title) => utils::escape_html(&utils::shorten_title(
+                            title,
+                            config.max_title_length,
+                        )),
+                        None => utils::escape_html(&format!(
+                            "{}:",
+                            relative_path.file_name().unwrap().to_string_lossy()
+                        )),
+                    };
+                    let folder_name =
+                        numbered_name(toc_config, section_numbers, relative_path, folder_name);
+                    // Folders containing the current page are marked as open
+                    prev_folders.push((folder_name, my_result.starts_with(relative_path)));
+                    // Entries that follow at this folder's depth are its siblings, so a folder
+                    // with no listed pages (yet) is dropped from the stack instead of wrapping them
+                    prev_depth = *depth + 1;
+                }
+            }
+            FirstPassResult::HtmlOutput { unlisted: true, .. }
+            | FirstPassResult::HtmlOutput { in_toc: false, .. } => continue,
+            FirstPassResult::HtmlOutput {
+                relative_path,
+                depth,
+                title,
+                ..
+            }
+            | FirstPassResult::Link {
+                relative_path,
+                depth,
+                title,
+            } => {
+                log::trace!("File: {}", &relative_path.to_string_lossy());
+                let mut depth_diff = *depth as i32 - prev_depth as i32;
+                while depth_diff < 0 {
+                    if prev_folders.pop().is_none() {
+                        let format_string = format!("</ul>");
+                        log::debug!("{}  (File, depth_diff={})", &format_string, depth_diff);
+                        table_of_contents_html.push_str(&format_string);
+                    }
+                    depth_diff += 1;
+                }
+                let mut pos_depth_diff = prev_folders.len();
+                while pos_depth_diff > 0 {
+                    let (folder_name, is_open) = prev_folders.remove(0);
+                    let format_string = if is_open {
+                        toc_config
+                            .open_folder_template
+                            .replace("{CLASS}", &toc_config.open_class)
+                    } else {
+                        toc_config.folder_template.clone()
+                    }
+                    .replace("{NAME}", &folder_name);
+                    log::debug!(
+                        "{} (folder, depth={})",
+                        &format_string,
+                        (*depth - pos_depth_diff)
+                    );
+                    table_of_contents_html.push_str(&format_string);
+                    let format_string = format!("<ul>");
+                    log::debug!("{}, prev_folders-={}", &format_string, &folder_name);
+                    table_of_contents_html.push_str(&format_string);
+                    pos_depth_diff -= 1;
+                }
+                prev_depth = *depth;
+                prev_file_depth = *depth;
+                let name = numbered_name(
+                    toc_config,
+                    section_numbers,
+                    relative_path,
+                    utils::escape_html(&utils::shorten_title(
+                        &title
+                            .clone()
+                            .unwrap_or_else(|| utils::page_name(relative_path, config.pretty_urls)),
+                        config.max_title_length,
+                    )),
+                );
+                let url = utils::page_link(my_depth, web_prefix, relative_path, config.pretty_urls);
+                let format_string = if relative_path == my_result {
+                    toc_config
+                        .active_item_template
+                        .replace("{CLASS}", &toc_config.active_class)
+                } else {
+                    toc_config.item_template.clone()
+                }
+                .replace("{URL}", &url)
+                .replace("{NAME}", &name);
+                log::debug!("{} (file, depth={})", &format_string, *depth);
+                table_of_contents_html.push_str(&format_string);
+            }
+        }
+    }
+    prev_depth -= prev_folders.len();
+    log::trace!("prev_depth - {} = {}", prev_folders.len(), prev_depth);
+    log::trace!("prev_file_depth = {}", prev_file_depth);
+    let mut depth_diff = 0 - prev_file_depth as i32;
+    while depth_diff < 0 {
+        let format_string = format!("</ul>");
+        log::debug!("{} (end, depth_diff={})", &format_string, depth_diff);
+        table_of_contents_html.push_str(&format_string);
+        depth_diff += 1;
+    }
+    // log::debug!("Table of contents: {}", &table_of_contents_html);
+    table_of_contents_html
+}
+
+/// Prefixes `name` with the section number of `relative_path`, if the table of contents is
+/// numbered.
+fn numbered_name(
+    toc_config: &TocConfig,
+    section_numbers: &HashMap<PathBuf, String>,
+    relative_path: &Path,
+    name: String,
+) -> String {
+    match section_numbers.get(relative_path) {
+        Some(number) if toc_config.numbered && !number.is_empty() => {
+            numbering::number_html(number) + &name
+        }
+        _ => name,
+    }
+}
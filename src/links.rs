@@ -0,0 +1,408 @@
+use std::{
+    collections::HashMap,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{LinksConfig, SiteConfig},
+    utils, FirstPassResult, SiteContext,
+};
+
+/// A link to a djot or markdown document, rewritten to point at the generated html.
+pub struct DocumentLink {
+    /// The rewritten link, including any `#fragment` or `?query` from the original
+    pub url: String,
+    /// The source document the link refers to
+    pub referenced_path: PathBuf,
+    /// Set when the link resolves outside the target directory (e.g. `../../../etc/notes.md`)
+    pub outside_target: bool,
+}
+
+/// Rewrites `link` if it refers to a djot or markdown document, returning `None` otherwise.
+///
+/// Links with a scheme (`https:`, `mailto:`, `tel:`, ...) or protocol-relative links (`//host`)
+/// are never treated as documents. Root-relative links (`/path`) resolve against the target
+/// directory, and are linked under the site's `web_prefix`. Links that resolve outside of it
+/// are marked, and should be left as they are.
+pub fn rewrite_document_link(
+    link: &str,
+    file_parent_dir: &Path,
+    context: &SiteContext,
+) -> Option<DocumentLink> {
+    if is_external(link) {
+        return None;
+    }
+    let (path, suffix) = match link.find(['#', '?']) {
+        Some(index) => link.split_at(index),
+        None => (link, ""),
+    };
+    let referenced_path = match path.strip_prefix('/') {
+        Some(root_relative) => context.target_path.join(root_relative),
+        None => file_parent_dir.join(path),
+    };
+    if !referenced_path
+        .extension()
+        .is_some_and(|ext| ext == "dj" || ext == "djot" || ext == "md")
+    {
+        return None;
+    }
+    let new_path = utils::html_link_path(context.fs, path, &referenced_path, &context.config);
+    let new_path = new_path.to_string_lossy();
+    let url = match (path.starts_with('/'), context.web_prefix) {
+        // Root-relative links are under the prefix the site is deployed to, like prefixed links
+        (true, Some(web_prefix)) => format!(
+            "{}/{}",
+            web_prefix.trim_end_matches('/'),
+            new_path.trim_start_matches('/')
+        ),
+        (true, None) => new_path.to_string(),
+        (false, web_prefix) => format!("{}{}", web_prefix.unwrap_or(""), new_path),
+    };
+    let url = match context.config.pretty_urls {
+        true => utils::directory_url(&url),
+        false => url,
+    };
+    Some(DocumentLink {
+        url: url + suffix,
+        outside_target: !utils::is_within(context.target_path, &referenced_path),
+        referenced_path,
+    })
+}
+
+/// Checks if `link` has a URL scheme (`https:`, `mailto:`, `tel:`, `ftp:`, ...) or is
+/// protocol-relative (`//host/path`), meaning it never refers to a file in the site.
+pub fn is_external(link: &str) -> bool {
+    if link.starts_with("//") {
+        return true;
+    }
+    match link.split_once(':') {
+        // Single letter schemes are treated as Windows drive letters
+        Some((scheme, _)) => {
+            scheme.len() > 1
+                && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        None => false,
+    }
+}
+
+/// Expands a link starting with one of the configured `link_prefixes` (e.g. `@api/guide.md`)
+/// into a link on the mapped site, converting document extensions to `.html`.
+pub fn expand_link_prefix(link: &str, config: &SiteConfig) -> Option<String> {
+    let (prefix, base_url) = config
+        .link_prefixes
+        .iter()
+        .filter(|(prefix, _)| {
+            link.strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+        .max_by_key(|(prefix, _)| prefix.len())?;
+    let rest = link[prefix.len()..].trim_start_matches('/');
+    let (path, suffix) = match rest.find(['#', '?']) {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+    let path = if Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext == "dj" || ext == "djot" || ext == "md")
+    {
+        Path::new(path)
+            .with_extension("html")
+            .to_string_lossy()
+            .to_string()
+    } else {
+        path.to_string()
+    };
+    Some(format!(
+        "{}/{}{}",
+        base_url.trim_end_matches('/'),
+        path,
+        suffix
+    ))
+}
+
+/// Maps each page id declared in front matter to the page's html path.
+pub fn collect_page_ids(
+    results: &[FirstPassResult],
+    context: &SiteContext,
+) -> HashMap<String, PathBuf> {
+    let mut page_ids = HashMap::new();
+    for result in results {
+        if let FirstPassResult::HtmlOutput {
+            id: Some(id),
+            relative_path,
+            ..
+        } = result
+        {
+            if let Some(existing) = page_ids.insert(id.clone(), relative_path.clone()) {
+                context.warn(format!(
+                    "Page id {:?} is used by both {:?} and {:?}",
+                    id, existing, relative_path
+                ));
+            }
+        }
+    }
+    page_ids
+}
+
+/// Replaces each `ref:<id>` link in `html` with the url of the page with that id.
+pub fn resolve_page_references(
+    html: &str,
+    page_ids: &HashMap<String, PathBuf>,
+    depth: usize,
+    relative_path: &Path,
+    context: &SiteContext,
+) -> String {
+    const REF_ATTRIBUTE: &str = "href=\"ref:";
+    if !html.contains(REF_ATTRIBUTE) {
+        return html.to_string();
+    }
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(REF_ATTRIBUTE) {
+        let value_start = start + REF_ATTRIBUTE.len();
+        let Some(value_len) = rest[value_start..].find('"') else {
+            break;
+        };
+        let reference = &rest[value_start..value_start + value_len];
+        let (id, suffix) = match reference.find(['#', '?']) {
+            Some(index) => reference.split_at(index),
+            None => (reference, ""),
+        };
+        output.push_str(&rest[..start]);
+        match page_ids.get(id) {
+            Some(target) => {
+                let url = utils::page_link(
+                    depth,
+                    context.web_prefix,
+                    target,
+                    context.config.pretty_urls,
+                );
+                output.push_str(&format!("href=\"{}{}\"", url, suffix));
+            }
+            None => {
+                context.warn(format!(
+                    "{:?} links to unknown page id {:?}",
+                    relative_path, id
+                ));
+                output.push_str(&rest[start..value_start + value_len + 1]);
+            }
+        }
+        rest = &rest[value_start + value_len + 1..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Prefixes each relative url in `html` (in `href`, `src`, `srcset`, `poster`, and `action`
+/// attributes) with `prefix`, for content moved from where its links were written. Urls with a
+/// scheme, root-relative urls, and fragments are left as they are.
+pub fn rebase_relative_urls(html: &str, prefix: &str) -> String {
+    let is_relative =
+        |url: &str| !url.is_empty() && !url.starts_with(['/', '#', '?']) && !is_external(url);
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        let Some(end) = tag_end(tag) else {
+            output.push('<');
+            rest = &tag[1..];
+            continue;
+        };
+        let mut tag = tag[..end].to_string();
+        for name in ["href", "src", "poster", "action"] {
+            if let Some(range) = attribute_range(&tag, name) {
+                if tag[range.clone()].starts_with("./") {
+                    tag.replace_range(range.start..range.start + 2, prefix);
+                } else if is_relative(tag[range.clone()].trim()) {
+                    tag.insert_str(range.start, prefix);
+                }
+            }
+        }
+        if let Some(range) = attribute_range(&tag, "srcset") {
+            let srcset = tag[range.clone()]
+                .split(',')
+                .map(|candidate| {
+                    let trimmed = candidate.trim_start();
+                    match is_relative(trimmed) {
+                        true => format!(
+                            "{}{}{}",
+                            &candidate[..candidate.len() - trimmed.len()],
+                            prefix,
+                            trimmed
+                        ),
+                        false => candidate.to_string(),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            tag.replace_range(range, &srcset);
+        }
+        output.push_str(&tag);
+        rest = &rest[start + end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Adds the classes (and icons) of `config` to each link in `html`, by whether it's internal,
+/// external, or a download. Links to the site's own `web_prefix` and `ref:` links are internal.
+pub fn classify_links(html: &str, config: &LinksConfig, web_prefix: Option<&str>) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("<a") {
+        let tag = &rest[start..];
+        let Some(end) = tag[2..]
+            .starts_with(char::is_whitespace)
+            .then(|| tag_end(tag))
+            .flatten()
+        else {
+            output.push_str(&rest[..start + 2]);
+            rest = &tag[2..];
+            continue;
+        };
+        output.push_str(&rest[..start]);
+        rest = &tag[end..];
+        let tag = &tag[..end];
+        let Some(href) = attribute(tag, "href") else {
+            output.push_str(tag);
+            continue;
+        };
+        let path = href.split(['#', '?']).next().unwrap_or_default();
+        let download = Path::new(path).extension().is_some_and(|ext| {
+            config
+                .download_extensions
+                .iter()
+                .any(|download| ext.eq_ignore_ascii_case(download.as_str()))
+        });
+        let external = is_external(href)
+            && !href.starts_with("ref:")
+            && !web_prefix.is_some_and(|prefix| is_external(prefix) && href.starts_with(prefix));
+        let classes = [
+            if external {
+                config.external_class.as_str()
+            } else {
+                config.internal_class.as_str()
+            },
+            if download {
+                config.download_class.as_str()
+            } else {
+                ""
+            },
+        ]
+        .into_iter()
+        .filter(|class| !class.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+        output.push_str(&add_class(tag, &classes));
+        let icon = if download {
+            config.download_icon.as_str()
+        } else if external {
+            config.external_icon.as_str()
+        } else {
+            ""
+        };
+        if let Some(close) = rest.find("</a>").filter(|_| !icon.is_empty()) {
+            output.push_str(&rest[..close]);
+            output.push_str(icon);
+            rest = &rest[close..];
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// The length of the start tag at the beginning of `tag`, up to its `>` outside of quotes.
+pub fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The start tags of the elements with one of `names` in `html`, in order.
+pub fn start_tags<'a>(html: &'a str, names: &'a [&str]) -> impl Iterator<Item = &'a str> {
+    let mut rest = html;
+    std::iter::from_fn(move || loop {
+        let start = rest.find('<')?;
+        let tag = &rest[start..];
+        let Some(end) = tag_end(tag) else {
+            rest = &tag[1..];
+            continue;
+        };
+        rest = &tag[end..];
+        let tag_name = tag[1..]
+            .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .next()
+            .unwrap_or_default();
+        if names.iter().any(|name| tag_name.eq_ignore_ascii_case(name)) {
+            return Some(&tag[..end]);
+        }
+    })
+}
+
+/// The range of the value of the quoted attribute `name` in the start tag `tag`.
+pub fn attribute_range(tag: &str, name: &str) -> Option<Range<usize>> {
+    let mut searched = 0;
+    while let Some(found) = tag[searched..].find(name) {
+        let start = searched + found;
+        searched = start + name.len();
+        if !tag[..start].ends_with(char::is_whitespace) {
+            continue;
+        }
+        let after = tag[searched..].trim_start();
+        let Some(value) = after.strip_prefix('=').map(str::trim_start) else {
+            continue;
+        };
+        let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            continue;
+        };
+        let value_start = tag.len() - value.len() + 1;
+        let value_len = tag[value_start..].find(quote)?;
+        return Some(value_start..value_start + value_len);
+    }
+    None
+}
+
+/// The value of the quoted attribute `name` in the start tag `tag`.
+pub fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    attribute_range(tag, name).map(|range| &tag[range])
+}
+
+/// Adds `classes` to the start tag `tag`, after any classes it already has.
+pub fn add_class(tag: &str, classes: &str) -> String {
+    if classes.is_empty() {
+        return tag.to_string();
+    }
+    match attribute_range(tag, "class") {
+        Some(range) => {
+            let separator = if range.is_empty() { "" } else { " " };
+            format!(
+                "{}{}{}{}",
+                &tag[..range.end],
+                separator,
+                classes,
+                &tag[range.end..]
+            )
+        }
+        None => {
+            let tag_end = tag.trim_end_matches('>').trim_end_matches('/').len();
+            format!(
+                "{} class=\"{}\"{}",
+                tag[..tag_end].trim_end(),
+                classes,
+                &tag[tag_end..]
+            )
+        }
+    }
+}
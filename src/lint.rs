@@ -0,0 +1,145 @@
+use std::{collections::BTreeMap, path::Path};
+
+use crate::{config::LintConfig, outputs::OutputWriter};
+
+/// Link text that says nothing about where the link goes.
+const VAGUE_LINK_TEXT: &[&str] = &["here", "click here", "this", "link", "more", "read more"];
+
+/// Collects the headings and links of a document.
+#[derive(Default)]
+struct HeadingCollector {
+    headings: Vec<(u16, String)>,
+    current: Option<(u16, String)>,
+    /// The url and text of each link
+    links: Vec<(String, String)>,
+    current_link: Option<String>,
+}
+
+impl OutputWriter for HeadingCollector {
+    fn start_heading(&mut self, level: u16) {
+        self.current = Some((level, String::new()));
+    }
+
+    fn end_heading(&mut self, _level: u16) {
+        if let Some((level, text)) = self.current.take() {
+            self.headings.push((level, text.trim().to_string()));
+        }
+    }
+
+    fn start_link(&mut self, _url: &str) {
+        self.current_link = Some(String::new());
+    }
+
+    fn end_link(&mut self, url: &str) {
+        if let Some(text) = self.current_link.take() {
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            self.links.push((url.to_string(), text));
+        }
+    }
+
+    fn code(&mut self, text: &str) {
+        self.text(text);
+    }
+
+    fn text(&mut self, text: &str) {
+        if let Some((_, heading)) = &mut self.current {
+            heading.push_str(text);
+        }
+        if let Some(link) = &mut self.current_link {
+            link.push_str(text);
+        }
+    }
+
+    fn soft_break(&mut self) {
+        self.text(" ");
+    }
+}
+
+/// Checks a page against the lint rules, returning a message for each problem found.
+///
+/// `relative_path` is the page's path relative to the target directory.
+pub fn lint_page(
+    input: &str,
+    is_markdown: bool,
+    relative_path: &Path,
+    config: &LintConfig,
+) -> Vec<String> {
+    let mut collector = HeadingCollector::default();
+    crate::outputs::write_document(input, is_markdown, &mut collector);
+    let HeadingCollector {
+        headings, links, ..
+    } = collector;
+    let mut problems = Vec::new();
+    if config.single_h1 {
+        let h1_count = headings.iter().filter(|(level, _)| *level == 1).count();
+        if h1_count != 1 {
+            problems.push(format!(
+                "expected exactly one level 1 heading, found {}",
+                h1_count
+            ));
+        }
+    }
+    if let Some(max_depth) = config.max_heading_depth {
+        for (level, text) in &headings {
+            if *level > max_depth {
+                problems.push(format!(
+                    "heading {:?} is level {}, deeper than the maximum of {}",
+                    text, level, max_depth
+                ));
+            }
+        }
+    }
+    for required in &config.required_sections {
+        if !relative_path.starts_with(&required.path) {
+            continue;
+        }
+        for heading in &required.headings {
+            if !headings.iter().any(|(_, text)| text == heading) {
+                problems.push(format!("missing required section {:?}", heading));
+            }
+        }
+    }
+    if config.link_text {
+        problems.extend(link_text_problems(&links));
+    }
+    problems
+}
+
+/// The links of a page whose text is vague (like "click here"), a bare url, or the same as
+/// the text of a link to somewhere else.
+fn link_text_problems(links: &[(String, String)]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut targets: BTreeMap<String, &str> = BTreeMap::new();
+    for (url, text) in links {
+        let words = text
+            .trim_matches(|c: char| c.is_ascii_punctuation())
+            .to_lowercase();
+        if VAGUE_LINK_TEXT.contains(&words.as_str()) {
+            problems.push(format!(
+                "the link to {:?} has the vague text {:?}, describe where it goes instead",
+                url, text
+            ));
+        } else if text == url
+            || text.starts_with("http://")
+            || text.starts_with("https://")
+            || text.starts_with("www.")
+        {
+            problems.push(format!(
+                "the link to {:?} has a bare url as its text, describe where it goes instead",
+                url
+            ));
+        } else if !words.is_empty() {
+            match targets.get(&words) {
+                Some(first_url) if first_url != url => problems.push(format!(
+                    "links to {:?} and {:?} have the same text {:?}, so they can't be told apart",
+                    first_url, url, text
+                )),
+                Some(_) => {}
+                None => {
+                    targets.insert(words, url);
+                }
+            }
+        }
+    }
+    problems
+}
@@ -0,0 +1,50 @@
+/// Elements removed entirely from lite pages, along with their contents.
+const REMOVED_ELEMENTS: [&str; 6] = ["script", "style", "noscript", "iframe", "video", "audio"];
+
+/// Strips `content` down for the lite mirror: scripts, styles, and embedded media are removed,
+/// and images are replaced with their alt text.
+pub fn strip_content(content: &str) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[..=tag_end];
+        let name = tag[1..]
+            .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        if REMOVED_ELEMENTS.contains(&name.as_str()) {
+            let closing_tag = format!("</{}>", name);
+            rest = match rest.to_ascii_lowercase().find(&closing_tag) {
+                Some(end) => &rest[end + closing_tag.len()..],
+                None => &rest[tag_end + 1..],
+            };
+            continue;
+        }
+        if name == "img" {
+            if let Some(alt) = attribute(tag, "alt").filter(|alt| !alt.is_empty()) {
+                output.push('[');
+                output.push_str(alt);
+                output.push(']');
+            }
+        } else {
+            output.push_str(tag);
+        }
+        rest = &rest[tag_end + 1..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Gets the (still escaped) value of a double quoted attribute in `tag`.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!(" {}=\"", name);
+    let start = tag.find(&pattern)? + pattern.len();
+    let end = start + tag[start..].find('"')?;
+    Some(&tag[start..end])
+}
@@ -0,0 +1,185 @@
+use crate::outputs::OutputWriter;
+
+/// Renders a document to a roff man page.
+pub struct ManWriter {
+    roff: String,
+    in_code_block: bool,
+    /// Item counters for the open lists (`None` for unordered lists)
+    lists: Vec<Option<usize>>,
+}
+
+impl ManWriter {
+    pub fn new(title: &str, section: u8) -> Self {
+        let mut writer = Self {
+            roff: String::new(),
+            in_code_block: false,
+            lists: Vec::new(),
+        };
+        writer.request(&format!(
+            ".TH \"{}\" \"{}\"",
+            title.to_uppercase().replace('"', ""),
+            section
+        ));
+        writer
+    }
+
+    pub fn finish(self) -> String {
+        self.roff
+    }
+
+    /// Writes a roff request on its own line.
+    fn request(&mut self, request: &str) {
+        if !self.roff.is_empty() && !self.roff.ends_with('\n') {
+            self.roff.push('\n');
+        }
+        self.roff.push_str(request);
+        self.roff.push('\n');
+    }
+
+    /// Ends the current line of text, if there is one.
+    fn end_line(&mut self) {
+        if !self.roff.ends_with('\n') {
+            self.roff.push('\n');
+        }
+    }
+
+    fn push_escaped(&mut self, text: &str) {
+        for c in text.chars() {
+            let at_line_start = self.roff.is_empty() || self.roff.ends_with('\n');
+            match c {
+                '\\' => self.roff.push_str("\\e"),
+                '-' => self.roff.push_str("\\-"),
+                '.' | '\'' if at_line_start => {
+                    self.roff.push_str("\\&");
+                    self.roff.push(c);
+                }
+                _ => self.roff.push(c),
+            }
+        }
+    }
+}
+
+impl OutputWriter for ManWriter {
+    fn start_heading(&mut self, level: u16) {
+        self.request(if level <= 1 { ".SH" } else { ".SS" });
+        // The heading text goes on the same line as the request
+        self.roff.pop();
+        self.roff.push(' ');
+    }
+
+    fn end_heading(&mut self, _level: u16) {
+        self.end_line();
+    }
+
+    fn start_paragraph(&mut self) {
+        if self.lists.is_empty() {
+            self.request(".PP");
+        }
+    }
+
+    fn end_paragraph(&mut self) {
+        self.end_line();
+    }
+
+    fn start_code_block(&mut self) {
+        self.in_code_block = true;
+        self.request(".PP");
+        self.request(".nf");
+        self.request(".RS 4");
+    }
+
+    fn end_code_block(&mut self) {
+        self.in_code_block = false;
+        self.request(".RE");
+        self.request(".fi");
+    }
+
+    fn start_block_quote(&mut self) {
+        self.request(".RS 4");
+    }
+
+    fn end_block_quote(&mut self) {
+        self.request(".RE");
+    }
+
+    fn start_list(&mut self, ordered: bool) {
+        if !self.lists.is_empty() {
+            self.request(".RS 4");
+        }
+        self.lists.push(ordered.then_some(0));
+    }
+
+    fn end_list(&mut self, _ordered: bool) {
+        self.lists.pop();
+        if !self.lists.is_empty() {
+            self.request(".RE");
+        }
+    }
+
+    fn start_item(&mut self) {
+        match self.lists.last_mut() {
+            Some(Some(count)) => {
+                *count += 1;
+                let request = format!(".IP {}. 4", count);
+                self.request(&request);
+            }
+            _ => self.request(".IP \\(bu 2"),
+        }
+    }
+
+    fn end_item(&mut self) {
+        self.end_line();
+    }
+
+    fn start_emphasis(&mut self) {
+        self.roff.push_str("\\fI");
+    }
+
+    fn end_emphasis(&mut self) {
+        self.roff.push_str("\\fR");
+    }
+
+    fn start_strong(&mut self) {
+        self.roff.push_str("\\fB");
+    }
+
+    fn end_strong(&mut self) {
+        self.roff.push_str("\\fR");
+    }
+
+    fn start_link(&mut self, _url: &str) {}
+
+    fn end_link(&mut self, url: &str) {
+        if !url.is_empty() && !url.starts_with('#') {
+            self.roff.push_str(" <");
+            self.push_escaped(url);
+            self.roff.push('>');
+        }
+    }
+
+    fn code(&mut self, text: &str) {
+        self.roff.push_str("\\fB");
+        self.push_escaped(text);
+        self.roff.push_str("\\fR");
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.in_code_block {
+            self.push_escaped(text);
+        } else {
+            self.push_escaped(&text.replace('\n', " "));
+        }
+    }
+
+    fn soft_break(&mut self) {
+        self.end_line();
+    }
+
+    fn hard_break(&mut self) {
+        self.request(".br");
+    }
+
+    fn rule(&mut self) {
+        self.request(".sp");
+    }
+}
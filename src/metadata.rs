@@ -0,0 +1,300 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::TextDirection, errors::SsgError, templates::BuiltInTemplate, utils, vfs::FileSystem,
+    SiteContext,
+};
+
+/// Metadata for a directory, read from an optional `_dir.toml` file inside it.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct DirMetadata {
+    /// Label to show for this directory in the table of contents
+    #[serde(rename = "_title")]
+    pub title: Option<String>,
+    /// Generate an index page listing the directory's files, if it has no index page
+    #[serde(rename = "_asset_index", default)]
+    pub asset_index: bool,
+}
+
+pub fn get_dir_metadata(fs: &dyn FileSystem, dir: &Path) -> anyhow::Result<DirMetadata> {
+    let metadata_file = dir.join("_dir.toml");
+    if !fs.is_file(&metadata_file) {
+        return Ok(DirMetadata::default());
+    }
+    log::trace!("Reading directory metadata from {:?}", &metadata_file);
+    let contents = fs.read_to_string(&metadata_file)?;
+    toml::from_str(&contents).map_err(|e| SsgError::MetadataError(metadata_file, e).into())
+}
+
+/// Additional formats a page can be rendered to, alongside its html.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// A roff man page, written next to the html as `<name>.<man_section>`
+    Man,
+    /// A standalone LaTeX document, written next to the html as `<name>.tex`
+    Latex,
+}
+
+/// A date (`2019-03-01`), year (`2019`), or any other text (`"Spring 2019"`).
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Date {
+    Datetime(toml::value::Datetime),
+    Year(i64),
+    Text(String),
+}
+
+impl Date {
+    /// The calendar date, if this is a date (or text that is one, as in YAML).
+    pub fn calendar_date(&self) -> Option<toml::value::Date> {
+        match self {
+            Self::Datetime(datetime) => datetime.date,
+            Self::Text(text) => text.parse::<toml::value::Datetime>().ok()?.date,
+            Self::Year(_) => None,
+        }
+    }
+
+    /// The calendar date as a `time` date, if it's a valid one.
+    pub fn to_date(&self) -> Option<time::Date> {
+        let date = self.calendar_date()?;
+        let month = time::Month::try_from(date.month).ok()?;
+        time::Date::from_calendar_date(date.year.into(), month, date.day).ok()
+    }
+
+    /// Formats the date with `format` (see `utils::format_date`), or as it's written if it isn't
+    /// a calendar date.
+    pub fn format(&self, format: Option<&str>) -> anyhow::Result<String> {
+        match self.to_date() {
+            Some(date) => utils::format_date(date.midnight().assume_utc(), format),
+            None => Ok(self.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Datetime(datetime) => datetime.fmt(f),
+            Self::Year(year) => year.fmt(f),
+            Self::Text(text) => text.fmt(f),
+        }
+    }
+}
+
+/// The template of a page: a built in template by name, or an html file relative to the page.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum PageTemplate {
+    BuiltIn(BuiltInTemplate),
+    File(PathBuf),
+}
+
+/// Per-page metadata, read from a front matter block at the start of a document: TOML
+/// delimited by `+++` lines, or YAML delimited by `---` lines.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct FrontMatter {
+    /// The page's title, instead of its first level 1 heading, also shown for it in the table of
+    /// contents instead of its file name
+    pub title: Option<String>,
+    /// When the page was written, used as its `lastmod` in the sitemap
+    pub date: Option<Date>,
+    /// When the event the page describes happens (a date, or a date and time), which lists it
+    /// in the site's calendar
+    pub event_date: Option<Date>,
+    /// When the event ends, for its entry in the site's events feed
+    pub event_end: Option<Date>,
+    /// Where the event happens, for its entry in the site's events feed
+    pub event_location: Option<String>,
+    /// The page's template, overriding `template.html` and `--template`
+    pub template: Option<PageTemplate>,
+    /// Leaves the page out of the site, unless it's generated with `--drafts`
+    pub draft: bool,
+    /// Keywords for the page, listed in its `<meta name="keywords">`
+    pub tags: Vec<String>,
+    pub output_formats: Vec<OutputFormat>,
+    /// The manual section for the `man` output format (defaults to 1)
+    pub man_section: Option<u8>,
+    /// Appends the releases from a changelog (a path relative to the page, or `git` for the
+    /// repository's tags) to the page
+    pub changelog: Option<String>,
+    /// Renders the page, but leaves it out of the table of contents, so it is only reachable by
+    /// direct link
+    pub unlisted: bool,
+    /// Encrypts the page's content with this password, to be decrypted in the browser
+    pub password: Option<String>,
+    /// Id that other pages can link to with `ref:<id>`, so links survive the page moving
+    pub id: Option<String>,
+    /// The language of the page, overriding the site's `lang`
+    pub lang: Option<String>,
+    /// The text direction of the page, overriding the site's `dir` (defaults to the direction
+    /// of `lang`)
+    pub dir: Option<TextDirection>,
+}
+
+/// Splits the front matter from the start of `input`, returning the parsed front matter and
+/// the remaining document.
+pub fn parse_front_matter<'a>(
+    input: &'a str,
+    path: &Path,
+    context: &SiteContext,
+) -> anyhow::Result<(FrontMatter, &'a str)> {
+    let (delimiter, front_matter, rest) = match split_front_matter(input) {
+        Some((delimiter, Some((front_matter, rest)))) => (delimiter, front_matter, rest),
+        Some((_, None)) => {
+            context.warn(format!("{:?} has an unterminated front matter block", path));
+            return Ok((FrontMatter::default(), input));
+        }
+        None => return Ok((FrontMatter::default(), input)),
+    };
+    let front_matter = if delimiter == "+++" {
+        toml::from_str(front_matter).map_err(|e| SsgError::MetadataError(path.to_path_buf(), e))?
+    } else if front_matter.trim().is_empty() {
+        FrontMatter::default()
+    } else {
+        serde_yaml::from_str(front_matter)
+            .map_err(|e| SsgError::FrontMatterError(path.to_path_buf(), e))?
+    };
+    Ok((front_matter, rest))
+}
+
+/// A page's front matter as it's written, in its own format, for rewriting it.
+pub enum RawFrontMatter {
+    Toml(toml::Table),
+    Yaml(serde_yaml::Mapping),
+}
+
+impl RawFrontMatter {
+    /// Parses `front_matter`, the contents of a block delimited by `delimiter` in the page at
+    /// `path`.
+    pub fn parse(front_matter: &str, delimiter: &str, path: &Path) -> anyhow::Result<Self> {
+        Ok(if delimiter == "+++" {
+            Self::Toml(
+                toml::from_str(front_matter)
+                    .map_err(|e| SsgError::MetadataError(path.to_path_buf(), e))?,
+            )
+        } else if front_matter.trim().is_empty() {
+            Self::Yaml(serde_yaml::Mapping::new())
+        } else {
+            Self::Yaml(
+                serde_yaml::from_str(front_matter)
+                    .map_err(|e| SsgError::FrontMatterError(path.to_path_buf(), e))?,
+            )
+        })
+    }
+
+    /// Writes the front matter as a block, with its delimiters.
+    pub fn to_block(&self) -> anyhow::Result<String> {
+        Ok(match self {
+            Self::Toml(table) => format!("+++\n{}+++\n", toml::to_string(table)?),
+            Self::Yaml(mapping) => format!("---\n{}---\n", serde_yaml::to_string(mapping)?),
+        })
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        match self {
+            Self::Toml(table) => table.contains_key(key),
+            Self::Yaml(mapping) => mapping.contains_key(key),
+        }
+    }
+
+    /// The value of `key` as text, if it's a string, number, or date.
+    pub fn text(&self, key: &str) -> Option<String> {
+        match self {
+            Self::Toml(table) => match table.get(key)? {
+                toml::Value::String(text) => Some(text.clone()),
+                toml::Value::Datetime(datetime) => Some(datetime.to_string()),
+                toml::Value::Integer(number) => Some(number.to_string()),
+                _ => None,
+            },
+            Self::Yaml(mapping) => match mapping.get(key)? {
+                serde_yaml::Value::String(text) => Some(text.clone()),
+                serde_yaml::Value::Number(number) => Some(number.to_string()),
+                _ => None,
+            },
+        }
+    }
+
+    /// The value of `key` as a list of strings (a single string being a list of one).
+    pub fn list(&self, key: &str) -> Vec<String> {
+        match self {
+            Self::Toml(table) => match table.get(key) {
+                Some(toml::Value::Array(values)) => values
+                    .iter()
+                    .filter_map(|value| value.as_str().map(str::to_string))
+                    .collect(),
+                Some(toml::Value::String(text)) => vec![text.clone()],
+                _ => Vec::new(),
+            },
+            Self::Yaml(mapping) => match mapping.get(key) {
+                Some(serde_yaml::Value::Sequence(values)) => values
+                    .iter()
+                    .filter_map(|value| value.as_str().map(str::to_string))
+                    .collect(),
+                Some(serde_yaml::Value::String(text)) => vec![text.clone()],
+                _ => Vec::new(),
+            },
+        }
+    }
+
+    /// Whether `key` is `true` or `false`, if it's either.
+    pub fn bool(&self, key: &str) -> Option<bool> {
+        match self {
+            Self::Toml(table) => table.get(key)?.as_bool(),
+            Self::Yaml(mapping) => mapping.get(key)?.as_bool(),
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        match self {
+            Self::Toml(table) => {
+                table.remove(key);
+            }
+            Self::Yaml(mapping) => {
+                mapping.shift_remove(key);
+            }
+        }
+    }
+
+    /// Sets `key` to `value`, which is any toml value but a date.
+    pub fn set(&mut self, key: &str, value: toml::Value) -> anyhow::Result<()> {
+        match self {
+            Self::Toml(table) => {
+                table.insert(key.to_string(), value);
+            }
+            Self::Yaml(mapping) => {
+                mapping.insert(key.into(), serde_yaml::to_value(value)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds the front matter block at the start of `input`, returning its delimiter (`+++` or
+/// `---`) and, unless the block is never closed, its contents and the remaining document.
+pub fn split_front_matter(input: &str) -> Option<(&'static str, Option<(&str, &str)>)> {
+    let (delimiter, rest) = ["+++", "---"].into_iter().find_map(|delimiter| {
+        let rest = input.strip_prefix(delimiter)?;
+        let rest = rest
+            .strip_prefix('\n')
+            .or_else(|| rest.strip_prefix("\r\n"))?;
+        Some((delimiter, rest))
+    })?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == delimiter {
+            return Some((
+                delimiter,
+                Some((&rest[..offset], &rest[offset + line.len()..])),
+            ));
+        }
+        offset += line.len();
+    }
+    Some((delimiter, None))
+}
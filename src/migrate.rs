@@ -0,0 +1,217 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use time::format_description::OwnedFormatItem;
+
+use crate::{
+    config,
+    errors::SsgError,
+    metadata::{self, RawFrontMatter},
+    vfs::FileSystem,
+};
+
+/// A migration script, with the steps applied (in order) to the front matter of each page:
+///
+/// ```toml
+/// [[step]]
+/// action = "rename"
+/// key = "author"
+/// to = "authors"
+///
+/// [[step]]
+/// action = "default"
+/// key = "lang"
+/// value = "en"
+///
+/// [[step]]
+/// action = "convert-date"
+/// key = "date"
+/// from = "[month]/[day]/[year]"
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct Migration {
+    #[serde(rename = "step")]
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum Step {
+    /// Renames `key` to `to`, on pages that don't have `to` already
+    Rename { key: String, to: String },
+    /// Sets `key` to `value` on pages without it
+    Default { key: String, value: toml::Value },
+    /// Rewrites the date in `key` from the `from` format (or a date such as `2024-01-31`) to the
+    /// `to` format (or a date), both `time` format descriptions
+    ConvertDate {
+        key: String,
+        from: Option<String>,
+        to: Option<String>,
+    },
+}
+
+impl RawFrontMatter {
+    /// Applies `step`, returning whether it changed anything.
+    fn apply(&mut self, step: &Step, formats: &DateFormats) -> anyhow::Result<bool> {
+        match step {
+            Step::Rename { key, to } => {
+                if !self.contains(key) || self.contains(to) {
+                    return Ok(false);
+                }
+                match self {
+                    Self::Toml(table) => {
+                        let value = table.remove(key).unwrap();
+                        table.insert(to.clone(), value);
+                    }
+                    Self::Yaml(mapping) => {
+                        let value = mapping.remove(key).unwrap();
+                        mapping.insert(to.as_str().into(), value);
+                    }
+                }
+            }
+            Step::Default { key, value } => {
+                if self.contains(key) {
+                    return Ok(false);
+                }
+                self.set(key, value.clone())?;
+            }
+            Step::ConvertDate { key, .. } => {
+                let text = match self {
+                    Self::Toml(table) => match table.get(key) {
+                        Some(toml::Value::String(text)) => Some(text.clone()),
+                        Some(toml::Value::Datetime(datetime)) => Some(datetime.to_string()),
+                        _ => None,
+                    },
+                    Self::Yaml(mapping) => mapping
+                        .get(key)
+                        .and_then(serde_yaml::Value::as_str)
+                        .map(str::to_string),
+                };
+                let Some(text) = text else {
+                    return Ok(false);
+                };
+                let text = text.as_str();
+                let date = match &formats.from {
+                    Some(from) => time::Date::parse(text, from).ok(),
+                    None => text
+                        .parse::<toml::value::Datetime>()
+                        .ok()
+                        .and_then(|datetime| datetime.date)
+                        .and_then(|date| {
+                            let month = time::Month::try_from(date.month).ok()?;
+                            time::Date::from_calendar_date(date.year.into(), month, date.day).ok()
+                        }),
+                };
+                let Some(date) = date else {
+                    return Err(anyhow::anyhow!(
+                        "{} {:?} isn't a date in the given format",
+                        key,
+                        text
+                    ));
+                };
+                let formatted = match &formats.to {
+                    Some(to) => date.format(to)?,
+                    None => date.to_string(),
+                };
+                match self {
+                    Self::Toml(table) => {
+                        let value = match &formats.to {
+                            Some(_) => toml::Value::String(formatted),
+                            None => toml::Value::Datetime(formatted.parse()?),
+                        };
+                        if table.get(key) == Some(&value) {
+                            return Ok(false);
+                        }
+                        table.insert(key.clone(), value);
+                    }
+                    Self::Yaml(mapping) => {
+                        if mapping.get(key).and_then(serde_yaml::Value::as_str) == Some(&formatted)
+                        {
+                            return Ok(false);
+                        }
+                        mapping.insert(key.as_str().into(), formatted.into());
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// The parsed formats of a `convert-date` step.
+#[derive(Default)]
+struct DateFormats {
+    from: Option<OwnedFormatItem>,
+    to: Option<OwnedFormatItem>,
+}
+
+impl DateFormats {
+    fn of(step: &Step) -> anyhow::Result<Self> {
+        let parse = |format: &Option<String>| -> anyhow::Result<Option<OwnedFormatItem>> {
+            format
+                .as_deref()
+                .map(|format| {
+                    time::format_description::parse_owned::<2>(format)
+                        .map_err(|e| SsgError::DateFormatError(format.to_string(), e).into())
+                })
+                .transpose()
+        };
+        match step {
+            Step::ConvertDate { from, to, .. } => Ok(Self {
+                from: parse(from)?,
+                to: parse(to)?,
+            }),
+            _ => Ok(Self::default()),
+        }
+    }
+}
+
+/// Applies `migration` to the front matter of each page in `target_path` (skipping the paths
+/// its `ssg.toml` ignores), returning the pages that changed, which aren't written if
+/// `dry_run`. Changed front matter is written again from its parsed values, so its comments
+/// and formatting aren't kept.
+pub fn migrate_site(
+    fs: &dyn FileSystem,
+    target_path: &Path,
+    migration: &Migration,
+    dry_run: bool,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let site_config = config::get_site_config(fs, target_path)?;
+    let formats = migration
+        .steps
+        .iter()
+        .map(DateFormats::of)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut migrated = Vec::new();
+    for entry in fs.walk(target_path) {
+        let (path, _) = entry?;
+        let is_page = path
+            .extension()
+            .is_some_and(|ext| ext == "dj" || ext == "djot" || ext == "md");
+        if !is_page || !fs.is_file(&path) || site_config.is_ignored(path.strip_prefix(target_path)?)
+        {
+            continue;
+        }
+        let input = fs.read_to_string(&path)?;
+        let Some((delimiter, Some((front_matter, rest)))) = metadata::split_front_matter(&input)
+        else {
+            continue;
+        };
+        let mut parsed = RawFrontMatter::parse(front_matter, delimiter, &path)?;
+        let mut changed = false;
+        for (step, formats) in migration.steps.iter().zip(&formats) {
+            match parsed.apply(step, formats) {
+                Ok(step_changed) => changed |= step_changed,
+                Err(e) => log::warn!("Could not migrate {:?}: {}", &path, e),
+            }
+        }
+        if !changed {
+            continue;
+        }
+        if !dry_run {
+            fs.write(&path, format!("{}{}", parsed.to_block()?, rest).as_bytes())?;
+        }
+        migrated.push(path);
+    }
+    Ok(migrated)
+}
@@ -0,0 +1,90 @@
+use std::path::{Path, PathBuf};
+
+use crate::{config::MountConfig, errors::SsgError, sandbox, utils, FirstPassResult, SiteContext};
+
+/// Copies each of the site's mounted trees into the output directory as they are, and adds a
+/// table of contents entry linking to each to `results`, after the other entries of the
+/// directory it's placed in.
+pub fn copy_mounts(
+    context: &SiteContext,
+    results: &mut Vec<FirstPassResult>,
+) -> anyhow::Result<()> {
+    let fs = context.fs;
+    for mount in &context.config.mounts {
+        let source = context.target_path.join(&mount.source);
+        if context.sandbox && !sandbox::is_inside(fs, context.target_path, &source) {
+            context.warn(SsgError::OutsideTarget(source));
+            continue;
+        }
+        if !fs.is_dir(&source) {
+            context.warn(format!("Mounted directory {:?} does not exist", source));
+            continue;
+        }
+        log::debug!("Copying {:?} to {:?}", &source, &mount.path);
+        for entry in fs.walk(&source) {
+            let path = match entry {
+                Ok((path, _)) if fs.is_file(&path) => path,
+                Ok(_) => continue,
+                Err(e) => {
+                    context.warn(SsgError::DirEntryError(e));
+                    continue;
+                }
+            };
+            let new_path = context
+                .output_path
+                .join(&mount.path)
+                .join(path.strip_prefix(&source)?);
+            fs.create_dir_all(new_path.parent().unwrap())?;
+            fs.copy(&path, &new_path)?;
+            let mut report = context.report.lock().unwrap();
+            report.assets.push(new_path.clone());
+            report.outputs.push(new_path);
+        }
+        let parent = mount.path.parent().unwrap_or(Path::new(""));
+        let position = results
+            .iter()
+            .rposition(|result| result_path(result).starts_with(parent))
+            .map_or(results.len(), |i| i + 1);
+        results.insert(
+            position,
+            FirstPassResult::Link {
+                depth: mount.path.components().count(),
+                relative_path: mount_index(mount),
+                title: Some(mount.title.clone().unwrap_or_else(|| {
+                    mount
+                        .path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string()
+                })),
+            },
+        );
+    }
+    Ok(())
+}
+
+/// The page of `mount` that the table of contents links to, relative to the output directory.
+fn mount_index(mount: &MountConfig) -> PathBuf {
+    mount
+        .path
+        .join(mount.index.as_deref().unwrap_or(Path::new("index.html")))
+}
+
+fn result_path(result: &FirstPassResult) -> &Path {
+    match result {
+        FirstPassResult::Dir { relative_path, .. }
+        | FirstPassResult::HtmlOutput { relative_path, .. }
+        | FirstPassResult::Link { relative_path, .. } => relative_path,
+    }
+}
+
+/// Checks if `path` (in the target directory) is inside the source of a mounted tree, so it's
+/// copied with the tree instead of processed.
+pub fn is_mount_source(path: &Path, context: &SiteContext) -> bool {
+    context
+        .config
+        .mounts
+        .iter()
+        .any(|mount| utils::is_within(&context.target_path.join(&mount.source), path))
+}
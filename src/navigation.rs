@@ -0,0 +1,119 @@
+use std::path::Path;
+
+use crate::{config::SiteConfig, utils, FirstPassResult};
+
+/// Generates the `<!-- {BREADCRUMBS} -->` trail for the page at `relative_path`: the site root,
+/// each folder containing the page (linked to its index page, if it has one), then the page.
+pub fn breadcrumbs_html(
+    results: &[FirstPassResult],
+    depth: usize,
+    relative_path: &Path,
+    web_prefix: Option<&str>,
+    config: &SiteConfig,
+) -> String {
+    let is_index = relative_path
+        .file_name()
+        .is_some_and(|name| name == "index.html");
+    let page_dir = relative_path.parent().unwrap_or(Path::new(""));
+    let mut crumbs = Vec::new();
+    for dir in page_dir.ancestors().collect::<Vec<_>>().into_iter().rev() {
+        let label = if dir.as_os_str().is_empty() {
+            "Home".to_string()
+        } else {
+            results
+                .iter()
+                .find_map(|result| match result {
+                    FirstPassResult::Dir {
+                        relative_path,
+                        title,
+                        ..
+                    } if relative_path == dir => title.clone(),
+                    _ => None,
+                })
+                .unwrap_or_else(|| dir.file_name().unwrap().to_string_lossy().to_string())
+        };
+        let label = utils::escape_html(&utils::shorten_title(&label, config.max_title_length));
+        let index = dir.join("index.html");
+        if is_index && dir == page_dir {
+            crumbs.push(format!("<li aria-current=\"page\">{}</li>", label));
+        } else if has_page(results, &index) {
+            crumbs.push(format!(
+                "<li><a href=\"{}\">{}</a></li>",
+                utils::page_link(depth, web_prefix, &index, config.pretty_urls),
+                label
+            ));
+        } else {
+            crumbs.push(format!("<li>{}</li>", label));
+        }
+    }
+    if !is_index {
+        crumbs.push(format!(
+            "<li aria-current=\"page\">{}</li>",
+            utils::escape_html(&utils::shorten_title(
+                &relative_path.file_stem().unwrap().to_string_lossy(),
+                config.max_title_length
+            ))
+        ));
+    }
+    format!(
+        "<nav class=\"breadcrumbs\" aria-label=\"Breadcrumbs\"><ol>{}</ol></nav>",
+        crumbs.concat()
+    )
+}
+
+/// Generates the `<!-- {PREV_NEXT} -->` links to the listed pages before and after the page at
+/// `relative_path`, in table of contents order. Unlisted pages get no links.
+pub fn prev_next_html(
+    results: &[FirstPassResult],
+    depth: usize,
+    relative_path: &Path,
+    web_prefix: Option<&str>,
+    config: &SiteConfig,
+) -> String {
+    let pages = results
+        .iter()
+        .filter_map(|result| match result {
+            FirstPassResult::HtmlOutput {
+                relative_path,
+                unlisted: false,
+                title,
+                ..
+            } => Some((relative_path.as_path(), title.as_deref())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let Some(position) = pages.iter().position(|(page, _)| *page == relative_path) else {
+        return String::new();
+    };
+    let link = |page: &Path, rel: &str, label: String| {
+        format!(
+            "<a class=\"{}\" rel=\"{}\" href=\"{}\">{}</a>",
+            rel,
+            rel,
+            utils::page_link(depth, web_prefix, page, config.pretty_urls),
+            label
+        )
+    };
+    let name = |(page, title): (&Path, Option<&str>)| {
+        let title = match title {
+            Some(title) => title.to_string(),
+            None => utils::page_name(page, config.pretty_urls),
+        };
+        utils::escape_html(&utils::shorten_title(&title, config.max_title_length))
+    };
+    let mut html = "<nav class=\"prev-next\">".to_string();
+    if let Some(prev) = position.checked_sub(1).map(|i| pages[i]) {
+        html.push_str(&link(prev.0, "prev", format!("&larr; {}", name(prev))));
+    }
+    if let Some(&next) = pages.get(position + 1) {
+        html.push_str(&link(next.0, "next", format!("{} &rarr;", name(next))));
+    }
+    html.push_str("</nav>");
+    html
+}
+
+fn has_page(results: &[FirstPassResult], page: &Path) -> bool {
+    results.iter().any(|result| {
+        matches!(result, FirstPassResult::HtmlOutput { relative_path, .. } if relative_path == page)
+    })
+}
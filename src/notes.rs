@@ -0,0 +1,365 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use time::OffsetDateTime;
+
+use crate::{
+    config::SiteConfig,
+    glossary,
+    metadata::{self, Date, RawFrontMatter},
+    slug, utils,
+    vfs::FileSystem,
+    SiteContext,
+};
+
+/// The generated index of every note, at the root of the output directory.
+pub const NOTES_INDEX: &str = "all-notes.html";
+
+/// Marks where a note's backlinks go, filled in once every note has been rendered.
+pub const BACKLINKS_PLACEHOLDER: &str = "<!-- {NOTE_BACKLINKS} -->";
+
+/// Elements whose text isn't searched for mentions of other notes.
+const SKIPPED_ELEMENTS: [&str; 11] = [
+    "a", "code", "pre", "script", "style", "h1", "h2", "h3", "h4", "h5", "h6",
+];
+
+/// A page of a notes site.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Note {
+    /// The note's source, relative to the target directory
+    pub source: PathBuf,
+    /// The note's generated page, relative to the output directory
+    pub html_path: PathBuf,
+    /// The note's front matter title, or else its file name
+    pub title: String,
+}
+
+/// The notes of a site generated with `notes`, and the links and mentions between them.
+#[derive(Debug, Default)]
+pub struct Notes {
+    pub notes: Vec<Note>,
+    /// The notes each note links to with wikilinks, by their indexes
+    links: Mutex<BTreeMap<usize, BTreeSet<usize>>>,
+    /// The notes each note mentions by name without linking to them, by their indexes
+    mentions: Mutex<BTreeMap<usize, BTreeSet<usize>>>,
+}
+
+impl Notes {
+    /// Finds every note in `target_path`, skipping the paths `config` ignores.
+    pub fn read(
+        fs: &dyn FileSystem,
+        target_path: &Path,
+        config: &SiteConfig,
+    ) -> anyhow::Result<Self> {
+        let mut notes = Vec::new();
+        for entry in fs.walk(target_path) {
+            let (path, _) = entry?;
+            let is_page = path
+                .extension()
+                .is_some_and(|ext| ext == "dj" || ext == "djot" || ext == "md");
+            let source = path.strip_prefix(target_path)?.to_path_buf();
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            if !is_page || !fs.is_file(&path) || stem.starts_with('_') || config.is_ignored(&source)
+            {
+                continue;
+            }
+            let html_path = if utils::is_index_alias(fs, &path, &config.index_names) {
+                source.with_file_name("index.html")
+            } else if config.pretty_urls {
+                utils::pretty_path(&slug::html_path(&source, config.file_name_slugs()))
+            } else {
+                slug::html_path(&source, config.file_name_slugs())
+            };
+            // Front matter errors are reported when the note is rendered
+            let input = fs.read_to_string(&path)?;
+            let title = match metadata::split_front_matter(&input) {
+                Some((delimiter, Some((front_matter, _)))) => {
+                    RawFrontMatter::parse(front_matter, delimiter, &path)
+                        .ok()
+                        .and_then(|front_matter| front_matter.text("title"))
+                }
+                _ => None,
+            };
+            notes.push(Note {
+                title: title.unwrap_or_else(|| stem.to_string()),
+                source,
+                html_path,
+            });
+        }
+        Ok(Self {
+            notes,
+            ..Default::default()
+        })
+    }
+
+    fn index_of(&self, html_path: &Path) -> Option<usize> {
+        self.notes
+            .iter()
+            .position(|note| note.html_path == html_path)
+    }
+
+    /// The note named `name`: its file name, title, or path without an extension (e.g.
+    /// `ideas/Gardens`), ignoring case.
+    fn find(&self, name: &str) -> Option<usize> {
+        let name = name.trim().to_lowercase();
+        self.notes.iter().position(|note| {
+            let path = note.source.with_extension("");
+            let stem = path.file_name().unwrap_or_default().to_string_lossy();
+            stem.to_lowercase() == name
+                || note.title.to_lowercase() == name
+                || path.to_string_lossy().replace('\\', "/").to_lowercase() == name
+        })
+    }
+
+    /// Replaces each wikilink (`[[Note]]`, `[[Note|label]]`, or `[[Note#heading]]`) in the
+    /// source of the note at `html_path`, `depth` deep, with a link to the note, recording it for
+    /// the note's backlinks. Wikilinks in code are left as they are, and so are those that don't
+    /// name a note, which are returned.
+    pub fn link_wikilinks(
+        &self,
+        input: &str,
+        html_path: &Path,
+        depth: usize,
+        web_prefix: Option<&str>,
+    ) -> (String, Vec<String>) {
+        let mut output = String::with_capacity(input.len());
+        let mut unresolved = Vec::new();
+        let mut linked = BTreeSet::new();
+        let mut fence: Option<String> = None;
+        for line in input.split_inclusive('\n') {
+            let trimmed = line.trim();
+            if let Some(open) = &fence {
+                if trimmed.starts_with(open.as_str())
+                    && trimmed.trim_start_matches(['`', '~']).is_empty()
+                {
+                    fence = None;
+                }
+                output.push_str(line);
+                continue;
+            }
+            let run = trimmed.len() - trimmed.trim_start_matches(['`', '~']).len();
+            if run >= 3 {
+                fence = Some(trimmed[..run].to_string());
+                output.push_str(line);
+                continue;
+            }
+            let mut rest = line;
+            while let Some(start) = rest.find(['`', '[']) {
+                output.push_str(&rest[..start]);
+                rest = &rest[start..];
+                if rest.starts_with('`') {
+                    // Verbatim text, up to the same number of backticks
+                    let ticks = rest.len() - rest.trim_start_matches('`').len();
+                    let end = rest[ticks..]
+                        .find(&rest[..ticks])
+                        .map_or(rest.len(), |end| end + 2 * ticks);
+                    output.push_str(&rest[..end]);
+                    rest = &rest[end..];
+                    continue;
+                }
+                let wikilink = rest
+                    .strip_prefix("[[")
+                    .and_then(|inner| Some(&inner[..inner.find("]]")?]))
+                    .filter(|inner| !inner.is_empty() && !inner.contains('['));
+                let Some(inner) = wikilink else {
+                    output.push('[');
+                    rest = &rest[1..];
+                    continue;
+                };
+                let (target, label) = match inner.split_once('|') {
+                    Some((target, label)) => (target, Some(label.trim())),
+                    None => (inner, None),
+                };
+                let (name, heading) = match target.split_once('#') {
+                    Some((name, heading)) => (name, Some(heading.trim())),
+                    None => (target, None),
+                };
+                match self.find(name) {
+                    Some(i) => {
+                        let mut url = utils::page_url(depth, web_prefix, &self.notes[i].html_path);
+                        if let Some(heading) = heading {
+                            url.push('#');
+                            url.push_str(heading);
+                        }
+                        output.push_str(&format!(
+                            "[{}]({})",
+                            label.unwrap_or(target.trim()),
+                            url.replace(' ', "%20")
+                        ));
+                        linked.insert(i);
+                    }
+                    None => {
+                        output.push_str(&rest[..inner.len() + 4]);
+                        unresolved.push(name.trim().to_string());
+                    }
+                }
+                rest = &rest[inner.len() + 4..];
+            }
+            output.push_str(rest);
+        }
+        if let Some(from) = self.index_of(html_path) {
+            if !linked.is_empty() {
+                self.links
+                    .lock()
+                    .unwrap()
+                    .entry(from)
+                    .or_default()
+                    .extend(linked);
+            }
+        }
+        (output, unresolved)
+    }
+
+    /// Records the notes whose names or titles the rendered note `html` at `html_path` mentions
+    /// without linking to them. Text in links, headings, and code is skipped.
+    pub fn find_mentions(&self, html: &str, html_path: &Path) {
+        let Some(from) = self.index_of(html_path) else {
+            return;
+        };
+        let mut text = String::new();
+        utils::map_text(html, &SKIPPED_ELEMENTS, |run| {
+            text.push_str(&utils::unescape_html(run).to_lowercase());
+            text.push('\n');
+            run.to_string()
+        });
+        let linked = self
+            .links
+            .lock()
+            .unwrap()
+            .get(&from)
+            .cloned()
+            .unwrap_or_default();
+        let mentioned = self
+            .notes
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != from && !linked.contains(i))
+            .filter(|(_, note)| {
+                let stem = note
+                    .source
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                glossary::find_word(&text, &note.title.to_lowercase()).is_some()
+                    || glossary::find_word(&text, &stem.to_lowercase()).is_some()
+            })
+            .map(|(i, _)| i)
+            .collect::<BTreeSet<_>>();
+        if !mentioned.is_empty() {
+            self.mentions.lock().unwrap().insert(from, mentioned);
+        }
+    }
+
+    /// Fills in the backlinks of the note `html` at `html_path`, `depth` deep: the notes that
+    /// link to it, and those that mention it without linking to it.
+    pub fn fill_backlinks(
+        &self,
+        html: &str,
+        html_path: &Path,
+        depth: usize,
+        web_prefix: Option<&str>,
+    ) -> String {
+        if !html.contains(BACKLINKS_PLACEHOLDER) {
+            return html.to_string();
+        }
+        let Some(to) = self.index_of(html_path) else {
+            return html.replace(BACKLINKS_PLACEHOLDER, "");
+        };
+        let list = |notes: &BTreeMap<usize, BTreeSet<usize>>| {
+            notes
+                .iter()
+                .filter(|(_, targets)| targets.contains(&to))
+                .map(|(from, _)| {
+                    let note = &self.notes[*from];
+                    format!(
+                        "<li><a href=\"{}\">{}</a></li>\n",
+                        utils::page_url(depth, web_prefix, &note.html_path),
+                        utils::escape_html(&note.title)
+                    )
+                })
+                .collect::<String>()
+        };
+        let links = list(&self.links.lock().unwrap());
+        let mentions = list(&self.mentions.lock().unwrap());
+        let mut backlinks = String::new();
+        if !links.is_empty() {
+            backlinks.push_str(&format!("<h2>Linked from</h2>\n<ul>\n{}</ul>\n", links));
+        }
+        if !mentions.is_empty() {
+            backlinks.push_str(&format!(
+                "<h2>Unlinked mentions</h2>\n<ul>\n{}</ul>\n",
+                mentions
+            ));
+        }
+        if !backlinks.is_empty() {
+            backlinks = format!("<section class=\"backlinks\">\n{}</section>\n", backlinks);
+        }
+        html.replace(BACKLINKS_PLACEHOLDER, &backlinks)
+    }
+
+    /// Renders the index of every note, newest first by when it was last modified. Daily notes,
+    /// named by their date (e.g. `2024-01-31.md`), are listed separately by that date.
+    pub fn index_html(&self, context: &SiteContext) -> anyhow::Result<String> {
+        let mut notes = Vec::new();
+        let mut daily_notes = Vec::new();
+        for note in &self.notes {
+            let stem = note
+                .source
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy();
+            match Date::Text(stem.to_string()).to_date() {
+                Some(date) => daily_notes.push((note, Some(date.midnight().assume_utc()))),
+                None => {
+                    let modified = context
+                        .fs
+                        .modified(&context.target_path.join(&note.source))
+                        .ok()
+                        .map(OffsetDateTime::from);
+                    notes.push((note, modified));
+                }
+            }
+        }
+        let mut html = "<h1>All notes</h1>\n".to_string();
+        for (class, heading, mut notes) in [
+            ("all-notes", None, notes),
+            ("daily-notes", Some("Daily notes"), daily_notes),
+        ] {
+            if notes.is_empty() {
+                continue;
+            }
+            // Newest first, then undated notes by title
+            notes.sort_by(|(a, a_date), (b, b_date)| {
+                b_date.cmp(a_date).then_with(|| a.title.cmp(&b.title))
+            });
+            if let Some(heading) = heading {
+                html.push_str(&format!("<h2>{}</h2>\n", heading));
+            }
+            html.push_str(&format!("<ul class=\"{}\">\n", class));
+            for (note, date) in notes {
+                let date = match date {
+                    Some(date) => format!(
+                        " <time datetime=\"{}\">{}</time>",
+                        utils::format_date(date, None)?,
+                        utils::escape_html(&utils::format_date(
+                            date,
+                            context.config.date_format.as_deref()
+                        )?)
+                    ),
+                    None => String::new(),
+                };
+                html.push_str(&format!(
+                    "<li><a href=\"{}\">{}</a>{}</li>\n",
+                    utils::page_url(1, context.web_prefix, &note.html_path),
+                    utils::escape_html(&note.title),
+                    date
+                ));
+            }
+            html.push_str("</ul>\n");
+        }
+        Ok(html)
+    }
+}
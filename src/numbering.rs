@@ -0,0 +1,140 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::FirstPassResult;
+
+/// The section number (e.g. `2.1`) of each folder and listed page, in table of contents order.
+/// Index pages share their folder's number, so the site's own index page has none.
+pub fn section_numbers(results: &[FirstPassResult]) -> HashMap<PathBuf, String> {
+    let listed = results
+        .iter()
+        .filter_map(|result| match result {
+            FirstPassResult::HtmlOutput {
+                relative_path,
+                unlisted: false,
+                ..
+            } => Some(relative_path.as_path()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut numbers = HashMap::new();
+    let mut counters: Vec<usize> = Vec::new();
+    for result in results {
+        let path = match result {
+            // Folders without listed pages aren't in the table of contents
+            FirstPassResult::Dir { relative_path, .. }
+                if !relative_path.as_os_str().is_empty()
+                    && listed.iter().any(|page| page.starts_with(relative_path)) =>
+            {
+                relative_path
+            }
+            FirstPassResult::HtmlOutput {
+                relative_path,
+                unlisted: false,
+                ..
+            } if relative_path
+                .file_name()
+                .is_some_and(|name| name == "index.html") =>
+            {
+                let folder = relative_path.parent().unwrap_or(Path::new(""));
+                let number = numbers.get(folder).cloned().unwrap_or_default();
+                numbers.insert(relative_path.clone(), number);
+                continue;
+            }
+            FirstPassResult::HtmlOutput {
+                relative_path,
+                unlisted: false,
+                ..
+            } => relative_path,
+            _ => continue,
+        };
+        let level = path.components().count();
+        counters.resize(level, 0);
+        counters[level - 1] += 1;
+        numbers.insert(path.clone(), join(&counters));
+    }
+    numbers
+}
+
+/// Formats a section number for the start of a heading or table of contents entry.
+pub fn number_html(number: &str) -> String {
+    format!("<span class=\"section-number\">{}</span> ", number)
+}
+
+/// Marks each heading in `html` to be numbered by `number_headings`.
+pub fn mark_headings(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("<h") {
+        let tag = &rest[start..];
+        let level = tag[2..].chars().next().and_then(|c| c.to_digit(10));
+        match (level, tag.find('>')) {
+            (Some(level @ 1..=6), Some(end))
+                if tag[3..].starts_with(|c: char| c == '>' || c.is_whitespace()) =>
+            {
+                output.push_str(&rest[..start + end + 1]);
+                output.push_str(&format!("<!-- {{SECTION_NUMBER:{}}} -->", level));
+                rest = &tag[end + 1..];
+            }
+            _ => {
+                output.push_str(&rest[..start + 2]);
+                rest = &tag[2..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Numbers the headings marked by `mark_headings` (e.g. `1.2` for the second level 2 heading
+/// after the first level 1 heading). On a page with a section number, level 1 headings get the
+/// page's number and deeper headings continue from it.
+pub fn number_headings(html: &str, page_number: Option<&str>) -> String {
+    let page_number = page_number.filter(|number| !number.is_empty());
+    let mut counters = [0; 6];
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("<!-- {SECTION_NUMBER:") {
+        output.push_str(&rest[..start]);
+        let marker = &rest[start..];
+        let Some(end) = marker.find("} -->") else {
+            break;
+        };
+        rest = &marker[end + "} -->".len()..];
+        let Some(level @ 1..=6) = marker["<!-- {SECTION_NUMBER:".len()..end]
+            .parse::<usize>()
+            .ok()
+        else {
+            continue;
+        };
+        counters[level - 1] += 1;
+        counters[level..].fill(0);
+        // Skipped heading levels are left out of the number
+        let number = match page_number {
+            Some(page_number) => std::iter::once(page_number.to_string())
+                .chain(
+                    counters[1..level]
+                        .iter()
+                        .filter(|c| **c > 0)
+                        .map(usize::to_string),
+                )
+                .collect::<Vec<_>>()
+                .join("."),
+            None => join(&counters[..level]),
+        };
+        output.push_str(&number_html(&number));
+    }
+    output.push_str(rest);
+    output
+}
+
+fn join(counters: &[usize]) -> String {
+    counters
+        .iter()
+        .filter(|counter| **counter > 0)
+        .map(usize::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
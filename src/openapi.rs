@@ -0,0 +1,305 @@
+use serde_json::Value;
+
+use crate::{config::SlugConfig, sandbox, slug, utils};
+
+/// The operations of a path item, in the order they're listed.
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Parses an OpenAPI 3 (or Swagger 2) spec, as json if `file_name` ends with `.json` and yaml
+/// otherwise.
+pub fn parse_spec(spec: &str, file_name: &str) -> anyhow::Result<Value> {
+    let spec: Value = if file_name.ends_with(".json") {
+        serde_json::from_str(spec)?
+    } else {
+        serde_yaml::from_str(spec)?
+    };
+    if spec.get("openapi").is_none() && spec.get("swagger").is_none() {
+        return Err(anyhow::anyhow!("{} isn't an OpenAPI spec", file_name));
+    }
+    Ok(spec)
+}
+
+/// Renders an API reference for `spec`: its info and servers, each operation with its
+/// parameters, request body, and responses, and each schema with its properties. Operations and
+/// schemas get anchors slugged with `slugs`, and schema references link to them.
+pub fn reference_html(spec: &Value, slugs: &SlugConfig) -> String {
+    let reference = Reference { spec, slugs };
+    let mut html = "<div class=\"openapi\">".to_string();
+    let info = &spec["info"];
+    html.push_str(&format!(
+        "<p class=\"openapi-info\"><strong>{}</strong> {}</p>",
+        text(&info["title"]),
+        text(&info["version"])
+    ));
+    html.push_str(&markdown(&info["description"]));
+    let servers = match spec["servers"].as_array() {
+        Some(servers) => servers
+            .iter()
+            .map(|server| format!("<code>{}</code>", text(&server["url"])))
+            .collect::<Vec<_>>(),
+        // Swagger 2 has a single host
+        None => spec["host"]
+            .as_str()
+            .map(|host| {
+                format!(
+                    "<code>{}{}</code>",
+                    utils::escape_html(host),
+                    text(&spec["basePath"])
+                )
+            })
+            .into_iter()
+            .collect(),
+    };
+    if !servers.is_empty() {
+        html.push_str(&format!("<p>Servers: {}</p>", servers.join(", ")));
+    }
+    if let Some(paths) = spec["paths"].as_object() {
+        html.push_str("<h2 id=\"openapi-endpoints\">Endpoints</h2>");
+        for (path, item) in paths {
+            for method in METHODS {
+                if let Some(operation) = item.get(method) {
+                    html.push_str(&reference.operation_html(path, method, item, operation));
+                }
+            }
+        }
+    }
+    let schemas = spec
+        .pointer("/components/schemas")
+        .or(spec.get("definitions"))
+        .and_then(Value::as_object);
+    if let Some(schemas) = schemas {
+        html.push_str("<h2 id=\"openapi-schemas\">Schemas</h2>");
+        for (name, schema) in schemas {
+            html.push_str(&reference.schema_html(name, schema));
+        }
+    }
+    html.push_str("</div>");
+    html
+}
+
+struct Reference<'a> {
+    spec: &'a Value,
+    slugs: &'a SlugConfig,
+}
+
+impl Reference<'_> {
+    fn operation_html(&self, path: &str, method: &str, item: &Value, operation: &Value) -> String {
+        let id = match operation["operationId"].as_str() {
+            Some(id) => slug::slugify(id, self.slugs),
+            None => slug::slugify(&format!("{} {}", method, path), self.slugs),
+        };
+        let deprecated = if operation["deprecated"].as_bool() == Some(true) {
+            " deprecated"
+        } else {
+            ""
+        };
+        let mut html = format!(
+            "<section class=\"openapi-operation{}\" id=\"{}\"><h3><span class=\"http-method http-{}\">{}</span> <code>{}</code></h3>",
+            deprecated,
+            id,
+            method,
+            method.to_uppercase(),
+            utils::escape_html(path)
+        );
+        if let Some(summary) = operation["summary"].as_str() {
+            html.push_str(&format!("<p>{}</p>", utils::escape_html(summary)));
+        }
+        html.push_str(&markdown(&operation["description"]));
+        // Parameters of the path apply to each of its operations, unless overridden
+        let mut parameters = Vec::new();
+        for parameter in [&operation["parameters"], &item["parameters"]]
+            .into_iter()
+            .filter_map(Value::as_array)
+            .flatten()
+            .map(|parameter| self.resolve(parameter))
+        {
+            let key = (&parameter["name"], &parameter["in"]);
+            if !parameters
+                .iter()
+                .any(|other: &&Value| (&other["name"], &other["in"]) == key)
+            {
+                parameters.push(parameter);
+            }
+        }
+        // Swagger 2 has the request body as a parameter
+        let (body, parameters): (Vec<_>, Vec<_>) = parameters
+            .into_iter()
+            .partition(|parameter| parameter["in"] == "body");
+        if !parameters.is_empty() {
+            html.push_str("<h4>Parameters</h4><table class=\"openapi-parameters\"><thead><tr><th>Name</th><th>In</th><th>Type</th><th>Required</th><th>Description</th></tr></thead><tbody>");
+            for parameter in parameters {
+                let schema = parameter.get("schema").unwrap_or(parameter);
+                html.push_str(&format!(
+                    "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    text(&parameter["name"]),
+                    text(&parameter["in"]),
+                    self.type_html(schema),
+                    yes_no(&parameter["required"]),
+                    text(&parameter["description"])
+                ));
+            }
+            html.push_str("</tbody></table>");
+        }
+        let request_body = self.resolve(&operation["requestBody"]);
+        if let Some(content) = request_body["content"].as_object() {
+            html.push_str("<h4>Request Body</h4><ul class=\"openapi-request-body\">");
+            for (media_type, media) in content {
+                html.push_str(&format!(
+                    "<li><code>{}</code>: {}</li>",
+                    utils::escape_html(media_type),
+                    self.type_html(&media["schema"])
+                ));
+            }
+            html.push_str("</ul>");
+        } else if let Some(body) = body.first() {
+            html.push_str(&format!(
+                "<h4>Request Body</h4><ul class=\"openapi-request-body\"><li>{}</li></ul>",
+                self.type_html(&body["schema"])
+            ));
+        }
+        if let Some(responses) = operation["responses"].as_object() {
+            html.push_str("<h4>Responses</h4><table class=\"openapi-responses\"><thead><tr><th>Status</th><th>Description</th><th>Type</th></tr></thead><tbody>");
+            for (status, response) in responses {
+                let response = self.resolve(response);
+                // OpenAPI 3 has a schema per media type, and Swagger 2 one for the response
+                let types = match response["content"].as_object() {
+                    Some(content) => content
+                        .iter()
+                        .map(|(media_type, media)| {
+                            format!(
+                                "<code>{}</code>: {}",
+                                utils::escape_html(media_type),
+                                self.type_html(&media["schema"])
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("<br>"),
+                    None if response.get("schema").is_some() => self.type_html(&response["schema"]),
+                    None => String::new(),
+                };
+                html.push_str(&format!(
+                    "<tr><td><code>{}</code></td><td>{}</td><td>{}</td></tr>",
+                    utils::escape_html(status),
+                    text(&response["description"]),
+                    types
+                ));
+            }
+            html.push_str("</tbody></table>");
+        }
+        html.push_str("</section>");
+        html
+    }
+
+    fn schema_html(&self, name: &str, schema: &Value) -> String {
+        let mut html = format!(
+            "<section class=\"openapi-schema\" id=\"{}\"><h3>{}</h3>",
+            self.schema_id(name),
+            utils::escape_html(name)
+        );
+        html.push_str(&markdown(&schema["description"]));
+        match schema["properties"].as_object() {
+            Some(properties) => {
+                let required = schema["required"].as_array();
+                html.push_str("<table class=\"openapi-properties\"><thead><tr><th>Name</th><th>Type</th><th>Required</th><th>Description</th></tr></thead><tbody>");
+                for (property, property_schema) in properties {
+                    let is_required = required.is_some_and(|required| {
+                        required.iter().any(|name| name.as_str() == Some(property))
+                    });
+                    html.push_str(&format!(
+                        "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                        utils::escape_html(property),
+                        self.type_html(property_schema),
+                        if is_required { "Yes" } else { "No" },
+                        text(&property_schema["description"])
+                    ));
+                }
+                html.push_str("</tbody></table>");
+            }
+            None => html.push_str(&format!("<p>Type: {}</p>", self.type_html(schema))),
+        }
+        html.push_str("</section>");
+        html
+    }
+
+    /// Describes the type of `schema`, linking references to other schemas.
+    fn type_html(&self, schema: &Value) -> String {
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.rsplit('/').next().unwrap_or(reference);
+            return format!(
+                "<a href=\"#{}\">{}</a>",
+                self.schema_id(name),
+                utils::escape_html(name)
+            );
+        }
+        for (combinator, separator) in [("oneOf", " or "), ("anyOf", " or "), ("allOf", " and ")] {
+            if let Some(schemas) = schema[combinator].as_array() {
+                return schemas
+                    .iter()
+                    .map(|schema| self.type_html(schema))
+                    .collect::<Vec<_>>()
+                    .join(separator);
+            }
+        }
+        let mut html = match schema["type"].as_str() {
+            Some("array") => format!("array of {}", self.type_html(&schema["items"])),
+            Some(name) => utils::escape_html(name),
+            None => "any".to_string(),
+        };
+        if let Some(format) = schema["format"].as_str() {
+            html.push_str(&format!(" ({})", utils::escape_html(format)));
+        }
+        if let Some(values) = schema["enum"].as_array() {
+            let values = values
+                .iter()
+                .map(|value| format!("<code>{}</code>", utils::escape_html(&value.to_string())))
+                .collect::<Vec<_>>();
+            html.push_str(&format!(": {}", values.join(", ")));
+        }
+        html
+    }
+
+    fn schema_id(&self, name: &str) -> String {
+        format!("schema-{}", slug::slugify(name, self.slugs))
+    }
+
+    /// Follows a `$ref` to a component in the spec, such as a shared parameter or response.
+    fn resolve<'a>(&'a self, value: &'a Value) -> &'a Value {
+        value["$ref"]
+            .as_str()
+            .and_then(|reference| reference.strip_prefix('#'))
+            .and_then(|pointer| self.spec.pointer(pointer))
+            .unwrap_or(value)
+    }
+}
+
+/// A string (or other scalar) from the spec, escaped.
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => utils::escape_html(text),
+        Value::Null => String::new(),
+        value => utils::escape_html(&value.to_string()),
+    }
+}
+
+fn yes_no(value: &Value) -> &'static str {
+    match value.as_bool() {
+        Some(true) => "Yes",
+        _ => "No",
+    }
+}
+
+/// Renders a CommonMark description from the spec, without any raw html in it.
+fn markdown(value: &Value) -> String {
+    let Some(description) = value.as_str() else {
+        return String::new();
+    };
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(
+        &mut html,
+        pulldown_cmark::Parser::new(description).filter(sandbox::keep_markdown_event),
+    );
+    // Blank lines would end the html block in a markdown page
+    html.trim_end().replace("\n\n", "\n&#10;")
+}
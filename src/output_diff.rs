@@ -0,0 +1,173 @@
+use std::{
+    collections::BTreeSet,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use crate::vfs::FileSystem;
+
+/// The most pairs of lines compared when diffing a page, beyond which the changed part is
+/// shown as removed and added as a whole.
+const MAX_DIFF_SIZE: usize = 4_000_000;
+
+/// How a file differs between two generated sites.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileChange {
+    Added(PathBuf),
+    Removed(PathBuf),
+    /// The file's contents changed, with the changed lines if it's an html page
+    Changed(PathBuf, Vec<DiffLine>),
+}
+
+/// A line of a page's html that was removed or added.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffLine {
+    Removed(String),
+    Added(String),
+}
+
+impl Display for FileChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Added(path) => write!(f, "added:   {}", path.display()),
+            Self::Removed(path) => write!(f, "removed: {}", path.display()),
+            Self::Changed(path, lines) => {
+                write!(f, "changed: {}", path.display())?;
+                for line in lines {
+                    match line {
+                        DiffLine::Removed(line) => write!(f, "\n  - {}", line)?,
+                        DiffLine::Added(line) => write!(f, "\n  + {}", line)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Compares the generated sites in `old` and `new`, returning the files added, removed, and
+/// changed, by their path relative to the site. Pages are compared by their html, ignoring
+/// differences in whitespace and line breaks.
+pub fn diff_outputs(
+    fs: &dyn FileSystem,
+    old: &Path,
+    new: &Path,
+) -> anyhow::Result<Vec<FileChange>> {
+    let old_files = files(fs, old)?;
+    let new_files = files(fs, new)?;
+    let mut changes = Vec::new();
+    for path in old_files.union(&new_files) {
+        let change = match (old_files.contains(path), new_files.contains(path)) {
+            (true, false) => FileChange::Removed(path.clone()),
+            (false, true) => FileChange::Added(path.clone()),
+            _ => {
+                let (old_file, new_file) = (fs.read(&old.join(path))?, fs.read(&new.join(path))?);
+                if old_file == new_file {
+                    continue;
+                }
+                let is_html = path
+                    .extension()
+                    .is_some_and(|ext| ext == "html" || ext == "htm");
+                let lines = if is_html {
+                    let old_lines = html_lines(&String::from_utf8_lossy(&old_file));
+                    let new_lines = html_lines(&String::from_utf8_lossy(&new_file));
+                    if old_lines == new_lines {
+                        continue;
+                    }
+                    diff_lines(&old_lines, &new_lines)
+                } else {
+                    Vec::new()
+                };
+                FileChange::Changed(path.clone(), lines)
+            }
+        };
+        changes.push(change);
+    }
+    Ok(changes)
+}
+
+/// The files in `dir`, relative to it.
+fn files(fs: &dyn FileSystem, dir: &Path) -> anyhow::Result<BTreeSet<PathBuf>> {
+    if !fs.is_dir(dir) {
+        return Err(anyhow::anyhow!("{} is not a directory", dir.display()));
+    }
+    let mut files = BTreeSet::new();
+    for entry in fs.walk(dir) {
+        let (path, _) = entry?;
+        if fs.is_file(&path) {
+            files.insert(path.strip_prefix(dir)?.to_path_buf());
+        }
+    }
+    Ok(files)
+}
+
+/// Splits `html` into a line per tag and per run of text between tags, with whitespace
+/// collapsed, so pages that only differ in formatting have the same lines.
+pub fn html_lines(html: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let end = if rest.starts_with('<') {
+            rest.find('>').map_or(rest.len(), |end| end + 1)
+        } else {
+            rest.find('<').unwrap_or(rest.len())
+        };
+        let line = rest[..end].split_whitespace().collect::<Vec<_>>().join(" ");
+        if !line.is_empty() {
+            lines.push(line);
+        }
+        rest = &rest[end..];
+    }
+    lines
+}
+
+/// The lines removed from `old` and added in `new`, in order, from their longest common
+/// subsequence.
+fn diff_lines(old: &[String], new: &[String]) -> Vec<DiffLine> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old = &old[prefix..old.len() - suffix];
+    let new = &new[prefix..new.len() - suffix];
+    let removed = |line: &String| DiffLine::Removed(line.clone());
+    let added = |line: &String| DiffLine::Added(line.clone());
+    if old.len().saturating_mul(new.len()) > MAX_DIFF_SIZE {
+        return old
+            .iter()
+            .map(removed)
+            .chain(new.iter().map(added))
+            .collect();
+    }
+    // lengths[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            lines.push(removed(&old[i]));
+            i += 1;
+        } else {
+            lines.push(added(&new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(removed));
+    lines.extend(new[j..].iter().map(added));
+    lines
+}
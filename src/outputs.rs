@@ -0,0 +1,148 @@
+use jotdown::{Container, Event};
+
+/// Receives the structure of a document, for rendering to non-html output formats (or linting).
+///
+/// Text inside code blocks is passed to `text`, so writers track whether they are in one. Every
+/// method does nothing by default.
+pub trait OutputWriter {
+    fn start_heading(&mut self, _level: u16) {}
+    fn end_heading(&mut self, _level: u16) {}
+    fn start_paragraph(&mut self) {}
+    fn end_paragraph(&mut self) {}
+    fn start_code_block(&mut self) {}
+    fn end_code_block(&mut self) {}
+    fn start_block_quote(&mut self) {}
+    fn end_block_quote(&mut self) {}
+    fn start_list(&mut self, _ordered: bool) {}
+    fn end_list(&mut self, _ordered: bool) {}
+    fn start_item(&mut self) {}
+    fn end_item(&mut self) {}
+    fn start_emphasis(&mut self) {}
+    fn end_emphasis(&mut self) {}
+    fn start_strong(&mut self) {}
+    fn end_strong(&mut self) {}
+    fn start_link(&mut self, _url: &str) {}
+    fn end_link(&mut self, _url: &str) {}
+    fn code(&mut self, _text: &str) {}
+    fn text(&mut self, _text: &str) {}
+    fn soft_break(&mut self) {}
+    fn hard_break(&mut self) {}
+    fn rule(&mut self) {}
+}
+
+/// Walks a markdown or djot document, passing its structure to `writer`.
+pub fn write_document(input: &str, is_markdown: bool, writer: &mut impl OutputWriter) {
+    if is_markdown {
+        write_markdown(input, writer);
+    } else {
+        write_djot(input, writer);
+    }
+}
+
+/// Walks a markdown document, passing its structure to `writer`.
+pub fn write_markdown(markdown_input: &str, writer: &mut impl OutputWriter) {
+    use pulldown_cmark::{Event, Tag, TagEnd};
+    let mut options = pulldown_cmark::Options::empty();
+    options.insert(pulldown_cmark::Options::ENABLE_GFM);
+    let mut link_urls = Vec::new();
+    for event in pulldown_cmark::Parser::new_ext(markdown_input, options) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => writer.start_heading(level as u16),
+            Event::End(TagEnd::Heading(level)) => writer.end_heading(level as u16),
+            Event::Start(Tag::Paragraph) => writer.start_paragraph(),
+            Event::End(TagEnd::Paragraph) => writer.end_paragraph(),
+            Event::Start(Tag::CodeBlock(_)) => writer.start_code_block(),
+            Event::End(TagEnd::CodeBlock) => writer.end_code_block(),
+            Event::Start(Tag::BlockQuote(_)) => writer.start_block_quote(),
+            Event::End(TagEnd::BlockQuote(_)) => writer.end_block_quote(),
+            Event::Start(Tag::List(start)) => writer.start_list(start.is_some()),
+            Event::End(TagEnd::List(ordered)) => writer.end_list(ordered),
+            Event::Start(Tag::Item) => writer.start_item(),
+            Event::End(TagEnd::Item) => writer.end_item(),
+            Event::Start(Tag::Emphasis) => writer.start_emphasis(),
+            Event::End(TagEnd::Emphasis) => writer.end_emphasis(),
+            Event::Start(Tag::Strong) => writer.start_strong(),
+            Event::End(TagEnd::Strong) => writer.end_strong(),
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                writer.start_link(&dest_url);
+                link_urls.push(dest_url);
+            }
+            Event::End(TagEnd::Link) => {
+                if let Some(url) = link_urls.pop() {
+                    writer.end_link(&url);
+                }
+            }
+            Event::Code(text) => writer.code(&text),
+            Event::Text(text) => writer.text(&text),
+            Event::TaskListMarker(checked) => writer.text(if checked { "[x] " } else { "[ ] " }),
+            Event::SoftBreak => writer.soft_break(),
+            Event::HardBreak => writer.hard_break(),
+            Event::Rule => writer.rule(),
+            _ => {}
+        }
+    }
+}
+
+/// Walks a djot document, passing its structure to `writer`.
+pub fn write_djot(djot_input: &str, writer: &mut impl OutputWriter) {
+    // Raw blocks (e.g. html) have no meaning outside of html output
+    let mut raw_depth = 0;
+    let mut in_verbatim = false;
+    for event in jotdown::Parser::new(djot_input) {
+        match event {
+            Event::Start(Container::RawBlock { .. } | Container::RawInline { .. }, _) => {
+                raw_depth += 1
+            }
+            Event::End(Container::RawBlock { .. } | Container::RawInline { .. }) => raw_depth -= 1,
+            _ if raw_depth > 0 => {}
+            Event::Start(container, _) => match container {
+                Container::Heading { level, .. } => writer.start_heading(level),
+                Container::Paragraph => writer.start_paragraph(),
+                Container::CodeBlock { .. } => writer.start_code_block(),
+                Container::Blockquote => writer.start_block_quote(),
+                Container::List { kind, .. } => {
+                    writer.start_list(matches!(kind, jotdown::ListKind::Ordered { .. }))
+                }
+                Container::ListItem => writer.start_item(),
+                Container::TaskListItem { checked } => {
+                    writer.start_item();
+                    writer.text(if checked { "[x] " } else { "[ ] " });
+                }
+                Container::Emphasis => writer.start_emphasis(),
+                Container::Strong => writer.start_strong(),
+                Container::Link(url, _) => writer.start_link(&url),
+                Container::Verbatim => in_verbatim = true,
+                _ => {}
+            },
+            Event::End(container) => match container {
+                Container::Heading { level, .. } => writer.end_heading(level),
+                Container::Paragraph => writer.end_paragraph(),
+                Container::CodeBlock { .. } => writer.end_code_block(),
+                Container::Blockquote => writer.end_block_quote(),
+                Container::List { kind, .. } => {
+                    writer.end_list(matches!(kind, jotdown::ListKind::Ordered { .. }))
+                }
+                Container::ListItem | Container::TaskListItem { .. } => writer.end_item(),
+                Container::Emphasis => writer.end_emphasis(),
+                Container::Strong => writer.end_strong(),
+                Container::Link(url, _) => writer.end_link(&url),
+                Container::Verbatim => in_verbatim = false,
+                _ => {}
+            },
+            Event::Str(text) if in_verbatim => writer.code(&text),
+            Event::Str(text) | Event::Symbol(text) => writer.text(&text),
+            Event::LeftSingleQuote => writer.text("‘"),
+            Event::RightSingleQuote => writer.text("’"),
+            Event::LeftDoubleQuote => writer.text("“"),
+            Event::RightDoubleQuote => writer.text("”"),
+            Event::Ellipsis => writer.text("…"),
+            Event::EnDash => writer.text("–"),
+            Event::EmDash => writer.text("—"),
+            Event::NonBreakingSpace => writer.text("\u{a0}"),
+            Event::Softbreak => writer.soft_break(),
+            Event::Hardbreak => writer.hard_break(),
+            Event::ThematicBreak(_) => writer.rule(),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::path::{Path, PathBuf};
+
+use crate::utils;
+
+/// One page of a paginated listing.
+pub struct ListingPage {
+    /// The page, relative to the output directory
+    pub path: PathBuf,
+    /// How deep the page is in the site (1 at its root)
+    pub depth: usize,
+    /// Which page this is, from 1
+    pub number: usize,
+    /// The entries on this page
+    pub entries: std::ops::Range<usize>,
+}
+
+/// Splits a listing of `count` entries at `path` into pages of `per_page` entries (or a single
+/// page without it). The first page is `path`, and the rest are `page/2.html`, `page/3.html`,
+/// ... in its directory if it's an `index.html`, or else in a directory named after it.
+pub fn paginate(path: &Path, count: usize, per_page: Option<usize>) -> Vec<ListingPage> {
+    let per_page = per_page
+        .filter(|per_page| *per_page > 0)
+        .unwrap_or(count.max(1));
+    let base = match path.file_stem() {
+        Some(stem) if stem != "index" => path.with_extension(""),
+        _ => path.parent().unwrap_or(Path::new("")).to_path_buf(),
+    };
+    (0..count.max(1))
+        .step_by(per_page)
+        .enumerate()
+        .map(|(i, start)| {
+            let path = match i {
+                0 => path.to_path_buf(),
+                _ => base.join("page").join(format!("{}.html", i + 1)),
+            };
+            ListingPage {
+                depth: path.components().count(),
+                path,
+                number: i + 1,
+                entries: start..(start + per_page).min(count),
+            }
+        })
+        .collect()
+}
+
+/// The `<nav>` of links to the pages before and after `pages[index]`, or nothing if there's
+/// only one page.
+pub fn pagination_html(pages: &[ListingPage], index: usize, web_prefix: Option<&str>) -> String {
+    if pages.len() < 2 {
+        return String::new();
+    }
+    let depth = pages[index].depth;
+    let mut html = "<nav class=\"pagination\">".to_string();
+    if let Some(prev) = index.checked_sub(1).map(|i| &pages[i]) {
+        html.push_str(&format!(
+            "<a class=\"prev\" rel=\"prev\" href=\"{}\">&larr; Previous</a>",
+            utils::page_url(depth, web_prefix, &prev.path)
+        ));
+    }
+    html.push_str(&format!(
+        "<span class=\"page-number\">Page {} of {}</span>",
+        pages[index].number,
+        pages.len()
+    ));
+    if let Some(next) = pages.get(index + 1) {
+        html.push_str(&format!(
+            "<a class=\"next\" rel=\"next\" href=\"{}\">Next &rarr;</a>",
+            utils::page_url(depth, web_prefix, &next.path)
+        ));
+    }
+    html.push_str("</nav>\n");
+    html
+}
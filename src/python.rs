@@ -0,0 +1,52 @@
+//! Python bindings for the rendering pipeline, built with `--features python` as the
+//! `simple_ssg` module.
+
+use std::path::PathBuf;
+
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+
+use crate::{render_page, run_program, ConsoleArgs};
+
+/// Renders a markdown page to html. `config` is the contents of an `ssg.toml`.
+#[pyfunction]
+#[pyo3(signature = (input, config = ""))]
+fn render_markdown(input: &str, config: &str) -> PyResult<String> {
+    render(input, config, true)
+}
+
+/// Renders a djot page to html. `config` is the contents of an `ssg.toml`.
+#[pyfunction]
+#[pyo3(signature = (input, config = ""))]
+fn render_djot(input: &str, config: &str) -> PyResult<String> {
+    render(input, config, false)
+}
+
+/// Generates the site in `directory` into `output_path` (defaults to `./output`).
+#[pyfunction]
+#[pyo3(signature = (directory, output_path = None, clean = false))]
+fn build_site(directory: PathBuf, output_path: Option<PathBuf>, clean: bool) -> PyResult<()> {
+    let args = ConsoleArgs {
+        directory: Some(directory),
+        output_path,
+        clean,
+        ..Default::default()
+    };
+    run_program(args).map(|_| ()).map_err(to_py_err)
+}
+
+#[pymodule]
+fn simple_ssg(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(render_markdown, module)?)?;
+    module.add_function(wrap_pyfunction!(render_djot, module)?)?;
+    module.add_function(wrap_pyfunction!(build_site, module)?)?;
+    Ok(())
+}
+
+fn render(input: &str, config: &str, is_markdown: bool) -> PyResult<String> {
+    let config = toml::from_str(config).map_err(to_py_err)?;
+    render_page(input, config, is_markdown).map_err(to_py_err)
+}
+
+fn to_py_err(e: impl ToString) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
@@ -0,0 +1,63 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use crate::{git, slug, utils, SiteContext};
+
+/// Writes a redirect page at the old url of each page that was moved or renamed in the git
+/// history, pointing to the page's current url.
+pub fn generate_moved_page_redirects(context: &SiteContext) -> anyhow::Result<()> {
+    let target_path = context.target_path;
+    // `target_path` may be reached through a symlink, while git reports canonical paths
+    let canonical_target_path = target_path.canonicalize()?;
+    let is_document = |path: &PathBuf| {
+        path.extension()
+            .is_some_and(|ext| ext == "dj" || ext == "djot" || ext == "md")
+    };
+    let moves: HashMap<PathBuf, PathBuf> = git::renamed_files(target_path)
+        .into_iter()
+        .filter_map(|(from, to)| {
+            Some((
+                from.strip_prefix(&canonical_target_path)
+                    .ok()?
+                    .to_path_buf(),
+                to.strip_prefix(&canonical_target_path).ok()?.to_path_buf(),
+            ))
+        })
+        .filter(|(from, to)| is_document(from) && is_document(to))
+        .collect();
+
+    for from in moves.keys() {
+        // Skip urls that a current page is generated at
+        if ["dj", "djot", "md"]
+            .iter()
+            .any(|ext| target_path.join(from.with_extension(ext)).exists())
+        {
+            continue;
+        }
+        // Follow the page through any later moves
+        let mut to = &moves[from];
+        for _ in 0..moves.len() {
+            match moves.get(to) {
+                Some(next) => to = next,
+                None => break,
+            }
+        }
+        let current_path = target_path.join(to);
+        if !context.fs.exists(&current_path) {
+            log::trace!("{:?} was moved to {:?}, which no longer exists", from, to);
+            continue;
+        }
+        let to_html = utils::html_link_path(
+            context.fs,
+            &to.to_string_lossy(),
+            &current_path,
+            &context.config,
+        );
+        let from_html = slug::html_path(from, context.config.file_name_slugs());
+        let url = utils::page_url(from_html.components().count(), context.web_prefix, &to_html);
+        let redirect_path = context.output_path.join(&from_html);
+        log::debug!("Redirecting {:?} to {:?}", &redirect_path, &url);
+        context.fs.create_dir_all(redirect_path.parent().unwrap())?;
+        context.write_output(&redirect_path, utils::redirect_html(&url).as_bytes())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+/// What a site generation did, returned from `run_program`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct BuildReport {
+    /// The html pages rendered, by their output path
+    pub pages: Vec<PathBuf>,
+    /// Files copied to the output directory as they are
+    pub assets: Vec<PathBuf>,
+    /// Every file written to the output directory, including pages and assets
+    pub outputs: Vec<PathBuf>,
+    /// Warnings reported while generating the site
+    pub warnings: Vec<String>,
+}
+
+impl BuildReport {
+    /// Adds everything from `other` (e.g. another version of a versioned site).
+    pub fn extend(&mut self, other: BuildReport) {
+        self.pages.extend(other.pages);
+        self.assets.extend(other.assets);
+        self.outputs.extend(other.outputs);
+        self.warnings.extend(other.warnings);
+    }
+
+    /// Sorts everything, since pages are generated in parallel and their files and warnings
+    /// are added in no particular order.
+    pub fn sort(&mut self) {
+        self.pages.sort();
+        self.assets.sort();
+        self.outputs.sort();
+        self.warnings.sort();
+    }
+}
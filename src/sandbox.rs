@@ -0,0 +1,121 @@
+use std::{borrow::Cow, path::Path};
+
+use jotdown::{Container, Event};
+
+use crate::{errors::SsgError, vfs::FileSystem, SiteContext};
+
+/// Checks if `path` is inside `root` once symlinks are resolved. Paths that can't be resolved
+/// (e.g. missing files) are not.
+pub fn is_inside(fs: &dyn FileSystem, root: &Path, path: &Path) -> bool {
+    match (fs.canonicalize(root), fs.canonicalize(path)) {
+        (Ok(root), Ok(path)) => path.starts_with(root),
+        _ => false,
+    }
+}
+
+/// Strips raw blocks and inlines (e.g. `` `<script>`{=html} ``) from djot content.
+#[derive(Default)]
+pub struct RawFilter {
+    /// Depth inside the raw element being stripped, or 0 when not stripping
+    skip_depth: usize,
+}
+
+impl RawFilter {
+    /// Checks if `event` should be kept in the output.
+    pub fn keep(&mut self, event: &Event) -> bool {
+        match event {
+            Event::Start(Container::RawBlock { .. } | Container::RawInline { .. }, _) => {
+                self.skip_depth += 1;
+                false
+            }
+            Event::End(Container::RawBlock { .. } | Container::RawInline { .. }) => {
+                self.skip_depth -= 1;
+                false
+            }
+            _ => self.skip_depth == 0,
+        }
+    }
+}
+
+/// Checks if a markdown event should be kept in the output, stripping raw html.
+pub fn keep_markdown_event(event: &pulldown_cmark::Event) -> bool {
+    !matches!(
+        event,
+        pulldown_cmark::Event::Html(_) | pulldown_cmark::Event::InlineHtml(_)
+    )
+}
+
+/// Checks if linking to or embedding `url` can't run script: `javascript:` and `vbscript:` urls
+/// can, and so can `data:` urls other than images.
+pub fn is_safe_url(url: &str) -> bool {
+    // Browsers ignore the case of schemes, and whitespace and control characters in them
+    let url = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .take(16)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    !(url.starts_with("javascript:")
+        || url.starts_with("vbscript:")
+        || (url.starts_with("data:") && !url.starts_with("data:image/")))
+}
+
+/// Replaces the destination of a djot link or image that could run script with `#`.
+pub fn safe_event<'s>(event: Event<'s>, context: &SiteContext) -> Event<'s> {
+    match event {
+        Event::Start(Container::Link(url, link_type), attributes) if !is_safe_url(&url) => {
+            context.warn(SsgError::UnsafeUrl(url.to_string()));
+            Event::Start(Container::Link(Cow::Borrowed("#"), link_type), attributes)
+        }
+        Event::End(Container::Link(url, link_type)) if !is_safe_url(&url) => {
+            Event::End(Container::Link(Cow::Borrowed("#"), link_type))
+        }
+        Event::Start(Container::Image(url, link_type), attributes) if !is_safe_url(&url) => {
+            context.warn(SsgError::UnsafeUrl(url.to_string()));
+            Event::Start(Container::Image(Cow::Borrowed("#"), link_type), attributes)
+        }
+        Event::End(Container::Image(url, link_type)) if !is_safe_url(&url) => {
+            Event::End(Container::Image(Cow::Borrowed("#"), link_type))
+        }
+        event => event,
+    }
+}
+
+/// Replaces the destination of a markdown link or image that could run script with `#`.
+pub fn safe_markdown_event<'a>(
+    event: pulldown_cmark::Event<'a>,
+    context: &SiteContext,
+) -> pulldown_cmark::Event<'a> {
+    use pulldown_cmark::{CowStr, Event, Tag};
+    match event {
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) if !is_safe_url(&dest_url) => {
+            context.warn(SsgError::UnsafeUrl(dest_url.to_string()));
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url: CowStr::Borrowed("#"),
+                title,
+                id,
+            })
+        }
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) if !is_safe_url(&dest_url) => {
+            context.warn(SsgError::UnsafeUrl(dest_url.to_string()));
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url: CowStr::Borrowed("#"),
+                title,
+                id,
+            })
+        }
+        event => event,
+    }
+}
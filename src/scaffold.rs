@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use time::Date;
+
+use crate::{templates::BuiltInTemplate, vfs::FileSystem};
+
+/// The `template.html` of a new site, with the macros most sites use.
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title><!-- {TITLE} --></title>
+    <link rel="stylesheet" href="<!-- {SITE_ROOT} -->assets/style.css">
+    <!-- {HEAD} -->
+</head>
+<body>
+    <header>
+        <a href="<!-- {SITE_ROOT} -->"><!-- {SITE_TITLE} --></a>
+        <!-- {BREADCRUMBS} -->
+    </header>
+    <nav>
+        <!-- {TABLE_OF_CONTENTS} -->
+    </nav>
+    <main>
+        <!-- {CONTENT} -->
+        <!-- {PREV_NEXT} -->
+    </main>
+    <footer>
+        <p>Last updated <!-- {LAST_UPDATED} --></p>
+        <!-- {FOOTER} -->
+    </footer>
+</body>
+</html>
+"#;
+
+/// The stylesheet of a new site, in its assets folder.
+const STYLESHEET: &str = "body {
+    max-width: 48rem;
+    margin: 0 auto;
+    padding: 1rem;
+    font-family: system-ui, sans-serif;
+    line-height: 1.6;
+}
+
+img {
+    max-width: 100%;
+}
+";
+
+/// Creates a site in `directory` (which must be empty or not exist yet): an `ssg.toml` with its
+/// title (from the directory's name), output path, and `template`, an index page, a
+/// `template.html` (unless a built in `template` is used), an `assets` folder with a
+/// stylesheet, and a `.gitignore` for the output directory. Returns the files written.
+pub fn new_site(
+    fs: &dyn FileSystem,
+    directory: &Path,
+    template: Option<BuiltInTemplate>,
+) -> anyhow::Result<Vec<PathBuf>> {
+    if fs.is_file(directory)
+        || (fs.is_dir(directory) && fs.walk(directory).into_iter().flatten().nth(1).is_some())
+    {
+        return Err(anyhow::anyhow!(
+            "{} isn't empty, create the site in a new directory",
+            directory.display()
+        ));
+    }
+    let title = directory
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "My Site".to_string());
+    let mut config = format!(
+        "title = {}\noutput_path = \"output\"\n",
+        toml::Value::from(title.as_str())
+    );
+    let builtin = template
+        .as_ref()
+        .and_then(|template| template.to_possible_value());
+    if let Some(template) = &builtin {
+        config.push_str(&format!("template = \"{}\"\n", template.get_name()));
+    }
+    config.push_str(
+        "\n# The language of every page\n# lang = \"en\"\n\n# Write a sitemap.xml, with absolute urls\n# [sitemap]\n# base_url = \"https://example.com/\"\n",
+    );
+    let mut files = vec![
+        ("ssg.toml", config),
+        (
+            "index.dj",
+            format!(
+                "# {}\n\nWelcome! Edit `index.dj` to change this page, and add more `.dj` or `.md` pages beside it, or in folders. Each page links to the others by their source files, like [this one](index.dj).\n\nFiles in `assets` are copied as they are.\n",
+                title
+            ),
+        ),
+        ("assets/style.css", STYLESHEET.to_string()),
+        (".gitignore", "/output/\n".to_string()),
+    ];
+    if builtin.is_none() {
+        files.push(("template.html", TEMPLATE.to_string()));
+    }
+    let mut written = Vec::new();
+    for (name, contents) in files {
+        let path = directory.join(name);
+        fs.create_dir_all(path.parent().unwrap())?;
+        fs.write(&path, contents.as_bytes())?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Creates a page at `path` (with a `.dj` extension if it has none), and any directories it's
+/// in, with front matter for a draft written on `today`. Its title is its file name, without a
+/// leading date (as in `2024-05-01-hello-world.dj`) and with spaces for `-` and `_`.
+pub fn new_page(fs: &dyn FileSystem, path: &Path, today: Date) -> anyhow::Result<PathBuf> {
+    let path = match path.extension() {
+        Some(_) => path.to_path_buf(),
+        None => path.with_extension("dj"),
+    };
+    if fs.exists(&path) {
+        return Err(anyhow::anyhow!("{} already exists", path.display()));
+    }
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = match (stem.get(..10), stem.get(10..11), stem.get(11..)) {
+        (Some(date), Some("-"), Some(name)) if date.parse::<toml::value::Datetime>().is_ok() => {
+            name
+        }
+        _ => &stem,
+    };
+    let name = name.replace(['-', '_'], " ");
+    let mut chars = name.trim().chars();
+    let title = chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+        .unwrap_or_default();
+    let page = format!(
+        "+++\ntitle = {}\ndate = {}-{:02}-{:02}\ndraft = true\n+++\n\n",
+        toml::Value::from(title.as_str()),
+        today.year(),
+        u8::from(today.month()),
+        today.day()
+    );
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs.create_dir_all(parent)?;
+    }
+    fs.write(&path, page.as_bytes())?;
+    Ok(path)
+}
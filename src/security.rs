@@ -0,0 +1,310 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256};
+
+use crate::{config::SecurityConfig, links, utils, SiteContext};
+
+/// The headers file for hosts like Netlify and Cloudflare Pages, relative to the output
+/// directory.
+pub const HEADERS_FILE: &str = "_headers";
+
+const REFERRER_POLICY: &str = "strict-origin-when-cross-origin";
+
+/// The sources allowed by each directive of a Content-Security-Policy.
+#[derive(Default)]
+struct Policy {
+    directives: BTreeMap<String, BTreeSet<String>>,
+    /// Elements have `style` attributes, so inline styles can't be allowed by their hashes
+    style_attributes: bool,
+}
+
+impl Policy {
+    fn allow(&mut self, directive: &str, source: impl Into<String>) {
+        self.directives
+            .entry(directive.to_string())
+            .or_default()
+            .insert(source.into());
+    }
+
+    /// Allows the origin of `url` for `directive`. Relative urls are already allowed by `'self'`.
+    fn allow_url(&mut self, directive: &str, url: &str) {
+        if let Some(source) = source(url) {
+            self.allow(directive, source);
+        }
+    }
+
+    /// Adds what `html` loads and runs to the policy.
+    fn scan(&mut self, html: &str) {
+        let mut rest = html;
+        while let Some(start) = rest.find('<') {
+            let tag = &rest[start..];
+            let Some(end) = links::tag_end(tag) else {
+                rest = &tag[1..];
+                continue;
+            };
+            let (tag, after) = tag.split_at(end);
+            rest = after;
+            let name = tag[1..]
+                .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            let attribute = |name: &str| links::attribute(tag, name).map(utils::unescape_html);
+            for handler in event_handlers(tag) {
+                self.allow("script-src", "'unsafe-hashes'");
+                self.allow("script-src", hash(&utils::unescape_html(handler)));
+            }
+            self.style_attributes |= attribute("style").is_some();
+            match name.as_str() {
+                "script" | "style" => {
+                    let close = format!("</{}", name);
+                    let content_end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+                    let content = &rest[..content_end];
+                    rest = &rest[content_end..];
+                    if name == "style" {
+                        if !content.trim().is_empty() {
+                            self.allow("style-src", hash(content));
+                        }
+                    } else if let Some(src) = attribute("src") {
+                        self.allow_url("script-src", &src);
+                    } else if is_javascript(attribute("type").as_deref()) && !content.is_empty() {
+                        self.allow("script-src", hash(content));
+                    }
+                }
+                "link" => {
+                    let (Some(rel), Some(href)) = (attribute("rel"), attribute("href")) else {
+                        continue;
+                    };
+                    for rel in rel.split_whitespace() {
+                        match rel.to_ascii_lowercase().as_str() {
+                            "stylesheet" => self.allow_url("style-src", &href),
+                            "icon" | "apple-touch-icon" => self.allow_url("img-src", &href),
+                            "manifest" => self.allow_url("manifest-src", &href),
+                            _ => {}
+                        }
+                    }
+                }
+                "img" | "video" | "audio" | "source" | "track" | "iframe" | "embed" | "object" => {
+                    let directive = match name.as_str() {
+                        "img" => "img-src",
+                        "iframe" => "frame-src",
+                        "embed" | "object" => "object-src",
+                        // A `<source>` of a `<picture>` is an image
+                        "source" if attribute("srcset").is_some() => "img-src",
+                        _ => "media-src",
+                    };
+                    for url in attribute("src").into_iter().chain(attribute("data")) {
+                        self.allow_url(directive, &url);
+                    }
+                    if let Some(poster) = attribute("poster") {
+                        self.allow_url("img-src", &poster);
+                    }
+                    if let Some(srcset) = attribute("srcset") {
+                        for candidate in srcset.split(',') {
+                            let url = candidate.split_whitespace().next().unwrap_or_default();
+                            self.allow_url(directive, url);
+                        }
+                    }
+                }
+                "form" => {
+                    if let Some(action) = attribute("action") {
+                        self.allow_url("form-action", &action);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The policy as the value of a `Content-Security-Policy`, with `default-src` first.
+    fn to_header(&self) -> String {
+        let mut directives = Vec::new();
+        for (name, sources) in self
+            .directives
+            .iter()
+            .filter(|(name, _)| *name == "default-src")
+            .chain(
+                self.directives
+                    .iter()
+                    .filter(|(name, _)| *name != "default-src"),
+            )
+        {
+            let sources: Vec<&str> = sources
+                .iter()
+                .map(String::as_str)
+                .filter(|source| {
+                    // Hashes turn `'unsafe-inline'` off, so they're left out when it's needed
+                    !(name == "style-src" && self.style_attributes && source.starts_with("'sha"))
+                })
+                .collect();
+            directives.push(format!("{} {}", name, sources.join(" ")));
+        }
+        directives.join("; ")
+    }
+}
+
+/// The values of the `on...` event handler attributes of the start tag `tag`.
+fn event_handlers(tag: &str) -> impl Iterator<Item = &str> {
+    tag.char_indices()
+        .filter(|(_, c)| c.is_whitespace())
+        .filter_map(move |(i, _)| {
+            let name: String = tag[i + 1..]
+                .chars()
+                .take_while(|c| c.is_ascii_alphabetic())
+                .collect();
+            let is_handler = name.len() > 2 && name[..2].eq_ignore_ascii_case("on");
+            is_handler
+                .then(|| links::attribute(&tag[i..], &name))
+                .flatten()
+        })
+}
+
+/// Whether a `<script>` of `type` runs, rather than holding data such as json.
+fn is_javascript(script_type: Option<&str>) -> bool {
+    match script_type.map(|script_type| script_type.trim().to_ascii_lowercase()) {
+        None => true,
+        Some(script_type) => {
+            script_type.is_empty() || script_type == "module" || script_type.ends_with("javascript")
+        }
+    }
+}
+
+/// The `'sha256-...'` source of an inline script, style, or event handler.
+fn hash(content: &str) -> String {
+    format!(
+        "'sha256-{}'",
+        STANDARD.encode(Sha256::digest(content.as_bytes()))
+    )
+}
+
+/// The source that allows `url`: the origin of an absolute url, the host of one without a
+/// scheme, or the scheme of a `data:` or `blob:` url.
+fn source(url: &str) -> Option<String> {
+    let url = url.trim();
+    if let Some(rest) = url.strip_prefix("//") {
+        return rest.split(['/', '?', '#']).next().map(str::to_string);
+    }
+    let (scheme, rest) = url.split_once(':')?;
+    if scheme.is_empty()
+        || !scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+')
+    {
+        return None;
+    }
+    match scheme.to_ascii_lowercase().as_str() {
+        "http" | "https" | "ws" | "wss" => {
+            let host = rest.trim_start_matches('/').split(['/', '?', '#']).next()?;
+            Some(format!("{}://{}", scheme.to_ascii_lowercase(), host))
+        }
+        "data" | "blob" => Some(format!("{}:", scheme.to_ascii_lowercase())),
+        _ => None,
+    }
+}
+
+/// The policy of `pages`: the defaults, what the pages use, and the sources in `config`.
+fn policy<'a>(
+    pages: impl IntoIterator<Item = &'a str>,
+    config: &SecurityConfig,
+    context: &SiteContext,
+    headers: bool,
+) -> Policy {
+    let mut policy = Policy::default();
+    for directive in [
+        "default-src",
+        "script-src",
+        "style-src",
+        "img-src",
+        "font-src",
+        "connect-src",
+        "base-uri",
+        "form-action",
+    ] {
+        policy.allow(directive, "'self'");
+    }
+    policy.allow("object-src", "'none'");
+    if headers {
+        // Ignored in a `<meta>` tag
+        policy.allow("frame-ancestors", "'self'");
+    }
+    if let Some(beacon) = &context.config.beacon {
+        policy.allow_url("connect-src", &beacon.endpoint);
+    }
+    for page in pages {
+        policy.scan(page);
+    }
+    if policy.style_attributes {
+        policy.allow("style-src", "'unsafe-inline'");
+    }
+    for (directive, sources) in &config.sources {
+        for source in sources {
+            policy.allow(directive, source.clone());
+        }
+    }
+    // Allowing sources turns `'none'` off
+    for sources in policy.directives.values_mut() {
+        if sources.len() > 1 {
+            sources.remove("'none'");
+        }
+    }
+    policy
+}
+
+/// Adds a `Content-Security-Policy` for what `html` uses, and a referrer policy, to the start
+/// of its `<head>` as `<meta>` tags.
+pub fn add_meta(html: &str, config: &SecurityConfig, context: &SiteContext) -> String {
+    let mut searched = 0;
+    let head_end = loop {
+        let Some(found) = html[searched..].find("<head") else {
+            return html.to_string();
+        };
+        let start = searched + found;
+        searched = start + 5;
+        if html[searched..].starts_with(|c: char| c == '>' || c.is_whitespace()) {
+            match links::tag_end(&html[start..]) {
+                Some(end) => break start + end,
+                None => return html.to_string(),
+            }
+        }
+    };
+    let policy = policy([html], config, context, false);
+    format!(
+        "{}\n<meta http-equiv=\"Content-Security-Policy\" content=\"{}\">\n<meta name=\"referrer\" content=\"{}\">{}",
+        &html[..head_end],
+        utils::escape_html(&policy.to_header()),
+        REFERRER_POLICY,
+        &html[head_end..]
+    )
+}
+
+/// Writes a `_headers` file to `output_path`, with a `Content-Security-Policy` for everything
+/// the site's pages use and other recommended security headers. A headers file of the site's
+/// own is left as it is.
+pub fn generate_headers(
+    context: &SiteContext,
+    output_path: &Path,
+    config: &SecurityConfig,
+) -> anyhow::Result<()> {
+    if context.fs.is_file(&context.target_path.join(HEADERS_FILE)) {
+        log::debug!("The site has its own headers file, leaving it as it is");
+        return Ok(());
+    }
+    let pages = context.report.lock().unwrap().pages.clone();
+    let pages = pages
+        .iter()
+        .map(|page| context.fs.read_to_string(page))
+        .collect::<Result<Vec<_>, _>>()?;
+    let policy = policy(pages.iter().map(String::as_str), config, context, true);
+    let headers = format!(
+        "/*\n  Content-Security-Policy: {}\n  X-Content-Type-Options: nosniff\n  Referrer-Policy: {}\n  X-Frame-Options: SAMEORIGIN\n",
+        policy.to_header(),
+        REFERRER_POLICY
+    );
+    log::debug!("Writing security headers");
+    context.write_output(&output_path.join(HEADERS_FILE), headers.as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,267 @@
+use std::{borrow::Cow, path::Path};
+
+use qrcode::{render::svg, QrCode};
+use serde::Deserialize;
+
+use crate::{errors::SsgError, links, metadata::Date, openapi, sandbox, utils, SiteContext};
+
+/// Expands each shortcode in `input` (the page at `path`) into raw html for the page's markup:
+///
+/// - `{{qr https://example.com}}`: an inline svg QR code of the text after `qr`
+/// - `{{timeline history.toml}}`: a timeline of the events in a toml file (relative to the
+///   page), or of the events written after `timeline` (see `timeline_html`)
+/// - `{{openapi openapi.yaml}}`: an API reference for an OpenAPI spec (relative to the page)
+/// - `{{svg icon.svg}}`: the svg file (relative to the page) placed inline (see `svg_html`)
+///
+/// Anything else in `{{ }}`, and anything in code blocks and code spans, is left as it is.
+pub fn expand_shortcodes<'a>(
+    input: &'a str,
+    path: &Path,
+    is_markdown: bool,
+    context: &SiteContext,
+) -> Cow<'a, str> {
+    if !input.contains("{{") {
+        return Cow::Borrowed(input);
+    }
+    let code = utils::code_ranges(input);
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = utils::find_braces(input, rest, &code) {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let shortcode = &rest[start..start + end + 4];
+        let html = match after[..end].trim().split_once(char::is_whitespace) {
+            Some(("qr", text)) => Some(
+                qr_svg(text.trim().trim_matches('"'))
+                    .map_err(|e| format!("a QR code that can't be generated: {}", e)),
+            ),
+            Some(("timeline", events)) => Some(
+                timeline_html(events.trim(), path, context)
+                    .map_err(|e| format!("a timeline that can't be rendered: {}", e)),
+            ),
+            Some(("openapi", file)) => Some(
+                openapi_html(file.trim(), path, context)
+                    .map_err(|e| format!("an API reference that can't be rendered: {}", e)),
+            ),
+            Some(("svg", args)) => Some(
+                svg_html(args.trim(), path, context)
+                    .map_err(|e| format!("an svg that can't be inlined: {}", e)),
+            ),
+            _ => None,
+        };
+        match html {
+            Some(Ok(html)) if is_markdown => output.push_str(&html),
+            Some(Ok(html)) => {
+                // Raw inlines are delimited by more backticks than they contain in a row
+                let longest = html
+                    .split(|c| c != '`')
+                    .map(str::len)
+                    .max()
+                    .unwrap_or_default();
+                let fence = "`".repeat(longest + 1);
+                output.push_str(&format!("{}{}{}{{=html}}", fence, html, fence))
+            }
+            Some(Err(problem)) => {
+                context.warn(format!("{:?} has {}", path, problem));
+                output.push_str(shortcode);
+            }
+            None => output.push_str(shortcode),
+        }
+        rest = &after[end + 2..];
+    }
+    output.push_str(rest);
+    Cow::Owned(output)
+}
+
+/// Renders `text` as a QR code, in an svg that can be placed inline in a page.
+pub fn qr_svg(text: &str) -> anyhow::Result<String> {
+    let svg = QrCode::new(text.as_bytes())?
+        .render::<svg::Color>()
+        .module_dimensions(4, 4)
+        .build();
+    let svg = svg
+        .strip_prefix(r#"<?xml version="1.0" standalone="yes"?>"#)
+        .unwrap_or(&svg);
+    Ok(svg.replacen(
+        "<svg ",
+        &format!(
+            "<svg class=\"qr-code\" role=\"img\" aria-label=\"QR code: {}\" ",
+            utils::escape_html(text)
+        ),
+        1,
+    ))
+}
+
+/// The events of a timeline shortcode.
+#[derive(Debug, Deserialize)]
+struct Timeline {
+    #[serde(rename = "event")]
+    events: Vec<TimelineEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TimelineEvent {
+    date: Date,
+    title: String,
+    description: Option<String>,
+}
+
+/// Renders a timeline, as a list of events in the order they're given. `events` is either a
+/// toml file relative to the page at `path`, or toml itself, with a table per event:
+///
+/// ```toml
+/// [[event]]
+/// date = 2019-03-01
+/// title = "Founded"
+/// description = "Started in a garage."
+/// ```
+pub fn timeline_html(events: &str, path: &Path, context: &SiteContext) -> anyhow::Result<String> {
+    let is_file = !events.contains(['\n', '=']);
+    let events = if is_file {
+        Cow::Owned(read_data_file(events, path, context)?)
+    } else {
+        Cow::Borrowed(events)
+    };
+    let timeline: Timeline = toml::from_str(&events)?;
+    let mut html = "<ol class=\"timeline\">".to_string();
+    for event in timeline.events {
+        html.push_str(&format!(
+            "<li class=\"timeline-event\"><time>{}</time> <strong class=\"timeline-title\">{}</strong>",
+            utils::escape_html(&event.date.to_string()),
+            utils::escape_html(&event.title)
+        ));
+        if let Some(description) = event.description {
+            html.push_str(&format!(
+                " <span class=\"timeline-description\">{}</span>",
+                utils::escape_html(&description)
+            ));
+        }
+        html.push_str("</li>");
+    }
+    html.push_str("</ol>");
+    Ok(html)
+}
+
+/// Renders an API reference for the OpenAPI spec in `file`, relative to the page at `path`.
+pub fn openapi_html(file: &str, path: &Path, context: &SiteContext) -> anyhow::Result<String> {
+    let spec = openapi::parse_spec(&read_data_file(file, path, context)?, file)?;
+    Ok(openapi::reference_html(
+        &spec,
+        &context.config.slugs.clone().unwrap_or_default(),
+    ))
+}
+
+/// Places the svg file named first in `args` (relative to the page at `path`) inline, so css can
+/// style it. It keeps its own `id` and `class`, and the rest of `args` can set them:
+///
+/// ```text
+/// {{svg icons/logo.svg id="logo" class="icon" title="Simple SSG"}}
+/// ```
+///
+/// `class` is added to the svg's classes, and `title` becomes its accessible name.
+pub fn svg_html(args: &str, path: &Path, context: &SiteContext) -> anyhow::Result<String> {
+    let mut args = split_args(args).into_iter();
+    let file = args.next().unwrap_or_default();
+    let svg = read_data_file(file, path, context)?;
+    let start = svg
+        .find("<svg")
+        .filter(|start| svg[start + 4..].starts_with(|c: char| c.is_whitespace() || c == '>'))
+        .ok_or_else(|| anyhow::anyhow!("{} has no <svg> element", file))?;
+    let svg = &svg[start..];
+    let tag_end =
+        links::tag_end(svg).ok_or_else(|| anyhow::anyhow!("{} has no <svg> element", file))?;
+    let (mut tag, content) = (svg[..tag_end].to_string(), &svg[tag_end..]);
+    let content = match content.rfind("</svg>") {
+        Some(end) => &content[..end + "</svg>".len()],
+        None => content,
+    };
+    let mut title = None;
+    for arg in args {
+        let Some((name, value)) = arg.split_once('=') else {
+            return Err(anyhow::anyhow!(
+                "{:?} isn't a `name=\"value\"` argument",
+                arg
+            ));
+        };
+        let value = utils::escape_html(value.trim_matches('"'));
+        match name {
+            "class" => tag = links::add_class(&tag, &value),
+            "id" => tag = set_attribute(&tag, "id", &value),
+            "title" => title = Some(value),
+            _ => return Err(anyhow::anyhow!("unknown argument {:?}", name)),
+        }
+    }
+    let title = match title {
+        Some(title) => {
+            if links::attribute(&tag, "role").is_none() {
+                tag = set_attribute(&tag, "role", "img");
+            }
+            format!("<title>{}</title>", title)
+        }
+        None => String::new(),
+    };
+    // On one line, so it stays in the paragraph (or html block) it's in
+    let html = format!("{}{}{}", tag, title, content);
+    Ok(html
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+/// Sets the attribute `name` of the start tag `tag` to `value`, which is already escaped.
+fn set_attribute(tag: &str, name: &str, value: &str) -> String {
+    match links::attribute_range(tag, name) {
+        Some(range) => format!("{}{}{}", &tag[..range.start], value, &tag[range.end..]),
+        None => {
+            let tag_end = tag.trim_end_matches('>').trim_end_matches('/').len();
+            format!(
+                "{} {}=\"{}\"{}",
+                tag[..tag_end].trim_end(),
+                name,
+                value,
+                &tag[tag_end..]
+            )
+        }
+    }
+}
+
+/// Splits the arguments of a shortcode at whitespace outside of double quotes.
+fn split_args(args: &str) -> Vec<&str> {
+    let mut split = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    for (i, c) in args.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if let Some(start) = start.take() {
+                    split.push(&args[start..i]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(i);
+    }
+    if let Some(start) = start {
+        split.push(&args[start..]);
+    }
+    split
+}
+
+/// Reads the file `name` used by a shortcode, relative to the page at `path`. The file has to be
+/// inside the site.
+fn read_data_file(name: &str, path: &Path, context: &SiteContext) -> anyhow::Result<String> {
+    let file = path.parent().unwrap().join(name.trim_matches('"'));
+    if !utils::is_within(context.target_path, &file)
+        || (context.sandbox && !sandbox::is_inside(context.fs, context.target_path, &file))
+    {
+        return Err(SsgError::OutsideTarget(file).into());
+    }
+    Ok(context.fs.read_to_string(&file)?)
+}
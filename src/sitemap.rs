@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use crate::{config::SitemapConfig, metadata::Date, utils, FirstPassResult, SiteContext};
+
+/// The most urls allowed in one sitemap file by the sitemap protocol.
+pub const MAX_SITEMAP_URLS: usize = 50_000;
+
+/// Writes a sitemap of the generated pages to `output_path/sitemap.xml`, with each page's
+/// `<lastmod>` from its front matter `date`, or else when its source was last modified.
+///
+/// When there are more than `max_urls` pages, they are split across `sitemap-1.xml`,
+/// `sitemap-2.xml`, ... and `sitemap.xml` is written as a sitemap index of them.
+pub fn generate_sitemap(
+    context: &SiteContext,
+    results: &[FirstPassResult],
+    output_path: &Path,
+    config: &SitemapConfig,
+    base_url: &str,
+) -> anyhow::Result<()> {
+    let max_urls = config.max_urls.clamp(1, MAX_SITEMAP_URLS);
+    let base_url = base_url.trim_end_matches('/');
+    let pages = || {
+        results.iter().filter_map(|result| match result {
+            FirstPassResult::HtmlOutput {
+                relative_path,
+                unlisted: false,
+                date,
+                layout,
+                ..
+            } => Some((relative_path, date, layout)),
+            _ => None,
+        })
+    };
+    let sitemaps = pages().count().div_ceil(max_urls);
+    let sitemap_path = output_path.join("sitemap.xml");
+    // Each sitemap is written as soon as it's filled, so only one is held at a time
+    let mut pages = pages();
+    let mut index = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n".to_string();
+    for i in 0..sitemaps {
+        let mut urlset = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n".to_string();
+        for (relative_path, date, layout) in pages.by_ref().take(max_urls) {
+            // Pages are linked as they are everywhere else, such as `about/` with pretty urls
+            let url = utils::page_link(1, None, relative_path, context.config.pretty_urls);
+            let url = format!(
+                "{}/{}",
+                base_url,
+                url.trim_start_matches("./").replace('\\', "/")
+            );
+            let lastmod = match lastmod(context, date.as_ref(), &layout.source) {
+                Some(lastmod) => format!("<lastmod>{}</lastmod>", lastmod),
+                None => String::new(),
+            };
+            urlset.push_str(&format!(
+                "<url><loc>{}</loc>{}</url>\n",
+                utils::escape_html(&url),
+                lastmod
+            ));
+        }
+        urlset.push_str("</urlset>\n");
+        if sitemaps == 1 {
+            log::debug!("Writing sitemap {:?}", &sitemap_path);
+            context.write_output(&sitemap_path, urlset.as_bytes())?;
+            return Ok(());
+        }
+        let name = format!("sitemap-{}.xml", i + 1);
+        log::debug!("Writing sitemap {:?}", &name);
+        context.write_output(&output_path.join(&name), urlset.as_bytes())?;
+        let url = format!("{}/{}", base_url, name);
+        index.push_str(&format!(
+            "<sitemap><loc>{}</loc></sitemap>\n",
+            utils::escape_html(&url)
+        ));
+    }
+    if sitemaps > 1 {
+        index.push_str("</sitemapindex>\n");
+        log::debug!("Writing sitemap index {:?}", &sitemap_path);
+        context.write_output(&sitemap_path, index.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// The `<lastmod>` of a page: its front matter `date`, if it's a full date, or else the day its
+/// `source` was last modified.
+fn lastmod(context: &SiteContext, date: Option<&Date>, source: &Path) -> Option<String> {
+    if let Some(date) = date.and_then(Date::calendar_date) {
+        return Some(date.to_string());
+    }
+    let modified = context.fs.modified(source).ok()?;
+    utils::format_date(modified.into(), None).ok()
+}
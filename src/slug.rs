@@ -0,0 +1,326 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+
+use jotdown::{Container, Event};
+
+use crate::{config::SlugConfig, links};
+
+/// Turns `text` into a slug following `config`, e.g. `Café au Lait!` into `cafe-au-lait`. Runs
+/// of anything besides letters and digits become a single separator. Returns an empty string if
+/// `text` has no (transliterable) letters or digits.
+pub fn slugify(text: &str, config: &SlugConfig) -> String {
+    let mut words = vec![String::new()];
+    for c in text.chars() {
+        if !c.is_alphanumeric() {
+            if !words.last().unwrap().is_empty() {
+                words.push(String::new());
+            }
+            continue;
+        }
+        let word = words.last_mut().unwrap();
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        match (c.is_ascii() || !config.transliterate, transliterate(lower)) {
+            (true, _) if config.lowercase => word.extend(c.to_lowercase()),
+            (true, _) => word.push(c),
+            (false, Some(ascii)) if config.lowercase || !c.is_uppercase() => word.push_str(ascii),
+            (false, Some(ascii)) => word.push_str(&ascii.to_uppercase()),
+            // Letters without an ascii equivalent are dropped
+            (false, None) => {}
+        }
+    }
+    let slug = words
+        .into_iter()
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(&config.separator);
+    match config.max_length {
+        Some(max_length) if slug.chars().count() > max_length => slug
+            .chars()
+            .take(max_length)
+            .collect::<String>()
+            .trim_end_matches(config.separator.as_str())
+            .to_string(),
+        _ => slug,
+    }
+}
+
+/// File names longer than this (in characters) are too long for some hosts and tools.
+const MAX_FILE_NAME_LENGTH: usize = 100;
+
+/// Characters that have a meaning in urls, or must be percent-encoded in them.
+const RESERVED_CHARACTERS: &str = "?#%&+:;[]<>\"\\^`{}|";
+
+/// What makes the file name `name` a problem in a url: emoji and other symbols, whitespace,
+/// reserved characters, or being very long.
+pub fn url_problems(name: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    if name
+        .chars()
+        .any(|c| !c.is_ascii() && !c.is_alphanumeric() && !is_combining_mark(c))
+    {
+        problems.push("emoji or other symbols".to_string());
+    }
+    if name.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        problems.push("whitespace".to_string());
+    }
+    let mut reserved = name
+        .chars()
+        .filter(|c| RESERVED_CHARACTERS.contains(*c))
+        .collect::<Vec<_>>();
+    reserved.dedup();
+    if !reserved.is_empty() {
+        problems.push(format!(
+            "the reserved characters {}",
+            reserved.into_iter().collect::<String>()
+        ));
+    }
+    let length = name.chars().count();
+    if length > MAX_FILE_NAME_LENGTH {
+        problems.push(format!(
+            "{} characters, more than {}",
+            length, MAX_FILE_NAME_LENGTH
+        ));
+    }
+    problems
+}
+
+/// Whether `c` is a combining mark, as in a decomposed `é`.
+fn is_combining_mark(c: char) -> bool {
+    matches!(c, '\u{300}'..='\u{36f}')
+}
+
+/// The path of the html generated from the page at `path`: the page's file name and the names
+/// of its directories slugged with `config` (e.g. `My Docs/Getting Started.md` to
+/// `my-docs/getting-started.html`), or just with an `.html` extension without it.
+pub fn html_path(path: &Path, config: Option<&SlugConfig>) -> PathBuf {
+    let path = file_path(path, config);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match config.map(|config| slugify(&stem, config)) {
+        Some(slug) if !slug.is_empty() => path.with_file_name(slug + ".html"),
+        _ => path.with_extension("html"),
+    }
+}
+
+/// `path` with the name of each of its directories (and its own name, so it should be a
+/// directory) slugged with `config`, or as it is without it. Names without anything to slug
+/// (such as `..`, or names of only symbols) are left as they are.
+pub fn dir_path(path: &Path, config: Option<&SlugConfig>) -> PathBuf {
+    let Some(config) = config else {
+        return path.to_path_buf();
+    };
+    path.components()
+        .map(|component| match component {
+            Component::Normal(name) => match slugify(&name.to_string_lossy(), config) {
+                slug if slug.is_empty() => name.to_os_string(),
+                slug => slug.into(),
+            },
+            component => component.as_os_str().to_os_string(),
+        })
+        .collect()
+}
+
+/// The path of a file at `path` in the output directory: in its directory's slugged path (see
+/// `dir_path`), under its own name.
+pub fn file_path(path: &Path, config: Option<&SlugConfig>) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => dir_path(parent, config).join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// The start of the `¶` link appended to headings by `add_heading_anchors`.
+pub const HEADING_ANCHOR: &str = "<a class=\"heading-anchor\"";
+
+/// Appends a `¶` link to its anchor to each heading in `html` that has an id, or that starts a
+/// `<section>` with one (as djot renders them).
+pub fn add_heading_anchors(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    let mut section_id = None;
+    while let Some(start) = rest.find('<') {
+        let tag = &rest[start..];
+        let Some(end) = links::tag_end(tag) else {
+            break;
+        };
+        if !rest[..start].trim().is_empty() {
+            section_id = None;
+        }
+        output.push_str(&rest[..start + end]);
+        let (tag, after) = tag.split_at(end);
+        rest = after;
+        let name = tag[1..]
+            .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let level = match name.as_str() {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => &name[1..],
+            "section" => {
+                section_id = links::attribute(tag, "id");
+                continue;
+            }
+            _ => {
+                section_id = None;
+                continue;
+            }
+        };
+        let id = links::attribute(tag, "id").or(section_id.take());
+        let close = format!("</h{}>", level);
+        let (Some(id), Some(content_end)) = (id.filter(|id| !id.is_empty()), rest.find(&close))
+        else {
+            continue;
+        };
+        output.push_str(&rest[..content_end]);
+        output.push_str(&format!(
+            " {} href=\"#{}\" aria-label=\"Link to this section\">¶</a>",
+            HEADING_ANCHOR, id
+        ));
+        rest = &rest[content_end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Gives the headings of a page unique slugs, numbering repeated ones (e.g. `usage-1`).
+struct HeadingSlugs<'a> {
+    config: &'a SlugConfig,
+    seen: HashMap<String, usize>,
+}
+
+impl<'a> HeadingSlugs<'a> {
+    fn new(config: &'a SlugConfig) -> Self {
+        Self {
+            config,
+            seen: HashMap::new(),
+        }
+    }
+
+    fn slug(&mut self, text: &str) -> String {
+        let mut slug = slugify(text, self.config);
+        if slug.is_empty() {
+            slug = "section".to_string();
+        }
+        let count = self.seen.entry(slug.clone()).or_insert(0);
+        *count += 1;
+        match *count {
+            1 => slug,
+            n => format!("{}{}{}", slug, self.config.separator, n - 1),
+        }
+    }
+}
+
+/// Replaces the ids djot generated for headings (and their sections) with slugs of the heading
+/// text, including in links to them. Ids given explicitly with `{#id}` are kept.
+pub fn slug_djot_headings(events: &mut [Event], config: &SlugConfig) {
+    let mut slugs = HeadingSlugs::new(config);
+    let mut ids = HashMap::new();
+    for i in 0..events.len() {
+        let Event::Start(Container::Heading { id, .. }, attributes) = &events[i] else {
+            continue;
+        };
+        let explicit = attributes.get_value("id").is_some()
+            || matches!(
+                i.checked_sub(1).map(|j| &events[j]),
+                Some(Event::Start(Container::Section { .. }, attributes))
+                    if attributes.get_value("id").is_some()
+            );
+        if explicit {
+            continue;
+        }
+        let mut text = String::new();
+        for event in &events[i + 1..] {
+            match event {
+                Event::End(Container::Heading { .. }) => break,
+                Event::Str(s) | Event::Symbol(s) => text.push_str(s),
+                _ => {}
+            }
+        }
+        ids.insert(id.to_string(), slugs.slug(&text));
+    }
+    if ids.is_empty() {
+        return;
+    }
+    let rewrite = |id: &mut Cow<str>| {
+        if let Some(slug) = ids.get(id.as_ref()) {
+            *id = Cow::Owned(slug.clone());
+        }
+    };
+    let rewrite_link = |url: &mut Cow<str>| {
+        if let Some(slug) = url.strip_prefix('#').and_then(|id| ids.get(id)) {
+            *url = Cow::Owned(format!("#{}", slug));
+        }
+    };
+    for event in events.iter_mut() {
+        match event {
+            Event::Start(Container::Section { id }, _)
+            | Event::End(Container::Section { id })
+            | Event::Start(Container::Heading { id, .. }, _)
+            | Event::End(Container::Heading { id, .. }) => rewrite(id),
+            Event::Start(Container::Link(url, _), _) | Event::End(Container::Link(url, _)) => {
+                rewrite_link(url)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Gives each markdown heading without an id a slug of its text.
+pub fn slug_markdown_headings(events: &mut [pulldown_cmark::Event], config: &SlugConfig) {
+    let mut slugs = HeadingSlugs::new(config);
+    for i in 0..events.len() {
+        if !matches!(
+            &events[i],
+            pulldown_cmark::Event::Start(pulldown_cmark::Tag::Heading { id: None, .. })
+        ) {
+            continue;
+        }
+        let mut text = String::new();
+        for event in &events[i + 1..] {
+            match event {
+                pulldown_cmark::Event::End(pulldown_cmark::TagEnd::Heading(_)) => break,
+                pulldown_cmark::Event::Text(s) | pulldown_cmark::Event::Code(s) => text.push_str(s),
+                _ => {}
+            }
+        }
+        if let pulldown_cmark::Event::Start(pulldown_cmark::Tag::Heading { id, .. }) =
+            &mut events[i]
+        {
+            *id = Some(pulldown_cmark::CowStr::Boxed(
+                slugs.slug(&text).into_boxed_str(),
+            ));
+        }
+    }
+}
+
+/// The ascii spelling of a lowercase latin letter with diacritics, if it has one.
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ð' | 'ď' | 'đ' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'œ' => "oe",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ś' | 'ŝ' | 'ş' | 'š' => "s",
+        'ß' => "ss",
+        'ţ' | 'ť' | 'ŧ' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    })
+}
@@ -0,0 +1,95 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use crate::{config, render_site_page, templates::BuiltInTemplate, utils, vfs::FileSystem};
+
+/// A snapshot that doesn't match the html its fixture renders to.
+#[derive(Debug)]
+pub struct Mismatch {
+    pub snapshot: PathBuf,
+    pub detail: String,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.snapshot.display(), self.detail)
+    }
+}
+
+/// Renders every `.dj` and `.md` fixture in `dir` and compares it to the `.html` snapshot
+/// beside it. Fixtures are rendered with `dir`'s `ssg.toml`, and wrapped in `template` (or
+/// `dir`'s `template.html`, if it has one). With `update`, snapshots are written instead of
+/// compared.
+pub fn check_snapshots(
+    fs: &dyn FileSystem,
+    dir: &Path,
+    template: Option<BuiltInTemplate>,
+    update: bool,
+) -> anyhow::Result<Vec<Mismatch>> {
+    let config = config::get_site_config(fs, dir)?;
+    let template = match template {
+        Some(template) => Some(template.get_template(&config.template_options)),
+        None if fs.is_file(&dir.join("template.html")) => {
+            Some(fs.read_to_string(&dir.join("template.html"))?)
+        }
+        None => None,
+    };
+    let mut mismatches = Vec::new();
+    for entry in fs.walk(dir) {
+        let (fixture, depth) = entry?;
+        let is_page = fixture
+            .extension()
+            .is_some_and(|ext| ext == "md" || ext == "dj");
+        if !is_page || depth != 1 {
+            continue;
+        }
+        let input = fs.read_to_string(&fixture)?;
+        let html = utils::wrap_html_content(
+            &render_site_page(&input, &fixture, config.clone(), fs)?,
+            template.as_deref(),
+        )?;
+        let snapshot = fixture.with_extension("html");
+        if update {
+            log::info!("Updating snapshot {:?}", &snapshot);
+            fs.write(&snapshot, html.as_bytes())?;
+            continue;
+        }
+        let Ok(expected) = fs.read_to_string(&snapshot) else {
+            mismatches.push(Mismatch {
+                snapshot,
+                detail: "missing (run with --update to write it)".to_string(),
+            });
+            continue;
+        };
+        // Snapshots may have been checked out with windows line endings
+        if let Some(detail) = first_difference(&expected.replace("\r\n", "\n"), &html) {
+            mismatches.push(Mismatch { snapshot, detail });
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Describes the first line that differs between `expected` and `actual`, if any does.
+fn first_difference(expected: &str, actual: &str) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+    let (mut expected_lines, mut actual_lines) = (expected.lines(), actual.lines());
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(expected), Some(actual)) if expected == actual => line += 1,
+            (None, None) => return Some("line endings differ".to_string()),
+            (expected, actual) => {
+                return Some(format!(
+                    "line {} differs\n  expected: {}\n  rendered: {}",
+                    line,
+                    expected.unwrap_or("<end of snapshot>"),
+                    actual.unwrap_or("<end of page>")
+                ))
+            }
+        }
+    }
+}
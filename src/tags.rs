@@ -0,0 +1,177 @@
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    metadata::Date,
+    pagination::{self, ListingPage},
+    slug, templating, utils, FirstPassResult, SiteContext,
+};
+
+/// The directory of the generated tag pages, at the root of the output directory.
+pub const TAGS_DIR: &str = "tags";
+
+/// A tag, and the listed pages tagged with it.
+pub struct Tag {
+    /// The tag as it's first written in the site's front matter
+    pub name: String,
+    /// The tag's page, relative to the output directory
+    pub path: PathBuf,
+    pub pages: Vec<TaggedPage>,
+}
+
+/// A page listed on a tag's page.
+pub struct TaggedPage {
+    title: String,
+    /// The page, relative to the output directory
+    path: PathBuf,
+    date: Option<Date>,
+}
+
+/// The page of the tag `name`, relative to the output directory, or nothing if it has no
+/// letters or digits to slug.
+pub fn tag_path(name: &str, context: &SiteContext) -> Option<PathBuf> {
+    let slug = slug::slugify(name, &context.config.slugs.clone().unwrap_or_default());
+    (!slug.is_empty()).then(|| Path::new(TAGS_DIR).join(slug + ".html"))
+}
+
+/// The tags of the listed pages, by their slug. Tags that slug the same (such as `Rust` and
+/// `rust`) are the same tag. Each tag's pages are newest first, then in the order of the table
+/// of contents.
+pub fn collect_tags(results: &[FirstPassResult], context: &SiteContext) -> BTreeMap<PathBuf, Tag> {
+    let titles = templating::site_pages(results);
+    let mut tags: BTreeMap<PathBuf, Tag> = BTreeMap::new();
+    for result in results {
+        let FirstPassResult::HtmlOutput {
+            relative_path,
+            unlisted: false,
+            date,
+            layout,
+            ..
+        } = result
+        else {
+            continue;
+        };
+        let path = relative_path.to_string_lossy().replace('\\', "/");
+        let title = titles
+            .iter()
+            .find(|page| page.path == path)
+            .and_then(|page| page.title.clone())
+            .unwrap_or(path);
+        for name in &layout.tags {
+            let Some(tag_path) = tag_path(name, context) else {
+                continue;
+            };
+            let tag = tags.entry(tag_path.clone()).or_insert_with(|| Tag {
+                name: name.trim().to_string(),
+                path: tag_path,
+                pages: Vec::new(),
+            });
+            if !tag.pages.iter().any(|page| page.path == *relative_path) {
+                tag.pages.push(TaggedPage {
+                    title: title.clone(),
+                    path: relative_path.clone(),
+                    date: date.clone(),
+                });
+            }
+        }
+    }
+    for tag in tags.values_mut() {
+        tag.pages
+            .sort_by_key(|page| Reverse(page.date.as_ref().and_then(Date::to_date)));
+    }
+    tags
+}
+
+/// The pages of the index of tags, each linked to its page with how many pages it has, and the
+/// content of each.
+pub fn index_pages(
+    tags: &BTreeMap<PathBuf, Tag>,
+    context: &SiteContext,
+) -> Vec<(ListingPage, String)> {
+    let mut tags = tags.values().collect::<Vec<_>>();
+    tags.sort_by_key(|tag| tag.name.to_lowercase());
+    let pages = pagination::paginate(
+        &Path::new(TAGS_DIR).join("index.html"),
+        tags.len(),
+        context.config.listing_page_size,
+    );
+    let mut contents = Vec::new();
+    for (i, page) in pages.iter().enumerate() {
+        let mut html = "<h1>Tags</h1>\n<ul class=\"tags\">\n".to_string();
+        for tag in &tags[page.entries.clone()] {
+            html.push_str(&format!(
+                "<li><a href=\"{}\" rel=\"tag\">{}</a> <span class=\"tag-count\">{}</span></li>\n",
+                utils::page_url(page.depth, context.web_prefix, &tag.path),
+                utils::escape_html(&tag.name),
+                tag.pages.len()
+            ));
+        }
+        html.push_str("</ul>\n");
+        html.push_str(&pagination::pagination_html(&pages, i, context.web_prefix));
+        contents.push(html);
+    }
+    pages.into_iter().zip(contents).collect()
+}
+
+/// The pages of `tag`, listing the pages tagged with it, and the content of each.
+pub fn tag_pages(tag: &Tag, context: &SiteContext) -> anyhow::Result<Vec<(ListingPage, String)>> {
+    let pages = pagination::paginate(&tag.path, tag.pages.len(), context.config.listing_page_size);
+    let mut contents = Vec::new();
+    for (i, page) in pages.iter().enumerate() {
+        let mut html = format!(
+            "<h1>{}</h1>\n<ul class=\"post-list\">\n",
+            utils::escape_html(&tag_title(tag))
+        );
+        for tagged in &tag.pages[page.entries.clone()] {
+            html.push_str(&format!(
+                "<li><a class=\"post-title\" href=\"{}\">{}</a>",
+                utils::page_url(page.depth, context.web_prefix, &tagged.path),
+                utils::escape_html(&tagged.title)
+            ));
+            if let Some(date) = &tagged.date {
+                html.push_str(&format!(
+                    " <time class=\"post-date\">{}</time>",
+                    utils::escape_html(&date.format(context.config.date_format.as_deref())?)
+                ));
+            }
+            html.push_str("</li>\n");
+        }
+        html.push_str("</ul>\n");
+        html.push_str(&pagination::pagination_html(&pages, i, context.web_prefix));
+        contents.push(html);
+    }
+    Ok(pages.into_iter().zip(contents).collect())
+}
+
+/// The title of the page of `tag`.
+pub fn tag_title(tag: &Tag) -> String {
+    format!("Tagged \u{201c}{}\u{201d}", tag.name)
+}
+
+/// The `<!-- {TAGS} -->` of a page at `depth` with `tags`: each linked to its page, when tag
+/// pages are generated.
+pub fn tags_html(tags: &[String], depth: usize, context: &SiteContext) -> String {
+    if tags.is_empty() {
+        return String::new();
+    }
+    let mut html = "<ul class=\"tags\">".to_string();
+    for name in tags {
+        let tag_path = tag_path(name, context).filter(|_| context.config.tag_pages);
+        match tag_path {
+            Some(tag_path) => html.push_str(&format!(
+                "<li><a href=\"{}\" rel=\"tag\">{}</a></li>",
+                utils::page_url(depth, context.web_prefix, &tag_path),
+                utils::escape_html(name)
+            )),
+            None => html.push_str(&format!(
+                "<li><span class=\"tag\">{}</span></li>",
+                utils::escape_html(name)
+            )),
+        }
+    }
+    html.push_str("</ul>");
+    html
+}
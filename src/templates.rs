@@ -1,23 +1,491 @@
+use std::path::Path;
+
 use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{ColorScheme, TemplateOptions},
+    utils,
+};
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum BuiltInTemplate {
     GithubMarkdown,
+    /// A documentation layout with a sidebar, breadcrumbs, and previous/next links
+    Docs,
+    /// A blog layout for posts and listings of posts
+    Blog,
+    /// Plain html without css or javascript, used for the `--lite` mirror
+    Lite,
     ForceNone,
 }
 
 impl BuiltInTemplate {
-    pub fn get_template(&self) -> String {
+    pub fn get_template(&self, options: &TemplateOptions) -> String {
         match self {
-            Self::ForceNone => {
-                "<!-- {CONTENT} -->".to_string()
+            Self::ForceNone => "<!-- {CONTENT} -->".to_string(),
+            Self::Lite => r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title><!-- {TITLE} --></title>
+</head>
+<body>
+<main>
+<!-- {CONTENT} -->
+</main>
+<hr>
+<nav>
+<!-- {TABLE_OF_CONTENTS} -->
+</nav>
+</body>
+</html>
+"##
+            .to_string(),
+            Self::Docs => {
+                let template = r##"<!DOCTYPE html>
+<html>
+
+<head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title><!-- {TITLE} --></title>
+    <style>
+        /* {COLOR_SCHEME_CSS} */
+
+        body {
+            margin: 0;
+            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", "Noto Sans", Helvetica, Arial, sans-serif;
+            font-size: 16px;
+            line-height: 1.6;
+            color: var(--fgColor-default);
+            background-color: var(--bgColor-default);
+        }
+
+        a {
+            color: var(--fgColor-accent);
+        }
+
+        pre,
+        code {
+            font-family: ui-monospace, SFMono-Regular, SF Mono, Menlo, Consolas, Liberation Mono, monospace;
+            font-size: 85%;
+            background-color: var(--bgColor-muted);
+            border-radius: 6px;
+        }
+
+        code {
+            padding: 0.2em 0.4em;
+        }
+
+        pre {
+            padding: 1rem;
+            overflow: auto;
+        }
+
+        pre code {
+            padding: 0;
+            font-size: 100%;
+        }
+
+        .docs-sidebar {
+            position: fixed;
+            top: 0;
+            bottom: 0;
+            inset-inline-start: 0;
+            box-sizing: border-box;
+            width: 16rem;
+            padding: 1rem;
+            overflow-y: auto;
+            font-size: 14px;
+            background-color: var(--bgColor-muted);
+            border-inline-end: 1px solid var(--borderColor-default);
+        }
+
+        .docs-sidebar ul {
+            margin: 0;
+            padding-inline-start: 1rem;
+            list-style: none;
+        }
+
+        .docs-sidebar>ul {
+            padding-inline-start: 0;
+        }
+
+        .docs-sidebar .active {
+            font-weight: 600;
+            color: var(--fgColor-accent);
+        }
+
+        .docs-main {
+            box-sizing: border-box;
+            max-width: 56rem;
+            margin-inline-start: 16rem;
+            padding: 0 2rem 2rem;
+        }
+
+        .docs-header {
+            display: flex;
+            align-items: center;
+            gap: 1rem;
+            padding: 1rem 0;
+            border-bottom: 1px solid var(--borderColor-default);
+        }
+
+        .breadcrumbs ol {
+            display: flex;
+            flex-wrap: wrap;
+            margin: 0;
+            padding: 0;
+            list-style: none;
+        }
+
+        .breadcrumbs li+li::before {
+            content: "\203A";
+            padding: 0 0.5em;
+            color: var(--fgColor-muted);
+        }
+
+        .prev-next {
+            display: flex;
+            justify-content: space-between;
+            margin-top: 2rem;
+            padding-top: 1rem;
+            border-top: 1px solid var(--borderColor-default);
+        }
+
+        .prev-next .next {
+            margin-inline-start: auto;
+        }
+
+        .menu-toggle,
+        .menu-button {
+            display: none;
+        }
+
+        .theme-toggle,
+        .print-button {
+            display: block;
+            margin-bottom: 1em;
+            color: var(--fgColor-default);
+            background: none;
+            border: 1px solid var(--fgColor-muted);
+            border-radius: 4px;
+            cursor: pointer;
+        }
+
+        @media (max-width: 48rem) {
+            .menu-button {
+                display: block;
+                font-size: 1.5rem;
+                cursor: pointer;
+            }
+
+            .docs-sidebar {
+                z-index: 1;
+                transform: translateX(-100%);
+                transition: transform 0.2s;
+            }
+
+            [dir="rtl"] .docs-sidebar {
+                transform: translateX(100%);
+            }
+
+            .menu-toggle:checked~.docs-sidebar {
+                transform: none;
+            }
+
+            .docs-main {
+                margin-inline-start: 0;
+                padding: 0 1rem 1rem;
+            }
+        }
+
+        /* {OPTIONS_CSS} */
+    </style>
+    <!-- {SCRIPTS} -->
+    <!-- {HEAD} -->
+</head>
+
+<body>
+    <input type="checkbox" id="menu-toggle" class="menu-toggle">
+    <nav class="docs-sidebar">
+        <!-- {LOGO} -->
+        <!-- {THEME_TOGGLE} -->
+        <!-- {PRINT_BUTTON} -->
+        <!-- {TABLE_OF_CONTENTS} -->
+    </nav>
+    <div class="docs-main">
+        <header class="docs-header">
+            <label for="menu-toggle" class="menu-button" aria-label="Menu">&#9776;</label>
+            <!-- {BREADCRUMBS} -->
+        </header>
+        <main>
+            <!-- {CONTENT} -->
+        </main>
+        <footer>
+            <!-- {PREV_NEXT} -->
+            <!-- {FOOTER} -->
+        </footer>
+    </div>
+</body>
+
+</html>
+"##;
+                apply_options(template, options, "body", DARK_COLORS, LIGHT_COLORS)
+            }
+            Self::Blog => {
+                let template = r##"<!DOCTYPE html>
+<html>
+
+<head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title><!-- {TITLE} --></title>
+    <style>
+        /* {COLOR_SCHEME_CSS} */
+
+        body {
+            margin: 0;
+            font-family: Charter, "Bitstream Charter", "Sitka Text", Cambria, Georgia, serif;
+            font-size: 18px;
+            line-height: 1.7;
+            color: var(--fgColor-default);
+            background-color: var(--bgColor-default);
+        }
+
+        a {
+            color: var(--fgColor-accent);
+        }
+
+        img {
+            max-width: 100%;
+        }
+
+        pre,
+        code {
+            font-family: ui-monospace, SFMono-Regular, SF Mono, Menlo, Consolas, Liberation Mono, monospace;
+            font-size: 85%;
+            background-color: var(--bgColor-muted);
+            border-radius: 6px;
+        }
+
+        code {
+            padding: 0.2em 0.4em;
+        }
+
+        pre {
+            padding: 1rem;
+            overflow: auto;
+        }
+
+        pre code {
+            padding: 0;
+            font-size: 100%;
+        }
+
+        blockquote {
+            margin: 0;
+            padding: 0 1em;
+            color: var(--fgColor-muted);
+            border-inline-start: 0.25em solid var(--borderColor-default);
+        }
+
+        .blog {
+            max-width: 42rem;
+            margin: 0 auto;
+            padding: 0 1rem;
+        }
+
+        .blog-header {
+            display: flex;
+            align-items: center;
+            justify-content: space-between;
+            gap: 1rem;
+            padding: 1.5rem 0;
+            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", "Noto Sans", Helvetica, Arial, sans-serif;
+            font-size: 15px;
+            border-bottom: 1px solid var(--borderColor-default);
+        }
+
+        .breadcrumbs ol {
+            display: flex;
+            flex-wrap: wrap;
+            margin: 0;
+            padding: 0;
+            list-style: none;
+        }
+
+        .breadcrumbs li+li::before {
+            content: "/";
+            padding: 0 0.5em;
+            color: var(--fgColor-muted);
+        }
+
+        .post-meta,
+        .post-date,
+        .tags {
+            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", "Noto Sans", Helvetica, Arial, sans-serif;
+            font-size: 14px;
+            color: var(--fgColor-muted);
+        }
+
+        .post-meta {
+            display: flex;
+            flex-wrap: wrap;
+            gap: 0 1em;
+            margin-top: 2rem;
+        }
+
+        .tags {
+            display: flex;
+            flex-wrap: wrap;
+            gap: 0.5em;
+            margin: 0;
+            padding: 0;
+            list-style: none;
+        }
+
+        .tags a,
+        .tag {
+            padding: 0.1em 0.6em;
+            text-decoration: none;
+            background-color: var(--bgColor-muted);
+            border-radius: 1em;
+        }
+
+        .post-list {
+            margin: 0;
+            padding: 0;
+            list-style: none;
+        }
+
+        .post-list>li {
+            padding: 1.25rem 0;
+            border-bottom: 1px solid var(--borderColor-muted);
+        }
+
+        .post-list .post-title {
+            font-size: 1.25em;
+            font-weight: 600;
+            text-decoration: none;
+        }
+
+        .post-list .post-date {
+            display: block;
+        }
+
+        .post-list .post-summary {
+            margin: 0.5em 0 0;
+        }
+
+        .archive h2 {
+            margin-top: 2rem;
+            font-size: 1em;
+            color: var(--fgColor-muted);
+        }
+
+        .prev-next,
+        .pagination {
+            display: flex;
+            justify-content: space-between;
+            gap: 1rem;
+            margin-top: 2rem;
+            padding-top: 1rem;
+            border-top: 1px solid var(--borderColor-default);
+        }
+
+        .prev-next .next,
+        .pagination .next {
+            margin-inline-start: auto;
+        }
+
+        .blog-footer {
+            padding: 2rem 0;
+            font-size: 15px;
+        }
+
+        .blog-footer summary {
+            cursor: pointer;
+        }
+
+        .site-author {
+            display: flex;
+            align-items: center;
+            gap: 0.75rem;
+            margin-top: 1rem;
+        }
+
+        .site-author .u-photo {
+            width: 3rem;
+            height: 3rem;
+            border-radius: 50%;
+        }
+
+        .site-author .p-note {
+            margin: 0;
+            color: var(--fgColor-muted);
+        }
+
+        .theme-toggle,
+        .print-button {
+            color: var(--fgColor-default);
+            background: none;
+            border: 1px solid var(--fgColor-muted);
+            border-radius: 4px;
+            cursor: pointer;
+        }
+
+        /* {OPTIONS_CSS} */
+    </style>
+    <!-- {SCRIPTS} -->
+    <!-- {HEAD} -->
+</head>
+
+<body>
+    <div class="blog">
+        <header class="blog-header">
+            <!-- {LOGO} -->
+            <!-- {BREADCRUMBS} -->
+            <!-- {THEME_TOGGLE} -->
+            <!-- {PRINT_BUTTON} -->
+        </header>
+        <article class="h-entry">
+            <div class="post-meta">
+                <!-- {LAST_UPDATED} -->
+                <!-- {CONTRIBUTORS} -->
+                <!-- {TAGS} -->
+            </div>
+            <!-- {H_ENTRY} -->
+            <div class="e-content">
+                <!-- {CONTENT} -->
+            </div>
+        </article>
+        <footer class="blog-footer">
+            <!-- {PREV_NEXT} -->
+            <details>
+                <summary>All pages</summary>
+                <!-- {TABLE_OF_CONTENTS} -->
+            </details>
+            <!-- {H_CARD} -->
+            <!-- {FOOTER} -->
+        </footer>
+    </div>
+</body>
+
+</html>
+"##;
+                apply_options(template, options, "body", DARK_COLORS, LIGHT_COLORS)
             }
             Self::GithubMarkdown => {
-                r##"
+                let template = r##"
 <!DOCTYPE html>
 <html>
 
 <head>
+    <title><!-- {TITLE} --></title>
     <style>
         .content-body {
             --base-size-4: 0.25rem;
@@ -30,125 +498,7 @@ impl BuiltInTemplate {
 
         }
 
-        @media (prefers-color-scheme: dark) {
-
-            .content-body,
-            [data-theme="dark"] {
-                /*dark*/
-                color-scheme: dark;
-                --focus-outlineColor: #1f6feb;
-                --fgColor-default: #e6edf3;
-                --fgColor-muted: #8d96a0;
-                --fgColor-accent: #4493f8;
-                --fgColor-success: #3fb950;
-                --fgColor-attention: #d29922;
-                --fgColor-danger: #f85149;
-                --fgColor-done: #ab7df8;
-                --bgColor-default: #0d1117;
-                --bgColor-muted: #161b22;
-                --bgColor-muted-2: #222222;
-                --bgColor-neutral-muted: #6e768166;
-                --bgColor-attention-muted: #bb800926;
-                --borderColor-default: #30363d;
-                --borderColor-muted: #30363db3;
-                --borderColor-neutral-muted: #6e768166;
-                --borderColor-accent-emphasis: #1f6feb;
-                --borderColor-success-emphasis: #238636;
-                --borderColor-attention-emphasis: #9e6a03;
-                --borderColor-danger-emphasis: #da3633;
-                --borderColor-done-emphasis: #8957e5;
-                --color-prettylights-syntax-comment: #8b949e;
-                --color-prettylights-syntax-constant: #79c0ff;
-                --color-prettylights-syntax-constant-other-reference-link: #a5d6ff;
-                --color-prettylights-syntax-entity: #d2a8ff;
-                --color-prettylights-syntax-storage-modifier-import: #c9d1d9;
-                --color-prettylights-syntax-entity-tag: #7ee787;
-                --color-prettylights-syntax-keyword: #ff7b72;
-                --color-prettylights-syntax-string: #a5d6ff;
-                --color-prettylights-syntax-variable: #ffa657;
-                --color-prettylights-syntax-brackethighlighter-unmatched: #f85149;
-                --color-prettylights-syntax-brackethighlighter-angle: #8b949e;
-                --color-prettylights-syntax-invalid-illegal-text: #f0f6fc;
-                --color-prettylights-syntax-invalid-illegal-bg: #8e1519;
-                --color-prettylights-syntax-carriage-return-text: #f0f6fc;
-                --color-prettylights-syntax-carriage-return-bg: #b62324;
-                --color-prettylights-syntax-string-regexp: #7ee787;
-                --color-prettylights-syntax-markup-list: #f2cc60;
-                --color-prettylights-syntax-markup-heading: #1f6feb;
-                --color-prettylights-syntax-markup-italic: #c9d1d9;
-                --color-prettylights-syntax-markup-bold: #c9d1d9;
-                --color-prettylights-syntax-markup-deleted-text: #ffdcd7;
-                --color-prettylights-syntax-markup-deleted-bg: #67060c;
-                --color-prettylights-syntax-markup-inserted-text: #aff5b4;
-                --color-prettylights-syntax-markup-inserted-bg: #033a16;
-                --color-prettylights-syntax-markup-changed-text: #ffdfb6;
-                --color-prettylights-syntax-markup-changed-bg: #5a1e02;
-                --color-prettylights-syntax-markup-ignored-text: #c9d1d9;
-                --color-prettylights-syntax-markup-ignored-bg: #1158c7;
-                --color-prettylights-syntax-meta-diff-range: #d2a8ff;
-                --color-prettylights-syntax-sublimelinter-gutter-mark: #484f58;
-            }
-        }
-
-        @media (prefers-color-scheme: light) {
-
-            .content-body,
-            [data-theme="light"] {
-                /*light*/
-                color-scheme: light;
-                --focus-outlineColor: #0969da;
-                --fgColor-default: #1f2328;
-                --fgColor-muted: #636c76;
-                --fgColor-accent: #0969da;
-                --fgColor-success: #1a7f37;
-                --fgColor-attention: #9a6700;
-                --fgColor-danger: #d1242f;
-                --fgColor-done: #8250df;
-                --bgColor-default: #ffffff;
-                --bgColor-muted: #f6f8fa;
-                --bgColor-muted-2: #dddddd;
-                --bgColor-neutral-muted: #afb8c133;
-                --bgColor-attention-muted: #fff8c5;
-                --borderColor-default: #d0d7de;
-                --borderColor-muted: #d0d7deb3;
-                --borderColor-neutral-muted: #afb8c133;
-                --borderColor-accent-emphasis: #0969da;
-                --borderColor-success-emphasis: #1a7f37;
-                --borderColor-attention-emphasis: #bf8700;
-                --borderColor-danger-emphasis: #cf222e;
-                --borderColor-done-emphasis: #8250df;
-                --color-prettylights-syntax-comment: #57606a;
-                --color-prettylights-syntax-constant: #0550ae;
-                --color-prettylights-syntax-constant-other-reference-link: #0a3069;
-                --color-prettylights-syntax-entity: #6639ba;
-                --color-prettylights-syntax-storage-modifier-import: #24292f;
-                --color-prettylights-syntax-entity-tag: #0550ae;
-                --color-prettylights-syntax-keyword: #cf222e;
-                --color-prettylights-syntax-string: #0a3069;
-                --color-prettylights-syntax-variable: #953800;
-                --color-prettylights-syntax-brackethighlighter-unmatched: #82071e;
-                --color-prettylights-syntax-brackethighlighter-angle: #57606a;
-                --color-prettylights-syntax-invalid-illegal-text: #f6f8fa;
-                --color-prettylights-syntax-invalid-illegal-bg: #82071e;
-                --color-prettylights-syntax-carriage-return-text: #f6f8fa;
-                --color-prettylights-syntax-carriage-return-bg: #cf222e;
-                --color-prettylights-syntax-string-regexp: #116329;
-                --color-prettylights-syntax-markup-list: #3b2300;
-                --color-prettylights-syntax-markup-heading: #0550ae;
-                --color-prettylights-syntax-markup-italic: #24292f;
-                --color-prettylights-syntax-markup-bold: #24292f;
-                --color-prettylights-syntax-markup-deleted-text: #82071e;
-                --color-prettylights-syntax-markup-deleted-bg: #ffebe9;
-                --color-prettylights-syntax-markup-inserted-text: #116329;
-                --color-prettylights-syntax-markup-inserted-bg: #dafbe1;
-                --color-prettylights-syntax-markup-changed-text: #953800;
-                --color-prettylights-syntax-markup-changed-bg: #ffd8b5;
-                --color-prettylights-syntax-markup-ignored-text: #eaeef2;
-                --color-prettylights-syntax-markup-ignored-bg: #0550ae;
-                --color-prettylights-syntax-meta-diff-range: #8250df;
-                --color-prettylights-syntax-sublimelinter-gutter-mark: #8c959f;
-            }
-        }
+        /* {COLOR_SCHEME_CSS} */
 
         .content-body {
             -ms-text-size-adjust: 100%;
@@ -466,14 +816,14 @@ impl BuiltInTemplate {
             margin: 0;
             padding: 0 1em;
             color: var(--fgColor-muted);
-            border-left: .25em solid var(--borderColor-default);
+            border-inline-start: .25em solid var(--borderColor-default);
         }
 
         .content-body ul,
         .content-body ol {
             margin-top: 0;
             margin-bottom: 0;
-            padding-left: 2em;
+            padding-inline-start: 2em;
         }
 
         .content-body ol ol,
@@ -489,7 +839,7 @@ impl BuiltInTemplate {
         }
 
         .content-body dd {
-            margin-left: 0;
+            margin-inline-start: 0;
         }
 
         .content-body tt,
@@ -554,9 +904,9 @@ impl BuiltInTemplate {
         }
 
         .content-body .anchor {
-            float: left;
-            padding-right: 4px;
-            margin-left: -20px;
+            float: inline-start;
+            padding-inline-end: 4px;
+            margin-inline-start: -20px;
             line-height: 1;
         }
 
@@ -644,7 +994,7 @@ impl BuiltInTemplate {
         .content-body summary h4 .anchor,
         .content-body summary h5 .anchor,
         .content-body summary h6 .anchor {
-            margin-left: -40px;
+            margin-inline-start: -40px;
         }
 
         .content-body summary h1,
@@ -920,7 +1270,7 @@ impl BuiltInTemplate {
             overflow: hidden;
             font-size: 12px;
             line-height: 1;
-            text-align: left;
+            text-align: start;
             white-space: nowrap;
         }
 
@@ -956,12 +1306,12 @@ impl BuiltInTemplate {
         }
 
         .content-body .footnotes ol {
-            padding-left: 16px;
+            padding-inline-start: 16px;
         }
 
         .content-body .footnotes ol ul {
             display: inline-block;
-            padding-left: 16px;
+            padding-inline-start: 16px;
             margin-top: 16px;
         }
 
@@ -972,9 +1322,9 @@ impl BuiltInTemplate {
         .content-body .footnotes li:target::before {
             position: absolute;
             top: -8px;
-            right: -8px;
+            inset-inline-end: -8px;
             bottom: -8px;
-            left: -24px;
+            inset-inline-start: -24px;
             pointer-events: none;
             content: "";
             border: 2px solid var(--borderColor-accent-emphasis);
@@ -1189,7 +1539,7 @@ impl BuiltInTemplate {
             padding: var(--base-size-8) var(--base-size-16);
             margin-bottom: var(--base-size-16);
             color: inherit;
-            border-left: .25em solid var(--borderColor-default);
+            border-inline-start: .25em solid var(--borderColor-default);
         }
 
         .content-body .markdown-alert>:first-child {
@@ -1208,7 +1558,7 @@ impl BuiltInTemplate {
         }
 
         .content-body .markdown-alert.markdown-alert-note {
-            border-left-color: var(--borderColor-accent-emphasis);
+            border-inline-start-color: var(--borderColor-accent-emphasis);
         }
 
         .content-body .markdown-alert.markdown-alert-note .markdown-alert-title {
@@ -1216,7 +1566,7 @@ impl BuiltInTemplate {
         }
 
         .content-body .markdown-alert.markdown-alert-important {
-            border-left-color: var(--borderColor-done-emphasis);
+            border-inline-start-color: var(--borderColor-done-emphasis);
         }
 
         .content-body .markdown-alert.markdown-alert-important .markdown-alert-title {
@@ -1224,7 +1574,7 @@ impl BuiltInTemplate {
         }
 
         .content-body .markdown-alert.markdown-alert-warning {
-            border-left-color: var(--borderColor-attention-emphasis);
+            border-inline-start-color: var(--borderColor-attention-emphasis);
         }
 
         .content-body .markdown-alert.markdown-alert-warning .markdown-alert-title {
@@ -1232,7 +1582,7 @@ impl BuiltInTemplate {
         }
 
         .content-body .markdown-alert.markdown-alert-tip {
-            border-left-color: var(--borderColor-success-emphasis);
+            border-inline-start-color: var(--borderColor-success-emphasis);
         }
 
         .content-body .markdown-alert.markdown-alert-tip .markdown-alert-title {
@@ -1240,7 +1590,7 @@ impl BuiltInTemplate {
         }
 
         .content-body .markdown-alert.markdown-alert-caution {
-            border-left-color: var(--borderColor-danger-emphasis);
+            border-inline-start-color: var(--borderColor-danger-emphasis);
         }
 
         .content-body .markdown-alert.markdown-alert-caution .markdown-alert-title {
@@ -1283,29 +1633,374 @@ impl BuiltInTemplate {
             padding: 1%;
         }
 
+        .table-of-contents .active {
+            color: var(--fgColor-accent);
+        }
+
+        .table-of-contents .open>b>u {
+            color: var(--fgColor-default);
+        }
+
         .my-container {
             display: flex;
             min-height: 100vh;
         }
+
+        .theme-toggle,
+        .print-button {
+            display: block;
+            margin-bottom: 1em;
+            color: var(--fgColor-default);
+            background: none;
+            border: 1px solid var(--fgColor-muted);
+            border-radius: 4px;
+            cursor: pointer;
+        }
+
+        /* {OPTIONS_CSS} */
     </style>
+    <!-- {SCRIPTS} -->
+    <!-- {HEAD} -->
 </head>
 
 <body class="outer-content content-body">
     <div class="my-container">
         <div class="table-of-contents">
+            <!-- {LOGO} -->
+            <!-- {THEME_TOGGLE} -->
+            <!-- {PRINT_BUTTON} -->
             Contents:
             <!-- {TABLE_OF_CONTENTS} -->
         </div>
         <div class="content-border">
             <div class="content-body">
                 <!-- {CONTENT} -->
+                <!-- {FOOTER} -->
             </div>
         </div>
     </div>
 </body>
 </html>
-                "##.to_string()
+                "##;
+                apply_options(
+                    template,
+                    options,
+                    ".content-body",
+                    DARK_COLORS,
+                    LIGHT_COLORS,
+                )
             }
         }
     }
 }
+
+/// Fills in the placeholders of a built-in template from `options`. The template's colors are
+/// css variables set on `selector`, and its text is styled by `selector` too.
+///
+/// `options.color_scheme` is used by default, and a `data-theme` attribute on the root element
+/// (set by the theme toggle) overrides it. `<!-- {LOGO} -->` and `<!-- {FOOTER} -->` are left
+/// for later, since they depend on the page.
+fn apply_options(
+    template: &str,
+    options: &TemplateOptions,
+    selector: &str,
+    dark_colors: &str,
+    light_colors: &str,
+) -> String {
+    let default_colors = match options.color_scheme {
+        ColorScheme::Dark => dark_colors,
+        ColorScheme::Auto | ColorScheme::Light => light_colors,
+    };
+    let mut css = format!("{} {{{}}}\n", selector, default_colors);
+    if options.color_scheme == ColorScheme::Auto {
+        css.push_str(&format!(
+            "@media (prefers-color-scheme: dark) {{ :root:not([data-theme]) {} {{{}}} }}\n",
+            selector, dark_colors
+        ));
+    }
+    css.push_str(&format!(
+        ":root[data-theme=\"dark\"] {} {{{}}}\n:root[data-theme=\"light\"] {} {{{}}}\n",
+        selector, dark_colors, selector, light_colors
+    ));
+    // Pages are always printed in the light color scheme
+    css.push_str(&format!(
+        "@media print {{ :root:not([data-theme]) {} , :root[data-theme] {} {{{}}} }}\n",
+        selector, selector, light_colors
+    ));
+    let mut scripts = PRINT_SCRIPT.to_string();
+    let toggle = if options.theme_toggle {
+        scripts.push_str(&THEME_SCRIPT.replace("{COLOR_SCHEME}", options.color_scheme.as_str()));
+        "<button class=\"theme-toggle\" type=\"button\" aria-label=\"Toggle dark mode\">&#9680;</button>"
+    } else {
+        ""
+    };
+    let print_button = if options.print_button {
+        PRINT_BUTTON
+    } else {
+        ""
+    };
+    let mut options_css = format!("{}{}", HEADING_ANCHOR_CSS, PRINT_CSS);
+    if let Some(accent_color) = &options.accent_color {
+        // Overrides the accent color of every color scheme
+        options_css.push_str(&format!(
+            "{} {{ --fgColor-accent: {} !important; }}\n",
+            selector, accent_color
+        ));
+    }
+    if let Some(font_family) = &options.font_family {
+        options_css.push_str(&format!(
+            "{} {{ font-family: {}; }}\n",
+            selector, font_family
+        ));
+    }
+    if options.logo.is_some() {
+        options_css.push_str(".logo { display: block; max-width: 100%; max-height: 4rem; }\n");
+    }
+    if options.footer.is_some() {
+        options_css.push_str(
+            ".site-footer { margin-top: 2rem; font-size: 14px; color: var(--fgColor-muted); }\n",
+        );
+    }
+    template
+        .replace("/* {COLOR_SCHEME_CSS} */", &css)
+        .replace("/* {OPTIONS_CSS} */", &options_css)
+        .replace("<!-- {SCRIPTS} -->", &scripts)
+        .replace("<!-- {THEME_TOGGLE} -->", toggle)
+        .replace("<!-- {PRINT_BUTTON} -->", print_button)
+}
+
+/// Generates the `<!-- {LOGO} -->` image for a page at `depth`. Urls are used as-is, and other
+/// paths are relative to the site root.
+pub fn logo_html(logo: &str, depth: usize, web_prefix: Option<&str>) -> String {
+    let url = if logo.contains("://") || logo.starts_with('/') || logo.starts_with("data:") {
+        logo.to_string()
+    } else {
+        utils::page_url(depth, web_prefix, Path::new(logo))
+    };
+    format!(
+        "<img class=\"logo\" src=\"{}\" alt=\"\">",
+        utils::escape_html(&url)
+    )
+}
+
+/// A button that prints the page, for `<!-- {PRINT_BUTTON} -->`.
+pub const PRINT_BUTTON: &str =
+    "<button class=\"print-button\" type=\"button\" onclick=\"window.print()\">Print this page</button>";
+
+/// Shows the `¶` links of headings (with `heading_anchors` in ssg.toml) only while the heading is
+/// hovered or the link is focused.
+const HEADING_ANCHOR_CSS: &str = r##"
+        .heading-anchor {
+            margin-inline-start: 0.25em;
+            color: var(--fgColor-muted);
+            text-decoration: none;
+            opacity: 0;
+        }
+
+        :is(h1, h2, h3, h4, h5, h6):hover .heading-anchor,
+        .heading-anchor:focus {
+            opacity: 1;
+        }
+"##;
+
+/// Hides navigation when printing, and shows the url of each external link after it.
+const PRINT_CSS: &str = r##"
+        @media print {
+            .table-of-contents,
+            .docs-sidebar,
+            .docs-header,
+            .blog-header,
+            .blog-footer details,
+            .menu-button,
+            .theme-toggle,
+            .print-button,
+            .prev-next,
+            .pagination,
+            .heading-anchor {
+                display: none !important;
+            }
+
+            body,
+            .outer-content,
+            .content-body,
+            .docs-main,
+            .blog {
+                max-width: none !important;
+                margin: 0 !important;
+                padding: 0 !important;
+                background: none !important;
+            }
+
+            .content-border {
+                outline: none !important;
+            }
+
+            a[href^="http"]::after {
+                content: " (" attr(href) ")";
+                font-size: 85%;
+                word-break: break-all;
+            }
+
+            pre,
+            blockquote,
+            table,
+            img {
+                break-inside: avoid;
+            }
+
+            h1,
+            h2,
+            h3,
+            h4,
+            h5,
+            h6 {
+                break-after: avoid;
+            }
+        }
+"##;
+
+/// Opens every `<details>` element while the page is printed, closing them again afterwards.
+const PRINT_SCRIPT: &str = r##"<script>
+        (function () {
+            let opened = [];
+            addEventListener("beforeprint", function () {
+                opened = Array.from(document.querySelectorAll("details:not([open])"));
+                opened.forEach(function (details) { details.open = true; });
+            });
+            addEventListener("afterprint", function () {
+                opened.forEach(function (details) { details.open = false; });
+            });
+        })();
+    </script>"##;
+
+/// Applies the stored theme before the page is drawn, and switches themes when the toggle is
+/// clicked.
+const THEME_SCRIPT: &str = r##"<script>
+        (function () {
+            const root = document.documentElement;
+            const stored = localStorage.getItem("theme");
+            if (stored) {
+                root.dataset.theme = stored;
+            }
+            document.addEventListener("DOMContentLoaded", function () {
+                document.querySelector(".theme-toggle").addEventListener("click", function () {
+                    const fallback = "{COLOR_SCHEME}" !== "auto" ? "{COLOR_SCHEME}"
+                        : matchMedia("(prefers-color-scheme: dark)").matches ? "dark" : "light";
+                    root.dataset.theme = (root.dataset.theme || fallback) === "dark" ? "light" : "dark";
+                    localStorage.setItem("theme", root.dataset.theme);
+                });
+            });
+        })();
+    </script>"##;
+
+/// Colors for the dark color scheme of the `github-markdown`, `docs`, and `blog` templates.
+const DARK_COLORS: &str = r##"
+        color-scheme: dark;
+        --focus-outlineColor: #1f6feb;
+        --fgColor-default: #e6edf3;
+        --fgColor-muted: #8d96a0;
+        --fgColor-accent: #4493f8;
+        --fgColor-success: #3fb950;
+        --fgColor-attention: #d29922;
+        --fgColor-danger: #f85149;
+        --fgColor-done: #ab7df8;
+        --bgColor-default: #0d1117;
+        --bgColor-muted: #161b22;
+        --bgColor-muted-2: #222222;
+        --bgColor-neutral-muted: #6e768166;
+        --bgColor-attention-muted: #bb800926;
+        --borderColor-default: #30363d;
+        --borderColor-muted: #30363db3;
+        --borderColor-neutral-muted: #6e768166;
+        --borderColor-accent-emphasis: #1f6feb;
+        --borderColor-success-emphasis: #238636;
+        --borderColor-attention-emphasis: #9e6a03;
+        --borderColor-danger-emphasis: #da3633;
+        --borderColor-done-emphasis: #8957e5;
+        --color-prettylights-syntax-comment: #8b949e;
+        --color-prettylights-syntax-constant: #79c0ff;
+        --color-prettylights-syntax-constant-other-reference-link: #a5d6ff;
+        --color-prettylights-syntax-entity: #d2a8ff;
+        --color-prettylights-syntax-storage-modifier-import: #c9d1d9;
+        --color-prettylights-syntax-entity-tag: #7ee787;
+        --color-prettylights-syntax-keyword: #ff7b72;
+        --color-prettylights-syntax-string: #a5d6ff;
+        --color-prettylights-syntax-variable: #ffa657;
+        --color-prettylights-syntax-brackethighlighter-unmatched: #f85149;
+        --color-prettylights-syntax-brackethighlighter-angle: #8b949e;
+        --color-prettylights-syntax-invalid-illegal-text: #f0f6fc;
+        --color-prettylights-syntax-invalid-illegal-bg: #8e1519;
+        --color-prettylights-syntax-carriage-return-text: #f0f6fc;
+        --color-prettylights-syntax-carriage-return-bg: #b62324;
+        --color-prettylights-syntax-string-regexp: #7ee787;
+        --color-prettylights-syntax-markup-list: #f2cc60;
+        --color-prettylights-syntax-markup-heading: #1f6feb;
+        --color-prettylights-syntax-markup-italic: #c9d1d9;
+        --color-prettylights-syntax-markup-bold: #c9d1d9;
+        --color-prettylights-syntax-markup-deleted-text: #ffdcd7;
+        --color-prettylights-syntax-markup-deleted-bg: #67060c;
+        --color-prettylights-syntax-markup-inserted-text: #aff5b4;
+        --color-prettylights-syntax-markup-inserted-bg: #033a16;
+        --color-prettylights-syntax-markup-changed-text: #ffdfb6;
+        --color-prettylights-syntax-markup-changed-bg: #5a1e02;
+        --color-prettylights-syntax-markup-ignored-text: #c9d1d9;
+        --color-prettylights-syntax-markup-ignored-bg: #1158c7;
+        --color-prettylights-syntax-meta-diff-range: #d2a8ff;
+        --color-prettylights-syntax-sublimelinter-gutter-mark: #484f58;
+"##;
+
+/// Colors for the light color scheme of the `github-markdown`, `docs`, and `blog` templates.
+const LIGHT_COLORS: &str = r##"
+        color-scheme: light;
+        --focus-outlineColor: #0969da;
+        --fgColor-default: #1f2328;
+        --fgColor-muted: #636c76;
+        --fgColor-accent: #0969da;
+        --fgColor-success: #1a7f37;
+        --fgColor-attention: #9a6700;
+        --fgColor-danger: #d1242f;
+        --fgColor-done: #8250df;
+        --bgColor-default: #ffffff;
+        --bgColor-muted: #f6f8fa;
+        --bgColor-muted-2: #dddddd;
+        --bgColor-neutral-muted: #afb8c133;
+        --bgColor-attention-muted: #fff8c5;
+        --borderColor-default: #d0d7de;
+        --borderColor-muted: #d0d7deb3;
+        --borderColor-neutral-muted: #afb8c133;
+        --borderColor-accent-emphasis: #0969da;
+        --borderColor-success-emphasis: #1a7f37;
+        --borderColor-attention-emphasis: #bf8700;
+        --borderColor-danger-emphasis: #cf222e;
+        --borderColor-done-emphasis: #8250df;
+        --color-prettylights-syntax-comment: #57606a;
+        --color-prettylights-syntax-constant: #0550ae;
+        --color-prettylights-syntax-constant-other-reference-link: #0a3069;
+        --color-prettylights-syntax-entity: #6639ba;
+        --color-prettylights-syntax-storage-modifier-import: #24292f;
+        --color-prettylights-syntax-entity-tag: #0550ae;
+        --color-prettylights-syntax-keyword: #cf222e;
+        --color-prettylights-syntax-string: #0a3069;
+        --color-prettylights-syntax-variable: #953800;
+        --color-prettylights-syntax-brackethighlighter-unmatched: #82071e;
+        --color-prettylights-syntax-brackethighlighter-angle: #57606a;
+        --color-prettylights-syntax-invalid-illegal-text: #f6f8fa;
+        --color-prettylights-syntax-invalid-illegal-bg: #82071e;
+        --color-prettylights-syntax-carriage-return-text: #f6f8fa;
+        --color-prettylights-syntax-carriage-return-bg: #cf222e;
+        --color-prettylights-syntax-string-regexp: #116329;
+        --color-prettylights-syntax-markup-list: #3b2300;
+        --color-prettylights-syntax-markup-heading: #0550ae;
+        --color-prettylights-syntax-markup-italic: #24292f;
+        --color-prettylights-syntax-markup-bold: #24292f;
+        --color-prettylights-syntax-markup-deleted-text: #82071e;
+        --color-prettylights-syntax-markup-deleted-bg: #ffebe9;
+        --color-prettylights-syntax-markup-inserted-text: #116329;
+        --color-prettylights-syntax-markup-inserted-bg: #dafbe1;
+        --color-prettylights-syntax-markup-changed-text: #953800;
+        --color-prettylights-syntax-markup-changed-bg: #ffd8b5;
+        --color-prettylights-syntax-markup-ignored-text: #eaeef2;
+        --color-prettylights-syntax-markup-ignored-bg: #0550ae;
+        --color-prettylights-syntax-meta-diff-range: #8250df;
+        --color-prettylights-syntax-sublimelinter-gutter-mark: #8c959f;
+"##;
@@ -0,0 +1,249 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{errors::SsgError, utils, FirstPassResult, SiteContext};
+
+/// Where a page's content goes in its template. Templates place it with
+/// `{{ content | safe }}` (or the placeholder itself), and it's filled in after rendering so the
+/// content is never read as template syntax.
+pub const CONTENT_PLACEHOLDER: &str = "<!-- {CONTENT} -->";
+
+/// The variables a template is rendered with.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Variables {
+    pub page: PageVariables,
+    pub site: SiteVariables,
+}
+
+/// A page, as seen by templates.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PageVariables {
+    /// The page's title, as text
+    pub title: Option<String>,
+    /// The url of the page, from the page being rendered
+    pub url: String,
+    /// The path of the page in the site, e.g. `guides/intro.html`
+    pub path: String,
+    /// The date from the page's front matter
+    pub date: Option<String>,
+    /// The tags from the page's front matter
+    pub tags: Vec<String>,
+    pub lang: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SiteVariables {
+    pub title: Option<String>,
+    /// The pages in the table of contents, in its order
+    pub pages: Vec<PageVariables>,
+}
+
+impl Variables {
+    /// The variables for `page`, `depth` deep, with the urls of the `site_pages` made relative to
+    /// it.
+    pub fn new(
+        page: PageVariables,
+        depth: usize,
+        site_pages: &[PageVariables],
+        context: &SiteContext,
+    ) -> Self {
+        let pages = site_pages
+            .iter()
+            .map(|site_page| PageVariables {
+                url: utils::page_url(depth, context.web_prefix, Path::new(&site_page.path)),
+                ..site_page.clone()
+            })
+            .collect();
+        Self {
+            page,
+            site: SiteVariables {
+                title: context.config.title.clone(),
+                pages,
+            },
+        }
+    }
+}
+
+/// The pages in the table of contents (including those of mounted trees), in its order, titled
+/// by their front matter title, first level 1 heading, or file name. Their urls are filled in
+/// for each page by `Variables::new`.
+pub fn site_pages(results: &[FirstPassResult]) -> Vec<PageVariables> {
+    let file_name = |path: &Path| path.file_stem().unwrap().to_string_lossy().to_string();
+    results
+        .iter()
+        .filter_map(|result| match result {
+            FirstPassResult::HtmlOutput {
+                html,
+                relative_path,
+                unlisted: false,
+                title,
+                date,
+                layout,
+                ..
+            } => Some(PageVariables {
+                title: Some(
+                    title
+                        .clone()
+                        .or_else(|| utils::first_heading(html).map(|h| utils::unescape_html(&h)))
+                        .unwrap_or_else(|| file_name(relative_path)),
+                ),
+                path: relative_path.to_string_lossy().replace('\\', "/"),
+                date: date.as_ref().map(ToString::to_string),
+                tags: layout.tags.clone(),
+                lang: layout.lang.clone(),
+                ..Default::default()
+            }),
+            FirstPassResult::Link {
+                relative_path,
+                title,
+                ..
+            } => Some(PageVariables {
+                title: Some(title.clone().unwrap_or_else(|| file_name(relative_path))),
+                path: relative_path.to_string_lossy().replace('\\', "/"),
+                ..Default::default()
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether the file at `path` is a template partial (such as `_header.html`), which templates
+/// can include, import, or extend, instead of a file copied to the site.
+pub fn is_partial(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "html")
+        && path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('_'))
+}
+
+/// Renders `template` (the template of `page`) as a Tera template with `variables`, leaving the
+/// content placeholder to be filled in. A template read from a file (in `source`) can include,
+/// import, or extend other templates (see `add_template`). Templates without any template
+/// syntax are returned as they are, so the variables are only made when they're needed.
+pub fn render(
+    template: String,
+    source: Option<(&Path, &SiteContext)>,
+    page: &str,
+    variables: impl FnOnce() -> Variables,
+) -> anyhow::Result<String> {
+    if !["{{", "{%", "{#"]
+        .iter()
+        .any(|syntax| template.contains(syntax))
+    {
+        return Ok(template);
+    }
+    let mut tera = tera::Tera::default();
+    // Escaped like the rest of the page, leaving the slashes in urls as they are
+    tera.set_escape_fn(utils::escape_html);
+    let name = match source {
+        Some((path, context)) => add_template(&mut tera, &template, path, context, &mut Vec::new())
+            .map_err(|e| template_error(page, e))?,
+        None => {
+            tera.add_raw_template("template.html", &template)
+                .map_err(|e| template_error(page, e.into()))?;
+            "template.html".to_string()
+        }
+    };
+    let mut tera_context = tera::Context::from_serialize(variables())?;
+    tera_context.insert("content", CONTENT_PLACEHOLDER);
+    tera.render(&name, &tera_context)
+        .map_err(|e| template_error(page, e.into()))
+}
+
+/// Adds `template` (read from `path`) to `tera`, named by its path in the site, after the
+/// templates it includes, imports, or extends, returning its name. Those are found like
+/// `template.html`: beside it or in one of its ancestors, except that a template extending a
+/// template with its own name (such as a section's `template.html` extending the site's) skips
+/// itself.
+fn add_template(
+    tera: &mut tera::Tera,
+    template: &str,
+    path: &Path,
+    context: &SiteContext,
+    loading: &mut Vec<PathBuf>,
+) -> anyhow::Result<String> {
+    let name = path
+        .strip_prefix(context.target_path)?
+        .to_string_lossy()
+        .replace('\\', "/");
+    // Templates including themselves (e.g. to render a tree) are added once they're loaded
+    if loading.iter().any(|loading| loading == path)
+        || tera.get_template_names().any(|added| added == name)
+    {
+        return Ok(name);
+    }
+    loading.push(path.to_path_buf());
+    let mut source = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{%") {
+        let end = rest[start..]
+            .find("%}")
+            .map_or(rest.len(), |end| start + end + 2);
+        let tag = &rest[start..end];
+        source.push_str(&rest[..start]);
+        let keyword = tag[2..]
+            .trim_start_matches('-')
+            .split_whitespace()
+            .next()
+            .unwrap_or_default();
+        if matches!(keyword, "include" | "import" | "extends") {
+            // Each quoted name in the tag, e.g. `{% include ["_a.html", "_b.html"] %}`
+            let mut tag_rest = tag;
+            while let Some(open) = tag_rest.find(['"', '\'', '`']) {
+                let quote = tag_rest[open..].chars().next().unwrap();
+                let Some(close) = tag_rest[open + 1..].find(quote) else {
+                    break;
+                };
+                let referenced = &tag_rest[open + 1..open + 1 + close];
+                source.push_str(&tag_rest[..open]);
+                match find_template(referenced, path, context)? {
+                    Some(file) => {
+                        let contents = context.fs.read_to_string(&file)?;
+                        let name = add_template(tera, &contents, &file, context, loading)?;
+                        source.push_str(&format!("\"{}\"", name));
+                    }
+                    // Left for Tera to report, unless it's `ignore missing`
+                    None => source.push_str(&tag_rest[open..open + close + 2]),
+                }
+                tag_rest = &tag_rest[open + close + 2..];
+            }
+            source.push_str(tag_rest);
+        } else {
+            source.push_str(tag);
+        }
+        rest = &rest[end..];
+    }
+    source.push_str(rest);
+    loading.pop();
+    tera.add_raw_template(&name, &source)?;
+    Ok(name)
+}
+
+/// Finds the template `name` referenced from the template at `path`.
+fn find_template(
+    name: &str,
+    path: &Path,
+    context: &SiteContext,
+) -> anyhow::Result<Option<PathBuf>> {
+    let cache = &context.template_cache;
+    let found = match cache.find(context, path, name)? {
+        Some(found) if found == path => match path.parent() {
+            Some(dir) if dir != context.target_path => cache.find(context, dir, name)?,
+            _ => None,
+        },
+        found => found,
+    };
+    Ok(found.filter(|found| utils::is_within(context.target_path, found)))
+}
+
+fn template_error(page: &str, e: anyhow::Error) -> anyhow::Error {
+    // Tera's own message only names the template, the reason is in its sources
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    SsgError::TemplateError(page.to_string(), message).into()
+}
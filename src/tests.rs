@@ -1,6 +1,6 @@
 use std::{
     env::temp_dir,
-    fs::{create_dir_all, remove_dir_all, File},
+    fs::{create_dir_all, read_to_string, remove_dir_all, File},
     io::Write,
     panic,
 };
@@ -49,6 +49,7 @@ fn site_with_links() -> anyhow::Result<()> {
                     clean: false,
                     web_prefix: None,
                     template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
                 };
                 log::trace!("Running program");
                 crate::run_program(args)?;
@@ -103,6 +104,7 @@ fn site_warn_without_index() -> anyhow::Result<()> {
                     web_prefix: None,
                     template: Some(crate::templates::BuiltInTemplate::ForceNone),
                     file: None,
+                    ..Default::default()
                 };
                 crate::run_program(args)?;
                 Ok(())
@@ -128,6 +130,5172 @@ fn site_warn_without_index() -> anyhow::Result<()> {
     }
 }
 
+#[test]
+fn site_with_dir_title() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/nested"))?;
+                create_dir_all(temp_dir.join("target/untitled"))?;
+                let mut template_file = File::create(temp_dir.join("target/template.html"))?;
+                write!(
+                    template_file,
+                    "<!-- {{TABLE_OF_CONTENTS}} --><!-- {{CONTENT}} -->"
+                )?;
+                template_file.flush()?;
+                let mut djot_file_1 = File::create(temp_dir.join("target/index.dj"))?;
+                write!(djot_file_1, "# Index")?;
+                djot_file_1.flush()?;
+                let mut djot_file_2 = File::create(temp_dir.join("target/nested/page.dj"))?;
+                write!(djot_file_2, "# Page")?;
+                djot_file_2.flush()?;
+                let mut djot_file_3 = File::create(temp_dir.join("target/untitled/other.dj"))?;
+                write!(djot_file_3, "# Other")?;
+                djot_file_3.flush()?;
+                let mut dir_file = File::create(temp_dir.join("target/nested/_dir.toml"))?;
+                write!(dir_file, "_title = \"Nested Section\"")?;
+                dir_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    file: None,
+                    output_path: Some(temp_dir.join("output")),
+                    clean: false,
+                    web_prefix: None,
+                    template: None,
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains("<li><b><u>Nested Section</u></b></li>"));
+                assert!(index.contains("<li><b><u>untitled:</u></b></li>"));
+                assert!(!temp_dir.join("output/nested/_dir.toml").exists());
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_toc_templates() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/nested"))?;
+                let mut template_file = File::create(temp_dir.join("target/template.html"))?;
+                write!(
+                    template_file,
+                    "<!-- {{TABLE_OF_CONTENTS}} --><!-- {{CONTENT}} -->"
+                )?;
+                template_file.flush()?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(
+                    config_file,
+                    "[toc]\nitem_template = '<li class=\"item\"><a href=\"{{URL}}\">{{NAME}}</a></li>'\nactive_item_template = '<li class=\"{{CLASS}}\">{{NAME}}</li>'\nfolder_template = '<li class=\"folder\">{{NAME}}</li>'\nactive_class = 'current'"
+                )?;
+                config_file.flush()?;
+                let mut djot_file_1 = File::create(temp_dir.join("target/index.dj"))?;
+                write!(djot_file_1, "# Index")?;
+                djot_file_1.flush()?;
+                let mut djot_file_2 = File::create(temp_dir.join("target/nested/page.dj"))?;
+                write!(djot_file_2, "# Page")?;
+                djot_file_2.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    file: None,
+                    output_path: Some(temp_dir.join("output")),
+                    clean: false,
+                    web_prefix: None,
+                    template: None,
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains("<li class=\"current\">index</li>"));
+                assert!(index.contains("<li class=\"folder\">nested:</li>"));
+                assert!(
+                    index.contains("<li class=\"item\"><a href=\"nested/page.html\">page</a></li>")
+                );
+                assert!(!temp_dir.join("output/ssg.toml").exists());
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_active_toc_classes() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/nested"))?;
+                create_dir_all(temp_dir.join("target/other"))?;
+                let mut template_file = File::create(temp_dir.join("target/template.html"))?;
+                write!(
+                    template_file,
+                    "<!-- {{TABLE_OF_CONTENTS}} --><!-- {{CONTENT}} -->"
+                )?;
+                template_file.flush()?;
+                let mut djot_file_1 = File::create(temp_dir.join("target/index.dj"))?;
+                write!(djot_file_1, "# Index")?;
+                djot_file_1.flush()?;
+                let mut djot_file_2 = File::create(temp_dir.join("target/nested/page.dj"))?;
+                write!(djot_file_2, "# Page")?;
+                djot_file_2.flush()?;
+                let mut djot_file_3 = File::create(temp_dir.join("target/other/page.dj"))?;
+                write!(djot_file_3, "# Other")?;
+                djot_file_3.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    file: None,
+                    output_path: Some(temp_dir.join("output")),
+                    clean: false,
+                    web_prefix: None,
+                    template: None,
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let page = read_to_string(temp_dir.join("output/nested/page.html"))?;
+                assert!(page.contains("<li class=\"open\"><b><u>nested:</u></b></li>"));
+                assert!(page.contains("<li><b><u>other:</u></b></li>"));
+                assert!(page.contains("<li class=\"active\"><b>page</b></li>"));
+                assert!(page.contains("<li><a href=\"../other/page.html\">page</a></li>"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_index_aliases() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/nested"))?;
+                create_dir_all(temp_dir.join("target/both"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(config_file, "index_names = [\"README\"]")?;
+                config_file.flush()?;
+                let mut md_file_1 = File::create(temp_dir.join("target/README.md"))?;
+                write!(md_file_1, "# Readme")?;
+                md_file_1.flush()?;
+                let mut md_file_2 = File::create(temp_dir.join("target/nested/README.md"))?;
+                write!(md_file_2, "# Nested readme")?;
+                md_file_2.flush()?;
+                let mut md_file_3 = File::create(temp_dir.join("target/both/README.md"))?;
+                write!(md_file_3, "# Both readme")?;
+                md_file_3.flush()?;
+                let mut djot_file = File::create(temp_dir.join("target/both/index.dj"))?;
+                write!(djot_file, "# Both index")?;
+                djot_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    file: None,
+                    output_path: Some(temp_dir.join("output")),
+                    clean: false,
+                    web_prefix: None,
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                assert!(temp_dir.join("output/index.html").exists());
+                assert!(!temp_dir.join("output/README.html").exists());
+                assert!(temp_dir.join("output/nested/index.html").exists());
+                assert!(temp_dir.join("output/both/README.html").exists());
+                let both = read_to_string(temp_dir.join("output/both/index.html"))?;
+                assert!(both.contains("Both index"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_readme_as_index() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/guide"))?;
+                let mut md_file_1 = File::create(temp_dir.join("target/README.md"))?;
+                write!(md_file_1, "# Readme\n\n[Guide](guide/README.md)")?;
+                md_file_1.flush()?;
+                let mut md_file_2 = File::create(temp_dir.join("target/guide/README.md"))?;
+                write!(md_file_2, "# Guide\n\n[Back](../README.md)")?;
+                md_file_2.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    readme_as_index: true,
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains("href=\"guide/index.html\""));
+                let guide = read_to_string(temp_dir.join("output/guide/index.html"))?;
+                assert!(guide.contains("href=\"../index.html\""));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_markdown_link_forms() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut md_file_1 = File::create(temp_dir.join("target/index.md"))?;
+                write!(
+                    md_file_1,
+                    "[inline](other.md#part) [ref][r] [other][] [other] <https://example.com/x.md>\n\n[r]: other.md\n[other]: other.md"
+                )?;
+                md_file_1.flush()?;
+                let mut md_file_2 = File::create(temp_dir.join("target/other.md"))?;
+                write!(md_file_2, "# Other")?;
+                md_file_2.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains("href=\"other.html#part\""));
+                assert_eq!(index.matches("href=\"other.html\"").count(), 3);
+                assert!(index.contains("href=\"https://example.com/x.md\""));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn links_with_schemes_are_not_rewritten() {
+    use crate::vfs::FileSystem;
+    let target_path = temp_dir().join(temp_dir_name());
+    let fs = crate::vfs::MemoryFs::new();
+    fs.create_dir_all(&target_path).unwrap();
+    let args = ConsoleArgs::default();
+    let context = crate::SiteContext::new(&target_path, &target_path, &args, &fs).unwrap();
+    for link in [
+        "mailto:someone@example.com",
+        "tel:+1-555-0100",
+        "ftp://example.com/notes.md",
+        "//example.com/notes.md",
+        "https://example.com/notes.md",
+        "custom+scheme.v2:notes.md",
+    ] {
+        assert!(crate::links::is_external(link), "{}", link);
+        assert!(crate::links::rewrite_document_link(link, &target_path, &context).is_none());
+    }
+    for link in ["notes.md", "../notes.dj", "/notes.md", "notes.md#mailto:x"] {
+        assert!(!crate::links::is_external(link), "{}", link);
+        assert!(crate::links::rewrite_document_link(link, &target_path, &context).is_some());
+    }
+    let root_relative =
+        crate::links::rewrite_document_link("/docs/notes.md", &target_path.join("a"), &context)
+            .unwrap();
+    assert_eq!(root_relative.url, "/docs/notes.html");
+    assert_eq!(
+        root_relative.referenced_path,
+        target_path.join("docs/notes.md")
+    );
+}
+
+#[test]
+fn links_with_mapped_prefixes() {
+    let mut config = crate::config::SiteConfig::default();
+    config
+        .link_prefixes
+        .insert("@api".to_string(), "https://example.com/api/".to_string());
+    config
+        .link_prefixes
+        .insert("@api/v2".to_string(), "https://v2.example.com".to_string());
+    assert_eq!(
+        crate::links::expand_link_prefix("@api/guide/intro.dj#setup", &config).as_deref(),
+        Some("https://example.com/api/guide/intro.html#setup")
+    );
+    assert_eq!(
+        crate::links::expand_link_prefix("@api/v2/image.png", &config).as_deref(),
+        Some("https://v2.example.com/image.png")
+    );
+    assert_eq!(
+        crate::links::expand_link_prefix("@api", &config).as_deref(),
+        Some("https://example.com/api/")
+    );
+    assert_eq!(
+        crate::links::expand_link_prefix("@apix/page.md", &config),
+        None
+    );
+    assert_eq!(crate::links::expand_link_prefix("page.md", &config), None);
+}
+
+#[test]
+fn site_with_versions() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                for version in ["v1.9", "v1.10"] {
+                    create_dir_all(temp_dir.join("target").join(version).join("nested"))?;
+                    let mut template_file =
+                        File::create(temp_dir.join("target").join(version).join("template.html"))?;
+                    write!(
+                        template_file,
+                        "<!-- {{VERSION_SWITCHER}} --><!-- {{CONTENT}} -->"
+                    )?;
+                    template_file.flush()?;
+                    let mut md_file =
+                        File::create(temp_dir.join("target").join(version).join("index.md"))?;
+                    write!(md_file, "# Version {}", version)?;
+                    md_file.flush()?;
+                    let mut md_file =
+                        File::create(temp_dir.join("target").join(version).join("nested/page.md"))?;
+                    write!(md_file, "# Page")?;
+                    md_file.flush()?;
+                }
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    versioned: true,
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                assert!(temp_dir.join("output/index.html").exists());
+                let latest = read_to_string(temp_dir.join("output/latest/index.html"))?;
+                assert!(latest.contains("Version v1.10"));
+                assert!(latest.contains("<li class=\"active\"><b>latest</b></li>"));
+                assert!(latest.contains("<a href=\"../v1.9/index.html\">v1.9</a>"));
+                let nested = read_to_string(temp_dir.join("output/v1.9/nested/page.html"))?;
+                assert!(nested.contains("<li class=\"active\"><b>v1.9</b></li>"));
+                assert!(nested.contains("<a href=\"../../v1.10/index.html\">v1.10</a>"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_man_page_output() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut md_file = File::create(temp_dir.join("target/tool.md"))?;
+                write!(
+                    md_file,
+                    "+++\noutput_formats = [\"man\"]\nman_section = 8\n+++\n# Name\n\ntool - does *things*\n\n## Options\n\n- `--verbose`\n- `--quiet`\n\n```\n.hidden\n```"
+                )?;
+                md_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/tool.html"))?;
+                assert!(!html.contains("output_formats"));
+                let man = read_to_string(temp_dir.join("output/tool.8"))?;
+                assert!(man.starts_with(".TH \"TOOL\" \"8\"\n"));
+                assert!(man.contains(".SH Name\n"));
+                assert!(man.contains("tool \\- does \\fIthings\\fR\n"));
+                assert!(man.contains(".SS Options\n"));
+                assert!(man.contains(".IP \\(bu 2\n\\fB\\-\\-verbose\\fR\n"));
+                assert!(man.contains(".nf\n.RS 4\n\\&.hidden\n"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_latex_output() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut md_file = File::create(temp_dir.join("target/manual.md"))?;
+                write!(
+                    md_file,
+                    "+++\noutput_formats = [\"latex\"]\n+++\n# Usage\n\nCosts 100% of *$5* [here](https://example.com/#a)\n\n1. `a_b`\n\n```\n\\raw & {{}}\n```"
+                )?;
+                md_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let tex = read_to_string(temp_dir.join("output/manual.tex"))?;
+                assert!(tex.starts_with("\\documentclass{article}\n"));
+                assert!(tex.contains("\\section*{Usage}\n"));
+                assert!(tex.contains(
+                    "Costs 100\\% of \\emph{\\$5} \\href{https://example.com/\\#a}{here}\n"
+                ));
+                assert!(tex.contains("\\begin{enumerate}\n\\item \\texttt{a\\_b}\n"));
+                assert!(tex.contains("\\begin{verbatim}\n\\raw & {}\n\\end{verbatim}\n"));
+                assert!(tex.ends_with("\\end{document}\n"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_changelog() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut changelog_file = File::create(temp_dir.join("CHANGELOG.md"))?;
+                write!(
+                    changelog_file,
+                    "# Changelog\n\n## [1.1.0] - 2024-02-03\n\n- Added *things*\n\n```md\n## Not a release\n```\n\n## 1.0.0\n\n- First release"
+                )?;
+                changelog_file.flush()?;
+                let mut md_file = File::create(temp_dir.join("target/releases.md"))?;
+                write!(
+                    md_file,
+                    "+++\nchangelog = \"../CHANGELOG.md\"\n+++\n# Releases"
+                )?;
+                md_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    web_prefix: Some("https://example.com/".to_string()),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/releases.html"))?;
+                assert!(html.starts_with("<h1 id=\"releases\">Releases</h1>"));
+                assert!(html.contains("<section class=\"release\" id=\"1.1.0\">\n<h2><a href=\"#1.1.0\">1.1.0</a></h2>\n<p class=\"release-date\"><time datetime=\"2024-02-03\">2024-02-03</time></p>\n<ul>\n<li>Added <em>things</em></li>"));
+                assert!(html.contains("## Not a release"));
+                assert!(html.contains("<section class=\"release\" id=\"1.0.0\">\n<h2><a href=\"#1.0.0\">1.0.0</a></h2>\n<ul>"));
+                let feed = read_to_string(temp_dir.join("output/releases.xml"))?;
+                assert!(feed.contains("<link>https://example.com/releases.html</link>"));
+                assert!(feed.contains(
+                    "<title>1.1.0</title>\n<link>https://example.com/releases.html#1.1.0</link>"
+                ));
+                assert!(feed.contains("<pubDate>03 Feb 2024 00:00:00 +0000</pubDate>"));
+                assert!(feed.contains("&lt;em&gt;things&lt;/em&gt;"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_last_updated() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut template_file = File::create(temp_dir.join("target/template.html"))?;
+                write!(
+                    template_file,
+                    "<!-- {{CONTENT}} --><footer>Updated <!-- {{LAST_UPDATED}} --></footer>"
+                )?;
+                template_file.flush()?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(config_file, "date_format = \"[year]\"\ntag_pages = true")?;
+                config_file.flush()?;
+                let mut dj_file = File::create(temp_dir.join("target/index.dj"))?;
+                write!(dj_file, "# Home")?;
+                dj_file.flush()?;
+                let mut post_file = File::create(temp_dir.join("target/post.dj"))?;
+                write!(post_file, "+++\ntags = [\"news\"]\n+++\n\n# Post")?;
+                post_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/index.html"))?;
+                let year = time::OffsetDateTime::now_utc().year();
+                assert!(html.ends_with(&format!("<footer>Updated {}</footer>", year)));
+                // Generated pages have no source file, so they're dated by the build
+                let tags = read_to_string(temp_dir.join("output/tags/news.html"))?;
+                assert!(tags.ends_with(&format!("<footer>Updated {}</footer>", year)));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_contributors() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let git = |args: &[&str]| -> anyhow::Result<()> {
+                    let status = std::process::Command::new("git")
+                        .arg("-C")
+                        .arg(&temp_dir)
+                        .args(args)
+                        .status()?;
+                    assert!(status.success());
+                    Ok(())
+                };
+                let mut template_file = File::create(temp_dir.join("target/template.html"))?;
+                write!(
+                    template_file,
+                    "<!-- {{CONTENT}} --><footer><!-- {{CONTRIBUTORS}} --></footer>"
+                )?;
+                template_file.flush()?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(
+                    config_file,
+                    "[contributors]\nitem_template = '<img src=\"{{AVATAR}}\">{{NAME}}'\navatar_url = \"https://example.com/{{EMAIL}}\""
+                )?;
+                config_file.flush()?;
+                git(&["init", "-q"])?;
+                for (name, text) in [("A & Co", "one"), ("B", "two"), ("A & Co", "three")] {
+                    let mut dj_file = File::create(temp_dir.join("target/index.dj"))?;
+                    write!(dj_file, "{}", text)?;
+                    dj_file.flush()?;
+                    git(&["add", "target/index.dj"])?;
+                    git(&[
+                        "-c",
+                        &format!("user.name={}", name),
+                        "-c",
+                        &format!("user.email={}@example.com", name.chars().next().unwrap()),
+                        "commit",
+                        "-q",
+                        "-m",
+                        text,
+                    ])?;
+                }
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(html.ends_with("<footer><img src=\"https://example.com/A@example.com\">A &amp; Co, <img src=\"https://example.com/B@example.com\">B</footer>"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_unlisted_page() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/drafts"))?;
+                let mut template_file = File::create(temp_dir.join("target/template.html"))?;
+                write!(
+                    template_file,
+                    "<!-- {{TABLE_OF_CONTENTS}} --><!-- {{CONTENT}} -->"
+                )?;
+                template_file.flush()?;
+                let mut dj_file = File::create(temp_dir.join("target/index.dj"))?;
+                write!(dj_file, "# Home")?;
+                dj_file.flush()?;
+                let mut md_file = File::create(temp_dir.join("target/drafts/secret.md"))?;
+                write!(md_file, "+++\nunlisted = true\n+++\n# Secret")?;
+                md_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(!html.contains("secret"));
+                assert!(!html.contains("drafts"));
+                let html = read_to_string(temp_dir.join("output/drafts/secret.html"))?;
+                assert!(html.ends_with("<h1 id=\"secret\">Secret</h1>\n"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_encrypted_page() -> anyhow::Result<()> {
+    use aes_gcm::{aead::Aead, Aes256Gcm, Key, KeyInit, Nonce};
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut md_file = File::create(temp_dir.join("target/private.md"))?;
+                write!(md_file, "+++\npassword = \"hunter2\"\n+++\n# Secret plans")?;
+                md_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/private.html"))?;
+                assert!(!html.contains("Secret plans"));
+                assert!(html.contains("crypto.subtle.decrypt"));
+                let attribute = |name: &str| -> anyhow::Result<Vec<u8>> {
+                    let start = html.find(&format!("data-{}=\"", name)).unwrap() + name.len() + 7;
+                    let end = start + html[start..].find('"').unwrap();
+                    Ok(STANDARD.decode(&html[start..end])?)
+                };
+                let key = crate::encrypt::derive_key("hunter2", &attribute("salt")?);
+                let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+                let decrypted = cipher
+                    .decrypt(
+                        Nonce::from_slice(&attribute("iv")?),
+                        attribute("ciphertext")?.as_slice(),
+                    )
+                    .unwrap();
+                assert_eq!(
+                    String::from_utf8(decrypted)?,
+                    "<h1 id=\"secret-plans\">Secret plans</h1>\n"
+                );
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_variables() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(
+                    config_file,
+                    "[variables]\nversion = \"1.2.3\"\nproduct_name = \"Widget\""
+                )?;
+                config_file.flush()?;
+                let mut md_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(
+                    md_file,
+                    "# {{{{ product_name }}}} {{{{version}}}}\n\n`install widget@{{{{version}}}}` {{{{unknown}}}} {{{{"
+                )?;
+                md_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/index.html"))?;
+                assert_eq!(
+                    html,
+                    "<h1 id=\"widget-1-2-3\">Widget 1.2.3</h1>\n<p><code>install widget@{{version}}</code> {{unknown}} {{</p>\n"
+                );
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_conditional_content() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut dj_file = File::create(temp_dir.join("target/index.dj"))?;
+                write!(
+                    dj_file,
+                    "Everyone\n\n::: only-internal\nInternal\n:::\n\n::: only-public\nPublic\n:::"
+                )?;
+                dj_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    flags: vec!["internal".to_string()],
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(html.contains("Everyone"));
+                assert!(html.contains("Internal"));
+                assert!(!html.contains("Public"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn lint_rules() -> anyhow::Result<()> {
+    let config: crate::config::SiteConfig = toml::from_str(
+        "[lint]\nsingle_h1 = true\nmax_heading_depth = 2\n[[lint.required_sections]]\npath = \"api\"\nheadings = [\"Overview\", \"Examples\"]",
+    )?;
+    let lint = |input: &str, path: &str| {
+        crate::lint::lint_page(input, true, std::path::Path::new(path), &config.lint)
+    };
+    assert!(lint("# Title\n\n## Overview\n\n## Examples", "api/thing.md").is_empty());
+    assert_eq!(
+        lint("# Title\n\n### Deep\n\n# `Another`", "guide.md"),
+        vec![
+            "expected exactly one level 1 heading, found 2".to_string(),
+            "heading \"Deep\" is level 3, deeper than the maximum of 2".to_string(),
+        ]
+    );
+    assert_eq!(
+        lint("# Title\n\n## Overview", "api/thing.md"),
+        vec!["missing required section \"Examples\"".to_string()]
+    );
+
+    let config: crate::config::SiteConfig = toml::from_str("[lint]\nlink_text = true")?;
+    let lint = |input: &str| {
+        crate::lint::lint_page(input, true, std::path::Path::new("page.md"), &config.lint)
+    };
+    assert!(
+        lint("See [the install guide](install.md) and [the install\nguide](install.md).")
+            .is_empty()
+    );
+    assert_eq!(
+        lint("Read it [here](a.md), or [Click here!](b.md).\n\n<https://example.com>\n\n[Docs](a.md) and [docs](b.md)"),
+        vec![
+            "the link to \"a.md\" has the vague text \"here\", describe where it goes instead".to_string(),
+            "the link to \"b.md\" has the vague text \"Click here!\", describe where it goes instead".to_string(),
+            "the link to \"https://example.com\" has a bare url as its text, describe where it goes instead".to_string(),
+            "links to \"a.md\" and \"b.md\" have the same text \"docs\", so they can't be told apart".to_string(),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn site_with_page_references() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/guides"))?;
+                let mut md_file = File::create(temp_dir.join("target/guides/install.md"))?;
+                write!(
+                    md_file,
+                    "+++\nid = \"install\"\n+++\n# Install\n\n[Home](ref:home#top)"
+                )?;
+                md_file.flush()?;
+                let mut md_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(
+                    md_file,
+                    "+++\nid = \"home\"\n+++\n[Installing](ref:install) [Missing](ref:missing)"
+                )?;
+                md_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(html.contains("<a href=\"guides/install.html\">Installing</a>"));
+                assert!(html.contains("<a href=\"ref:missing\">Missing</a>"));
+                let html = read_to_string(temp_dir.join("output/guides/install.html"))?;
+                assert!(html.contains("<a href=\"../index.html#top\">Home</a>"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_moved_page_redirects() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/guides"))?;
+                let git = |args: &[&str]| -> anyhow::Result<()> {
+                    let status = std::process::Command::new("git")
+                        .arg("-C")
+                        .arg(&temp_dir)
+                        .args(["-c", "user.name=A", "-c", "user.email=a@example.com"])
+                        .args(args)
+                        .status()?;
+                    assert!(status.success());
+                    Ok(())
+                };
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(config_file, "redirect_moved_pages = true")?;
+                config_file.flush()?;
+                let mut md_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(md_file, "# Home")?;
+                md_file.flush()?;
+                let mut md_file = File::create(temp_dir.join("target/setup.md"))?;
+                write!(
+                    md_file,
+                    "# Setting up\n\nSome longer content that stays the same"
+                )?;
+                md_file.flush()?;
+                git(&["init", "-q"])?;
+                git(&["add", "."])?;
+                git(&["commit", "-q", "-m", "Add pages"])?;
+                git(&["mv", "target/setup.md", "target/install.md"])?;
+                git(&["commit", "-q", "-m", "Rename"])?;
+                git(&["mv", "target/install.md", "target/guides/install.md"])?;
+                git(&["commit", "-q", "-m", "Move"])?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                assert!(temp_dir.join("output/guides/install.html").exists());
+                let html = read_to_string(temp_dir.join("output/setup.html"))?;
+                assert!(html.contains(
+                    "<meta http-equiv=\"refresh\" content=\"0; url=guides/install.html\">"
+                ));
+                let html = read_to_string(temp_dir.join("output/install.html"))?;
+                assert!(html.contains("url=guides/install.html"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_split_sitemap() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(
+                    config_file,
+                    "[sitemap]\nbase_url = \"https://example.com/docs/\"\nmax_urls = 2"
+                )?;
+                config_file.flush()?;
+                for name in ["index", "a", "b", "c"] {
+                    let mut md_file = File::create(temp_dir.join(format!("target/{}.md", name)))?;
+                    write!(md_file, "# {}", name)?;
+                    md_file.flush()?;
+                }
+                let mut md_file = File::create(temp_dir.join("target/hidden.md"))?;
+                write!(md_file, "+++\nunlisted = true\n+++\n# Hidden")?;
+                md_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/sitemap.xml"))?;
+                assert!(index.contains("<sitemapindex"));
+                assert!(index.contains("<sitemap><loc>https://example.com/docs/sitemap-1.xml</loc></sitemap>\n<sitemap><loc>https://example.com/docs/sitemap-2.xml</loc></sitemap>\n</sitemapindex>"));
+                let sitemaps = read_to_string(temp_dir.join("output/sitemap-1.xml"))?
+                    + &read_to_string(temp_dir.join("output/sitemap-2.xml"))?;
+                assert_eq!(sitemaps.matches("<url>").count(), 4);
+                // Pages without a date are dated by when their source was last modified
+                let modified = std::fs::metadata(temp_dir.join("target/a.md"))?.modified()?;
+                assert!(sitemaps.contains(&format!(
+                    "<url><loc>https://example.com/docs/a.html</loc><lastmod>{}</lastmod></url>",
+                    time::OffsetDateTime::from(modified).date()
+                )));
+                assert!(!sitemaps.contains("hidden"));
+                assert!(!temp_dir.join("output/sitemap-3.xml").exists());
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_lite_mirror() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/guides"))?;
+                let mut md_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(
+                    md_file,
+                    "# Home\n\n![A diagram](diagram.png) [Guide](guides/guide.md)\n\n<script>alert(1)</script>\n\n<Style>p {{}}</Style>"
+                )?;
+                md_file.flush()?;
+                let mut md_file = File::create(temp_dir.join("target/guides/guide.md"))?;
+                write!(md_file, "# Guide")?;
+                md_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::GithubMarkdown),
+                    lite: true,
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(html.contains("<script>alert(1)</script>"));
+                let lite = read_to_string(temp_dir.join("output/lite/index.html"))?;
+                assert!(lite.contains("<h1 id=\"home\">Home</h1>"));
+                assert!(lite.contains("<p>[A diagram] <a href=\"guides/guide.html\">Guide</a></p>"));
+                assert!(!lite.contains("<script"));
+                assert!(!lite.to_lowercase().contains("<style"));
+                assert!(lite.contains("<a href=\"guides/guide.html\">guide</a>"));
+                assert!(temp_dir.join("output/lite/guides/guide.html").exists());
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_theme_toggle() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(
+                    config_file,
+                    "[template_options]\ncolor_scheme = \"dark\"\ntheme_toggle = true\nprint_button = true"
+                )?;
+                config_file.flush()?;
+                let mut md_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(md_file, "# Home")?;
+                md_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::GithubMarkdown),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(html.contains(".content-body {\n        color-scheme: dark;"));
+                assert!(!html.contains("@media (prefers-color-scheme: dark)"));
+                assert!(html.contains(
+                    ":root[data-theme=\"light\"] .content-body {\n        color-scheme: light;"
+                ));
+                assert!(html.contains("<button class=\"theme-toggle\""));
+                assert!(html.contains("<button class=\"print-button\""));
+                assert!(html.contains("localStorage.setItem(\"theme\""));
+                assert!(!html.contains("{COLOR_SCHEME}"));
+                assert!(!html.contains("<!-- {THEME"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_docs_template() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/guides"))?;
+                let mut dir_file = File::create(temp_dir.join("target/guides/_dir.toml"))?;
+                write!(dir_file, "_title = \"Guides\"")?;
+                dir_file.flush()?;
+                for page in ["index.md", "guides/index.md", "guides/install.md"] {
+                    let mut md_file = File::create(temp_dir.join("target").join(page))?;
+                    write!(md_file, "# {}", page)?;
+                    md_file.flush()?;
+                }
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::Docs),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/guides/install.html"))?;
+                assert!(html.contains("<nav class=\"breadcrumbs\" aria-label=\"Breadcrumbs\"><ol><li><a href=\"../index.html\">Home</a></li><li><a href=\"../guides/index.html\">Guides</a></li><li aria-current=\"page\">install</li></ol></nav>"));
+                assert!(html.contains("<nav class=\"prev-next\"><a class="));
+                assert!(html.contains("<label for=\"menu-toggle\" class=\"menu-button\""));
+                let html = read_to_string(temp_dir.join("output/guides/index.html"))?;
+                assert!(html.contains("<li aria-current=\"page\">Guides</li></ol>"));
+                assert!(!html.contains("<!-- {"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_blog_template() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/posts"))?;
+                let mut md_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(md_file, "# Blog")?;
+                md_file.flush()?;
+                let mut md_file = File::create(temp_dir.join("target/posts/hello.md"))?;
+                write!(md_file, "# Hello")?;
+                md_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::Blog),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/posts/hello.html"))?;
+                assert!(html.contains(".post-list"));
+                assert!(html.contains("<li aria-current=\"page\">hello</li>"));
+                assert!(html.contains("<summary>All pages</summary>"));
+                assert!(!html.contains("<!-- {"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_template_options() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/guides"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(
+                    config_file,
+                    "[template_options]\naccent_color = \"#d63384\"\nfont_family = \"Georgia, serif\"\nlogo = \"logo.svg\"\nfooter = \"&copy; Example\""
+                )?;
+                config_file.flush()?;
+                let mut md_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(md_file, "# Home")?;
+                md_file.flush()?;
+                let mut md_file = File::create(temp_dir.join("target/guides/install.md"))?;
+                write!(md_file, "# Install")?;
+                md_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::Docs),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/guides/install.html"))?;
+                assert!(html.contains("body { --fgColor-accent: #d63384 !important; }"));
+                assert!(html.contains("body { font-family: Georgia, serif; }"));
+                assert!(html.contains("<img class=\"logo\" src=\"../logo.svg\" alt=\"\">"));
+                assert!(html.contains("<div class=\"site-footer\">&copy; Example</div>"));
+                assert!(html.contains("@media print"));
+                assert!(html.contains("addEventListener(\"beforeprint\""));
+                assert!(!html.contains("<!-- {PRINT_BUTTON} -->"));
+                assert!(!html.contains("class=\"print-button\" type"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_rtl_pages() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(config_file, "lang = \"en\"")?;
+                config_file.flush()?;
+                let mut md_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(md_file, "# Home")?;
+                md_file.flush()?;
+                let mut md_file = File::create(temp_dir.join("target/arabic.md"))?;
+                write!(md_file, "+++\nlang = \"ar-EG\"\n+++\n# مرحبا")?;
+                md_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::Docs),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(html.contains("<html lang=\"en\" dir=\"ltr\">"));
+                let html = read_to_string(temp_dir.join("output/arabic.html"))?;
+                assert!(html.contains("<html lang=\"ar-EG\" dir=\"rtl\">"));
+                assert!(html.contains("[dir=\"rtl\"] .docs-sidebar"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_stylesheet_cascade() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/guides/advanced"))?;
+                for stylesheet in ["style.css", "guides/style.css"] {
+                    let mut css_file = File::create(temp_dir.join("target").join(stylesheet))?;
+                    write!(css_file, "body {{ color: red; }}")?;
+                    css_file.flush()?;
+                }
+                for page in ["index.md", "guides/advanced/tips.md"] {
+                    let mut md_file = File::create(temp_dir.join("target").join(page))?;
+                    write!(md_file, "# Page")?;
+                    md_file.flush()?;
+                }
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::Blog),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(html.contains("<link rel=\"stylesheet\" href=\"style.css\">\n</head>"));
+                let html = read_to_string(temp_dir.join("output/guides/advanced/tips.html"))?;
+                assert!(html.contains("<link rel=\"stylesheet\" href=\"../../guides/style.css\">"));
+                assert!(temp_dir.join("output/guides/style.css").exists());
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_partials() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/guides"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(config_file, "[template_options]\nfooter = \"Site footer\"")?;
+                config_file.flush()?;
+                let mut head_file = File::create(temp_dir.join("target/_head.html"))?;
+                write!(
+                    head_file,
+                    "<meta name=\"generator\" content=\"simple-ssg\">"
+                )?;
+                head_file.flush()?;
+                let mut footer_file = File::create(temp_dir.join("target/guides/_footer.html"))?;
+                write!(footer_file, "<p>Guides footer</p>")?;
+                footer_file.flush()?;
+                for page in ["index.md", "guides/install.md"] {
+                    let mut md_file = File::create(temp_dir.join("target").join(page))?;
+                    write!(md_file, "# Page")?;
+                    md_file.flush()?;
+                }
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::Docs),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(html.contains("<meta name=\"generator\" content=\"simple-ssg\">"));
+                assert!(html.contains("<div class=\"site-footer\">Site footer</div>"));
+                let html = read_to_string(temp_dir.join("output/guides/install.html"))?;
+                assert!(html.contains("<meta name=\"generator\" content=\"simple-ssg\">"));
+                assert!(html.contains("<p>Guides footer</p>"));
+                assert!(!html.contains("Site footer"));
+                assert!(!temp_dir.join("output/_head.html").exists());
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn single_file_to_stdout() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(&temp_dir)?;
+                let mut md_file = File::create(temp_dir.join("page.md"))?;
+                write!(md_file, "# Page")?;
+                md_file.flush()?;
+
+                let args = ConsoleArgs {
+                    file: Some(temp_dir.join("page.md")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    stdout: true,
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                assert!(!temp_dir.join("page.html").exists());
+                assert_eq!(
+                    crate::render_page("# Page", Default::default(), true)?,
+                    "<h1 id=\"page\">Page</h1>\n"
+                );
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_as_fragments() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(config_file, "lang = \"ar\"")?;
+                config_file.flush()?;
+                let mut template_file = File::create(temp_dir.join("target/template.html"))?;
+                write!(
+                    template_file,
+                    "<html><head></head><!-- {{CONTENT}} --></html>"
+                )?;
+                template_file.flush()?;
+                let mut css_file = File::create(temp_dir.join("target/style.css"))?;
+                write!(css_file, "body {{ color: red; }}")?;
+                css_file.flush()?;
+                let mut md_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(md_file, "# Home")?;
+                md_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    fragment: true,
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                assert_eq!(
+                    read_to_string(temp_dir.join("output/index.html"))?,
+                    "<h1 id=\"home\">Home</h1>\n"
+                );
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn ci_summary_fails_on_errors() {
+    let summary = crate::diagnostics::summarize(&Err(anyhow::anyhow!("Target path missing")));
+    assert!(!summary.success);
+    assert_eq!(summary.error.as_deref(), Some("Target path missing"));
+    let json = serde_json::to_string(&summary).unwrap();
+    assert!(json.starts_with("{\"success\":false,\"error\":\"Target path missing\","));
+}
+
+#[test]
+fn site_skips_pages_over_limits() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(config_file, "[limits]\nmax_page_size = 1000")?;
+                config_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.dj"))?;
+                write!(index_file, "# Home")?;
+                index_file.flush()?;
+                let mut huge_file = File::create(temp_dir.join("target/huge.dj"))?;
+                write!(huge_file, "{}", "- item\n".repeat(500))?;
+                huge_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                let report = crate::run_program(args)?;
+                assert!(temp_dir.join("output/index.html").exists());
+                assert!(!temp_dir.join("output/huge.html").exists());
+                assert_eq!(report.pages, [temp_dir.join("output/index.html")]);
+                assert_eq!(report.warnings.len(), 1);
+                assert!(report.warnings[0].contains("huge.dj"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_skips_pages_as_they_outgrow_limits() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(
+                    config_file,
+                    "[limits]\nmax_page_size = 1000\n\n[lint]\nsingle_h1 = true"
+                )?;
+                config_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.dj"))?;
+                write!(index_file, "# Home")?;
+                index_file.flush()?;
+                let mut icon_file = File::create(temp_dir.join("target/icon.svg"))?;
+                write!(
+                    icon_file,
+                    "<svg xmlns=\"http://www.w3.org/2000/svg\">{}</svg>",
+                    "<rect width=\"1\" height=\"1\"/>".repeat(50)
+                )?;
+                icon_file.flush()?;
+                let mut icon_page = File::create(temp_dir.join("target/icon.dj"))?;
+                write!(icon_page, "{{{{svg icon.svg}}}}")?;
+                icon_page.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                let report = crate::run_program(args)?;
+                assert!(!temp_dir.join("output/icon.html").exists());
+                // The page is skipped once its shortcodes are expanded, before it's linted
+                assert_eq!(report.warnings.len(), 1);
+                assert!(report.warnings[0].contains("icon.dj"));
+                assert!(report.warnings[0].contains("page size limit"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn sandboxed_site_stays_inside_target() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut secret_file = File::create(temp_dir.join("secret.md"))?;
+                write!(secret_file, "Secret")?;
+                secret_file.flush()?;
+                std::os::unix::fs::symlink(
+                    temp_dir.join("secret.md"),
+                    temp_dir.join("target/secret.md"),
+                )?;
+                std::os::unix::fs::symlink(
+                    temp_dir.join("secret.md"),
+                    temp_dir.join("target/template.html"),
+                )?;
+                let mut index_file = File::create(temp_dir.join("target/index.dj"))?;
+                write!(
+                    index_file,
+                    "# Home\n\n```=html\n<script>alert(1)</script>\n```\n\nText `<b>`{{=html}}"
+                )?;
+                index_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    sandbox: true,
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let html = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(html.contains("Home</h1>"));
+                assert!(!html.contains("Secret"));
+                assert!(!html.contains("<script>"));
+                assert!(!html.contains("<b>"));
+                assert!(!temp_dir.join("output/secret.html").exists());
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn sandboxed_links_cannot_run_script() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(root.join("template.html"), "<!-- {CONTENT} -->");
+    fs.insert(
+        root.join("index.md"),
+        "# Home\n\n[a](javascript:alert(1)) [b](JavaScript:alert(2)) [c](vbscript:msgbox(3)) [d](data:text/html;base64,PHNjcmlwdD4=) [e](https://example.com)\n\n![f](javascript:alert(4)) ![g](data:text/html,x) ![h](data:image/png;base64,iVBORw0KGgo=)\n",
+    );
+    fs.insert(
+        root.join("page.dj"),
+        "[a](javascript:alert(1)) [b](vbscript:msgbox(2)) [c](data:text/html,x) ![d](javascript:alert(3)) ![e](data:image/gif;base64,R0lGOD==)\n",
+    );
+    let build = |sandbox| {
+        crate::run_program_with(
+            ConsoleArgs {
+                directory: Some(root.clone()),
+                output_path: Some(root.join("output")),
+                sandbox,
+                ..Default::default()
+            },
+            &fs,
+        )
+        .unwrap()
+    };
+    let report = build(true);
+    assert_eq!(
+        report
+            .warnings
+            .iter()
+            .filter(|warning| warning.contains("could run script"))
+            .count(),
+        10
+    );
+    let index = fs.read_to_string(&root.join("output/index.html")).unwrap();
+    assert!(!index.contains("javascript:") && !index.contains("JavaScript:"));
+    assert!(!index.contains("vbscript:"));
+    assert!(!index.contains("data:text/html"));
+    assert!(index.contains(
+        "<a href=\"#\">a</a> <a href=\"#\">b</a> <a href=\"#\">c</a> <a href=\"#\">d</a>"
+    ));
+    assert!(index.contains("<a href=\"https://example.com\">e</a>"));
+    assert!(index.contains("<img src=\"#\" alt=\"f\" /> <img src=\"#\" alt=\"g\" />"));
+    assert!(index.contains("<img src=\"data:image/png;base64,iVBORw0KGgo=\" alt=\"h\" />"));
+    let page = fs.read_to_string(&root.join("output/page.html")).unwrap();
+    assert!(!page.contains("javascript:") && !page.contains("vbscript:"));
+    assert!(!page.contains("data:text/html"));
+    assert!(page.contains("data:image/gif;base64,R0lGOD=="));
+
+    // Without --sandbox, pages are trusted
+    build(false);
+    let index = fs.read_to_string(&root.join("output/index.html")).unwrap();
+    assert!(index.contains("javascript:alert(1)"));
+}
+
+#[test]
+fn root_relative_links_with_web_prefix() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(root.join("template.html"), "<!-- {CONTENT} -->");
+    fs.insert(root.join("index.md"), "# Home\n");
+    fs.insert(root.join("docs/x.md"), "# X\n");
+    fs.insert(
+        root.join("guides/intro.md"),
+        "[Root](/docs/x.md#setup), [relative](../docs/x.md)\n",
+    );
+    let build = |web_prefix: Option<&str>| {
+        crate::run_program_with(
+            ConsoleArgs {
+                directory: Some(root.clone()),
+                output_path: Some(root.join("output")),
+                web_prefix: web_prefix.map(ToString::to_string),
+                ..Default::default()
+            },
+            &fs,
+        )
+        .unwrap();
+        fs.read_to_string(&root.join("output/guides/intro.html"))
+            .unwrap()
+    };
+    // Deployed under a sub-path, root-relative links are under it too
+    let intro = build(Some("/simple-ssg/"));
+    assert!(intro.contains("<a href=\"/simple-ssg/docs/x.html#setup\">Root</a>"));
+    let intro = build(Some("https://example.com/simple-ssg"));
+    assert!(intro.contains("<a href=\"https://example.com/simple-ssg/docs/x.html#setup\">Root</a>"));
+    let intro = build(None);
+    assert!(intro.contains("<a href=\"/docs/x.html#setup\">Root</a>"));
+    assert!(intro.contains("<a href=\"../docs/x.html\">relative</a>"));
+}
+
+#[test]
+fn links_outside_target_are_marked() {
+    use crate::vfs::FileSystem;
+    let target_path = temp_dir().join(temp_dir_name()).join("target");
+    let fs = crate::vfs::MemoryFs::new();
+    fs.create_dir_all(&target_path).unwrap();
+    let args = ConsoleArgs::default();
+    let context = crate::SiteContext::new(&target_path, &target_path, &args, &fs).unwrap();
+    let guides = target_path.join("guides");
+    for link in ["../index.md", "./a/../b.dj", "/guides/../notes.md"] {
+        let link = crate::links::rewrite_document_link(link, &guides, &context).unwrap();
+        assert!(!link.outside_target, "{}", link.url);
+    }
+    for link in [
+        "../../notes.md",
+        "../../../../etc/passwd.md",
+        "/../notes.md",
+    ] {
+        let link = crate::links::rewrite_document_link(link, &guides, &context).unwrap();
+        assert!(link.outside_target, "{}", link.url);
+    }
+    assert_eq!(
+        crate::utils::normalize_path(std::path::Path::new("../a/./b/../../c")),
+        std::path::Path::new("../c")
+    );
+}
+
+#[test]
+fn site_in_memory() -> anyhow::Result<()> {
+    use crate::vfs::FileSystem;
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    let fs = crate::vfs::MemoryFs::new();
+    fs.insert(
+        root.join("site/index.md"),
+        "# Home\n\n[Intro](guides/intro.dj)",
+    );
+    fs.insert(root.join("site/guides/intro.dj"), "# Intro");
+    fs.insert(root.join("site/guides/_dir.toml"), "_title = \"Guides\"");
+    fs.insert(root.join("site/logo.svg"), "<svg></svg>");
+
+    let args = ConsoleArgs {
+        directory: Some(root.join("site")),
+        output_path: Some(root.join("output")),
+        template: Some(crate::templates::BuiltInTemplate::Docs),
+        ..Default::default()
+    };
+    let report = crate::run_program_with(args, &fs)?;
+    assert_eq!(
+        report.pages,
+        [
+            root.join("output/guides/intro.html"),
+            root.join("output/index.html")
+        ]
+    );
+    assert_eq!(report.assets, [root.join("output/logo.svg")]);
+    assert!(report.warnings.is_empty());
+    let html = fs.read_to_string(&root.join("output/index.html"))?;
+    assert!(html.contains("<a href=\"guides/intro.html\">Intro</a>"));
+    assert!(html.contains("Guides"));
+    assert!(fs.is_file(&root.join("output/guides/intro.html")));
+    assert_eq!(fs.read(&root.join("output/logo.svg"))?, b"<svg></svg>");
+    let output = root.join("output");
+    assert_eq!(
+        fs.files()
+            .iter()
+            .filter(|file| file.starts_with(&output))
+            .count(),
+        3
+    );
+    assert!(!root.exists());
+    // Like the disk, files can't be written into missing directories
+    assert_eq!(
+        fs.write(&root.join("missing/file.txt"), b"")
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::NotFound
+    );
+    let report = crate::SiteBuilder::new(root.join("site"))
+        .output_path(root.join("built"))
+        .generate_site_with(&fs)?;
+    assert_eq!(report.pages.len(), 2);
+    assert!(fs.is_file(&root.join("built/guides/intro.html")));
+    Ok(())
+}
+
+#[test]
+fn rendering_snapshots() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    // Run with UPDATE_SNAPSHOTS=1 to rewrite the snapshots after changing the rendering
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots");
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let mismatches =
+        crate::snapshots::check_snapshots(&crate::vfs::RealFs, &fixtures, None, update).unwrap();
+    for mismatch in &mismatches {
+        log::error!("{}", mismatch);
+    }
+    assert!(
+        mismatches.is_empty(),
+        "{} snapshot(s) don't match",
+        mismatches.len()
+    );
+}
+
+#[test]
+fn site_slugs_follow_config() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(
+                    config_file,
+                    "[slugs]\nseparator = \"_\"\nmax_length = 12\nfile_names = true"
+                )?;
+                config_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.dj"))?;
+                write!(
+                    index_file,
+                    "# Über Uns\n\nRead the [guide](Getting Started.md)."
+                )?;
+                index_file.flush()?;
+                let mut guide_file = File::create(temp_dir.join("target/Getting Started.md"))?;
+                write!(guide_file, "# Café Setup\n\n## Café Setup\n\n## Über Uns")?;
+                guide_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains("id=\"uber_uns\""));
+                assert!(index.contains("href=\"getting_star.html\""));
+                let guide = read_to_string(temp_dir.join("output/getting_star.html"))?;
+                assert!(guide.contains("<h1 id=\"cafe_setup\">"));
+                assert!(guide.contains("<h2 id=\"cafe_setup_1\">"));
+                assert!(guide.contains("<h2 id=\"uber_uns\">"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_numbers_sections() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/guides"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(
+                    config_file,
+                    "[toc]\nnumbered = true\nnumber_headings = true"
+                )?;
+                config_file.flush()?;
+                let mut template_file = File::create(temp_dir.join("target/template.html"))?;
+                write!(
+                    template_file,
+                    "<!-- {{TABLE_OF_CONTENTS}} --><!-- {{CONTENT}} -->"
+                )?;
+                template_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(index_file, "Welcome")?;
+                index_file.flush()?;
+                let mut intro_file = File::create(temp_dir.join("target/intro.md"))?;
+                write!(intro_file, "# Intro\n\n## Scope\n\n## Terms")?;
+                intro_file.flush()?;
+                let mut setup_file = File::create(temp_dir.join("target/guides/setup.md"))?;
+                write!(setup_file, "# Setup\n\n### Detail")?;
+                setup_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains("<span class=\"section-number\">1</span> guides:"));
+                assert!(index.contains("<span class=\"section-number\">1.1</span> setup"));
+                assert!(index.contains("<span class=\"section-number\">2</span> intro"));
+                let intro = read_to_string(temp_dir.join("output/intro.html"))?;
+                assert!(intro.contains(
+                    "<h1 id=\"intro\"><span class=\"section-number\">2</span> Intro</h1>"
+                ));
+                assert!(intro.contains(
+                    "<h2 id=\"terms\"><span class=\"section-number\">2.2</span> Terms</h2>"
+                ));
+                let setup = read_to_string(temp_dir.join("output/guides/setup.html"))?;
+                assert!(setup.contains(
+                    "<h3 id=\"detail\"><span class=\"section-number\">1.1.1</span> Detail"
+                ));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_glossary_links_terms() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(config_file, "[glossary]\nlink_terms = true")?;
+                config_file.flush()?;
+                let mut glossary_file = File::create(temp_dir.join("target/_glossary.dj"))?;
+                write!(
+                    glossary_file,
+                    ": API\n\n  Application programming interface.\n\n: Djot\n\n  A light markup language."
+                )?;
+                glossary_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(index_file, "The API is stable. The API is `API`.")?;
+                index_file.flush()?;
+                let mut guide_file = File::create(temp_dir.join("target/guide.md"))?;
+                write!(guide_file, "## API\n\nWrite Djot, not Djots.")?;
+                guide_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains(
+                    "The <a class=\"glossary-term\" href=\"glossary.html#api\">API</a> is stable. The API is"
+                ));
+                let guide = read_to_string(temp_dir.join("output/guide.html"))?;
+                assert!(guide.contains("<h2 id=\"api\">API</h2>"));
+                assert!(guide.contains(
+                    "Write <a class=\"glossary-term\" href=\"glossary.html#djot\">Djot</a>, not Djots."
+                ));
+                let glossary = read_to_string(temp_dir.join("output/glossary.html"))?;
+                assert!(glossary.contains("<dt id=\"api\">API</dt>"));
+                assert!(glossary.contains("Application programming interface."));
+                assert!(glossary.contains("Used in: <a href=\"index.html\">index</a>"));
+                assert!(glossary.contains("Used in: <a href=\"guide.html\">guide</a>"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_renders_citations() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(config_file, "[bibliography]\npath = \"refs.bib\"")?;
+                config_file.flush()?;
+                let mut bib_file = File::create(temp_dir.join("target/refs.bib"))?;
+                write!(
+                    bib_file,
+                    "@book{{knuth1984,\n  author = {{Knuth, Donald E.}},\n  title = {{The {{TeX}}book}},\n  publisher = {{Addison-Wesley}},\n  year = 1984\n}}\n\n@book{{lamport1994,\n  author = \"Leslie Lamport\",\n  title = \"LaTeX\",\n  year = \"1994\"\n}}\n"
+                )?;
+                bib_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(
+                    index_file,
+                    "As shown [@knuth1984, p. 12; @lamport1994], not [@missing]. `[@knuth1984]`"
+                )?;
+                index_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                let report = crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains("<span class=\"citation\">(<a href=\"#ref-knuth1984\">Knuth 1984</a>, p. 12; <a href=\"#ref-lamport1994\">Lamport 1994</a>)</span>"));
+                assert!(index.contains("not [@missing]."));
+                assert!(index.contains("<code>[@knuth1984]</code>"));
+                assert!(index.contains("<li id=\"ref-knuth1984\">Knuth, Donald E. (1984). <cite>The TeXbook</cite>. Addison-Wesley.</li>"));
+                assert!(index.contains(
+                    "<li id=\"ref-lamport1994\">Lamport, Leslie (1994). <cite>LaTeX</cite>.</li>"
+                ));
+                assert_eq!(report.warnings.len(), 1);
+                assert!(report.warnings[0].contains("@missing"));
+
+                let csl = crate::bibliography::parse_csl_json(
+                    r#"[{"id": "knuth1984", "author": [{"family": "Knuth", "given": "Donald E."}], "title": "The TeXbook", "issued": {"date-parts": [[1984, 1]]}, "DOI": "10.5555/1102013"}]"#,
+                )?;
+                assert_eq!(
+                    csl[0].authors,
+                    [("Knuth".to_string(), "Donald E.".to_string())]
+                );
+                assert_eq!(csl[0].year.as_deref(), Some("1984"));
+                assert_eq!(
+                    csl[0].url.as_deref(),
+                    Some("https://doi.org/10.5555/1102013")
+                );
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_expands_abbreviations() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut abbreviations_file =
+                    File::create(temp_dir.join("target/_abbreviations.toml"))?;
+                write!(
+                    abbreviations_file,
+                    "SSG = \"Static site generator\"\nHTML = \"HyperText Markup Language\""
+                )?;
+                abbreviations_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(
+                    index_file,
+                    "*[HTML]: Hypertext & more\n\nAn SSG makes HTML, not `SSG` or SSGs.\n"
+                )?;
+                index_file.flush()?;
+                let mut other_file = File::create(temp_dir.join("target/other.md"))?;
+                write!(other_file, "Plain HTML.")?;
+                other_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains(
+                    "<p>An <abbr title=\"Static site generator\">SSG</abbr> makes <abbr title=\"Hypertext &amp; more\">HTML</abbr>, not <code>SSG</code> or SSGs.</p>"
+                ));
+                assert!(!index.contains("*[HTML]"));
+                let other = read_to_string(temp_dir.join("output/other.html"))?;
+                assert!(other.contains(
+                    "<p>Plain <abbr title=\"HyperText Markup Language\">HTML</abbr>.</p>"
+                ));
+                assert!(!temp_dir.join("output/_abbreviations.toml").exists());
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_classifies_links() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(
+                    config_file,
+                    "[links]\nexternal_icon = \"<span class=\\\"icon\\\">↗</span>\"\ninternal_class = \"\""
+                )?;
+                config_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(
+                    index_file,
+                    "[Other](other.md), [Rust](https://www.rust-lang.org/), [Guide](files/guide.PDF?v=2), [Mail](mailto:me@example.com), [Top](#top)"
+                )?;
+                index_file.flush()?;
+                File::create(temp_dir.join("target/other.md"))?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains("<a href=\"other.html\">Other</a>"));
+                assert!(index.contains("<a href=\"https://www.rust-lang.org/\" class=\"external\">Rust<span class=\"icon\">↗</span></a>"));
+                assert!(
+                    index.contains("<a href=\"files/guide.PDF?v=2\" class=\"download\">Guide</a>")
+                );
+                assert!(index.contains("<a href=\"mailto:me@example.com\" class=\"external\">"));
+                assert!(index.contains("<a href=\"#top\">Top</a>"));
+
+                let config = crate::config::LinksConfig::default();
+                assert_eq!(
+                    crate::links::classify_links(
+                        "<a class='button' href='https://example.com/docs/a.html'>A</a> <abbr>B</abbr> <a id=\"c\">C</a>",
+                        &config,
+                        Some("https://example.com/docs/")
+                    ),
+                    "<a class='button internal' href='https://example.com/docs/a.html'>A</a> <abbr>B</abbr> <a id=\"c\">C</a>"
+                );
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_generates_asset_index() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/files"))?;
+                let mut template_file = File::create(temp_dir.join("target/template.html"))?;
+                write!(
+                    template_file,
+                    "<!-- {{TABLE_OF_CONTENTS}} --><!-- {{CONTENT}} -->"
+                )?;
+                template_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(index_file, "# Home")?;
+                index_file.flush()?;
+                let mut dir_file = File::create(temp_dir.join("target/files/_dir.toml"))?;
+                write!(dir_file, "_title = \"Downloads\"\n_asset_index = true")?;
+                dir_file.flush()?;
+                let mut pdf_file = File::create(temp_dir.join("target/files/guide.pdf"))?;
+                pdf_file.write_all(&[0; 2048])?;
+                pdf_file.flush()?;
+                let mut zip_file = File::create(temp_dir.join("target/files/a & b.zip"))?;
+                write!(zip_file, "zip")?;
+                zip_file.flush()?;
+                let mut notes_file = File::create(temp_dir.join("target/files/notes.md"))?;
+                write!(notes_file, "# Notes")?;
+                notes_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let listing = read_to_string(temp_dir.join("output/files/index.html"))?;
+                assert!(listing.contains("<h1>Downloads</h1>"));
+                assert!(listing.contains(
+                    "<tr><td><a href=\"a &amp; b.zip\">a &amp; b.zip</a></td><td>3 B</td><td>"
+                ));
+                assert!(listing.contains(
+                    "<tr><td><a href=\"guide.pdf\">guide.pdf</a></td><td>2.0 KiB</td><td>"
+                ));
+                assert!(!listing.contains("notes.md"));
+                assert!(!listing.contains("_dir.toml"));
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains("<a href=\"files/index.html\">"));
+                assert!(temp_dir.join("output/files/guide.pdf").exists());
+
+                assert_eq!(crate::asset_index::format_size(1023), "1023 B");
+                assert_eq!(crate::asset_index::format_size(1536 * 1024), "1.5 MiB");
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_renders_qr_shortcodes() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut index_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(
+                    index_file,
+                    "Scan {{{{qr https://example.com/?a=1&b=2}}}} or {{{{other thing}}}}.\n\n{{{{qr {}}}}}",
+                    "x".repeat(8000)
+                )?;
+                index_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                let report = crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains("<p>Scan <svg class=\"qr-code\" role=\"img\" aria-label=\"QR code: https://example.com/?a=1&amp;b=2\" xmlns=\"http://www.w3.org/2000/svg\""));
+                assert!(index.contains("<path fill=\"#000\" d=\"M"));
+                assert!(index.contains("</svg> or {{other thing}}.</p>"));
+                assert!(index.contains("<p>{{qr xxx"));
+                assert_eq!(report.warnings.len(), 1);
+                assert!(report.warnings[0].contains("QR code"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn watched_site_rebuilds_affected_pages() -> anyhow::Result<()> {
+    use crate::vfs::FileSystem;
+    use crate::watch::Change;
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    let fs = crate::vfs::MemoryFs::new();
+    let site = root.join("site");
+    let output = root.join("output");
+    fs.insert(site.join("template.html"), "<!-- {CONTENT} -->");
+    fs.insert(site.join("index.md"), "# Home");
+    fs.insert(site.join("guides/intro.md"), "# Intro");
+    fs.insert(site.join("guides/setup.md"), "# Setup");
+    fs.insert(site.join("logo.svg"), "<svg></svg>");
+
+    let args = ConsoleArgs::default();
+    let mut watcher = crate::watch::SiteWatcher::new(&site, &output, &args, &fs);
+    assert_eq!(watcher.build()?.pages.len(), 3);
+
+    // Only the changed page is written again
+    fs.insert(site.join("guides/intro.md"), "# Introduction");
+    let report = watcher.rebuild(&[Change::Modified(site.join("guides/intro.md"))])?;
+    assert_eq!(report.pages, [output.join("guides/intro.html")]);
+    assert!(fs
+        .read_to_string(&output.join("guides/intro.html"))?
+        .contains("Introduction"));
+
+    // A template rewrites the pages below it
+    fs.insert(
+        site.join("guides/template.html"),
+        "<main><!-- {CONTENT} --></main>",
+    );
+    let report = watcher.rebuild(&[Change::Modified(site.join("guides/template.html"))])?;
+    assert_eq!(
+        report.pages,
+        [
+            output.join("guides/intro.html"),
+            output.join("guides/setup.html")
+        ]
+    );
+    assert!(fs
+        .read_to_string(&output.join("guides/setup.html"))?
+        .starts_with("<main>"));
+
+    // Changed assets are copied again
+    fs.insert(site.join("logo.svg"), "<svg/>");
+    let report = watcher.rebuild(&[Change::Modified(site.join("logo.svg"))])?;
+    assert!(report.pages.is_empty());
+    assert_eq!(fs.read(&output.join("logo.svg"))?, b"<svg/>");
+
+    // Unlisting a page changes every page's table of contents
+    fs.insert(
+        site.join("guides/setup.md"),
+        "+++\nunlisted = true\n+++\n# Setup",
+    );
+    let report = watcher.rebuild(&[Change::Modified(site.join("guides/setup.md"))])?;
+    assert_eq!(report.pages.len(), 3);
+
+    // So does retitling one, or changing its date or tags
+    fs.insert(
+        site.join("guides/intro.md"),
+        "+++\ntitle = \"Getting started\"\n+++\n# Introduction",
+    );
+    let report = watcher.rebuild(&[Change::Modified(site.join("guides/intro.md"))])?;
+    assert_eq!(report.pages.len(), 3);
+    fs.insert(
+        site.join("guides/intro.md"),
+        "+++\ntitle = \"Getting started\"\ndate = 2024-01-01\ntags = [\"rust\"]\n+++\n# Introduction",
+    );
+    let report = watcher.rebuild(&[Change::Modified(site.join("guides/intro.md"))])?;
+    assert_eq!(report.pages.len(), 3);
+    let report = watcher.rebuild(&[Change::Modified(site.join("guides/intro.md"))])?;
+    assert_eq!(report.pages, [output.join("guides/intro.html")]);
+
+    // New files rebuild the whole site
+    fs.insert(site.join("guides/faq.md"), "# FAQ");
+    let report = watcher.rebuild(&[Change::Structure])?;
+    assert_eq!(report.pages.len(), 4);
+    assert!(fs.is_file(&output.join("guides/faq.html")));
+    Ok(())
+}
+
+#[test]
+fn watched_site_rebuilds_site_wide_outputs() -> anyhow::Result<()> {
+    use crate::vfs::FileSystem;
+    use crate::watch::Change;
+    let root = std::path::PathBuf::from(temp_dir_name());
+    let fs = crate::vfs::MemoryFs::new();
+    let site = root.join("site");
+    let output = root.join("output");
+    fs.insert(
+        site.join("ssg.toml"),
+        "[sitemap]\nbase_url = \"https://example.com\"\n\n[feed]\n",
+    );
+    fs.insert(site.join("template.html"), "<!-- {CONTENT} -->");
+    fs.insert(site.join("index.md"), "# Home");
+    fs.insert(
+        site.join("meetup.md"),
+        "+++\ntitle = \"Meetup\"\ndate = 2024-04-01\nevent_date = 2024-05-01\n+++\nSee you there",
+    );
+
+    let args = ConsoleArgs::default();
+    let mut watcher = crate::watch::SiteWatcher::new(&site, &output, &args, &fs);
+    assert_eq!(watcher.build()?.pages.len(), 3);
+
+    // Moving the event rewrites the calendar and its feed along with the page
+    fs.insert(
+        site.join("meetup.md"),
+        "+++\ntitle = \"Meetup\"\ndate = 2024-04-01\nevent_date = 2024-06-01\n+++\nSee you there",
+    );
+    let report = watcher.rebuild(&[Change::Modified(site.join("meetup.md"))])?;
+    assert_eq!(
+        report.pages,
+        [output.join("calendar.html"), output.join("meetup.html")]
+    );
+    assert!(fs
+        .read_to_string(&output.join("events.ics"))?
+        .contains("DTSTART;VALUE=DATE:20240601"));
+
+    // The feeds have the changed content of pages, which are dated so they are in them
+    fs.insert(
+        site.join("meetup.md"),
+        "+++\ntitle = \"Meetup\"\ndate = 2024-04-01\nevent_date = 2024-06-01\n+++\nBring snacks",
+    );
+    let report = watcher.rebuild(&[Change::Modified(site.join("meetup.md"))])?;
+    assert_eq!(report.pages, [output.join("meetup.html")]);
+    assert!(fs
+        .read_to_string(&output.join("feed.xml"))?
+        .contains("Bring snacks"));
+    assert!(fs
+        .read_to_string(&output.join("sitemap.xml"))?
+        .contains("<loc>https://example.com/meetup.html</loc>"));
+    Ok(())
+}
+
+#[test]
+fn site_renders_timelines() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/about"))?;
+                let mut history_file = File::create(temp_dir.join("target/about/history.toml"))?;
+                write!(
+                    history_file,
+                    "[[event]]\ndate = 2019-03-01\ntitle = \"Founded\"\ndescription = \"In a <garage>.\"\n\n[[event]]\ndate = 2021\ntitle = \"First release\"\n"
+                )?;
+                history_file.flush()?;
+                let mut about_file = File::create(temp_dir.join("target/about/index.md"))?;
+                write!(
+                    about_file,
+                    "# About\n\n{{{{timeline history.toml}}}}\n\n{{{{timeline\n[[event]]\ndate = \"Today\"\ntitle = \"Still going\"\n}}}}\n\n{{{{timeline missing.toml}}}}\n"
+                )?;
+                about_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                let report = crate::run_program(args)?;
+                let about = read_to_string(temp_dir.join("output/about/index.html"))?;
+                assert!(about.contains("<ol class=\"timeline\"><li class=\"timeline-event\"><time>2019-03-01</time> <strong class=\"timeline-title\">Founded</strong> <span class=\"timeline-description\">In a &lt;garage&gt;.</span></li><li class=\"timeline-event\"><time>2021</time> <strong class=\"timeline-title\">First release</strong></li></ol>"));
+                assert!(about.contains("<ol class=\"timeline\"><li class=\"timeline-event\"><time>Today</time> <strong class=\"timeline-title\">Still going</strong></li></ol>"));
+                assert!(about.contains("{{timeline missing.toml}}"));
+                assert!(report
+                    .warnings
+                    .iter()
+                    .any(|warning| warning.contains("a timeline that can't be rendered")));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_renders_openapi_reference() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/api"))?;
+                let mut spec_file = File::create(temp_dir.join("target/api/openapi.yaml"))?;
+                write!(
+                    spec_file,
+                    "openapi: 3.0.0\ninfo:\n  title: Pet Store\n  version: 1.0.0\nservers:\n  - url: https://api.example.com\npaths:\n  /pets/{{petId}}:\n    parameters:\n      - name: petId\n        in: path\n        required: true\n        schema:\n          type: integer\n          format: int64\n    get:\n      operationId: getPet\n      summary: Get a pet\n      responses:\n        '200':\n          description: The pet\n          content:\n            application/json:\n              schema:\n                $ref: '#/components/schemas/Pet'\n    put:\n      requestBody:\n        content:\n          application/json:\n            schema:\n              $ref: '#/components/schemas/Pet'\n      responses:\n        '204':\n          description: Updated\ncomponents:\n  schemas:\n    Pet:\n      required: [name]\n      properties:\n        name:\n          type: string\n          description: The <name> of the pet\n        tags:\n          type: array\n          items:\n            type: string\n"
+                )?;
+                spec_file.flush()?;
+                let mut api_file = File::create(temp_dir.join("target/api/index.md"))?;
+                write!(
+                    api_file,
+                    "# API\n\n{{{{openapi openapi.yaml}}}}\n\n{{{{openapi index.md}}}}\n"
+                )?;
+                api_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                let report = crate::run_program(args)?;
+                let api = read_to_string(temp_dir.join("output/api/index.html"))?;
+                assert!(api.contains("<p class=\"openapi-info\"><strong>Pet Store</strong> 1.0.0</p><p>Servers: <code>https://api.example.com</code></p>"));
+                assert!(api.contains("<section class=\"openapi-operation\" id=\"getpet\"><h3><span class=\"http-method http-get\">GET</span> <code>/pets/{petId}</code></h3><p>Get a pet</p>"));
+                assert!(api.contains("<tr><td><code>petId</code></td><td>path</td><td>integer (int64)</td><td>Yes</td><td></td></tr>"));
+                assert!(api.contains("<tr><td><code>200</code></td><td>The pet</td><td><code>application/json</code>: <a href=\"#schema-pet\">Pet</a></td></tr>"));
+                assert!(api.contains("<section class=\"openapi-operation\" id=\"put-pets-petid\">"));
+                assert!(api.contains("<h4>Request Body</h4><ul class=\"openapi-request-body\"><li><code>application/json</code>: <a href=\"#schema-pet\">Pet</a></li></ul>"));
+                assert!(api
+                    .contains("<section class=\"openapi-schema\" id=\"schema-pet\"><h3>Pet</h3>"));
+                assert!(api.contains("<tr><td><code>name</code></td><td>string</td><td>Yes</td><td>The &lt;name&gt; of the pet</td></tr><tr><td><code>tags</code></td><td>array of string</td><td>No</td><td></td></tr>"));
+                assert!(api.contains("{{openapi index.md}}"));
+                assert!(report
+                    .warnings
+                    .iter()
+                    .any(|warning| warning.contains("an API reference that can't be rendered")));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_config_sets_options() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/drafts"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(
+                    config_file,
+                    "title = \"Docs & More\"\nweb_prefix = \"/docs/\"\noutput_path = \"public\"\ntemplate = \"lite\"\nignore = [\"drafts\", \"*.bak\"]\n"
+                )?;
+                config_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(index_file, "# Home\n\n[Guide](guide.md)\n")?;
+                index_file.flush()?;
+                let mut guide_file = File::create(temp_dir.join("target/guide.md"))?;
+                write!(guide_file, "How to use it.")?;
+                guide_file.flush()?;
+                File::create(temp_dir.join("target/notes.bak"))?;
+                File::create(temp_dir.join("target/drafts/wip.md"))?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let output = temp_dir.join("target/public");
+                let index = read_to_string(output.join("index.html"))?;
+                assert!(index.contains("<title>Home</title>"));
+                assert!(index.contains("href=\"/docs/guide.html\""));
+                let guide = read_to_string(output.join("guide.html"))?;
+                assert!(guide.contains("<title>Docs &amp; More</title>"));
+                assert!(!output.join("notes.bak").exists());
+                assert!(!output.join("drafts").exists());
+                assert!(!output.join("public").exists());
+
+                // Options on the command line win
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    web_prefix: Some("https://example.com/".to_string()),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(!index.contains("<title>"));
+                assert!(index.contains("href=\"https://example.com/guide.html\""));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_front_matter() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(config_file, "[sitemap]\nbase_url = \"https://example.com\"")?;
+                config_file.flush()?;
+                let mut template_file = File::create(temp_dir.join("target/template.html"))?;
+                write!(
+                    template_file,
+                    "<html><head></head><body><!-- {{TABLE_OF_CONTENTS}} --><!-- {{CONTENT}} --></body></html>"
+                )?;
+                template_file.flush()?;
+                let mut plain_file = File::create(temp_dir.join("target/plain.html"))?;
+                write!(plain_file, "<main><!-- {{CONTENT}} --></main>")?;
+                plain_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(
+                    index_file,
+                    "---\ntitle: Getting Started\ndate: 2024-05-01\ntags: [intro, setup]\n---\n# Hello"
+                )?;
+                index_file.flush()?;
+                let mut about_file = File::create(temp_dir.join("target/about.md"))?;
+                write!(
+                    about_file,
+                    "+++\ntitle = \"About Us\"\ntemplate = \"plain.html\"\n+++\n# About"
+                )?;
+                about_file.flush()?;
+                let mut draft_file = File::create(temp_dir.join("target/draft.md"))?;
+                write!(draft_file, "---\ndraft: true\n---\n# Not yet")?;
+                draft_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains("<meta name=\"keywords\" content=\"intro, setup\">"));
+                assert!(index.contains("<a href=\"about.html\">About Us</a>"));
+                assert!(index.contains("<b>Getting Started</b>"));
+                assert!(!index.contains("draft"));
+                assert!(!index.contains("title:"));
+                let about = read_to_string(temp_dir.join("output/about.html"))?;
+                assert!(about.starts_with("<main>"));
+                assert!(!temp_dir.join("output/draft.html").exists());
+                let sitemap = read_to_string(temp_dir.join("output/sitemap.xml"))?;
+                assert!(sitemap.contains(
+                    "<url><loc>https://example.com/index.html</loc><lastmod>2024-05-01</lastmod></url>"
+                ));
+                assert!(!sitemap.contains("draft.html"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_mounted_api_docs() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/guide"))?;
+                create_dir_all(temp_dir.join("target/vendor/js"))?;
+                create_dir_all(temp_dir.join("doc/my_crate"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(
+                    config_file,
+                    "[[mounts]]\npath = \"guide/api\"\nsource = \"../doc\"\nindex = \"my_crate/index.html\"\ntitle = \"API Reference\"\n\n[[mounts]]\npath = \"js\"\nsource = \"vendor/js\""
+                )?;
+                config_file.flush()?;
+                let mut template_file = File::create(temp_dir.join("target/template.html"))?;
+                write!(
+                    template_file,
+                    "<!-- {{TABLE_OF_CONTENTS}} --><!-- {{CONTENT}} -->"
+                )?;
+                template_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(index_file, "# Home")?;
+                index_file.flush()?;
+                let mut usage_file = File::create(temp_dir.join("target/guide/usage.md"))?;
+                write!(usage_file, "# Usage")?;
+                usage_file.flush()?;
+                let mut readme_file = File::create(temp_dir.join("target/vendor/js/README.md"))?;
+                write!(readme_file, "# Not a page")?;
+                readme_file.flush()?;
+                let mut doc_file = File::create(temp_dir.join("doc/my_crate/index.html"))?;
+                write!(doc_file, "<html><!-- {{CONTENT}} --></html>")?;
+                doc_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let doc = read_to_string(temp_dir.join("output/guide/api/my_crate/index.html"))?;
+                assert_eq!(doc, "<html><!-- {CONTENT} --></html>");
+                assert!(temp_dir.join("output/js/README.md").exists());
+                assert!(!temp_dir.join("output/vendor").exists());
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains("<ul><li><b><u>guide:</u></b></li><ul><li><a href=\"guide/usage.html\">usage</a></li><li><a href=\"guide/api/my_crate/index.html\">API Reference</a></li></ul><li class=\"active\"><b>index</b></li><li><a href=\"js/index.html\">js</a></li></ul>"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_reports_pages_missing_from_previous_sitemap() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/guide"))?;
+                create_dir_all(temp_dir.join("previous"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(
+                    config_file,
+                    "[sitemap]\nbase_url = \"https://example.com/docs/\""
+                )?;
+                config_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(index_file, "# Home")?;
+                index_file.flush()?;
+                let mut guide_file = File::create(temp_dir.join("target/guide/index.md"))?;
+                write!(guide_file, "# Guide")?;
+                guide_file.flush()?;
+                let mut index_sitemap = File::create(temp_dir.join("previous/sitemap.xml"))?;
+                write!(
+                    index_sitemap,
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n<sitemap><loc>https://example.com/docs/sitemap-1.xml</loc></sitemap>\n</sitemapindex>"
+                )?;
+                index_sitemap.flush()?;
+                let mut sitemap = File::create(temp_dir.join("previous/sitemap-1.xml"))?;
+                write!(
+                    sitemap,
+                    "<urlset>\n<url><loc>https://example.com/docs/index.html</loc></url>\n<url><loc>https://example.com/docs/guide/</loc></url>\n<url><loc>https://example.com/docs/old.html?a=1&amp;b=2</loc></url>\n</urlset>"
+                )?;
+                sitemap.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::ForceNone),
+                    previous_sitemap: Some(
+                        temp_dir
+                            .join("previous/sitemap.xml")
+                            .to_string_lossy()
+                            .to_string(),
+                    ),
+                    ..Default::default()
+                };
+                let report = crate::run_program(args)?;
+                let missing = report
+                    .warnings
+                    .iter()
+                    .filter(|warning| warning.contains("was in the previous sitemap"))
+                    .collect::<Vec<_>>();
+                assert_eq!(missing.len(), 1);
+                assert!(missing[0].starts_with("https://example.com/docs/old.html?a=1&b=2 "));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_fills_in_page_titles() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/docs"))?;
+                let mut template_file = File::create(temp_dir.join("target/template.html"))?;
+                write!(
+                    template_file,
+                    "<html><head></head><body><header><!-- {{TITLE}} --></header><!-- {{CONTENT}} --></body></html>"
+                )?;
+                template_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(index_file, "Intro\n\n# Hello *&* welcome\n\n# Second")?;
+                index_file.flush()?;
+                let mut about_file = File::create(temp_dir.join("target/about.md"))?;
+                write!(about_file, "---\ntitle: About <Us>\n---\n# Ignored")?;
+                about_file.flush()?;
+                let mut guide_file = File::create(temp_dir.join("target/docs/guide.md"))?;
+                write!(guide_file, "# Guide")?;
+                guide_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains("<title>Hello &amp; welcome</title>"));
+                assert!(index.contains("<header>Hello &amp; welcome</header>"));
+                let about = read_to_string(temp_dir.join("output/about.html"))?;
+                assert!(about.contains("<title>About &lt;Us&gt;</title>"));
+                assert!(about.contains("<header>About &lt;Us&gt;</header>"));
+
+                // Built in templates have the title as their `<title>`
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    template: Some(crate::templates::BuiltInTemplate::Docs),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let guide = read_to_string(temp_dir.join("output/docs/guide.html"))?;
+                assert!(guide.contains("<title>Guide</title>"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn macros_in_page_content_are_not_filled() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let mut template_file = File::create(temp_dir.join("target/template.html"))?;
+                write!(
+                    template_file,
+                    "<header><!-- {{TITLE}} --><!-- {{SITE_ROOT}} --></header><!-- {{CONTENT}} -->"
+                )?;
+                template_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(
+                    index_file,
+                    "# Macros\n\nWrite <!-- {{TITLE}} --> or <!-- {{SITE_ROOT}} --> in a template."
+                )?;
+                index_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains("<header>Macros./</header>"));
+                assert!(
+                    index.contains("Write <!-- {TITLE} --> or <!-- {SITE_ROOT} --> in a template.")
+                );
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn diff_output_compares_sites() -> anyhow::Result<()> {
+    use crate::output_diff::{diff_outputs, DiffLine, FileChange};
+    let root = std::path::PathBuf::from(temp_dir_name());
+    let fs = crate::vfs::MemoryFs::new();
+    fs.insert(
+        root.join("old/index.html"),
+        "<html>\n  <body>\n    <h1>Home</h1>\n    <p>Welcome  to the site.</p>\n  </body>\n</html>",
+    );
+    fs.insert(
+        root.join("old/about.html"),
+        "<h1>About</h1><p>Written in Rust.</p><p>By us.</p>",
+    );
+    fs.insert(root.join("old/old.html"), "<h1>Old</h1>");
+    fs.insert(root.join("old/style.css"), "body {}");
+    fs.insert(
+        root.join("new/index.html"),
+        "<html><body><h1>Home</h1><p>Welcome to the site.</p></body></html>",
+    );
+    fs.insert(
+        root.join("new/about.html"),
+        "<h1>About</h1><p>Written in <em>Rust</em>.</p><p>By us.</p>",
+    );
+    fs.insert(root.join("new/new.html"), "<h1>New</h1>");
+    fs.insert(root.join("new/style.css"), "body { margin: 0 }");
+
+    let changes = diff_outputs(&fs, &root.join("old"), &root.join("new"))?;
+    assert_eq!(
+        changes,
+        vec![
+            FileChange::Changed(
+                "about.html".into(),
+                vec![
+                    DiffLine::Removed("Written in Rust.".into()),
+                    DiffLine::Added("Written in".into()),
+                    DiffLine::Added("<em>".into()),
+                    DiffLine::Added("Rust".into()),
+                    DiffLine::Added("</em>".into()),
+                    DiffLine::Added(".".into()),
+                ]
+            ),
+            FileChange::Added("new.html".into()),
+            FileChange::Removed("old.html".into()),
+            FileChange::Changed("style.css".into(), Vec::new()),
+        ]
+    );
+    assert!(diff_outputs(&fs, &root.join("old"), &root.join("missing")).is_err());
+    Ok(())
+}
+
+#[test]
+fn site_with_tera_template() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/posts"))?;
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(config_file, "title = \"My Site\"")?;
+                config_file.flush()?;
+                let mut template_file = File::create(temp_dir.join("target/template.html"))?;
+                template_file.write_all(
+                    br#"<html><head><title>{{ page.title }} | {{ site.title }}</title></head><body>
+<nav>{% for p in site.pages %}<a href="{{ p.url }}"{% if p.path == page.path %} class="active"{% endif %}>{{ p.title | upper }}</a>{% endfor %}</nav>
+{% if page.date %}<time>{{ page.date }}</time>{% endif %}
+{% for tag in page.tags %}<span class="tag">{{ tag }}</span>{% endfor %}
+<main>{{ content | safe }}</main><aside><!-- {TABLE_OF_CONTENTS} --></aside></body></html>"#,
+                )?;
+                template_file.flush()?;
+                let mut index_file = File::create(temp_dir.join("target/index.md"))?;
+                write!(
+                    index_file,
+                    "# Home\n\nUse `{{{{ page.title }}}}` in templates."
+                )?;
+                index_file.flush()?;
+                let mut post_file = File::create(temp_dir.join("target/posts/first.md"))?;
+                write!(
+                    post_file,
+                    "---\ntitle: First & Best\ndate: 2024-01-31\ntags: [intro, news]\n---\nHello"
+                )?;
+                post_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains("<title>Home | My Site</title>"));
+                assert!(index.contains("<a href=\"index.html\" class=\"active\">HOME</a>"));
+                assert!(index.contains("<a href=\"posts/first.html\">FIRST &amp; BEST</a>"));
+                assert!(!index.contains("<time>"));
+                // The content isn't rendered as a template
+                assert!(index.contains("<code>{{ page.title }}</code>"));
+                assert!(index.contains("<aside><ul>"));
+                let post = read_to_string(temp_dir.join("output/posts/first.html"))?;
+                assert!(post.contains("<title>First &amp; Best | My Site</title>"));
+                assert!(post.contains("<a href=\"../index.html\">HOME</a>"));
+                assert!(post.contains("<time>2024-01-31</time>"));
+                assert!(post
+                    .contains("<span class=\"tag\">intro</span><span class=\"tag\">news</span>"));
+
+                // Undefined variables fail the build
+                let mut template_file = File::create(temp_dir.join("target/template.html"))?;
+                template_file.write_all(b"<html><body>{{ page.missing }}</body></html>")?;
+                template_file.flush()?;
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    ..Default::default()
+                };
+                assert!(crate::run_program(args).is_err());
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn migrate_front_matter() -> anyhow::Result<()> {
+    use crate::vfs::FileSystem;
+    let root = std::path::PathBuf::from(temp_dir_name());
+    let fs = crate::vfs::MemoryFs::new();
+    fs.insert(
+        root.join("site/post.md"),
+        "+++\nauthor = \"Ann\"\ndate = \"01/31/2024\"\n+++\n# Post",
+    );
+    fs.insert(
+        root.join("site/notes/note.dj"),
+        "---\ntitle: Note\ndate: 02/29/2024\nlang: fr\n---\n# Note",
+    );
+    fs.insert(root.join("site/plain.md"), "# No front matter");
+    fs.insert(root.join("site/drafts/old.md"), "---\nauthor: Bob\n---\n");
+    fs.insert(root.join("site/ssg.toml"), "ignore = [\"drafts\"]");
+    let migration: crate::migrate::Migration = toml::from_str(
+        r#"
+[[step]]
+action = "rename"
+key = "author"
+to = "authors"
+
+[[step]]
+action = "default"
+key = "lang"
+value = "en"
+
+[[step]]
+action = "convert-date"
+key = "date"
+from = "[month]/[day]/[year]"
+"#,
+    )?;
+
+    let migrated = crate::migrate::migrate_site(&fs, &root.join("site"), &migration, true)?;
+    assert_eq!(
+        migrated,
+        vec![root.join("site/notes/note.dj"), root.join("site/post.md")]
+    );
+    assert!(fs
+        .read_to_string(&root.join("site/post.md"))?
+        .contains("author = \"Ann\""));
+
+    crate::migrate::migrate_site(&fs, &root.join("site"), &migration, false)?;
+    assert_eq!(
+        fs.read_to_string(&root.join("site/post.md"))?,
+        "+++\ndate = 2024-01-31\nauthors = \"Ann\"\nlang = \"en\"\n+++\n# Post"
+    );
+    assert_eq!(
+        fs.read_to_string(&root.join("site/notes/note.dj"))?,
+        "---\ntitle: Note\ndate: 2024-02-29\nlang: fr\n---\n# Note"
+    );
+    assert_eq!(
+        fs.read_to_string(&root.join("site/drafts/old.md"))?,
+        "---\nauthor: Bob\n---\n"
+    );
+    // Migrating again changes nothing
+    assert!(crate::migrate::migrate_site(&fs, &root.join("site"), &migration, false)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn site_with_template_partials() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .try_init();
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/docs"))?;
+                let files: [(&str, &[u8]); 7] = [
+                    (
+                        "_base.html",
+                        b"<html><head><title>{% block title %}{{ page.title }}{% endblock %}</title></head><body>{% include \"_header.html\" %}{% block main %}<main>{{ content | safe }}</main>{% endblock %}</body></html>",
+                    ),
+                    ("_header.html", b"<header>{{ site.title }}</header>"),
+                    ("template.html", b"{% extends \"_base.html\" %}"),
+                    (
+                        "docs/template.html",
+                        b"{% extends \"template.html\" %}{% block main %}{% include \"_sidebar.html\" %}<div class=\"docs\">{{ super() }}</div>{% endblock %}",
+                    ),
+                    ("docs/_sidebar.html", b"<nav>Docs</nav>"),
+                    ("index.md", b"# Home"),
+                    ("docs/guide.md", b"# Guide"),
+                ];
+                for (name, contents) in files {
+                    let mut file = File::create(temp_dir.join("target").join(name))?;
+                    file.write_all(contents)?;
+                    file.flush()?;
+                }
+                let mut config_file = File::create(temp_dir.join("target/ssg.toml"))?;
+                write!(config_file, "title = \"My Site\"")?;
+                config_file.flush()?;
+
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                let index = read_to_string(temp_dir.join("output/index.html"))?;
+                assert!(index.contains(
+                    "<title>Home</title></head><body><header>My Site</header><main><h1 id=\"home\">Home</h1>"
+                ));
+                let guide = read_to_string(temp_dir.join("output/docs/guide.html"))?;
+                assert!(guide.contains("<title>Guide</title>"));
+                assert!(guide.contains(
+                    "<header>My Site</header><nav>Docs</nav><div class=\"docs\"><main><h1 id=\"guide\">Guide</h1>"
+                ));
+                // Partials aren't copied to the site
+                assert!(!temp_dir.join("output/_base.html").exists());
+                assert!(!temp_dir.join("output/docs/_sidebar.html").exists());
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn import_jekyll_and_hugo_sites() -> anyhow::Result<()> {
+    use crate::import::ImportSource;
+    use crate::vfs::FileSystem;
+    let root = std::path::PathBuf::from(temp_dir_name());
+    let fs = crate::vfs::MemoryFs::new();
+    let jekyll = root.join("jekyll");
+    fs.insert(jekyll.join("_config.yml"), "title: Blog\nlang: en\n");
+    fs.insert(
+        jekyll.join("_posts/2024-01-31-hello.md"),
+        "---\nlayout: post\ncategories: news rust\n---\n{% highlight rust %}\nfn main() {}\n{% endhighlight %}\n\n[About]({% link about.markdown %})\n{% include note.html %}\n",
+    );
+    fs.insert(
+        jekyll.join("about.markdown"),
+        "---\ntitle: About\nredirect_from: /me/\n---\n[First]({% post_url 2024-01-31-hello %})\n",
+    );
+    fs.insert(jekyll.join("_layouts/post.html"), "{{ content }}");
+    fs.insert(jekyll.join("images/logo.svg"), "<svg></svg>");
+
+    let report = crate::import::import_site(&fs, ImportSource::Jekyll, &jekyll, &root.join("a"))?;
+    assert_eq!(
+        report,
+        crate::import::ImportReport {
+            pages: 2,
+            files: 1,
+            redirects: 2,
+        }
+    );
+    assert_eq!(
+        fs.read_to_string(&root.join("a/posts/hello.md"))?,
+        "---\ndate: 2024-01-31\ntags:\n- news\n- rust\n---\n```rust\nfn main() {}\n```\n\n[About](../about.md)\n{% include note.html %}\n"
+    );
+    assert!(fs
+        .read_to_string(&root.join("a/about.md"))?
+        .ends_with("---\n[First](posts/hello.md)\n"));
+    assert!(fs
+        .read_to_string(&root.join("a/news/rust/2024/01/31/hello.html"))?
+        .contains("url=../../../../../posts/hello.html"));
+    assert!(fs
+        .read_to_string(&root.join("a/me/index.html"))?
+        .contains("url=../about.html"));
+    assert_eq!(
+        fs.read_to_string(&root.join("a/ssg.toml"))?,
+        "title = \"Blog\"\nlang = \"en\"\n"
+    );
+    assert!(fs.is_file(&root.join("a/images/logo.svg")));
+    assert!(!fs.exists(&root.join("a/_layouts")));
+
+    let hugo = root.join("hugo");
+    fs.insert(
+        hugo.join("hugo.toml"),
+        "title = \"Docs\"\nlanguageCode = \"fr\"",
+    );
+    fs.insert(
+        hugo.join("content/_index.md"),
+        "+++\ntitle = \"Home\"\n+++\n",
+    );
+    fs.insert(
+        hugo.join("content/guides/setup.md"),
+        "+++\ntitle = \"Setup\"\naliases = [\"/install.html\"]\n+++\nSee [the home page]({{< relref \"/_index.md\" >}}).\n\n{{< figure src=\"shot.png\" alt=\"Screenshot\" >}}\n{{< youtube abc >}}\n",
+    );
+    fs.insert(hugo.join("static/favicon.ico"), "icon");
+
+    let report = crate::import::import_site(&fs, ImportSource::Hugo, &hugo, &root.join("b"))?;
+    assert_eq!(
+        report,
+        crate::import::ImportReport {
+            pages: 2,
+            files: 1,
+            redirects: 2,
+        }
+    );
+    assert_eq!(
+        fs.read_to_string(&root.join("b/guides/setup.md"))?,
+        "+++\ntitle = \"Setup\"\n+++\nSee [the home page](../index.md).\n\n![Screenshot](shot.png)\n{{< youtube abc >}}\n"
+    );
+    assert!(fs.is_file(&root.join("b/index.md")));
+    assert!(fs
+        .read_to_string(&root.join("b/guides/setup/index.html"))?
+        .contains("url=../../guides/setup.html"));
+    assert!(fs.is_file(&root.join("b/install.html")));
+    assert!(fs.is_file(&root.join("b/favicon.ico")));
+
+    // Sites are only imported into empty directories
+    assert!(crate::import::import_site(&fs, ImportSource::Hugo, &hugo, &root.join("b")).is_err());
+    Ok(())
+}
+
+#[test]
+fn template_date_and_site_root() -> anyhow::Result<()> {
+    use crate::vfs::FileSystem;
+    let root = std::path::PathBuf::from(temp_dir_name());
+    let fs = crate::vfs::MemoryFs::new();
+    fs.insert(
+        root.join("site/template.html"),
+        "<a href=\"<!-- {SITE_ROOT} -->\">Home</a><link href=\"<!-- {SITE_ROOT} -->style.css\"><time><!-- {DATE} --></time><!-- {CONTENT} -->",
+    );
+    fs.insert(
+        root.join("site/ssg.toml"),
+        "date_format = \"[month repr:long] [day padding:none], [year]\"",
+    );
+    fs.insert(root.join("site/index.md"), "# Home");
+    fs.insert(
+        root.join("site/posts/first.md"),
+        "+++\ndate = 2024-01-31\n+++\n# First",
+    );
+    fs.insert(
+        root.join("site/posts/later.md"),
+        "---\ndate: Spring 2024\n---\n# Later",
+    );
+
+    let args = ConsoleArgs {
+        directory: Some(root.join("site")),
+        output_path: Some(root.join("output")),
+        ..Default::default()
+    };
+    crate::run_program_with(args, &fs)?;
+    assert!(fs
+        .read_to_string(&root.join("output/index.html"))?
+        .starts_with("<a href=\"./\">Home</a><link href=\"./style.css\"><time></time>"));
+    assert!(fs
+        .read_to_string(&root.join("output/posts/first.html"))?
+        .starts_with(
+            "<a href=\"../\">Home</a><link href=\"../style.css\"><time>January 31, 2024</time>"
+        ));
+    assert!(fs
+        .read_to_string(&root.join("output/posts/later.html"))?
+        .contains("<time>Spring 2024</time>"));
+    Ok(())
+}
+
+#[test]
+fn import_html_pages() -> anyhow::Result<()> {
+    use crate::import::ImportSource;
+    use crate::vfs::FileSystem;
+    let root = std::path::PathBuf::from(temp_dir_name());
+    let fs = crate::vfs::MemoryFs::new();
+    let source = root.join("legacy");
+    fs.insert(
+        source.join("index.html"),
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head><title>My Homepage</title><style>body { color: red; }</style></head>
+<body>
+<header><a href="index.html">Home</a></header>
+<h1>Welcome</h1>
+<p>Read <a href="docs/guide.html#setup">the <b>guide</b></a>, or see <img src="images/me.png" alt="me">.
+<p>Prices: 5 * 3 &amp; <code>a_b</code><br>
+Second line</p>
+<ul>
+  <li>One</li>
+  <li>Two
+    <ol start="3"><li>Three</li></ol>
+  </li>
+</ul>
+<pre><code class="language-rust">fn main() {
+    println!("&lt;hi&gt;");
+}</code></pre>
+<table>
+<tr><th>Name</th><th>Size</th></tr>
+<tr><td>a|b</td><td><em>big</em></td></tr>
+</table>
+<blockquote><p>Quoted</p></blockquote>
+<iframe src="https://example.com/map"></iframe>
+<footer>&copy; 2003</footer>
+</body>
+</html>
+"#,
+    );
+    fs.insert(
+        source.join("docs/guide.html"),
+        r#"<html><head><title>Guide</title></head><body><main><h1>Guide</h1><h2 id="setup">Setup</h2><p>Back <a href="/">home</a>.</p></main></body></html>"#,
+    );
+    fs.insert(source.join("images/me.png"), "png");
+
+    let report = crate::import::import_site(&fs, ImportSource::Html, &source, &root.join("site"))?;
+    assert_eq!(
+        report,
+        crate::import::ImportReport {
+            pages: 2,
+            files: 1,
+            redirects: 0,
+        }
+    );
+    assert_eq!(
+        fs.read_to_string(&root.join("site/index.dj"))?,
+        r#"+++
+title = "My Homepage"
+lang = "en"
++++
+# Welcome
+
+Read [the *guide*](docs/guide.dj#setup), or see ![me](images/me.png).
+
+Prices: 5 \* 3 & `a_b`\
+Second line
+
+- One
+
+- Two
+
+  3. Three
+
+```rust
+fn main() {
+    println!("<hi>");
+}
+```
+
+| Name | Size |
+|---|---|
+| a\|b | _big_ |
+
+> Quoted
+
+```=html
+<iframe src="https://example.com/map"></iframe>
+```
+"#
+    );
+    assert_eq!(
+        fs.read_to_string(&root.join("site/docs/guide.dj"))?,
+        "# Guide\n\n## Setup\n\nBack [home](../index.dj).\n"
+    );
+    assert!(fs.is_file(&root.join("site/images/me.png")));
+    Ok(())
+}
+
+#[test]
+fn highlight_code_blocks() -> anyhow::Result<()> {
+    use crate::vfs::FileSystem;
+    let root = std::path::PathBuf::from(temp_dir_name());
+    let fs = crate::vfs::MemoryFs::new();
+    fs.insert(
+        root.join("site/ssg.toml"),
+        "[highlight]\ndark_theme = \"base16-ocean.dark\"",
+    );
+    fs.insert(
+        root.join("site/template.html"),
+        "<html><head></head><body><!-- {CONTENT} --></body></html>",
+    );
+    fs.insert(
+        root.join("site/index.md"),
+        "# Home\n\n```rust\nfn main() {}\n```\n\n```unknown-language\n<plain>\n```",
+    );
+    fs.insert(root.join("site/guides/plain.md"), "# Plain\n\n    indented");
+
+    let args = ConsoleArgs {
+        directory: Some(root.join("site")),
+        output_path: Some(root.join("output")),
+        ..Default::default()
+    };
+    crate::run_program_with(args, &fs)?;
+    let html = fs.read_to_string(&root.join("output/index.html"))?;
+    assert!(html.contains("<link rel=\"stylesheet\" href=\"highlight.css\">"));
+    assert!(html.contains(
+        "<pre class=\"hl-code\"><code class=\"language-rust\"><span class=\"hl-source hl-rust\">"
+    ));
+    assert!(html.contains("<span class=\"hl-storage hl-type hl-function hl-rust\">fn</span>"));
+    assert!(html
+        .contains("<pre><code class=\"language-unknown-language\">&lt;plain&gt;\n</code></pre>"));
+    let plain = fs.read_to_string(&root.join("output/guides/plain.html"))?;
+    assert!(!plain.contains("highlight.css"));
+    let css = fs.read_to_string(&root.join("output/highlight.css"))?;
+    assert!(css.contains(".hl-code {\n color: #323232;\n background-color: #ffffff;\n}"));
+    assert!(
+        css.contains("@media (prefers-color-scheme: dark) {\n/*\n * theme \"Base16 Ocean Dark\" generated by syntect")
+    );
+    Ok(())
+}
+
+#[test]
+fn convert_between_djot_and_markdown() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("page.md"),
+        "+++\ntitle = \"Page\"\n+++\n# Heading\n\nSome **strong** and _emphasized_ text with \
+         [a link](other.md) and ~~deleted~~ `code`.\n\n- one\n- two\n\n1\\. Not a list\n\n\
+         ```rust\nfn main() {}\n```\n",
+    );
+    let command = crate::Command::Convert {
+        file: root.join("page.md"),
+        output_path: None,
+    };
+    crate::run_command(command, &fs).unwrap();
+    assert_eq!(
+        fs.read_to_string(&root.join("page.dj")).unwrap(),
+        "+++\ntitle = \"Page\"\n+++\n# Heading\n\nSome *strong* and _emphasized_ text with \
+         [a link](other.md) and {-deleted-} `code`.\n\n- one\n- two\n\n1\\. Not a list\n\n\
+         ```rust\nfn main() {}\n```\n"
+    );
+
+    // The converted page isn't overwritten
+    let command = crate::Command::Convert {
+        file: root.join("page.md"),
+        output_path: None,
+    };
+    assert!(crate::run_command(command, &fs).is_err());
+
+    // Djot back to markdown
+    fs.insert(
+        root.join("notes.dj"),
+        "# Notes\n\n``` rust\nfn main() {}\n```\n",
+    );
+    let command = crate::Command::Convert {
+        file: root.join("notes.dj"),
+        output_path: Some(root.join("out.md")),
+    };
+    crate::run_command(command, &fs).unwrap();
+    assert_eq!(
+        fs.read_to_string(&root.join("out.md")).unwrap(),
+        "# Notes\n\n```rust\nfn main() {}\n```\n"
+    );
+}
+
+#[test]
+fn feeds() {
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("ssg.toml"),
+        "title = \"Blog\"\n\n[feed]\nbase_url = \"https://example.com/\"\npath = \"posts\"\nmax_items = 2\n",
+    );
+    fs.insert(
+        root.join("index.md"),
+        "+++\ndate = 2024-05-01\n+++\n# Home\n",
+    );
+    fs.insert(
+        root.join("posts/first.md"),
+        "+++\ndate = 2024-01-02\n+++\n# First & foremost\n\nHello\n",
+    );
+    fs.insert(
+        root.join("posts/second.md"),
+        "+++\ndate = 2024-02-03\ntitle = \"Second\"\n+++\nMore\n",
+    );
+    fs.insert(
+        root.join("posts/third.md"),
+        "+++\ndate = 2024-03-04\n+++\n# Third\n",
+    );
+    fs.insert(
+        root.join("posts/hidden.md"),
+        "+++\ndate = 2024-04-05\nunlisted = true\n+++\n",
+    );
+    crate::run_program_with(
+        ConsoleArgs {
+            directory: Some(root.clone()),
+            output_path: Some(root.join("output")),
+            ..Default::default()
+        },
+        &fs,
+    )
+    .unwrap();
+    use crate::vfs::FileSystem;
+    let rss = fs.read_to_string(&root.join("output/feed.xml")).unwrap();
+    assert!(rss.contains("<title>Blog</title>\n<link>https://example.com/</link>"));
+    assert!(rss.contains(
+        "<item>\n<title>Third</title>\n<link>https://example.com/posts/third.html</link>"
+    ));
+    assert!(rss.contains("<pubDate>Mon, 04 Mar 2024 00:00:00 +0000</pubDate>"));
+    assert!(rss.contains("<title>Second</title>"));
+    // Only the newest `max_items` pages under `path`, without unlisted pages
+    assert!(!rss.contains("First"));
+    assert!(!rss.contains("Home"));
+    assert!(!rss.contains("hidden"));
+    assert!(rss.find("Third").unwrap() < rss.find("Second").unwrap());
+
+    let atom = fs.read_to_string(&root.join("output/atom.xml")).unwrap();
+    assert!(atom.contains("<updated>2024-03-04T00:00:00Z</updated>\n<entry>"));
+    assert!(atom.contains("<link rel=\"self\" href=\"https://example.com/atom.xml\"/>"));
+    assert!(atom.contains("<content type=\"html\">&lt;p&gt;More&lt;/p&gt;"));
+}
+
+#[test]
+fn notes_vault() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(root.join("index.md"), "# Home\n\nStart at [[Gardens]].\n");
+    fs.insert(
+        root.join("ideas/Gardens.md"),
+        "# Gardens\n\nGrow [[compost|the pile]] and [[Missing note]], not `[[code]]`.\n",
+    );
+    fs.insert(
+        root.join("ideas/compost.md"),
+        "+++\ntitle = \"Compost\"\n+++\nFeeds the gardens.\n",
+    );
+    fs.insert(
+        root.join("2024-01-02.md"),
+        "Read about [[ideas/gardens#soil]].\n",
+    );
+    let report = crate::run_program_with(
+        ConsoleArgs {
+            directory: Some(root.clone()),
+            output_path: Some(root.join("output")),
+            notes: true,
+            ..Default::default()
+        },
+        &fs,
+    )
+    .unwrap();
+    assert!(report
+        .warnings
+        .iter()
+        .any(|warning| warning.contains("missing note \"Missing note\"")));
+
+    let index = fs.read_to_string(&root.join("output/index.html")).unwrap();
+    assert!(index.contains("<a href=\"ideas/Gardens.html\">Gardens</a>"));
+    let daily = fs
+        .read_to_string(&root.join("output/2024-01-02.html"))
+        .unwrap();
+    assert!(daily.contains("<a href=\"ideas/Gardens.html#soil\">ideas/gardens#soil</a>"));
+
+    let gardens = fs
+        .read_to_string(&root.join("output/ideas/Gardens.html"))
+        .unwrap();
+    assert!(gardens.contains("<a href=\"../ideas/compost.html\">the pile</a>"));
+    assert!(gardens.contains("[[Missing note]]"));
+    assert!(gardens.contains("<code>[[code]]</code>"));
+    assert!(gardens.contains(
+        "<section class=\"backlinks\">\n<h2>Linked from</h2>\n<ul>\n\
+         <li><a href=\"../2024-01-02.html\">2024-01-02</a></li>\n\
+         <li><a href=\"../index.html\">index</a></li>\n</ul>\n\
+         <h2>Unlinked mentions</h2>\n<ul>\n<li><a href=\"../ideas/compost.html\">Compost</a></li>\n</ul>\n\
+         </section>"
+    ));
+    // Linked, not just mentioned
+    let compost = fs
+        .read_to_string(&root.join("output/ideas/compost.html"))
+        .unwrap();
+    assert!(compost.contains("<h2>Linked from</h2>\n<ul>\n<li><a href=\"../ideas/Gardens.html\">"));
+    assert!(!compost.contains("Unlinked mentions"));
+
+    let all_notes = fs
+        .read_to_string(&root.join("output/all-notes.html"))
+        .unwrap();
+    assert!(all_notes.contains("<h1>All notes</h1>\n<ul class=\"all-notes\">\n"));
+    assert!(all_notes.contains(
+        "<h2>Daily notes</h2>\n<ul class=\"daily-notes\">\n<li><a href=\"2024-01-02.html\">\
+         2024-01-02</a> <time datetime=\"2024-01-02\">2024-01-02</time></li>\n</ul>"
+    ));
+}
+
+#[test]
+fn calendar_of_events() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("ssg.toml"),
+        "[sitemap]\nbase_url = \"https://example.com/\"\n",
+    );
+    fs.insert(root.join("index.md"), "# Club\n");
+    fs.insert(
+        root.join("events/meetup.md"),
+        "+++\nevent_date = 2024-03-05T19:00:00+01:00\n+++\n# Meetup, with snacks\n",
+    );
+    fs.insert(
+        root.join("events/picnic.md"),
+        "+++\nevent_date = 2024-04-01\nevent_end = 2024-04-02\nevent_location = \"The park; by the pond\"\n+++\n# Picnic\n",
+    );
+    fs.insert(
+        root.join("events/secret.md"),
+        "+++\nevent_date = 2024-04-02\ndraft = true\n+++\n# Secret\n",
+    );
+    crate::run_program_with(
+        ConsoleArgs {
+            directory: Some(root.clone()),
+            output_path: Some(root.join("output")),
+            template: Some(crate::templates::BuiltInTemplate::ForceNone),
+            ..Default::default()
+        },
+        &fs,
+    )
+    .unwrap();
+
+    let calendar = fs
+        .read_to_string(&root.join("output/calendar.html"))
+        .unwrap();
+    // March 2024 starts on a Friday, and April 2024 on a Monday
+    assert!(calendar.contains(
+        "<h2>March 2024</h2>\n<table class=\"calendar\">\n<thead>\n<tr><th>Mon</th><th>Tue</th>"
+    ));
+    assert!(calendar.contains(
+        "<tbody>\n<tr><td></td><td></td><td></td><td></td><td><span class=\"calendar-day\">1</span></td>"
+    ));
+    assert!(calendar.contains(
+        "<td><span class=\"calendar-day\">5</span><ul><li><a href=\"events/meetup.html\">\
+         Meetup, with snacks</a></li></ul></td>"
+    ));
+    assert!(calendar.contains(
+        "<tbody>\n<tr><td><span class=\"calendar-day\">1</span><ul><li><a href=\"events/picnic.html\">Picnic</a>"
+    ));
+    assert!(calendar.contains("<td><span class=\"calendar-day\">30</span></td><td></td><td></td><td></td><td></td><td></td></tr>\n</tbody>"));
+    assert!(!calendar.contains("Secret"));
+
+    assert!(calendar.contains("<a href=\"events.ics\">Subscribe to these events</a>"));
+
+    let ics = fs.read_to_string(&root.join("output/events.ics")).unwrap();
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(ics.contains(
+        "BEGIN:VEVENT\r\nUID:https://example.com/events/meetup.html\r\n\
+         DTSTAMP:20240305T000000Z\r\nDTSTART:20240305T180000Z\r\n\
+         SUMMARY:Meetup\\, with snacks\r\nURL:https://example.com/events/meetup.html\r\n\
+         END:VEVENT\r\n"
+    ));
+    // All day events end the day after their last day
+    assert!(ics.contains(
+        "DTSTART;VALUE=DATE:20240401\r\nDTEND;VALUE=DATE:20240403\r\n\
+         SUMMARY:Picnic\r\nLOCATION:The park\\; by the pond\r\n"
+    ));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+}
+
+#[test]
+fn incremental_build() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(root.join("index.md"), "# Home\n");
+    fs.insert(root.join("about.md"), "# About\n\n[Gone](gone.md)\n");
+    let build = || {
+        crate::run_program_with(
+            ConsoleArgs {
+                directory: Some(root.clone()),
+                output_path: Some(root.join("output")),
+                incremental: true,
+                ..Default::default()
+            },
+            &fs,
+        )
+        .unwrap()
+    };
+    assert_eq!(build().warnings.len(), 1);
+    let cache_path = root.join("output").join(crate::cache::CACHE_FILE);
+    let cache = fs.read_to_string(&cache_path).unwrap();
+    assert!(cache.contains("about.md"));
+    // Unchanged pages are taken from the cache instead of being rendered again
+    fs.insert(
+        cache_path.clone(),
+        cache.replace("About</h1>", "About (cached)</h1>"),
+    );
+    fs.insert(root.join("index.md"), "# Home, edited\n");
+    let report = build();
+    let about = fs.read_to_string(&root.join("output/about.html")).unwrap();
+    assert!(about.contains("About (cached)"));
+    let index = fs.read_to_string(&root.join("output/index.html")).unwrap();
+    assert!(index.contains("Home, edited"));
+    assert!(report.outputs.contains(&root.join("output/about.html")));
+    // Cached pages give the warnings they gave when they were rendered
+    assert_eq!(report.warnings.len(), 1);
+    assert!(report.warnings[0].contains("gone.md"));
+    // Unchanged outputs aren't written again
+    fs.insert(root.join("output/index.html"), "stale");
+    let cache = fs.read_to_string(&cache_path).unwrap();
+    build();
+    assert_eq!(
+        fs.read_to_string(&root.join("output/index.html")).unwrap(),
+        "stale"
+    );
+    assert_eq!(fs.read_to_string(&cache_path).unwrap(), cache);
+    // Changing the site's configuration renders every page again
+    fs.insert(root.join("ssg.toml"), "title = \"Site\"\n");
+    build();
+    let about = fs.read_to_string(&root.join("output/about.html")).unwrap();
+    assert!(!about.contains("(cached)"));
+}
+
+#[test]
+fn site_builder() -> anyhow::Result<()> {
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        (|| -> anyhow::Result<()> {
+            create_dir_all(temp_dir.join("target/nested"))?;
+            std::fs::write(temp_dir.join("target/index.md"), "# Home\n")?;
+            std::fs::write(temp_dir.join("target/nested/page.md"), "# Page\n")?;
+            let report = crate::SiteBuilder::new(temp_dir.join("target"))
+                .output_path(temp_dir.join("output"))
+                .template(crate::BuiltInTemplate::ForceNone)
+                .generate_site()?;
+            assert_eq!(report.pages.len(), 2);
+            assert!(read_to_string(temp_dir.join("output/nested/page.html"))?.contains("Page"));
+
+            let results = crate::SiteBuilder::new(temp_dir.join("target"))
+                .output_path(temp_dir.join("output"))
+                .first_pass()?;
+            assert!(results.iter().any(|result| matches!(
+                result,
+                crate::FirstPassResult::HtmlOutput { relative_path, html, .. }
+                    if relative_path.ends_with("index.html") && html.contains("Home")
+            )));
+
+            let html = crate::process_markdown("# Hello *there*\n", crate::SiteConfig::default())?;
+            assert!(html.contains("<em>there</em>"));
+            Ok(())
+        })()
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn export_email() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("ssg.toml"),
+        "[sitemap]\nbase_url = \"https://example.com/\"\n",
+    );
+    fs.insert(root.join("index.md"), "# Home\n");
+    fs.insert(
+        root.join("posts/hello.md"),
+        "+++\ntitle = \"Hello & welcome\"\n+++\n# Hello\n\nSee [the home page](../index.md), \
+         [a section](#hello), and [elsewhere](https://example.org/).\n\n\
+         ![A cat](cat.png)\n\n<script>alert(1)</script>\n\n<p style=\"color:red\">Red</p>\n",
+    );
+    fs.insert(root.join("posts/cat.png"), "");
+    let command = crate::Command::ExportEmail {
+        page: root.join("posts/hello.md"),
+        output_path: Some(root.join("hello.html")),
+        base_url: None,
+    };
+    crate::run_command(command, &fs).unwrap();
+    let email = fs.read_to_string(&root.join("hello.html")).unwrap();
+    assert!(email.starts_with("<!DOCTYPE html>\n<html>\n"));
+    assert!(email.contains("<title>Hello &amp; welcome</title>"));
+    assert!(email.contains("<table role=\"presentation\" width=\"600\""));
+    assert!(email.contains(
+        "<a style=\"color:#0969da;text-decoration:underline;\" href=\"https://example.com/index.html\">"
+    ));
+    assert!(email.contains("href=\"#hello\""));
+    assert!(email.contains("href=\"https://example.org/\""));
+    assert!(email.contains(
+        "<img style=\"max-width:100%;height:auto;border:0;\" src=\"https://example.com/posts/cat.png\""
+    ));
+    // The page's own styles take precedence
+    assert!(email.contains("<p style=\"margin:0 0 16px;color:red\">Red</p>"));
+    assert!(!email.contains("script"));
+    // Nothing is written besides the email
+    assert!(!fs.exists(&root.join("output")));
+}
+
+#[test]
+fn indieweb_metadata() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("ssg.toml"),
+        "[sitemap]\nbase_url = \"https://example.com/\"\n\n[indieweb]\n\
+         webmention = \"https://webmention.io/example.com/webmention\"\n\
+         authorization_endpoint = \"https://indieauth.com/auth\"\n\
+         me = [\"https://github.com/example\"]\nauthor = \"Ada\"\nauthor_photo = \"ada.png\"\n\
+         fediverse = \"@ada@example.social\"\n",
+    );
+    // The built-in blog template has these placeholders too
+    fs.insert(
+        root.join("template.html"),
+        "<html><head>\n</head><body><article class=\"h-entry\"><!-- {H_ENTRY} -->\
+         <!-- {CONTENT} --></article><!-- {H_CARD} --></body></html>",
+    );
+    fs.insert(root.join("index.md"), "# Home\n");
+    fs.insert(
+        root.join("posts/first.md"),
+        "+++\ntitle = \"First post\"\ndate = 2024-01-02\n+++\nHello\n",
+    );
+    crate::run_program_with(
+        ConsoleArgs {
+            directory: Some(root.clone()),
+            output_path: Some(root.join("output")),
+            ..Default::default()
+        },
+        &fs,
+    )
+    .unwrap();
+    let post = fs
+        .read_to_string(&root.join("output/posts/first.html"))
+        .unwrap();
+    assert!(post.contains(
+        "<link rel=\"webmention\" href=\"https://webmention.io/example.com/webmention\">\n\
+         <link rel=\"authorization_endpoint\" href=\"https://indieauth.com/auth\">\n\
+         <link rel=\"me\" href=\"https://github.com/example\">\n\
+         <link rel=\"me\" href=\"https://example.social/@ada\">\n\
+         <meta name=\"fediverse:creator\" content=\"@ada@example.social\">\n</head>"
+    ));
+    assert!(post.contains(
+        "<div class=\"h-entry-properties\" hidden>\n<data class=\"p-name\" value=\"First post\"></data>\n\
+         <a class=\"u-url\" href=\"https://example.com/posts/first.html\"></a>\n\
+         <time class=\"dt-published\" datetime=\"2024-01-02\">2024-01-02</time>\n\
+         <a class=\"p-author h-card\" href=\"https://example.com/\">Ada</a>\n</div>"
+    ));
+    assert!(post.contains(
+        "<div class=\"h-card site-author\">\n<img class=\"u-photo\" src=\"../ada.png\" alt=\"\">\n\
+         <a class=\"p-name u-url\" href=\"https://example.com/\">Ada</a>\n</div>"
+    ));
+
+    let webfinger = fs
+        .read_to_string(&root.join("output/.well-known/webfinger"))
+        .unwrap();
+    let webfinger: serde_json::Value = serde_json::from_str(&webfinger).unwrap();
+    assert_eq!(webfinger["subject"], "acct:ada@example.social");
+    assert_eq!(
+        webfinger["links"][1]["href"],
+        "https://example.social/users/ada"
+    );
+}
+
+#[test]
+fn cli_subcommands() {
+    use crate::vfs::FileSystem;
+    use clap::Parser;
+    let cli = crate::Cli::try_parse_from(["simple-ssg", "build", "docs", "-o", "out", "--clean"])
+        .unwrap();
+    let crate::Command::Build(args) = cli.command else {
+        panic!("Expected the build command");
+    };
+    assert_eq!(args.directory, Some(std::path::PathBuf::from("docs")));
+    assert_eq!(args.output_path, Some(std::path::PathBuf::from("out")));
+    assert!(args.clean && !args.watch);
+    assert_eq!(args.jobs, None);
+    let cli = crate::Cli::try_parse_from(["simple-ssg", "build", "docs", "-j", "2"]).unwrap();
+    assert!(
+        matches!(cli.command, crate::Command::Build(args) if args.jobs.is_some_and(|jobs| jobs.get() == 2))
+    );
+    assert!(crate::Cli::try_parse_from(["simple-ssg", "build", "--jobs", "0"]).is_err());
+    let cli =
+        crate::Cli::try_parse_from(["simple-ssg", "build", "docs", "--io-jobs", "4"]).unwrap();
+    assert!(
+        matches!(cli.command, crate::Command::Build(args) if args.io_jobs.is_some_and(|io_jobs| io_jobs.get() == 4))
+    );
+    let cli = crate::Cli::try_parse_from(["simple-ssg", "serve", "--port", "9000"]).unwrap();
+    let crate::Command::Serve { options, port, .. } = cli.command else {
+        panic!("Expected the serve command");
+    };
+    assert_eq!(
+        (options.directory, port),
+        (std::path::PathBuf::from("."), 9000)
+    );
+    // Options of other commands aren't accepted
+    assert!(crate::Cli::try_parse_from(["simple-ssg", "serve", "--stdin"]).is_err());
+    assert!(crate::Cli::try_parse_from(["simple-ssg", "docs"]).is_err());
+
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name()).join("my-site");
+    let cli = crate::Cli::try_parse_from(["simple-ssg", "init", "my-site", "-t", "docs"]).unwrap();
+    assert!(matches!(cli.command, crate::Command::Init { .. }));
+    let command = crate::Command::Init {
+        directory: root.clone(),
+        template: Some(crate::BuiltInTemplate::Docs),
+    };
+    crate::run_command(command, &fs).unwrap();
+    assert!(fs
+        .read_to_string(&root.join("ssg.toml"))
+        .unwrap()
+        .starts_with("title = \"my-site\"\noutput_path = \"output\"\ntemplate = \"docs\"\n"));
+    assert!(fs
+        .read_to_string(&root.join("index.dj"))
+        .unwrap()
+        .starts_with("# my-site\n"));
+    // The built in template is used instead of a template.html
+    assert!(!fs.exists(&root.join("template.html")));
+    assert!(fs.is_file(&root.join("assets/style.css")));
+    // A site isn't created over another
+    let command = crate::Command::Init {
+        directory: root.clone(),
+        template: None,
+    };
+    assert!(crate::run_command(command, &fs).is_err());
+
+    fs.insert(
+        root.join("template.html"),
+        "<main><!-- {CONTENT} --></main>",
+    );
+    fs.write(
+        &root.join("ssg.toml"),
+        b"title = \"my-site\"\noutput_path = \"output\"\n",
+    )
+    .unwrap();
+    let check = |fs: &crate::vfs::MemoryFs| {
+        crate::run_command(
+            crate::Command::Check {
+                options: crate::SiteOptions {
+                    directory: root.clone(),
+                    template: None,
+                    readme_as_index: false,
+                    flags: Vec::new(),
+                    lite: false,
+                    pretty_urls: false,
+                    notes: false,
+                    sandbox: false,
+                    drafts: false,
+                },
+                previous_sitemap: None,
+            },
+            fs,
+        )
+    };
+    let report = check(&fs).unwrap();
+    assert_eq!(report.pages.len(), 1);
+    // Nothing is left behind
+    assert!(!fs.exists(&root.join("output")));
+    assert!(fs
+        .files()
+        .iter()
+        .all(|path| path.starts_with(&root) || !path.to_string_lossy().contains("check")));
+    fs.insert(root.join("broken.dj"), "[A link](missing.dj)\n");
+    assert!(check(&fs).is_err());
+
+    crate::run_command(
+        crate::Command::Build(ConsoleArgs {
+            directory: Some(root.clone()),
+            ..Default::default()
+        }),
+        &fs,
+    )
+    .unwrap();
+    assert!(fs.is_file(&root.join("output/index.html")));
+    let command = crate::Command::Clean {
+        directory: root.clone(),
+        output_path: None,
+    };
+    crate::run_command(command, &fs).unwrap();
+    assert!(!fs.exists(&root.join("output")));
+}
+
+#[test]
+fn send_webmentions() {
+    use crate::vfs::FileSystem;
+    use std::io::{BufRead, BufReader, Read};
+    use std::sync::{Arc, Mutex};
+
+    // A server with a page that has a webmention endpoint, and one without
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let server = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let received = Arc::clone(&requests);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            let mut length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let request = request.trim().to_string();
+            let response = if request.starts_with("GET /post ") {
+                "<html><head><link rel=\"webmention\" href=\"/webmention\"></head></html>"
+            } else {
+                "<html><head></head></html>"
+            };
+            received.lock().unwrap().push(format!(
+                "{} {}",
+                request,
+                String::from_utf8(body).unwrap()
+            ));
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response.len(),
+                response
+            )
+            .unwrap();
+        }
+    });
+
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("ssg.toml"),
+        "[sitemap]\nbase_url = \"https://example.com/\"\n",
+    );
+    fs.insert(
+        root.join("template.html"),
+        "<main><!-- {CONTENT} --></main>",
+    );
+    fs.insert(
+        root.join("index.dj"),
+        format!(
+            "+++\ndate = 2024-01-01\n+++\n# Home\n\n[A post]({0}/post#top), [a page]({0}/plain), \
+             and [about](https://example.com/about.html).\n",
+            server
+        ),
+    );
+    let build = |dry_run| {
+        crate::run_program_with(
+            ConsoleArgs {
+                directory: Some(root.clone()),
+                output_path: Some(root.join("output")),
+                send_webmentions: true,
+                dry_run,
+                ..Default::default()
+            },
+            &fs,
+        )
+        .unwrap()
+    };
+    let posts = |requests: &Mutex<Vec<String>>| {
+        requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request.starts_with("POST"))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    // A dry run finds the endpoints, but sends and records nothing
+    let report = build(true);
+    assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    assert_eq!(requests.lock().unwrap().len(), 2);
+    assert!(posts(&requests).is_empty());
+    assert!(!fs.exists(&root.join(crate::webmention::SENT_FILE)));
+
+    build(false);
+    let target = format!("{}/post", server)
+        .replace(':', "%3A")
+        .replace('/', "%2F");
+    assert_eq!(
+        posts(&requests),
+        [format!(
+            "POST /webmention HTTP/1.1 source=https%3A%2F%2Fexample.com%2Findex.html&target={}",
+            target
+        )]
+    );
+    let sent = fs
+        .read_to_string(&root.join(crate::webmention::SENT_FILE))
+        .unwrap();
+    assert!(sent.contains(&format!("{}/plain", server)));
+    assert!(!sent.contains("about.html"));
+    // The record isn't part of the site
+    assert!(!fs.exists(&root.join("output").join(crate::webmention::SENT_FILE)));
+
+    // Links that were already mentioned aren't mentioned again
+    let count = requests.lock().unwrap().len();
+    build(false);
+    assert_eq!(requests.lock().unwrap().len(), count);
+    assert_eq!(posts(&requests).len(), 1);
+}
+
+#[test]
+fn init_site() -> anyhow::Result<()> {
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        (|| -> anyhow::Result<()> {
+            let site = temp_dir.join("site");
+            let command = crate::Command::Init {
+                directory: site.clone(),
+                template: None,
+            };
+            crate::run_command(command, &crate::vfs::RealFs)?;
+            for file in ["ssg.toml", "index.dj", "template.html", "assets/style.css"] {
+                assert!(site.join(file).is_file(), "{} wasn't created", file);
+            }
+            // The new site generates without warnings
+            let report = crate::SiteBuilder::new(&site).generate_site()?;
+            assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+            let html = read_to_string(site.join("output/index.html"))?;
+            assert!(html.contains("<title>site</title>"));
+            assert!(html.contains("href=\"./assets/style.css\""));
+            assert!(html.contains(">site</h1>"));
+            assert!(!html.contains("<!-- {"));
+            assert!(site.join("output/assets/style.css").is_file());
+            Ok(())
+        })()
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn page_view_beacon() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("ssg.toml"),
+        "[beacon]\nendpoint = \"https://stats.example.com/hit</script>\"\n",
+    );
+    fs.insert(
+        root.join("template.html"),
+        "<html><body><!-- {CONTENT} --></body></html>",
+    );
+    fs.insert(root.join("index.dj"), "# Home\n");
+    let build = |fragment| {
+        crate::run_program_with(
+            ConsoleArgs {
+                directory: Some(root.clone()),
+                output_path: Some(root.join("output")),
+                fragment,
+                ..Default::default()
+            },
+            &fs,
+        )
+        .unwrap();
+        fs.read_to_string(&root.join("output/index.html")).unwrap()
+    };
+    let html = build(false);
+    assert!(html.contains("fetch(\"https://stats.example.com/hit<\\/script>\",{method:\"POST\""));
+    assert!(html.contains("credentials:\"omit\""));
+    assert!(html.contains("navigator.doNotTrack"));
+    assert!(html.ends_with("</script>\n</body></html>"));
+    // Fragments are embedded elsewhere, without scripts
+    assert!(!build(true).contains("<script>"));
+
+    fs.insert(
+        root.join("ssg.toml"),
+        "[beacon]\nendpoint = \"/hit\"\nignore_do_not_track = true\n",
+    );
+    let html = build(false);
+    assert!(html.contains("fetch(\"/hit\""));
+    assert!(!html.contains("doNotTrack"));
+}
+
+#[test]
+fn new_page() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    let today = time::Date::from_calendar_date(2024, time::Month::March, 5).unwrap();
+    let path =
+        crate::scaffold::new_page(&fs, &root.join("posts/2024-03-05-hello_world"), today).unwrap();
+    assert_eq!(path, root.join("posts/2024-03-05-hello_world.dj"));
+    assert_eq!(
+        fs.read_to_string(&path).unwrap(),
+        "+++\ntitle = \"Hello world\"\ndate = 2024-03-05\ndraft = true\n+++\n\n"
+    );
+    let path = crate::scaffold::new_page(&fs, &root.join("notes.md"), today).unwrap();
+    assert!(fs
+        .read_to_string(&path)
+        .unwrap()
+        .starts_with("+++\ntitle = \"Notes\"\n"));
+    // Pages aren't overwritten
+    assert!(crate::scaffold::new_page(&fs, &root.join("notes.md"), today).is_err());
+
+    // The front matter is read back as written
+    fs.insert(root.join("template.html"), "<!-- {CONTENT} -->");
+    fs.insert(root.join("index.dj"), "# Home\n");
+    let report = crate::run_program_with(
+        ConsoleArgs {
+            directory: Some(root.clone()),
+            output_path: Some(root.join("output")),
+            ..Default::default()
+        },
+        &fs,
+    )
+    .unwrap();
+    assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    // New pages are drafts
+    assert!(fs.is_file(&root.join("output/index.html")));
+    assert!(!fs.exists(&root.join("output/posts/2024-03-05-hello_world.html")));
+}
+
+#[test]
+fn draft_pages() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("template.html"),
+        "<nav><!-- {TABLE_OF_CONTENTS} --></nav><!-- {CONTENT} -->",
+    );
+    fs.insert(root.join("index.dj"), "# Home\n");
+    fs.insert(root.join("post.dj"), "+++\ndraft = true\n+++\n# Post\n");
+    fs.insert(root.join("_draft-idea.md"), "# Idea\n");
+    fs.insert(root.join("_drafts.png"), "");
+    let build = |drafts| {
+        let output = root.join(if drafts { "preview" } else { "output" });
+        crate::run_program_with(
+            ConsoleArgs {
+                directory: Some(root.clone()),
+                output_path: Some(output.clone()),
+                drafts,
+                ..Default::default()
+            },
+            &fs,
+        )
+        .unwrap();
+        output
+    };
+
+    let output = build(false);
+    assert!(!fs.exists(&output.join("post.html")));
+    assert!(!fs.exists(&output.join("_draft-idea.html")));
+    assert!(!fs.exists(&output.join("_drafts.png")));
+    assert!(!fs
+        .read_to_string(&output.join("index.html"))
+        .unwrap()
+        .contains("post.html"));
+
+    // Previews include the drafts, in the table of contents too
+    let output = build(true);
+    assert!(fs.is_file(&output.join("post.html")));
+    assert!(fs.is_file(&output.join("_draft-idea.html")));
+    assert!(fs.is_file(&output.join("_drafts.png")));
+    assert!(fs
+        .read_to_string(&output.join("index.html"))
+        .unwrap()
+        .contains("post.html"));
+}
+
+#[test]
+fn content_security_policy() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(root.join("ssg.toml"), "[security]\n");
+    fs.insert(
+        root.join("template.html"),
+        "<html><head><script src=\"https://cdn.example.com/lib.js\"></script><script>init()</script><script type=\"application/ld+json\">{}</script></head><body><button onclick=\"window.print()\">Print</button><!-- {CONTENT} --></body></html>",
+    );
+    fs.insert(
+        root.join("index.dj"),
+        "# Home\n\n![logo](https://images.example.org/logo.png)\n",
+    );
+    let build = || {
+        crate::run_program_with(
+            ConsoleArgs {
+                directory: Some(root.clone()),
+                output_path: Some(root.join("output")),
+                ..Default::default()
+            },
+            &fs,
+        )
+        .unwrap();
+        fs.read_to_string(&root.join("output/index.html")).unwrap()
+    };
+    let html = build();
+    assert!(html.starts_with("<html><head>\n<meta http-equiv=\"Content-Security-Policy\" content=\"default-src &#39;self&#39;; "));
+    assert!(html.contains("<meta name=\"referrer\" content=\"strict-origin-when-cross-origin\">"));
+    // The hashes of `window.print()` and `init()`, but not of the json
+    assert!(html.contains("script-src &#39;self&#39; &#39;sha256-MguIPR6qNR8D3B+eAlK+bIRTZe8t3wkOY4B/56Me9FU=&#39; &#39;sha256-w4ujnOpjBoH2vcasx+reJRUwYivG8Q3afx/XevGJod8=&#39; &#39;unsafe-hashes&#39; https://cdn.example.com;"));
+    assert!(html.contains("img-src &#39;self&#39; https://images.example.org;"));
+    assert!(html.contains("object-src &#39;none&#39;"));
+    assert!(!html.contains("frame-ancestors"));
+    assert!(!fs.exists(&root.join("output").join(crate::security::HEADERS_FILE)));
+
+    fs.insert(
+        root.join("ssg.toml"),
+        "[security]\ncsp = \"headers\"\n\n[security.sources]\nfont-src = [\"https://fonts.gstatic.com\"]\n",
+    );
+    let html = build();
+    assert!(!html.contains("Content-Security-Policy"));
+    let headers = fs
+        .read_to_string(&root.join("output").join(crate::security::HEADERS_FILE))
+        .unwrap();
+    assert!(headers.starts_with("/*\n  Content-Security-Policy: default-src 'self'; "));
+    assert!(headers.contains("font-src 'self' https://fonts.gstatic.com;"));
+    assert!(headers.contains("frame-ancestors 'self'"));
+    assert!(headers.contains("  X-Content-Type-Options: nosniff\n"));
+}
+
+#[test]
+fn tag_pages() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("ssg.toml"),
+        "tag_pages = true\n\n[sitemap]\nbase_url = \"https://example.com\"\n",
+    );
+    fs.insert(
+        root.join("template.html"),
+        "<html><body><nav><!-- {TABLE_OF_CONTENTS} --></nav><!-- {TAGS} --><!-- {CONTENT} --></body></html>",
+    );
+    fs.insert(root.join("index.dj"), "# Home\n");
+    fs.insert(
+        root.join("posts/old.dj"),
+        "+++\ntitle = \"Old post\"\ndate = 2023-01-01\ntags = [\"Rust\", \"Web Dev\"]\n+++\n\nOld\n",
+    );
+    fs.insert(
+        root.join("posts/new.dj"),
+        "+++\ntitle = \"New post\"\ndate = 2024-06-01\ntags = [\"rust\"]\n+++\n\nNew\n",
+    );
+    crate::run_program_with(
+        ConsoleArgs {
+            directory: Some(root.clone()),
+            output_path: Some(root.join("output")),
+            ..Default::default()
+        },
+        &fs,
+    )
+    .unwrap();
+    let output = root.join("output");
+    let index = fs.read_to_string(&output.join("tags/index.html")).unwrap();
+    // Tags are named as they're first written, and posts/new.dj comes first
+    assert!(index.contains("<li><a href=\"../tags/rust.html\" rel=\"tag\">rust</a> <span class=\"tag-count\">2</span></li>\n<li><a href=\"../tags/web-dev.html\" rel=\"tag\">Web Dev</a> <span class=\"tag-count\">1</span></li>"));
+    let rust = fs.read_to_string(&output.join("tags/rust.html")).unwrap();
+    assert!(rust.contains("<h1>Tagged “rust”</h1>"));
+    // Newest first
+    let new = rust.find("../posts/new.html\">New post</a>").unwrap();
+    let old = rust.find("../posts/old.html\">Old post</a>").unwrap();
+    assert!(new < old);
+    // Only the index is in the table of contents
+    assert!(rust.contains("tags/index.html"));
+    assert!(!rust.contains("href=\"../tags/web-dev.html\""));
+    // But every tag page is in the sitemap
+    let sitemap = fs.read_to_string(&output.join("sitemap.xml")).unwrap();
+    assert!(sitemap.contains("<loc>https://example.com/tags/index.html</loc>"));
+    assert!(sitemap.contains("<loc>https://example.com/tags/web-dev.html</loc>"));
+    let post = fs.read_to_string(&output.join("posts/old.html")).unwrap();
+    assert!(post.contains("<ul class=\"tags\"><li><a href=\"../tags/rust.html\" rel=\"tag\">Rust</a></li><li><a href=\"../tags/web-dev.html\" rel=\"tag\">Web Dev</a></li></ul>"));
+}
+
+#[test]
+fn shortened_titles() {
+    use crate::utils::shorten_title;
+    assert_eq!(shorten_title("Short  title", Some(20)), "Short title");
+    assert_eq!(
+        shorten_title("A very long title indeed", Some(16)),
+        "A very long…"
+    );
+    assert_eq!(shorten_title("Supercalifragilistic", Some(6)), "Super…");
+    assert_eq!(shorten_title("Untouched title", None), "Untouched title");
+
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(root.join("ssg.toml"), "max_title_length = 16\n");
+    fs.insert(
+        root.join("template.html"),
+        "<html><body><nav><!-- {TABLE_OF_CONTENTS} --></nav><!-- {CONTENT} --><!-- {PREV_NEXT} --></body></html>",
+    );
+    fs.insert(root.join("index.dj"), "# Home\n");
+    fs.insert(
+        root.join("next.dj"),
+        "+++\ntitle = \"Generics <T> & a long title\"\n+++\n\nNext\n",
+    );
+    crate::run_program_with(
+        ConsoleArgs {
+            directory: Some(root.clone()),
+            output_path: Some(root.join("output")),
+            ..Default::default()
+        },
+        &fs,
+    )
+    .unwrap();
+    let html = fs.read_to_string(&root.join("output/index.html")).unwrap();
+    assert!(html.contains("<a href=\"next.html\">Generics &lt;T&gt; &amp;…</a>"));
+    assert!(html.contains("href=\"next.html\">Generics &lt;T&gt; &amp;… &rarr;</a>"));
+}
+
+#[test]
+fn paginated_listings() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("ssg.toml"),
+        "tag_pages = true\nlisting_page_size = 2\n",
+    );
+    fs.insert(
+        root.join("template.html"),
+        "<html><body><!-- {CONTENT} --></body></html>",
+    );
+    fs.insert(root.join("index.dj"), "# Home\n");
+    for day in 1..=5 {
+        fs.insert(
+            root.join(format!("post{}.dj", day)),
+            format!(
+                "+++\ntitle = \"Post {}\"\ndate = 2024-01-0{}\ntags = [\"news\", \"tag{}\"]\n+++\n",
+                day, day, day
+            ),
+        );
+    }
+    crate::run_program_with(
+        ConsoleArgs {
+            directory: Some(root.clone()),
+            output_path: Some(root.join("output")),
+            ..Default::default()
+        },
+        &fs,
+    )
+    .unwrap();
+    let output = root.join("output");
+    let first = fs.read_to_string(&output.join("tags/news.html")).unwrap();
+    assert!(first.contains("Post 5") && first.contains("Post 4") && !first.contains("Post 3"));
+    assert!(first.contains("<nav class=\"pagination\"><span class=\"page-number\">Page 1 of 3</span><a class=\"next\" rel=\"next\" href=\"../tags/news/page/2.html\">Next &rarr;</a></nav>"));
+    let second = fs
+        .read_to_string(&output.join("tags/news/page/2.html"))
+        .unwrap();
+    assert!(second.contains("<a class=\"post-title\" href=\"../../../post3.html\">Post 3</a>"));
+    assert!(second.contains("<a class=\"prev\" rel=\"prev\" href=\"../../../tags/news.html\">&larr; Previous</a><span class=\"page-number\">Page 2 of 3</span><a class=\"next\" rel=\"next\" href=\"../../../tags/news/page/3.html\">"));
+    assert!(fs
+        .read_to_string(&output.join("tags/news/page/3.html"))
+        .unwrap()
+        .contains("Post 1"));
+    // Six tags, so the index has three pages too
+    assert!(fs.exists(&output.join("tags/page/3.html")));
+    assert!(!fs.exists(&output.join("tags/page/4.html")));
+}
+
+#[test]
+fn problematic_file_names() {
+    use crate::slug::url_problems;
+    assert!(url_problems("getting-started.html").is_empty());
+    assert!(url_problems("café").is_empty());
+    assert_eq!(
+        url_problems("🎉 party?"),
+        vec![
+            "emoji or other symbols".to_string(),
+            "whitespace".to_string(),
+            "the reserved characters ?".to_string(),
+        ]
+    );
+    assert_eq!(
+        url_problems(&"a".repeat(101)),
+        vec!["101 characters, more than 100".to_string()]
+    );
+
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("template.html"),
+        "<html><body><!-- {CONTENT} --></body></html>",
+    );
+    fs.insert(root.join("index.dj"), "# Home\n");
+    fs.insert(root.join("Q&A.dj"), "# Questions\n");
+    fs.insert(root.join("files/report #1.pdf"), "pdf");
+    let build = || {
+        crate::run_program_with(
+            ConsoleArgs {
+                directory: Some(root.clone()),
+                output_path: Some(root.join("output")),
+                ..Default::default()
+            },
+            &fs,
+        )
+        .unwrap()
+        .warnings
+    };
+    let warnings = build();
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings.iter().any(|warning| warning
+        .contains("\"Q&A.dj\" will have the reserved characters &")
+        && warning.contains("set file_names = true in the [slugs] section")));
+    assert!(warnings.iter().any(|warning| warning
+        .contains("whitespace, the reserved characters #")
+        && warning.ends_with("rename it")));
+    // Slugged pages are renamed
+    fs.insert(root.join("ssg.toml"), "[slugs]\nfile_names = true\n");
+    assert_eq!(build().len(), 1);
+    assert!(fs.exists(&root.join("output/q-a.html")));
+}
+
+#[test]
+fn pretty_urls() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("ssg.toml"),
+        "[sitemap]\nbase_url = \"https://example.com/\"\n",
+    );
+    fs.insert(
+        root.join("template.html"),
+        "<html><head><link rel=\"stylesheet\" href=\"<!-- {SITE_ROOT} -->style.css\"></head><body><nav><!-- {TABLE_OF_CONTENTS} --></nav><!-- {CONTENT} --></body></html>",
+    );
+    fs.insert(
+        root.join("index.dj"),
+        "# Home\n\nSee [about](about.dj) and [the guide](docs/guide.dj#setup).\n",
+    );
+    fs.insert(
+        root.join("about.dj"),
+        "# About\n\n[Home](index.dj), [the guide](./docs/guide.dj), ![photo](images/me.png), [top](#about), and [elsewhere](https://example.com).\n",
+    );
+    fs.insert(
+        root.join("docs/guide.dj"),
+        "# Guide\n\n[About](../about.dj)\n",
+    );
+    crate::run_program_with(
+        ConsoleArgs {
+            directory: Some(root.clone()),
+            output_path: Some(root.join("output")),
+            pretty_urls: true,
+            ..Default::default()
+        },
+        &fs,
+    )
+    .unwrap();
+    let output = root.join("output");
+    assert!(!fs.exists(&output.join("about.html")));
+    let index = fs.read_to_string(&output.join("index.html")).unwrap();
+    assert!(index.contains("<a href=\"about/\">about</a>"));
+    assert!(index.contains("<a href=\"docs/guide/#setup\">the guide</a>"));
+    assert!(index.contains("<li><a href=\"about/\">about</a></li>"));
+    let about = fs.read_to_string(&output.join("about/index.html")).unwrap();
+    assert!(about.contains("href=\"../style.css\""));
+    assert!(about.contains("<a href=\"../\">Home</a>"));
+    assert!(about.contains("<a href=\"../docs/guide/\">the guide</a>"));
+    assert!(about.contains("<img src=\"../images/me.png\" alt=\"photo\">"));
+    assert!(about.contains("<a href=\"#about\">top</a>"));
+    assert!(about.contains("<a href=\"https://example.com\">elsewhere</a>"));
+    // The table of contents links to the directories
+    assert!(about.contains("<li><a href=\"../\">index</a></li>"));
+    let guide = fs
+        .read_to_string(&output.join("docs/guide/index.html"))
+        .unwrap();
+    assert!(guide.contains("<a href=\"../../about/\">About</a>"));
+    assert!(guide.contains("href=\"../../style.css\""));
+    // As does the sitemap
+    let sitemap = fs.read_to_string(&output.join("sitemap.xml")).unwrap();
+    assert!(sitemap.contains("<url><loc>https://example.com/about/</loc></url>\n<url><loc>https://example.com/docs/guide/</loc></url>\n<url><loc>https://example.com/</loc></url>\n"));
+}
+
+#[test]
+fn slugged_file_names() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("template.html"),
+        "<html><body><!-- {CONTENT} --></body></html>",
+    );
+    fs.insert(
+        root.join("index.dj"),
+        "# Home\n\n[Cool](My Cool Page.dj) [Intro](My Docs/Intro Page.md)\n",
+    );
+    fs.insert(root.join("My Cool Page.dj"), "# Cool\n");
+    fs.insert(
+        root.join("My Docs/Intro Page.md"),
+        "# Intro\n\n![Diagram](diagram.png) [Home](../index.dj)\n",
+    );
+    fs.insert(root.join("My Docs/diagram.png"), "png");
+    let build = || {
+        crate::run_program_with(
+            ConsoleArgs {
+                directory: Some(root.clone()),
+                output_path: Some(root.join("output")),
+                ..Default::default()
+            },
+            &fs,
+        )
+        .unwrap()
+        .warnings
+    };
+    // File names aren't slugged unless they're turned on, only anchors
+    fs.insert(root.join("ssg.toml"), "[slugs]\n");
+    let warnings = build();
+    assert_eq!(warnings.len(), 3);
+    assert!(warnings
+        .iter()
+        .all(|warning| warning.contains("will have whitespace")));
+    assert!(fs.exists(&root.join("output/My Cool Page.html")));
+    assert!(fs.exists(&root.join("output/My Docs/Intro Page.html")));
+
+    fs.remove_dir_all(&root.join("output")).unwrap();
+    fs.insert(root.join("ssg.toml"), "[slugs]\nfile_names = true\n");
+    assert!(build().is_empty());
+    assert!(fs.exists(&root.join("output/my-cool-page.html")));
+    let index = fs.read_to_string(&root.join("output/index.html")).unwrap();
+    assert!(index.contains("href=\"my-cool-page.html\""));
+    // Directories are slugged too, with the files in them
+    assert!(index.contains("href=\"my-docs/intro-page.html\""));
+    assert!(!fs.exists(&root.join("output/My Docs")));
+    let intro = fs
+        .read_to_string(&root.join("output/my-docs/intro-page.html"))
+        .unwrap();
+    assert!(intro.contains("src=\"diagram.png\""));
+    assert!(fs.exists(&root.join("output/my-docs/diagram.png")));
+
+    // Pages that slug the same as another page or a file are warned about
+    fs.insert(root.join("my-cool-page.html"), "<p>Mine</p>");
+    let warnings = build();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("written over the site's own file"));
+    fs.insert(root.join("my-cool-page.md"), "# Also cool\n");
+    let warnings = build();
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings
+        .iter()
+        .any(|warning| warning.contains("are both generated as \"my-cool-page.html\"")));
+}
+
+#[test]
+fn content_filters() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("template.html"),
+        "<html><body><!-- {CONTENT} --></body></html>",
+    );
+    fs.insert(
+        root.join("ssg.toml"),
+        r#"
+[[filters]]
+find = "https://old.example.com"
+replace = "https://example.com"
+
+[[filters]]
+find = '\bWidget\b'
+replace = "Widget™"
+regex = true
+paths = ["products/**"]
+
+[[filters]]
+find = '(\d+) USD'
+replace = "$$$1"
+regex = true
+"#,
+    );
+    fs.insert(
+        root.join("index.dj"),
+        "# Home\n\n[Old](https://old.example.com/page) Widget for 5 USD\n",
+    );
+    fs.insert(
+        root.join("products/widget.dj"),
+        "# Widget\n\nWidgets and a Widget\n",
+    );
+    let warnings = crate::run_program_with(
+        ConsoleArgs {
+            directory: Some(root.clone()),
+            output_path: Some(root.join("output")),
+            ..Default::default()
+        },
+        &fs,
+    )
+    .unwrap()
+    .warnings;
+    assert!(warnings.is_empty());
+    let index = fs.read_to_string(&root.join("output/index.html")).unwrap();
+    assert!(index.contains("href=\"https://example.com/page\""));
+    assert!(index.contains("Widget for $5"));
+    let widget = fs
+        .read_to_string(&root.join("output/products/widget.html"))
+        .unwrap();
+    assert!(widget.contains(">Widget™</h1>"));
+    assert!(widget.contains("Widgets and a Widget™"));
+
+    // Invalid filters stop the build
+    fs.insert(
+        root.join("ssg.toml"),
+        "[[filters]]\nfind = \"(\"\nreplace = \"\"\nregex = true\n",
+    );
+    assert!(crate::run_program_with(
+        ConsoleArgs {
+            directory: Some(root.clone()),
+            output_path: Some(root.join("output")),
+            ..Default::default()
+        },
+        &fs,
+    )
+    .is_err());
+}
+
+#[test]
+fn heading_anchors() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("template.html"),
+        "<html><head><title><!-- {TITLE} --></title></head><body><!-- {CONTENT} --></body></html>",
+    );
+    fs.insert(root.join("index.dj"), "# Home\n\n## Getting started\n");
+    fs.insert(root.join("guide.md"), "# The Guide\n\n## Install it\n");
+    let build = || {
+        crate::run_program_with(
+            ConsoleArgs {
+                directory: Some(root.clone()),
+                output_path: Some(root.join("output")),
+                ..Default::default()
+            },
+            &fs,
+        )
+        .unwrap()
+    };
+    // Markdown headings get ids without [slugs]
+    build();
+    let guide = fs.read_to_string(&root.join("output/guide.html")).unwrap();
+    assert!(guide.contains("<h2 id=\"install-it\">Install it</h2>"));
+    assert!(!guide.contains("heading-anchor"));
+
+    fs.insert(root.join("ssg.toml"), "heading_anchors = true\n");
+    build();
+    let guide = fs.read_to_string(&root.join("output/guide.html")).unwrap();
+    assert!(guide.contains(
+        "<h2 id=\"install-it\">Install it <a class=\"heading-anchor\" href=\"#install-it\" aria-label=\"Link to this section\">¶</a></h2>"
+    ));
+    // Titles are taken from the heading without its anchor
+    assert!(guide.contains("<title>The Guide</title>"));
+    let index = fs.read_to_string(&root.join("output/index.html")).unwrap();
+    assert!(
+        index.contains("href=\"#Getting-started\" aria-label=\"Link to this section\">¶</a></h2>")
+    );
+
+    use crate::slug::add_heading_anchors;
+    assert_eq!(
+        add_heading_anchors("<section id=\"usage\">\n<h2>Usage</h2>\n<p>Text</p>\n<h3>No id</h3>"),
+        "<section id=\"usage\">\n<h2>Usage <a class=\"heading-anchor\" href=\"#usage\" aria-label=\"Link to this section\">¶</a></h2>\n<p>Text</p>\n<h3>No id</h3>"
+    );
+}
+
+#[test]
+fn inline_svg_shortcode() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("template.html"),
+        "<html><body><!-- {CONTENT} --></body></html>",
+    );
+    fs.insert(
+        root.join("icons/logo.svg"),
+        "<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"logo\" viewBox=\"0 0 8 8\">\n\n<circle r=\"4\"/>\n</svg>\n",
+    );
+    fs.insert(
+        root.join("index.md"),
+        "# Home\n\n{{svg icons/logo.svg class=\"icon big\" id=\"site-logo\" title=\"Simple & small\"}}\n\n{{svg icons/missing.svg}}\n",
+    );
+    fs.insert(root.join("guide.md"), "# Guide\n\n{{svg icons/logo.svg}}\n");
+    let warnings = crate::run_program_with(
+        ConsoleArgs {
+            directory: Some(root.clone()),
+            output_path: Some(root.join("output")),
+            ..Default::default()
+        },
+        &fs,
+    )
+    .unwrap()
+    .warnings;
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("an svg that can't be inlined"));
+    let index = fs.read_to_string(&root.join("output/index.html")).unwrap();
+    assert!(index.contains(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"logo icon big\" viewBox=\"0 0 8 8\" id=\"site-logo\" role=\"img\"><title>Simple &amp; small</title> <circle r=\"4\"/> </svg>"
+    ));
+    assert!(!index.contains("<?xml"));
+    assert!(index.contains("{{svg icons/missing.svg}}"));
+    let guide = fs.read_to_string(&root.join("output/guide.html")).unwrap();
+    assert!(guide.contains(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"logo\" viewBox=\"0 0 8 8\"> <circle r=\"4\"/> </svg>"
+    ));
+}
+
+#[test]
+fn lists_of_figures_and_tables() {
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("template.html"),
+        "<html><body><!-- {LIST_OF_FIGURES} --><!-- {LIST_OF_TABLES} --><!-- {CONTENT} --></body></html>",
+    );
+    fs.insert(root.join("ssg.toml"), "[figures]\n");
+    fs.insert(root.join("index.md"), "# Home\n");
+    fs.insert(
+        root.join("report.md"),
+        "# Report\n\n<figure><img src=\"chart.png\" alt=\"\"><figcaption>Sales <em>by</em> year</figcaption></figure>\n\n<figure id=\"map\"><figcaption>Where & when</figcaption></figure>\n\n<figure><img src=\"uncaptioned.png\" alt=\"\"></figure>\n\n<table><caption>Results</caption><tr><td>1</td></tr></table>\n",
+    );
+    fs.insert(root.join("chart.png"), "png");
+    fs.insert(root.join("uncaptioned.png"), "png");
+    let build = || {
+        crate::run_program_with(
+            ConsoleArgs {
+                directory: Some(root.clone()),
+                output_path: Some(root.join("output")),
+                ..Default::default()
+            },
+            &fs,
+        )
+        .unwrap()
+    };
+    assert!(build().warnings.is_empty());
+    let report = fs.read_to_string(&root.join("output/report.html")).unwrap();
+    assert!(report.contains("<figure id=\"figure-1\"><img"));
+    assert!(report.contains("<figure id=\"map\">"));
+    assert!(report.contains("<table id=\"table-1\"><caption>"));
+    assert!(report.contains(
+        "<body><ol class=\"list-of-figures\"><li><a href=\"#figure-1\">Sales by year</a></li><li><a href=\"#map\">Where &amp; when</a></li></ol><ol class=\"list-of-tables\"><li><a href=\"#table-1\">Results</a></li></ol>"
+    ));
+    let index = fs.read_to_string(&root.join("output/index.html")).unwrap();
+    assert!(index.contains("<body><h1"));
+
+    let figures = fs
+        .read_to_string(&root.join("output/figures.html"))
+        .unwrap();
+    assert!(figures.contains("<h1>List of Figures</h1>"));
+    assert!(figures.contains(
+        "<h2><a href=\"report.html\">Report</a></h2>\n<ol class=\"list-of-figures\"><li><a href=\"report.html#figure-1\">Sales by year</a></li>"
+    ));
+    assert!(!figures.contains("Home"));
+    let tables = fs.read_to_string(&root.join("output/tables.html")).unwrap();
+    assert!(tables.contains("<a href=\"report.html#table-1\">Results</a>"));
+
+    // Without list pages, only the lists on each page are filled in
+    fs.insert(root.join("ssg.toml"), "[figures]\nlist_pages = false\n");
+    fs.remove_dir_all(&root.join("output")).unwrap();
+    build();
+    assert!(!fs.exists(&root.join("output/figures.html")));
+    let report = fs.read_to_string(&root.join("output/report.html")).unwrap();
+    assert!(report.contains("<a href=\"#table-1\">Results</a>"));
+}
+
+#[test]
+fn site_with_tag_pages_on_disk() -> anyhow::Result<()> {
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/posts"))?;
+                let files: [(&str, &[u8]); 4] = [
+                    ("ssg.toml", b"tag_pages = true\n"),
+                    (
+                        "template.html",
+                        b"<html><body><!-- {CONTENT} --></body></html>",
+                    ),
+                    ("index.dj", b"# Home\n"),
+                    (
+                        "posts/post.dj",
+                        b"+++\ntitle = \"Post\"\ntags = [\"rust\"]\n+++\n\nPost\n",
+                    ),
+                ];
+                for (name, contents) in files {
+                    File::create(temp_dir.join("target").join(name))?.write_all(contents)?;
+                }
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                // Nothing else creates the tags directory
+                assert!(read_to_string(temp_dir.join("output/tags/index.html"))?.contains("rust"));
+                assert!(read_to_string(temp_dir.join("output/tags/rust.html"))?
+                    .contains("../posts/post.html"));
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_paginated_listings_on_disk() -> anyhow::Result<()> {
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target"))?;
+                let files: [(&str, &[u8]); 5] = [
+                    ("ssg.toml", b"tag_pages = true\nlisting_page_size = 1\n"),
+                    (
+                        "template.html",
+                        b"<html><body><!-- {CONTENT} --></body></html>",
+                    ),
+                    ("index.dj", b"# Home\n"),
+                    (
+                        "first.dj",
+                        b"+++\ntitle = \"First\"\ndate = 2024-01-01\ntags = [\"news\"]\n+++\n",
+                    ),
+                    (
+                        "second.dj",
+                        b"+++\ntitle = \"Second\"\ndate = 2024-01-02\ntags = [\"news\"]\n+++\n",
+                    ),
+                ];
+                for (name, contents) in files {
+                    File::create(temp_dir.join("target").join(name))?.write_all(contents)?;
+                }
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                assert!(read_to_string(temp_dir.join("output/tags/news.html"))?.contains("Second"));
+                assert!(
+                    read_to_string(temp_dir.join("output/tags/news/page/2.html"))?
+                        .contains("First")
+                );
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn site_with_pretty_urls_on_disk() -> anyhow::Result<()> {
+    let temp_dir = temp_dir().join(temp_dir_name());
+    let res = panic::catch_unwind(|| {
+        {
+            (|| -> anyhow::Result<()> {
+                create_dir_all(temp_dir.join("target/docs"))?;
+                let files: [(&str, &[u8]); 4] = [
+                    (
+                        "template.html",
+                        b"<html><body><!-- {CONTENT} --></body></html>",
+                    ),
+                    ("index.dj", b"# Home\n\n[About](about.dj)\n"),
+                    ("about.dj", b"# About\n"),
+                    ("docs/guide.dj", b"# Guide\n"),
+                ];
+                for (name, contents) in files {
+                    File::create(temp_dir.join("target").join(name))?.write_all(contents)?;
+                }
+                let args = ConsoleArgs {
+                    directory: Some(temp_dir.join("target")),
+                    output_path: Some(temp_dir.join("output")),
+                    pretty_urls: true,
+                    ..Default::default()
+                };
+                crate::run_program(args)?;
+                assert!(read_to_string(temp_dir.join("output/index.html"))?
+                    .contains("<a href=\"about/\">About</a>"));
+                assert!(read_to_string(temp_dir.join("output/about/index.html"))?.contains("About"));
+                assert!(
+                    read_to_string(temp_dir.join("output/docs/guide/index.html"))?
+                        .contains("Guide")
+                );
+                assert!(!temp_dir.join("output/about.html").exists());
+                Ok(())
+            })()
+        }
+    });
+
+    let _ = remove_dir_all(&temp_dir);
+    match res {
+        Ok(e) => e,
+        _ => Err(anyhow::anyhow!("Panic occurred")),
+    }
+}
+
+#[test]
+fn shortcodes_in_code() {
+    use crate::utils::code_ranges;
+    let source =
+        "a `{{x}}` b ``c ` {{y}}`` {{z}}\n```djot\n{{qr a}}\n```\n~~~~\n```\n~~~~\n`open\n";
+    let code = code_ranges(source)
+        .into_iter()
+        .map(|range| &source[range])
+        .collect::<Vec<_>>();
+    assert_eq!(
+        code,
+        [
+            "`{{x}}`",
+            "``c ` {{y}}``",
+            "```djot\n{{qr a}}\n```\n",
+            "~~~~\n```\n~~~~\n"
+        ]
+    );
+    // Unclosed code blocks run to the end
+    assert_eq!(code_ranges("```\n{{qr a}}\n"), vec![0..13]);
+
+    use crate::vfs::FileSystem;
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(
+        root.join("template.html"),
+        "<html><body><!-- {CONTENT} --></body></html>",
+    );
+    fs.insert(
+        root.join("index.md"),
+        "# Home\n\nWrite `{{qr <text>}}`, e.g.\n\n```\n{{qr https://example.com}}\n{{svg missing.svg}}\n```\n\n{{qr https://example.com}}\n",
+    );
+    let report = crate::run_program_with(
+        ConsoleArgs {
+            directory: Some(root.clone()),
+            output_path: Some(root.join("output")),
+            ..Default::default()
+        },
+        &fs,
+    )
+    .unwrap();
+    assert!(report.warnings.is_empty());
+    let html = fs.read_to_string(&root.join("output/index.html")).unwrap();
+    assert!(html.contains("<code>{{qr &lt;text&gt;}}</code>"));
+    assert!(html.contains("{{qr https://example.com}}\n{{svg missing.svg}}\n</code></pre>"));
+    assert_eq!(html.matches("<svg class=\"qr-code\"").count(), 1);
+}
+
+#[test]
+fn build_reports_are_sorted() {
+    let fs = crate::vfs::MemoryFs::new();
+    let root = std::path::PathBuf::from(temp_dir_name());
+    fs.insert(root.join("index.md"), "# Home\n");
+    for page in 0..20 {
+        fs.insert(
+            root.join(format!("page{}.md", page)),
+            format!("# Page\n\n[Gone](gone{}.md)\n", page),
+        );
+        fs.insert(root.join(format!("image{}.png", page)), "png");
+    }
+    let build = || {
+        crate::run_program_with(
+            ConsoleArgs {
+                directory: Some(root.clone()),
+                output_path: Some(root.join("output")),
+                ..Default::default()
+            },
+            &fs,
+        )
+        .unwrap()
+    };
+    // Pages are generated in parallel, but every build reports them the same way
+    let report = build();
+    assert_eq!(report.warnings.len(), 20);
+    // Including with a single thread
+    let single = crate::SiteBuilder::new(root.clone())
+        .output_path(root.join("output"))
+        .jobs(std::num::NonZeroUsize::MIN)
+        .generate_site_with(&fs)
+        .unwrap();
+    assert_eq!(single, report);
+    // And reading and writing one file at a time
+    let limited = crate::SiteBuilder::new(root.clone())
+        .output_path(root.join("output"))
+        .io_jobs(std::num::NonZeroUsize::MIN)
+        .generate_site_with(&fs)
+        .unwrap();
+    assert_eq!(limited, report);
+    assert!(report.warnings.is_sorted());
+    assert!(report.pages.is_sorted() && report.assets.is_sorted() && report.outputs.is_sorted());
+    assert_eq!(build(), report);
+}
+
+#[test]
+fn standalone_pages_render_like_site_pages() -> anyhow::Result<()> {
+    let config: crate::SiteConfig = toml::from_str("[links]\n[figures]\n")?;
+    let html = crate::process_markdown(
+        "[Out](https://example.com)\n\n<table><caption>Sizes</caption></table>\n\n{{svg /etc/hostname}}\n",
+        config,
+    )?;
+    assert!(html.contains("class=\"external\""));
+    assert!(html.contains("<table id=\"table-1\">"));
+    // There are no other files to read
+    assert!(html.contains("{{svg /etc/hostname}}"));
+    Ok(())
+}
+
 fn temp_dir_name() -> String {
     format!(
         ".simple-ssg-test-{}",
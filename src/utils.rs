@@ -1,37 +1,238 @@
 use std::{
-    fs::read_to_string,
-    path::{Path, PathBuf},
+    borrow::Cow,
+    collections::HashMap,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
 };
 
-pub fn check_has_index(target_path: &Path) -> bool {
-    target_path.join("index.dj").exists()
-        || target_path.join("index.djot").exists()
-        || target_path.join("index.md").exists()
+use crate::{
+    config::{ContributorsConfig, SiteConfig, TextDirection},
+    errors::SsgError,
+    git::Contributor,
+    slug,
+    templating::{self, PageVariables, Variables},
+    vfs::FileSystem,
+    SiteContext,
+};
+
+pub fn check_has_index(fs: &dyn FileSystem, target_path: &Path, index_names: &[String]) -> bool {
+    std::iter::once("index")
+        .chain(index_names.iter().map(String::as_str))
+        .any(|name| has_source_file(fs, target_path, name))
 }
 
-pub fn get_template_if_exists(
-    djot_document_path: &Path,
-    root_path: &Path,
-) -> anyhow::Result<Option<String>> {
-    if !is_ancestor(root_path, djot_document_path) {
-        Err(anyhow::anyhow!("Root path is not an ancestor of main path"))
+/// Checks if `path` should be rendered as its directory's `index.html`, i.e. its file stem is
+/// one of `index_names` and the directory doesn't already have an `index.{dj|djot|md}`.
+pub fn is_index_alias(fs: &dyn FileSystem, path: &Path, index_names: &[String]) -> bool {
+    let Some(stem) = path.file_stem().map(|x| x.to_string_lossy()) else {
+        return false;
+    };
+    if !index_names.iter().any(|name| *name == stem) {
+        return false;
+    }
+    !has_source_file(fs, path.parent().unwrap(), "index")
+}
+
+/// Gets the rewritten path for a link `link` to the document at `referenced_path`.
+pub fn html_link_path(
+    fs: &dyn FileSystem,
+    link: &str,
+    referenced_path: &Path,
+    config: &SiteConfig,
+) -> PathBuf {
+    if is_index_alias(fs, referenced_path, &config.index_names) {
+        Path::new(link).with_file_name("index.html")
+    } else if config.pretty_urls {
+        pretty_path(&slug::html_path(Path::new(link), config.file_name_slugs()))
     } else {
-        let mut current = PathBuf::from(djot_document_path.parent().unwrap());
-        loop {
-            let template_file = current.join("template.html");
-            log::trace!("Checking for template file at {:?}", &template_file);
-            if template_file.exists() {
-                return Ok(Some(read_to_string(&template_file)?));
-            }
-            if current == root_path {
-                break;
+        slug::html_path(Path::new(link), config.file_name_slugs())
+    }
+}
+
+/// The path of the page at the html path `path` with pretty urls: `about.html` is written to
+/// `about/index.html`. Index pages stay where they are.
+pub fn pretty_path(path: &Path) -> PathBuf {
+    match path.file_stem() {
+        Some(stem) if stem != "index" => path.with_extension("").join("index.html"),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// `url` without the `index.html` at its end, so it links to the directory (`./` for the
+/// current one).
+pub fn directory_url(url: &str) -> String {
+    match url.strip_suffix("index.html") {
+        Some("") => "./".to_string(),
+        Some(dir) if dir.ends_with('/') => dir.to_string(),
+        _ => url.to_string(),
+    }
+}
+
+fn has_source_file(fs: &dyn FileSystem, dir: &Path, stem: &str) -> bool {
+    ["dj", "djot", "md"]
+        .iter()
+        .any(|ext| fs.exists(&dir.join(stem).with_extension(ext)))
+}
+
+/// Files found beside pages or in their ancestors (such as `template.html`) during a build, so
+/// each directory is only checked, and each file only read, once.
+#[derive(Debug, Default)]
+pub struct TemplateCache {
+    /// The nearest file of each name for each directory
+    found: Mutex<HashMap<(PathBuf, String), Option<PathBuf>>>,
+    contents: Mutex<HashMap<PathBuf, String>>,
+}
+
+impl TemplateCache {
+    /// Finds the nearest `file_name` beside `path` or in one of its ancestors, up to the
+    /// target directory. Sandboxed sites ignore files symlinked from outside of it.
+    pub fn find(
+        &self,
+        context: &SiteContext,
+        path: &Path,
+        file_name: &str,
+    ) -> anyhow::Result<Option<PathBuf>> {
+        if !is_ancestor(context.target_path, path) {
+            Err(anyhow::anyhow!("Root path is not an ancestor of main path"))
+        } else {
+            Ok(self.find_from(context, path.parent().unwrap(), file_name))
+        }
+    }
+
+    /// Reads the nearest `file_name` beside `path` or in one of its ancestors, up to the
+    /// target directory.
+    pub fn read(
+        &self,
+        context: &SiteContext,
+        path: &Path,
+        file_name: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let Some(file) = self.find(context, path, file_name)? else {
+            return Ok(None);
+        };
+        if let Some(contents) = self.contents.lock().unwrap().get(&file) {
+            return Ok(Some(contents.clone()));
+        }
+        let contents = context.fs.read_to_string(&file)?;
+        self.contents.lock().unwrap().insert(file, contents.clone());
+        Ok(Some(contents))
+    }
+
+    fn find_from(&self, context: &SiteContext, dir: &Path, file_name: &str) -> Option<PathBuf> {
+        let key = (dir.to_path_buf(), file_name.to_string());
+        if let Some(found) = self.found.lock().unwrap().get(&key) {
+            return found.clone();
+        }
+        let file = dir.join(file_name);
+        log::trace!("Checking for {} at {:?}", file_name, &file);
+        let found = if context.fs.exists(&file) {
+            if context.sandbox && !crate::sandbox::is_inside(context.fs, context.target_path, &file)
+            {
+                context.warn(SsgError::OutsideTarget(file));
+                None
+            } else {
+                Some(file)
             }
-            current = current.parent().unwrap().to_path_buf();
+        } else if dir == context.target_path {
+            None
+        } else {
+            self.find_from(context, dir.parent().unwrap(), file_name)
+        };
+        self.found.lock().unwrap().insert(key, found.clone());
+        found
+    }
+}
+
+/// Links the stylesheet at `url` at the end of the `<head>` of `html`, so it overrides the
+/// template's own styles. Pages without a `<head>` are left as they are.
+pub fn link_stylesheet(html: &str, url: &str) -> String {
+    match html.find("</head>") {
+        Some(end) => format!(
+            "{}<link rel=\"stylesheet\" href=\"{}\">\n{}",
+            &html[..end],
+            escape_html(url),
+            &html[end..]
+        ),
+        None => html.to_string(),
+    }
+}
+
+/// Adds a `<meta>` named `name` with `content` to the `<head>` of `html`. Pages without a
+/// `<head>` are left as they are.
+pub fn add_meta(html: &str, name: &str, content: &str) -> String {
+    match html.find("</head>") {
+        Some(end) => format!(
+            "{}<meta name=\"{}\" content=\"{}\">\n{}",
+            &html[..end],
+            escape_html(name),
+            escape_html(content),
+            &html[end..]
+        ),
+        None => html.to_string(),
+    }
+}
+
+/// Adds a `<title>` of `title` (escaped html text) to the `<head>` of `html`, unless it already
+/// has one. Pages without a `<head>` are left as they are.
+pub fn set_title(html: &str, title: &str) -> String {
+    match html.find("</head>") {
+        Some(end) if !html[..end].contains("<title") => {
+            format!("{}<title>{}</title>\n{}", &html[..end], title, &html[end..])
         }
-        Ok(None)
+        _ => html.to_string(),
     }
 }
 
+/// `title` with its whitespace collapsed, cut after the last whole word that fits in
+/// `max_length` characters (including the `…` that ends it) if it's longer.
+pub fn shorten_title(title: &str, max_length: Option<usize>) -> String {
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    let Some(max_length) = max_length.filter(|max| title.chars().count() > *max) else {
+        return title;
+    };
+    let cut = title
+        .chars()
+        .take(max_length.saturating_sub(1))
+        .collect::<String>();
+    // Cut between words, unless the first word alone is too long
+    let cut = match title[cut.len()..].starts_with(' ') {
+        true => cut.as_str(),
+        false => cut
+            .rsplit_once(' ')
+            .map_or(cut.as_str(), |(words, _)| words),
+    };
+    format!("{}…", cut.trim_end())
+}
+
+/// The text of the first `<h1>` in `html`, without any tags in it, if it has one.
+pub fn first_heading(html: &str) -> Option<String> {
+    let start = html.find("<h1")?;
+    let rest = &html[start + 3..];
+    // Not another element starting with `h1`
+    if !rest.starts_with(['>', ' ', '\n', '\t']) {
+        return first_heading(rest);
+    }
+    let rest = &rest[rest.find('>')? + 1..];
+    let heading = &rest[..rest.find("</h1>")?];
+    let heading = heading
+        .split(slug::HEADING_ANCHOR)
+        .next()
+        .unwrap_or_default();
+    let mut text = String::with_capacity(heading.len());
+    let mut in_tag = false;
+    for c in heading.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
 /// Checks if `ancestor` is an ancestor of `descendant`.
 fn is_ancestor(ancestor: &Path, descendant: &Path) -> bool {
     let mut current = PathBuf::from(descendant);
@@ -44,9 +245,379 @@ fn is_ancestor(ancestor: &Path, descendant: &Path) -> bool {
     false
 }
 
-pub fn wrap_html_content(content: &str, template: Option<&str>) -> String {
-    match template {
-        Some(tmpl) => tmpl.to_string().replace("<!-- {CONTENT} -->", content),
-        None => content.to_string(),
+/// Resolves the `.` and `..` components of `path` without touching the filesystem. Leading
+/// `..` components of relative paths are kept.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Checks if `path` stays inside `root_path` once `..` components are resolved.
+pub fn is_within(root_path: &Path, path: &Path) -> bool {
+    normalize_path(path).starts_with(normalize_path(root_path))
+}
+
+/// The url of the page at `relative_path` (relative to the output directory), from a page at
+/// `from_depth`.
+pub fn page_url(from_depth: usize, web_prefix: Option<&str>, relative_path: &Path) -> String {
+    format!(
+        "{}{}{}",
+        "../".repeat(from_depth.saturating_sub(1)),
+        web_prefix.unwrap_or(""), // "./" if "" doesn't work
+        relative_path.to_string_lossy(),
+    )
+}
+
+/// The url of the page at `relative_path`, as `page_url`, linking to its directory instead of
+/// its `index.html` with `pretty_urls`.
+pub fn page_link(
+    from_depth: usize,
+    web_prefix: Option<&str>,
+    relative_path: &Path,
+    pretty_urls: bool,
+) -> String {
+    let url = page_url(from_depth, web_prefix, relative_path);
+    match pretty_urls {
+        true => directory_url(&url),
+        false => url,
+    }
+}
+
+/// The name of the page at `relative_path` for navigation, when it has no title: its file
+/// name, or its directory's with `pretty_urls`.
+pub fn page_name(relative_path: &Path, pretty_urls: bool) -> String {
+    let stem = relative_path.file_stem().unwrap_or_default();
+    let name = match relative_path.parent().and_then(Path::file_name) {
+        Some(dir) if pretty_urls && stem == "index" => dir,
+        _ => stem,
+    };
+    name.to_string_lossy().to_string()
+}
+
+/// Sets the `lang` and `dir` attributes of a page's `<html>` element, or wraps `html` in a
+/// `<div>` with them if it has no `<html>` element (such as without a template).
+pub fn set_language(html: &str, lang: Option<&str>, dir: Option<TextDirection>) -> String {
+    let mut attributes = String::new();
+    if let Some(lang) = lang {
+        attributes.push_str(&format!(" lang=\"{}\"", escape_html(lang)));
+    }
+    if let Some(dir) = dir {
+        attributes.push_str(&format!(" dir=\"{}\"", dir.as_str()));
+    }
+    if attributes.is_empty() {
+        return html.to_string();
+    }
+    match html.find("<html") {
+        Some(start) => {
+            let (before, after) = html.split_at(start + "<html".len());
+            format!("{}{}{}", before, attributes, after)
+        }
+        None => format!("<div{}>{}</div>", attributes, html),
+    }
+}
+
+/// A page that immediately redirects to `url`.
+pub fn redirect_html(url: &str) -> String {
+    let url = escape_html(url);
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Redirecting…</title>\n<link rel=\"canonical\" href=\"{}\">\n<meta http-equiv=\"refresh\" content=\"0; url={}\">\n</head>\n<body>\n<a href=\"{}\">Redirecting…</a>\n</body>\n</html>\n",
+        url, url, url
+    )
+}
+
+/// Places `content` in `template`, with its first heading as the `<!-- {TITLE} -->`.
+pub fn wrap_html_content(content: &str, template: Option<&str>) -> anyhow::Result<String> {
+    wrap_html_content_with(content, template, |_| Ok(None))
+}
+
+/// [`wrap_html_content`], also filling in the template's other macros with `macros`.
+pub fn wrap_html_content_with(
+    content: &str,
+    template: Option<&str>,
+    mut macros: impl FnMut(&str) -> anyhow::Result<Option<String>>,
+) -> anyhow::Result<String> {
+    let Some(template) = template else {
+        return Ok(content.to_string());
+    };
+    let title = first_heading(content);
+    let template = templating::render(template.to_string(), None, "the page", || Variables {
+        page: PageVariables {
+            title: title.as_deref().map(unescape_html),
+            ..Default::default()
+        },
+        ..Default::default()
+    })?;
+    fill_macros(&template, |name| match name {
+        "TITLE" => Ok(Some(title.clone().unwrap_or_default())),
+        "CONTENT" => Ok(Some(content.to_string())),
+        name => macros(name),
+    })
+}
+
+/// Fills in each `<!-- {NAME} -->` macro of `text` with what `fill` gives for its `NAME`, in
+/// one pass, leaving the macros it gives nothing for as they are. What's filled in (such as a
+/// page's content) isn't searched for macros itself.
+pub fn fill_macros(
+    text: &str,
+    mut fill: impl FnMut(&str) -> anyhow::Result<Option<String>>,
+) -> anyhow::Result<String> {
+    const START: &str = "<!-- {";
+    const END: &str = "} -->";
+    let mut filled = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(START) {
+        filled.push_str(&rest[..start]);
+        let after = &rest[start + START.len()..];
+        let name = after.find(END).map(|end| &after[..end]).filter(|name| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        });
+        let value = match name {
+            Some(name) => fill(name)?.map(|value| (name, value)),
+            None => None,
+        };
+        match value {
+            Some((name, value)) => {
+                filled.push_str(&value);
+                rest = &after[name.len() + END.len()..];
+            }
+            None => {
+                filled.push_str(START);
+                rest = after;
+            }
+        }
+    }
+    filled.push_str(rest);
+    Ok(filled)
+}
+
+/// Replaces each run of text in `html` with `f` of it, except inside the `skipped_elements`
+/// (such as `code`), which are copied as is.
+pub fn map_text(
+    html: &str,
+    skipped_elements: &[&str],
+    mut f: impl FnMut(&str) -> String,
+) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    let mut skip_depth = 0usize;
+    while !rest.is_empty() {
+        let text_end = rest.find('<').unwrap_or(rest.len());
+        let text = &rest[..text_end];
+        if skip_depth == 0 && !text.is_empty() {
+            output.push_str(&f(text));
+        } else {
+            output.push_str(text);
+        }
+        rest = &rest[text_end..];
+        if rest.is_empty() {
+            break;
+        }
+        let tag_end = rest.find('>').map_or(rest.len(), |end| end + 1);
+        let tag = &rest[..tag_end];
+        let name = tag
+            .trim_start_matches(['<', '/'])
+            .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        if skipped_elements.contains(&name.as_str()) && !tag.ends_with("/>") {
+            if tag.starts_with("</") {
+                skip_depth = skip_depth.saturating_sub(1);
+            } else {
+                skip_depth += 1;
+            }
+        }
+        output.push_str(tag);
+        rest = &rest[tag_end..];
+    }
+    output
+}
+
+/// Escapes `text` as it appears in rendered html text, e.g. to search for it.
+pub fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Escapes text for use in html (or xml) content and attribute values.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses `escape_html`, e.g. to get the text of a heading.
+pub fn unescape_html(html: &str) -> String {
+    html.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Formats `date` with a `time` format description, defaulting to `[year]-[month]-[day]`.
+pub fn format_date(date: time::OffsetDateTime, format: Option<&str>) -> anyhow::Result<String> {
+    let format = format.unwrap_or("[year]-[month]-[day]");
+    let description = time::format_description::parse_borrowed::<2>(format)
+        .map_err(|e| SsgError::DateFormatError(format.to_string(), e))?;
+    Ok(date.format(&description)?)
+}
+
+/// Generates the `<!-- {CONTRIBUTORS} -->` list.
+pub fn contributors_html(contributors: &[Contributor], config: &ContributorsConfig) -> String {
+    contributors
+        .iter()
+        .map(|contributor| {
+            let name = escape_html(&contributor.name);
+            let email = escape_html(&contributor.email);
+            let avatar = config
+                .avatar_url
+                .replace("{NAME}", &name)
+                .replace("{EMAIL}", &email);
+            config
+                .item_template
+                .replace("{AVATAR}", &avatar)
+                .replace("{NAME}", &name)
+                .replace("{EMAIL}", &email)
+        })
+        .collect::<Vec<_>>()
+        .join(&config.separator)
+}
+
+/// The byte ranges of the fenced code blocks and code spans of a page's source, whose `{{ }}`
+/// are left as written.
+pub fn code_ranges(input: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    // The start of the open code block, and its fence
+    let mut block: Option<(usize, char, usize)> = None;
+    let mut offset = 0;
+    for line in input.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let trimmed = line.trim_start();
+        let fence_char = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'));
+        let fence_len =
+            fence_char.map_or(0, |c| trimmed.len() - trimmed.trim_start_matches(c).len());
+        match (block, fence_char) {
+            (Some((start, c, len)), Some(fence))
+                if fence == c && fence_len >= len && trimmed[fence_len..].trim().is_empty() =>
+            {
+                ranges.push(start..offset);
+                block = None;
+            }
+            (Some(_), _) => {}
+            (None, Some(fence))
+                if fence_len >= 3 && (fence == '~' || !trimmed[fence_len..].contains('`')) =>
+            {
+                block = Some((line_start, fence, fence_len));
+            }
+            (None, _) => {
+                // Code spans end at the next run of as many backticks
+                let mut i = 0;
+                while let Some(found) = line[i..].find('`') {
+                    let start = i + found;
+                    let len = line[start..].len() - line[start..].trim_start_matches('`').len();
+                    let after = start + len;
+                    i = after;
+                    let mut j = after;
+                    while let Some(found) = line[j..].find('`') {
+                        let close = j + found;
+                        let close_len =
+                            line[close..].len() - line[close..].trim_start_matches('`').len();
+                        j = close + close_len;
+                        if close_len == len {
+                            ranges.push(line_start + start..line_start + j);
+                            i = j;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    if let Some((start, _, _)) = block {
+        ranges.push(start..input.len());
+    }
+    ranges
+}
+
+/// Where the first `{{` of `rest`, the end of the page source `input`, is outside of `code`,
+/// the `code_ranges` of `input`.
+pub fn find_braces(input: &str, rest: &str, code: &[Range<usize>]) -> Option<usize> {
+    let offset = input.len() - rest.len();
+    let mut from = 0;
+    loop {
+        let start = from + rest[from..].find("{{")?;
+        match code.iter().find(|range| range.contains(&(offset + start))) {
+            Some(range) => from = range.end - offset,
+            None => return Some(start),
+        }
+    }
+}
+
+/// Replaces each `{{name}}` in `input` (the page at `path`) with its value from the site's
+/// `variables`. Unknown names, and anything in code blocks and code spans, are left as they are.
+pub fn substitute_variables<'a>(
+    input: &'a str,
+    path: &Path,
+    context: &SiteContext,
+) -> Cow<'a, str> {
+    let variables = &context.config.variables;
+    if variables.is_empty() || !input.contains("{{") {
+        return Cow::Borrowed(input);
+    }
+    let code = code_ranges(input);
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = find_braces(input, rest, &code) {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = after[..end].trim();
+        match variables.get(name) {
+            Some(value) => output.push_str(value),
+            None => {
+                if !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+                {
+                    context.warn(format!("{:?} uses undefined variable {:?}", path, name));
+                }
+                output.push_str(&rest[start..start + end + 4]);
+            }
+        }
+        rest = &after[end + 2..];
     }
+    output.push_str(rest);
+    Cow::Owned(output)
 }
@@ -0,0 +1,115 @@
+use std::{cmp::Ordering, path::Path};
+
+use crate::{generate_site, report::BuildReport, vfs::FileSystem, ConsoleArgs, SiteContext};
+
+/// The versions of a versioned site, and which one is currently being generated.
+#[derive(Clone, Debug)]
+pub struct Versions {
+    pub current: String,
+    /// All versions, newest first
+    pub all: Vec<String>,
+}
+
+impl Versions {
+    /// Generates the `<!-- {VERSION_SWITCHER} -->` list for a page at `depth` in the version.
+    pub fn switcher_html(&self, depth: usize) -> String {
+        let to_output_root = "../".repeat(depth);
+        let mut html = "<ul class=\"version-switcher\">".to_string();
+        for version in std::iter::once("latest").chain(self.all.iter().map(String::as_str)) {
+            if version == self.current {
+                html.push_str(&format!("<li class=\"active\"><b>{}</b></li>", version));
+            } else {
+                html.push_str(&format!(
+                    "<li><a href=\"{}{}/index.html\">{}</a></li>",
+                    to_output_root, version, version
+                ));
+            }
+        }
+        html.push_str("</ul>");
+        html
+    }
+}
+
+/// Generates each subdirectory of `target_path` as its own version of the site in
+/// `output_path/<version>`, plus the newest version again in `output_path/latest`.
+pub fn generate_versioned_site(
+    target_path: &Path,
+    output_path: &Path,
+    args: &ConsoleArgs,
+    fs: &dyn FileSystem,
+) -> anyhow::Result<BuildReport> {
+    let mut all = fs
+        .walk(target_path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|(path, depth)| *depth == 1 && fs.is_dir(path))
+        .map(|(path, _)| path.file_name().unwrap().to_string_lossy().to_string())
+        .filter(|name| name != "latest")
+        .collect::<Vec<_>>();
+    all.sort_by(|a, b| compare_versions(b, a));
+    let Some(latest) = all.first().cloned() else {
+        return Err(anyhow::anyhow!(
+            "No version directories found in {}",
+            target_path.display()
+        ));
+    };
+    log::info!("Generating versions {:?} (latest is {})", &all, &latest);
+    let mut report = BuildReport::default();
+    for (version, output_name) in all
+        .iter()
+        .map(|version| (version.as_str(), version.as_str()))
+        .chain(std::iter::once((latest.as_str(), "latest")))
+    {
+        let version_path = target_path.join(version);
+        let version_output_path = output_path.join(output_name);
+        let mut context = SiteContext::new(&version_path, &version_output_path, args, fs)?;
+        context.versions = Some(Versions {
+            current: output_name.to_string(),
+            all: all.clone(),
+        });
+        report.extend(generate_site(&version_path, &context)?);
+    }
+    let index_path = output_path.join("index.html");
+    fs.write(
+        &index_path,
+        b"<!DOCTYPE html>\n<meta http-equiv=\"refresh\" content=\"0; url=latest/index.html\">\n",
+    )?;
+    report.outputs.push(index_path);
+    Ok(report)
+}
+
+/// Compares version names, treating runs of digits as numbers (so `v1.10` > `v1.9`).
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let mut x_num = String::new();
+                while let Some(c) = a.next_if(|c| c.is_ascii_digit()) {
+                    x_num.push(c);
+                }
+                let mut y_num = String::new();
+                while let Some(c) = b.next_if(|c| c.is_ascii_digit()) {
+                    y_num.push(c);
+                }
+                let x_num = x_num.trim_start_matches('0');
+                let y_num = y_num.trim_start_matches('0');
+                let ordering = x_num.len().cmp(&y_num.len()).then(x_num.cmp(y_num));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    io,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::{Condvar, Mutex},
+    time::SystemTime,
+};
+
+use walkdir::WalkDir;
+
+use crate::utils;
+
+/// The file IO of a site generation, so sites can be generated from (and into) memory as well
+/// as the disk.
+pub trait FileSystem: Debug + Send + Sync {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+    fn is_file(&self, path: &Path) -> bool;
+    fn is_dir(&self, path: &Path) -> bool;
+    /// The size of the file at `path`, in bytes
+    fn len(&self, path: &Path) -> io::Result<u64>;
+    /// When the file at `path` was last modified, if the file system records it
+    fn modified(&self, path: &Path) -> io::Result<SystemTime>;
+    /// The absolute path of `path`, with any symlinks resolved
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+    /// Every directory and file under `root` (including `root` itself) with its depth below
+    /// `root`, depth first with the entries of each directory sorted by file name.
+    fn walk(&self, root: &Path) -> Vec<io::Result<(PathBuf, usize)>>;
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.write(to, &self.read(from)?)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+}
+
+/// The disk.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RealFs;
+
+impl FileSystem for RealFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        std::fs::write(path, contents)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        std::fs::remove_dir_all(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn len(&self, path: &Path) -> io::Result<u64> {
+        Ok(std::fs::metadata(path)?.len())
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        std::fs::metadata(path)?.modified()
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+
+    fn walk(&self, root: &Path) -> Vec<io::Result<(PathBuf, usize)>> {
+        WalkDir::new(root)
+            .sort_by_file_name()
+            .into_iter()
+            .map(|entry| {
+                entry
+                    .map(|entry| (entry.path().to_path_buf(), entry.depth()))
+                    .map_err(io::Error::from)
+            })
+            .collect()
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::copy(from, to).map(|_| ())
+    }
+}
+
+/// Files and directories held in memory, e.g. for generating a site from pages stored in a
+/// database. Like the disk, files can only be written into existing directories, and paths are
+/// compared after resolving their `.` and `..` components.
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+    /// Each path's contents, or `None` for directories
+    entries: Mutex<BTreeMap<PathBuf, Option<Vec<u8>>>>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file and any missing parent directories, replacing any file already at `path`.
+    pub fn insert(&self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) {
+        if let Some(parent) = path.as_ref().parent() {
+            let _ = self.create_dir_all(parent);
+        }
+        let _ = self.write(path.as_ref(), &contents.into());
+    }
+
+    /// The paths of every file, in order.
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, contents)| contents.is_some())
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    fn add_dirs(entries: &mut BTreeMap<PathBuf, Option<Vec<u8>>>, path: &Path) -> io::Result<()> {
+        for dir in path.ancestors().filter(|dir| !dir.as_os_str().is_empty()) {
+            match entries.get(dir) {
+                Some(Some(_)) => return Err(io::Error::other(format!("{:?} is a file", dir))),
+                Some(None) => break,
+                None => {
+                    entries.insert(dir.to_path_buf(), None);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{:?} not found", path))
+}
+
+impl FileSystem for MemoryFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self
+            .entries
+            .lock()
+            .unwrap()
+            .get(&utils::normalize_path(path))
+        {
+            Some(Some(contents)) => Ok(contents.clone()),
+            _ => Err(not_found(path)),
+        }
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let path = utils::normalize_path(path);
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(None) = entries.get(&path) {
+            return Err(io::Error::other(format!("{:?} is a directory", path)));
+        }
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            if !matches!(entries.get(parent), Some(None)) {
+                return Err(not_found(parent));
+            }
+        }
+        entries.insert(path, Some(contents.to_vec()));
+        Ok(())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        Self::add_dirs(
+            &mut self.entries.lock().unwrap(),
+            &utils::normalize_path(path),
+        )
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        let path = utils::normalize_path(path);
+        let mut entries = self.entries.lock().unwrap();
+        if !matches!(entries.get(&path), Some(None)) {
+            return Err(not_found(&path));
+        }
+        entries.retain(|entry, _| !entry.starts_with(&path));
+        Ok(())
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        matches!(
+            self.entries
+                .lock()
+                .unwrap()
+                .get(&utils::normalize_path(path)),
+            Some(Some(_))
+        )
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        matches!(
+            self.entries
+                .lock()
+                .unwrap()
+                .get(&utils::normalize_path(path)),
+            Some(None)
+        )
+    }
+
+    fn len(&self, path: &Path) -> io::Result<u64> {
+        self.read(path).map(|contents| contents.len() as u64)
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        self.read(path)?;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "files in memory have no modification times",
+        ))
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        // There are no symlinks in memory
+        let path = utils::normalize_path(path);
+        match self.entries.lock().unwrap().contains_key(&path) {
+            true => Ok(path),
+            false => Err(not_found(&path)),
+        }
+    }
+
+    fn walk(&self, root: &Path) -> Vec<io::Result<(PathBuf, usize)>> {
+        let root = utils::normalize_path(root);
+        let root_depth = root.components().count();
+        // Paths are ordered component by component, which puts each directory's entries
+        // right after it
+        self.entries
+            .lock()
+            .unwrap()
+            .range(root.clone()..)
+            .take_while(|(path, _)| path.starts_with(&root))
+            .map(|(path, _)| Ok((path.clone(), path.components().count() - root_depth)))
+            .collect()
+    }
+}
+
+/// Another file system, reading and writing at most so many files at once (for `--io-jobs`),
+/// so a parallel build doesn't flood a slow disk or network mount.
+#[derive(Debug)]
+pub struct LimitedFs<'a> {
+    fs: &'a dyn FileSystem,
+    /// How many more files can be read or written at once
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+impl<'a> LimitedFs<'a> {
+    pub fn new(fs: &'a dyn FileSystem, limit: NonZeroUsize) -> Self {
+        Self {
+            fs,
+            available: Mutex::new(limit.get()),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Runs `io` once fewer than the limit of files are being read or written.
+    fn limit<T>(&self, io: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+        let mut available = self
+            .released
+            .wait_while(self.available.lock().unwrap(), |available| *available == 0)
+            .unwrap();
+        *available -= 1;
+        drop(available);
+        let result = io();
+        *self.available.lock().unwrap() += 1;
+        self.released.notify_one();
+        result
+    }
+}
+
+impl FileSystem for LimitedFs<'_> {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.limit(|| self.fs.read(path))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.limit(|| self.fs.write(path, contents))
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.fs.create_dir_all(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.fs.remove_dir_all(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.fs.is_file(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.fs.is_dir(path)
+    }
+
+    fn len(&self, path: &Path) -> io::Result<u64> {
+        self.fs.len(path)
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        self.fs.modified(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.fs.canonicalize(path)
+    }
+
+    fn walk(&self, root: &Path) -> Vec<io::Result<(PathBuf, usize)>> {
+        self.fs.walk(root)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.limit(|| self.fs.copy(from, to))
+    }
+}
@@ -0,0 +1,36 @@
+//! Bindings for rendering pages in the browser (e.g. for a live preview), built with
+//! `--features wasm` for `wasm32-unknown-unknown`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{config::SiteConfig, render_page, templates::BuiltInTemplate, utils};
+
+/// Renders a markdown page to html. `config` is the contents of the site's `ssg.toml`, which
+/// may be empty.
+#[wasm_bindgen(js_name = renderMarkdown)]
+pub fn render_markdown(input: &str, config: &str) -> Result<String, JsError> {
+    render(input, config, true).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Renders a djot page to html. `config` is the contents of the site's `ssg.toml`, which may
+/// be empty.
+#[wasm_bindgen(js_name = renderDjot)]
+pub fn render_djot(input: &str, config: &str) -> Result<String, JsError> {
+    render(input, config, false).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Wraps rendered html in `template`, either the contents of a `template.html` or the name of
+/// a built-in template (such as `github-markdown`).
+#[wasm_bindgen(js_name = wrapHtml)]
+pub fn wrap_html(content: &str, template: &str, config: &str) -> Result<String, JsError> {
+    let config: SiteConfig = toml::from_str(config)?;
+    let template = match <BuiltInTemplate as clap::ValueEnum>::from_str(template, false) {
+        Ok(built_in) => built_in.get_template(&config.template_options),
+        Err(_) => template.to_string(),
+    };
+    utils::wrap_html_content(content, Some(&template)).map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn render(input: &str, config: &str, is_markdown: bool) -> anyhow::Result<String> {
+    render_page(input, toml::from_str(config)?, is_markdown)
+}
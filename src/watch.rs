@@ -0,0 +1,319 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::Duration,
+};
+
+use notify::{event::ModifyKind, EventKind, RecursiveMode, Watcher};
+
+use crate::{
+    abbreviations, generate_pages, glossary, metadata, process_path, process_site,
+    report::BuildReport, second_pass, templating, vfs::FileSystem, ConsoleArgs, FirstPassResult,
+    SiteContext,
+};
+
+/// How long to wait for more changes after one, so a burst of saves is rebuilt once.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Files whose changes can affect any page, so they rebuild the whole site.
+const SITE_FILES: [&str; 4] = [
+    "ssg.toml",
+    "_dir.toml",
+    abbreviations::ABBREVIATIONS_FILE,
+    glossary::GLOSSARY_SOURCE,
+];
+
+/// Files that affect the pages in their directory and below it, along with template partials.
+const TEMPLATE_FILES: [&str; 2] = ["template.html", "style.css"];
+
+/// A change to the files of a watched site.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// The contents of the file at the path changed
+    Modified(PathBuf),
+    /// Files were created, removed, or renamed
+    Structure,
+}
+
+/// A site that is rebuilt as its files change, keeping the first pass results of its pages so
+/// only the pages a change affects are rendered again.
+pub struct SiteWatcher<'a> {
+    target_path: &'a Path,
+    output_path: &'a Path,
+    args: &'a ConsoleArgs,
+    fs: &'a dyn FileSystem,
+    results: Vec<FirstPassResult>,
+    /// The pages the site generates from `results`, such as the calendar and tag pages
+    generated: Vec<FirstPassResult>,
+}
+
+impl<'a> SiteWatcher<'a> {
+    pub fn new(
+        target_path: &'a Path,
+        output_path: &'a Path,
+        args: &'a ConsoleArgs,
+        fs: &'a dyn FileSystem,
+    ) -> Self {
+        Self {
+            target_path,
+            output_path,
+            args,
+            fs,
+            results: Vec::new(),
+            generated: Vec::new(),
+        }
+    }
+
+    /// Builds the whole site.
+    pub fn build(&mut self) -> anyhow::Result<BuildReport> {
+        let context = SiteContext::new(self.target_path, self.output_path, self.args, self.fs)?;
+        self.results = process_site(self.target_path, &context)?;
+        let results = self.generate_pages(&context)?;
+        second_pass(self.target_path, &results, None, &context)?;
+        let mut report = std::mem::take(&mut *context.report.lock().unwrap());
+        report.sort();
+        Ok(report)
+    }
+
+    /// Rebuilds what `changes` affect. Changed pages (and the pages using a changed template)
+    /// are rendered again and written, along with the generated pages, sitemap and feeds that
+    /// changed with them, and changed files are copied again, unless the change can affect the
+    /// whole site (such as to `ssg.toml`, or files being added).
+    pub fn rebuild(&mut self, changes: &[Change]) -> anyhow::Result<BuildReport> {
+        let context = SiteContext::new(self.target_path, self.output_path, self.args, self.fs)?;
+        let mut sources = Vec::new();
+        for change in changes {
+            let affected = match change {
+                Change::Modified(path) => self.affected_sources(path, &context)?,
+                Change::Structure => None,
+            };
+            match affected {
+                Some(affected) => sources.extend(affected),
+                None => return self.build(),
+            }
+        }
+        sources.sort();
+        sources.dedup();
+        let mut pages = Vec::new();
+        // Pages listed differently change the table of contents, breadcrumbs and links to the
+        // previous and next pages of every page
+        let mut listing_changed = false;
+        for source in sources {
+            let depth = source.strip_prefix(self.target_path)?.components().count();
+            let is_page = is_page(&source);
+            let mut results = Vec::new();
+            process_path(&source, &context, depth, &mut results)?;
+            if is_page && results.is_empty() {
+                return self.build();
+            }
+            for result in results {
+                let FirstPassResult::HtmlOutput {
+                    relative_path,
+                    unlisted,
+                    id,
+                    title,
+                    date,
+                    layout,
+                    ..
+                } = &result
+                else {
+                    continue;
+                };
+                let previous = self.results.iter_mut().find(|previous| match previous {
+                    FirstPassResult::HtmlOutput {
+                        relative_path: path,
+                        ..
+                    } => path == relative_path,
+                    FirstPassResult::Dir { .. } | FirstPassResult::Link { .. } => false,
+                });
+                let Some(previous) = previous else {
+                    return self.build();
+                };
+                if let FirstPassResult::HtmlOutput {
+                    unlisted: previous_unlisted,
+                    id: previous_id,
+                    title: previous_title,
+                    date: previous_date,
+                    layout: previous_layout,
+                    ..
+                } = previous
+                {
+                    listing_changed |= previous_unlisted != unlisted
+                        || previous_id != id
+                        || previous_title != title
+                        || previous_date != date
+                        || previous_layout.tags != layout.tags;
+                }
+                pages.push(relative_path.clone());
+                *previous = result;
+            }
+        }
+        let previous = std::mem::take(&mut self.generated);
+        let results = self.generate_pages(&context)?;
+        // Generated pages that are added or removed change the table of contents too
+        listing_changed |= previous.len() != self.generated.len();
+        for (previous, generated) in previous.iter().zip(&self.generated) {
+            match (previous, generated) {
+                (
+                    FirstPassResult::HtmlOutput {
+                        relative_path: previous_path,
+                        html: previous_html,
+                        ..
+                    },
+                    FirstPassResult::HtmlOutput {
+                        relative_path,
+                        html,
+                        ..
+                    },
+                ) if previous_path == relative_path => {
+                    if previous_html != html {
+                        pages.push(relative_path.clone());
+                    }
+                }
+                (
+                    FirstPassResult::Dir {
+                        relative_path: previous_path,
+                        ..
+                    },
+                    FirstPassResult::Dir { relative_path, .. },
+                ) if previous_path == relative_path => {}
+                _ => listing_changed = true,
+            }
+        }
+        let pages = (!listing_changed).then_some(&pages[..]);
+        second_pass(self.target_path, &results, pages, &context)?;
+        let mut report = std::mem::take(&mut *context.report.lock().unwrap());
+        report.sort();
+        Ok(report)
+    }
+
+    /// The first pass results of the site: its pages, followed by the pages it generates from
+    /// them, which are kept as `generated`.
+    fn generate_pages(&mut self, context: &SiteContext) -> anyhow::Result<Vec<FirstPassResult>> {
+        let mut results = self.results.clone();
+        generate_pages(self.target_path, context, &mut results)?;
+        self.generated = results[self.results.len()..].to_vec();
+        Ok(results)
+    }
+
+    /// The files to process again after the file at `path` changed, or `None` if the whole site
+    /// has to be rebuilt.
+    fn affected_sources(
+        &self,
+        path: &Path,
+        context: &SiteContext,
+    ) -> anyhow::Result<Option<Vec<PathBuf>>> {
+        if path
+            .strip_prefix(self.target_path)
+            .is_ok_and(|relative| context.config.is_ignored(relative))
+        {
+            return Ok(Some(Vec::new()));
+        }
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let bibliography = context
+            .config
+            .bibliography
+            .path
+            .as_ref()
+            .map(|bibliography| self.target_path.join(bibliography));
+        // Glossary and note backlinks depend on every page
+        if SITE_FILES.contains(&file_name.as_ref())
+            || context.glossary.is_some()
+            || context.notes.is_some()
+            || bibliography.is_some_and(|bibliography| bibliography == path)
+        {
+            return Ok(None);
+        }
+        let dir = path.parent().unwrap_or(self.target_path);
+        if TEMPLATE_FILES.contains(&file_name.as_ref()) || templating::is_partial(path) {
+            let pages = self
+                .fs
+                .walk(dir)
+                .into_iter()
+                .flatten()
+                .map(|(page, _)| page)
+                .filter(|page| is_page(page))
+                .collect();
+            return Ok(Some(pages));
+        }
+        let mut sources = vec![path.to_path_buf()];
+        // Asset indexes list the size and modification date of each file
+        if !is_page(path) && metadata::get_dir_metadata(self.fs, dir)?.asset_index {
+            sources.push(dir.to_path_buf());
+        }
+        Ok(Some(sources))
+    }
+}
+
+fn is_page(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "dj" || ext == "djot" || ext == "md")
+}
+
+/// The changes to the site in `target_path` that `event` reports, ignoring changes to the
+/// output directory and to hidden files.
+fn site_changes(
+    event: &notify::Event,
+    target_path: &Path,
+    output_path: &Path,
+    fs: &dyn FileSystem,
+) -> Vec<Change> {
+    let paths = event.paths.iter().filter(|path| {
+        !path.starts_with(output_path)
+            && !path
+                .strip_prefix(target_path)
+                .unwrap_or(path)
+                .components()
+                .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+    });
+    match event.kind {
+        EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Any) => paths
+            .filter(|path| fs.is_file(path))
+            .cloned()
+            .map(Change::Modified)
+            .collect(),
+        EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))
+            if paths.clone().next().is_some() =>
+        {
+            vec![Change::Structure]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Builds the site in `target_path`, then rebuilds it as its files change, until the program is
+/// stopped. Changes to the output directory (if it's inside the site) and to hidden files are
+/// ignored.
+pub fn watch_site(
+    target_path: &Path,
+    output_path: &Path,
+    args: &ConsoleArgs,
+    fs: &dyn FileSystem,
+) -> anyhow::Result<BuildReport> {
+    let mut site = SiteWatcher::new(target_path, output_path, args, fs);
+    site.build()?;
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(target_path, RecursiveMode::Recursive)?;
+    log::info!("Watching {:?} for changes...", target_path);
+    while let Ok(event) = receiver.recv() {
+        let mut changes = Vec::new();
+        let mut event = Some(event);
+        while let Some(next) = event {
+            match next {
+                Ok(next) => changes.extend(site_changes(&next, target_path, output_path, fs)),
+                Err(e) => log::warn!("Could not watch for changes: {}", e),
+            }
+            event = receiver.recv_timeout(DEBOUNCE).ok();
+        }
+        if changes.is_empty() {
+            continue;
+        }
+        log::info!("Rebuilding after {} change(s)...", changes.len());
+        match site.rebuild(&changes) {
+            Ok(report) => log::info!("Wrote {} page(s)", report.pages.len()),
+            Err(e) => log::error!("Could not rebuild the site: {}", e),
+        }
+    }
+    Ok(BuildReport::default())
+}
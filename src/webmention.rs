@@ -0,0 +1,177 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use anyhow::anyhow;
+
+use crate::{links, report::BuildReport, SiteContext};
+
+/// The external links that webmentions were sent for, by the url of the page linking to them,
+/// relative to the site's directory so it's kept between builds.
+pub const SENT_FILE: &str = ".webmentions.json";
+
+/// The external links of each page, by the page's url.
+type LinkGraph = BTreeMap<String, BTreeSet<String>>;
+
+/// Sends a webmention to each external page that a page of `report` links to, but didn't in
+/// the previous build, if the page has a webmention endpoint. With `dry_run`, the webmentions
+/// are listed instead, and nothing is recorded. Links whose webmention couldn't be sent are
+/// tried again by the next build.
+pub fn send_webmentions(
+    report: &BuildReport,
+    context: &SiteContext,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let base_url = context.config.sitemap.base_url.as_deref().ok_or_else(|| {
+        anyhow!("Sending webmentions needs a `base_url` in the `[sitemap]` section of ssg.toml")
+    })?;
+    let sent_path = context.target_path.join(SENT_FILE);
+    let previous: LinkGraph = match context.fs.read_to_string(&sent_path) {
+        Ok(json) => serde_json::from_str(&json)?,
+        Err(_) => LinkGraph::new(),
+    };
+    let mut current = LinkGraph::new();
+    let mut sent = 0;
+    for page in &report.pages {
+        let Ok(relative) = page.strip_prefix(context.output_path) else {
+            continue;
+        };
+        let source = format!(
+            "{}/{}",
+            base_url.trim_end_matches('/'),
+            relative.to_string_lossy().replace('\\', "/")
+        );
+        let known = previous.get(&source);
+        for target in external_links(&context.fs.read_to_string(page)?, base_url) {
+            if !known.is_some_and(|known| known.contains(&target)) {
+                match discover_endpoint(&target) {
+                    Ok(None) => log::debug!("{} has no webmention endpoint", target),
+                    Ok(Some(endpoint)) if dry_run => {
+                        println!("Would send a webmention from {} to {}", source, target);
+                        log::debug!("The webmention endpoint of {} is {}", target, endpoint);
+                    }
+                    Ok(Some(endpoint)) => match send(&endpoint, &source, &target) {
+                        Ok(()) => {
+                            println!("Sent a webmention from {} to {}", source, target);
+                            sent += 1;
+                        }
+                        Err(e) => {
+                            context.warn(format!(
+                                "Could not send a webmention from {} to {}: {}",
+                                source, target, e
+                            ));
+                            continue;
+                        }
+                    },
+                    Err(e) => {
+                        context.warn(format!(
+                            "Could not find the webmention endpoint of {}: {}",
+                            target, e
+                        ));
+                        continue;
+                    }
+                }
+            }
+            current.entry(source.clone()).or_default().insert(target);
+        }
+    }
+    if !dry_run {
+        log::info!("Sent {} webmention(s)", sent);
+        context.fs.write(
+            &sent_path,
+            serde_json::to_string_pretty(&current)?.as_bytes(),
+        )?;
+    }
+    Ok(())
+}
+
+/// The external `http(s)` links of the page `html`, without their fragments. Links to the
+/// site itself (under `base_url`) aren't external.
+fn external_links(html: &str, base_url: &str) -> BTreeSet<String> {
+    links::start_tags(html, &["a"])
+        .filter_map(|tag| links::attribute(tag, "href"))
+        .map(|href| crate::utils::unescape_html(href.split('#').next().unwrap_or_default()))
+        .filter(|href| href.starts_with("http://") || href.starts_with("https://"))
+        .filter(|href| !href.starts_with(base_url))
+        .collect()
+}
+
+/// Finds the webmention endpoint of the page at `target`: the first `Link` header with
+/// `rel="webmention"`, or else the first `<link>` or `<a>` element with it.
+fn discover_endpoint(target: &str) -> anyhow::Result<Option<String>> {
+    let response = ureq::get(target).call()?;
+    let url = response.get_url().to_string();
+    for header in response.all("Link") {
+        for link in header.split(',') {
+            let Some((href, params)) = link
+                .trim()
+                .strip_prefix('<')
+                .and_then(|link| link.split_once('>'))
+            else {
+                continue;
+            };
+            let is_webmention = params.split(';').any(|param| {
+                param.trim().strip_prefix("rel=").is_some_and(|rels| {
+                    rels.trim_matches('"')
+                        .split_whitespace()
+                        .any(|rel| rel.eq_ignore_ascii_case("webmention"))
+                })
+            });
+            if is_webmention {
+                return Ok(Some(resolve_url(&url, href)));
+            }
+        }
+    }
+    let is_html = response
+        .header("Content-Type")
+        .is_some_and(|content_type| content_type.contains("html"));
+    if !is_html {
+        return Ok(None);
+    }
+    let html = response.into_string()?;
+    let endpoint = links::start_tags(&html, &["link", "a"])
+        .find(|tag| {
+            links::attribute(tag, "rel").is_some_and(|rels| {
+                rels.split_whitespace()
+                    .any(|rel| rel.eq_ignore_ascii_case("webmention"))
+            })
+        })
+        .and_then(|tag| links::attribute(tag, "href"))
+        .map(|href| resolve_url(&url, &crate::utils::unescape_html(href)));
+    Ok(endpoint)
+}
+
+/// Sends the webmention that `source` links to `target` to `endpoint`.
+fn send(endpoint: &str, source: &str, target: &str) -> anyhow::Result<()> {
+    ureq::post(endpoint).send_form(&[("source", source), ("target", target)])?;
+    Ok(())
+}
+
+/// Resolves `href`, found on the page at `url`, to an absolute url. An empty `href` is the page
+/// itself.
+fn resolve_url(url: &str, href: &str) -> String {
+    if href.contains("://") {
+        return href.to_string();
+    }
+    let (scheme, rest) = url.split_once("://").unwrap_or(("https", url));
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    if let Some(path) = href.strip_prefix("//") {
+        format!("{}://{}", scheme, path)
+    } else if href.starts_with('/') {
+        format!("{}://{}{}", scheme, host, href)
+    } else if href.is_empty() {
+        url.split('#').next().unwrap_or(url).to_string()
+    } else if href.starts_with('?') {
+        format!("{}{}", url.split(['?', '#']).next().unwrap_or(url), href)
+    } else {
+        let path = rest[host.len()..]
+            .split(['?', '#'])
+            .next()
+            .unwrap_or_default();
+        let dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+        let joined = crate::utils::normalize_path(Path::new(&format!("{}/{}", dir, href)));
+        let joined = joined.to_string_lossy().replace('\\', "/");
+        format!("{}://{}/{}", scheme, host, joined.trim_start_matches('/'))
+    }
+}